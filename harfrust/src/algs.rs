@@ -1,3 +1,8 @@
+// libm used for f64::log2(), f64::ceil(), f64::powi() and f64::abs()
+#[cfg(not(feature = "std"))]
+#[allow(unused_imports)]
+use core_maths::CoreFloat as _;
+
 // FLAG macro in harfbuzz.
 #[inline]
 pub const fn rb_flag(x: u32) -> u32 {
@@ -66,3 +71,64 @@ pub const fn HB_CODEPOINT_DECODE3_11_7_14_2(v: u32) -> u32 {
 pub const fn HB_CODEPOINT_DECODE3_11_7_14_3(v: u32) -> u32 {
     v & 0x3FFF
 }
+
+/* Finds a root of a monotonically increasing function `f` such that
+ * `min_y <= f(x) <= max_y`, using the ITP (Interpolate Truncate and
+ * Project) method. `ya` and `yb` are `f(a)` and `f(b)` respectively.
+ *
+ * Returns the solution together with the function value at it, if one
+ * was evaluated there. Otherwise the midpoint of the final bracket is
+ * returned. */
+// Port of HarfBuzz's solve_itp, used by hb_shape_justify.
+#[allow(clippy::too_many_arguments)]
+pub fn solve_itp(
+    mut f: impl FnMut(f64) -> f64,
+    mut a: f64,
+    mut b: f64,
+    epsilon: f64,
+    min_y: f64,
+    max_y: f64,
+    mut ya: f64,
+    mut yb: f64,
+) -> (f64, Option<f64>) {
+    let n1_2 = ((b - a) / epsilon).log2().ceil() - 1.0;
+    let n1_2 = n1_2.max(0.0) as u32;
+    let n0 = 1; // Hardwired
+    let k1 = 0.2 / (b - a); // Hardwired.
+    let nmax = n0 + n1_2;
+    let mut scaled_epsilon = epsilon * 2f64.powi(nmax as i32);
+    let _2_epsilon = 2.0 * epsilon;
+    while b - a > _2_epsilon {
+        let x1_2 = 0.5 * (a + b);
+        let r = scaled_epsilon - 0.5 * (b - a);
+        let xf = (yb * a - ya * b) / (yb - ya);
+        let sigma = x1_2 - xf;
+        let b_a = b - a;
+        // This has k2 = 2 hardwired for efficiency.
+        let b_a_k2 = b_a * b_a;
+        let delta = k1 * b_a_k2;
+        let sigma_sign = if sigma >= 0.0 { 1.0 } else { -1.0 };
+        let xt = if delta <= (x1_2 - xf).abs() {
+            xf + delta * sigma_sign
+        } else {
+            x1_2
+        };
+        let xitp = if (xt - x1_2).abs() <= r {
+            xt
+        } else {
+            x1_2 - r * sigma_sign
+        };
+        let yitp = f(xitp);
+        if yitp > max_y {
+            b = xitp;
+            yb = yitp;
+        } else if yitp < min_y {
+            a = xitp;
+            ya = yitp;
+        } else {
+            return (xitp, Some(yitp));
+        }
+        scaled_epsilon *= 0.5;
+    }
+    (0.5 * (a + b), None)
+}
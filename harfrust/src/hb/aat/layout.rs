@@ -3,11 +3,13 @@
 use super::map;
 use super::{layout_kerx_table, layout_morx_table, layout_trak_table};
 use crate::hb::aat::layout_common::{AatApplyContext, HB_BUFFER_SCRATCH_FLAG_AAT_HAS_DELETED};
+#[cfg(feature = "profile")]
+use crate::hb::profile::LookupProfile;
 use crate::hb::{
     buffer::hb_buffer_t, face::Scale, hb_font_t, hb_tag_t, ot_shape_plan::hb_ot_shape_plan_t,
     GlyphInfo,
 };
-use crate::Feature;
+use crate::{AatFeature, Feature};
 
 pub type FeatureType = u8;
 
@@ -505,24 +507,43 @@ pub fn substitute(
     face: &hb_font_t,
     buffer: &mut hb_buffer_t,
     features: &[Feature],
+    aat_features: &[AatFeature],
+    #[cfg(feature = "profile")] profile: Option<&mut LookupProfile>,
 ) {
+    let has_features = !features.is_empty() || !aat_features.is_empty();
     let mut aat_map = map::AatMap::default();
-    if !features.is_empty() {
+    if has_features {
         let mut builder = map::AatMapBuilder::default();
         for feature in features {
             builder.add_feature(face, feature);
         }
+        for feature in aat_features {
+            builder.add_aat_feature(face, feature);
+        }
         builder.compile(face, &mut aat_map);
     }
 
+    let map = if !has_features {
+        &plan.aat_map
+    } else {
+        &aat_map
+    };
     let mut c = AatApplyContext::new(plan, face, Scale::default(), buffer);
+    #[cfg(feature = "mort")]
+    if face.aat_tables.morx.is_none() {
+        super::layout_mort_table::apply(
+            &mut c,
+            map,
+            #[cfg(feature = "profile")]
+            profile,
+        );
+        return;
+    }
     layout_morx_table::apply(
         &mut c,
-        if features.is_empty() {
-            &plan.aat_map
-        } else {
-            &aat_map
-        },
+        map,
+        #[cfg(feature = "profile")]
+        profile,
     );
 }
 
@@ -563,7 +584,8 @@ pub fn track(
     face: &hb_font_t,
     scale: Scale,
     point_size: Option<f32>,
+    track: f32,
     buffer: &mut hb_buffer_t,
 ) {
-    layout_trak_table::apply(plan, face, scale, point_size, buffer);
+    layout_trak_table::apply(plan, face, scale, point_size, track, buffer);
 }
@@ -0,0 +1,100 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use read_fonts::tables::feat::FeatureName;
+use read_fonts::types::NameId;
+
+use crate::hb::face::hb_font_t;
+
+/// A feature type exposed by a font's AAT `feat` table.
+///
+/// Returned by [`Shaper::aat_feature_types`](crate::Shaper::aat_feature_types).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AatFeatureType {
+    /// The feature type, e.g. `1` for ligatures.
+    pub feature_type: u16,
+    /// The `name` table ID of the feature type's name.
+    pub name_id: NameId,
+    /// The feature type's name, resolved with [`Shaper::name`](crate::Shaper::name).
+    pub name: Option<String>,
+    /// Whether at most one selector of this feature type can be enabled
+    /// at a time.
+    pub is_exclusive: bool,
+    /// The selector enabled by default, for exclusive feature types.
+    pub default_selector: Option<u16>,
+    /// The selectors of this feature type.
+    pub selectors: Vec<AatFeatureSelector>,
+}
+
+/// A selector of an [`AatFeatureType`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AatFeatureSelector {
+    /// The `name` table ID of the selector's name.
+    pub name_id: NameId,
+    /// The selector's name, resolved with [`Shaper::name`](crate::Shaper::name).
+    pub name: Option<String>,
+    /// The selector value that enables this setting.
+    pub enable: u16,
+    /// The selector value that disables this setting.
+    ///
+    /// For exclusive feature types this is the default selector, otherwise
+    /// it is the selector that turns off this non-exclusive setting.
+    pub disable: u16,
+}
+
+impl hb_font_t<'_> {
+    /// Returns the feature types of the font's AAT `feat` table, or an
+    /// empty list if the font has none.
+    ///
+    /// These are the feature types and selectors that can be passed to
+    /// [`ShapeOptions::aat_features`](crate::ShapeOptions::aat_features).
+    ///
+    /// This combines HarfBuzz's `hb_aat_layout_get_feature_types`,
+    /// `hb_aat_layout_feature_type_get_name_id` and
+    /// `hb_aat_layout_feature_type_get_selector_infos`.
+    pub fn aat_feature_types(&self) -> Vec<AatFeatureType> {
+        let Some(feat) = self.aat_tables.feat.as_ref() else {
+            return Vec::new();
+        };
+        feat.names()
+            .iter()
+            .map(|feature| self.aat_feature_type(feature, feat.offset_data()))
+            .collect()
+    }
+
+    // FeatureName::get_selector_infos: <https://github.com/harfbuzz/harfbuzz/blob/main/src/hb-aat-layout-feat-table.hh>
+    fn aat_feature_type(
+        &self,
+        feature: &FeatureName,
+        data: read_fonts::FontData,
+    ) -> AatFeatureType {
+        let settings = feature
+            .setting_table(data)
+            .map(|table| table.settings())
+            .unwrap_or_default();
+        let is_exclusive = feature.is_exclusive();
+        let default_selector = if is_exclusive {
+            settings
+                .get(usize::from(feature.default_setting_index()))
+                .map(|setting| setting.setting())
+        } else {
+            None
+        };
+        let selectors = settings
+            .iter()
+            .map(|setting| AatFeatureSelector {
+                name_id: setting.name_index(),
+                name: self.name(setting.name_index()),
+                enable: setting.setting(),
+                disable: default_selector.unwrap_or(setting.setting().wrapping_add(1)),
+            })
+            .collect();
+        AatFeatureType {
+            feature_type: feature.feature(),
+            name_id: feature.name_index(),
+            name: self.name(feature.name_index()),
+            is_exclusive,
+            default_selector,
+            selectors,
+        }
+    }
+}
@@ -0,0 +1,680 @@
+//! The legacy `mort` table.
+//!
+//! `mort` is the predecessor of `morx`. Its subtables work the same way, but
+//! state tables have 8-bit classes and states, and entries refer to glyphs
+//! and actions by byte or word offsets from the start of the subtable
+//! instead of by index. The state tables are wrapped so that the `morx`
+//! drivers can run them.
+
+use super::layout_common::{AatApplyContext, ClassCache};
+use super::layout_morx_table::{
+    apply_noncontextual, collect_start_end_safe_to_break, compile_chain_flags, drive, ChainFeature,
+    ContextualCtx, ContextualSubstitutions, DriverContext, InsertionCtx, LigatureArrays,
+    LigatureCtx, RearrangementCtx, StateMachine,
+};
+use super::map::{AatMap, AatMapBuilder, RangeFlags};
+#[cfg(feature = "profile")]
+use crate::hb::profile::LookupProfile;
+use alloc::vec;
+use alloc::vec::Vec;
+use read_fonts::tables::aat::{self, LookupU16, NoPayload, StateEntry};
+use read_fonts::tables::morx::{ContextualEntryData, InsertionEntryData};
+use read_fonts::types::{BigEndian, FixedSize, GlyphId, GlyphId16};
+use read_fonts::{FontData, FontRead, ReadError};
+
+const REARRANGEMENT: u16 = 0;
+const CONTEXTUAL: u16 = 1;
+const LIGATURE: u16 = 2;
+const NONCONTEXTUAL: u16 = 4;
+const INSERTION: u16 = 5;
+
+/// A chain of the `mort` table.
+struct Chain<'a> {
+    default_flags: u32,
+    features: FontData<'a>,
+    subtables: FontData<'a>,
+    n_subtables: u16,
+}
+
+impl<'a> Chain<'a> {
+    fn features(&self) -> impl Iterator<Item = ChainFeature> + 'a {
+        let data = self.features;
+        (0..data.len() / 12).filter_map(move |i| {
+            let offset = i * 12;
+            Some(ChainFeature {
+                kind: data.read_at(offset).ok()?,
+                setting: data.read_at(offset + 2).ok()?,
+                enable_flags: data.read_at(offset + 4).ok()?,
+                disable_flags: data.read_at(offset + 8).ok()?,
+            })
+        })
+    }
+
+    fn subtables(&self) -> impl Iterator<Item = Subtable<'a>> + 'a {
+        let data = self.subtables;
+        let mut offset = 0;
+        (0..self.n_subtables).map_while(move |_| {
+            let length: u16 = data.read_at(offset).ok()?;
+            let coverage: u16 = data.read_at(offset + 2).ok()?;
+            let sub_feature_flags: u32 = data.read_at(offset + 4).ok()?;
+            if length < 8 {
+                return None;
+            }
+            let start = offset + 8;
+            let end = offset + usize::from(length);
+            let subtable_data = data.slice(start..end)?;
+            offset = end;
+            Some(Subtable {
+                coverage,
+                sub_feature_flags,
+                data: subtable_data,
+            })
+        })
+    }
+}
+
+/// A subtable of a `mort` chain.
+struct Subtable<'a> {
+    coverage: u16,
+    sub_feature_flags: u32,
+    data: FontData<'a>,
+}
+
+impl Subtable<'_> {
+    fn kind(&self) -> u16 {
+        self.coverage & 0xFF
+    }
+
+    /// Returns the coverage with the direction bits where `morx` has them.
+    /// `mort` has no equivalent of the `morx` logical order bit.
+    fn morx_coverage(&self) -> u32 {
+        u32::from(self.coverage & 0xE000) << 16
+    }
+}
+
+fn chains(mort: FontData<'_>) -> impl Iterator<Item = Chain<'_>> {
+    let version: u32 = mort.read_at(0).unwrap_or_default();
+    let n_chains: u32 = if version >> 16 == 1 {
+        mort.read_at(4).unwrap_or_default()
+    } else {
+        0
+    };
+    let mut offset = 8;
+    (0..n_chains).map_while(move |_| {
+        let default_flags: u32 = mort.read_at(offset).ok()?;
+        let length: u32 = mort.read_at(offset + 4).ok()?;
+        let n_features: u16 = mort.read_at(offset + 8).ok()?;
+        let n_subtables: u16 = mort.read_at(offset + 10).ok()?;
+        let end = offset.checked_add(length as usize)?;
+        let chain = mort.slice(offset..end)?;
+        let features_end = 12 + usize::from(n_features) * 12;
+        offset = end;
+        Some(Chain {
+            default_flags,
+            features: chain.slice(12..features_end)?,
+            subtables: chain.slice(features_end..)?,
+            n_subtables,
+        })
+    })
+}
+
+// Chain::compile_flags in harfbuzz
+pub fn compile_flags(
+    mort: &FontData,
+    builder: &AatMapBuilder,
+    map: &mut AatMap,
+    has_feature: impl Fn(u16, u16) -> bool,
+) -> Option<()> {
+    let chain_len = chains(*mort).count();
+    map.chain_flags.resize(chain_len, vec![]);
+
+    for (chain, chain_flags) in chains(*mort).zip(map.chain_flags.iter_mut()) {
+        chain_flags.push(RangeFlags {
+            flags: compile_chain_flags(chain.default_flags, chain.features(), &has_feature),
+            cluster_first: builder.range_first as u32,
+            cluster_last: builder.range_last as u32,
+        });
+    }
+
+    Some(())
+}
+
+// Chain::apply in harfbuzz
+pub fn apply<'a>(
+    c: &mut AatApplyContext<'a>,
+    map: &'a AatMap,
+    #[cfg(feature = "profile")] mut profile: Option<&mut LookupProfile>,
+) -> Option<()> {
+    c.buffer.unsafe_to_concat(None, None);
+
+    let (mort, subtable_caches) = c.face.aat_tables.mort.as_ref()?;
+
+    let mut last_chain_index = u32::MAX;
+    let mut chain_flags = None;
+    #[cfg(feature = "profile")]
+    let mut glyphs_before = Vec::new();
+
+    for subtable_cache in *subtable_caches {
+        if subtable_cache.chain_index != last_chain_index {
+            // Chain boundary: restore buffer order, load this chain's flags.
+            if c.buffer_is_reversed {
+                c.reverse_buffer();
+            }
+            last_chain_index = subtable_cache.chain_index;
+            chain_flags = map.chain_flags.get(subtable_cache.chain_index as usize);
+        }
+        let Some(chain_flags) = chain_flags else {
+            continue;
+        };
+        c.range_flags = Some(chain_flags.as_slice());
+
+        if chain_flags.len() == 1 && (subtable_cache.sub_feature_flags & chain_flags[0].flags == 0)
+        {
+            continue;
+        }
+
+        let coverage = subtable_cache.coverage;
+        let is_all_directions = coverage & 0x2000_0000 != 0;
+        let is_vertical = coverage & 0x8000_0000 != 0;
+        if !is_all_directions && c.buffer.direction.is_vertical() != is_vertical {
+            continue;
+        }
+
+        c.subtable_flags = subtable_cache.sub_feature_flags;
+        c.first_set = None;
+        c.machine_class_cache = Some(&subtable_cache.class_cache);
+        c.start_end_safe_to_break = subtable_cache.start_end_safe_to_break;
+
+        let is_backwards = coverage & 0x4000_0000 != 0;
+        let reverse = is_backwards != c.buffer.direction.is_backward();
+        if reverse != c.buffer_is_reversed {
+            c.reverse_buffer();
+        }
+
+        let Some(data) =
+            mort.slice(subtable_cache.data_start as usize..subtable_cache.data_end as usize)
+        else {
+            continue;
+        };
+        #[cfg(feature = "profile")]
+        if let Some(profile) = profile.as_deref_mut() {
+            profile.aat_subtable(
+                subtable_cache.chain_index as usize,
+                subtable_cache.subtable_index as usize,
+                &mut glyphs_before,
+                c,
+                |c| apply_subtable(subtable_cache.kind, data, c),
+            );
+            continue;
+        }
+        apply_subtable(subtable_cache.kind, data, c);
+    }
+    if c.buffer_is_reversed {
+        c.reverse_buffer();
+    }
+
+    Some(())
+}
+
+fn apply_subtable<'a>(kind: u16, data: FontData<'a>, ac: &mut AatApplyContext<'a>) {
+    match kind {
+        REARRANGEMENT => {
+            if let Ok(machine) = ObsoleteStateTable::<NoPayload>::new(data, 0, keep_entry) {
+                drive(&machine, &mut RearrangementCtx::default(), ac);
+            }
+        }
+        CONTEXTUAL => {
+            if let Ok(table) = ObsoleteContextual::new(data) {
+                let mut c = ContextualCtx::new(table.clone());
+                drive(&table.machine, &mut c, ac);
+            }
+        }
+        LIGATURE => {
+            if let Ok(table) = ObsoleteLigature::new(data) {
+                let mut c = LigatureCtx::new(table.clone());
+                drive(&table.machine, &mut c, ac);
+            }
+        }
+        NONCONTEXTUAL => {
+            if let Ok(lookup) = LookupU16::read(data) {
+                apply_noncontextual(&lookup, ac);
+            }
+        }
+        INSERTION => {
+            if let Ok(machine) = insertion_machine(data) {
+                let glyphs = data
+                    .read_array::<BigEndian<GlyphId16>>(0..data.len() & !1)
+                    .unwrap_or_default();
+                drive(&machine, &mut InsertionCtx::new(glyphs), ac);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A state table of a `mort` subtable.
+///
+/// Entries are converted to their `morx` form as they are read.
+#[derive(Clone)]
+struct ObsoleteStateTable<'a, T> {
+    data: FontData<'a>,
+    n_classes: u16,
+    first_glyph: u16,
+    n_glyphs: u16,
+    class_array: usize,
+    state_array: u16,
+    entry_table: usize,
+    payload_len: usize,
+    convert: fn(&mut StateEntry<T>),
+}
+
+impl<'a, T: bytemuck::AnyBitPattern + FixedSize> ObsoleteStateTable<'a, T> {
+    /// Reads the state table at the start of a subtable whose entries have
+    /// `payload_len` bytes after the new state and flags.
+    fn new(
+        data: FontData<'a>,
+        payload_len: usize,
+        convert: fn(&mut StateEntry<T>),
+    ) -> Result<Self, ReadError> {
+        let n_classes: u16 = data.read_at(0)?;
+        if n_classes == 0 {
+            return Err(ReadError::MalformedData("empty AAT state table"));
+        }
+        let class_table = usize::from(data.read_at::<u16>(2)?);
+        Ok(Self {
+            data,
+            n_classes,
+            first_glyph: data.read_at(class_table)?,
+            n_glyphs: data.read_at(class_table + 2)?,
+            class_array: class_table + 4,
+            state_array: data.read_at(4)?,
+            entry_table: usize::from(data.read_at::<u16>(6)?),
+            payload_len,
+            convert,
+        })
+    }
+
+    fn entry_size(&self) -> usize {
+        4 + self.payload_len
+    }
+}
+
+impl<T: bytemuck::AnyBitPattern + FixedSize> StateMachine<T> for ObsoleteStateTable<'_, T> {
+    fn class(&self, glyph_id: GlyphId, cache: &ClassCache) -> u16 {
+        let glyph_id = glyph_id.to_u32();
+        if glyph_id == 0xFFFF {
+            return u16::from(aat::class::DELETED_GLYPH);
+        }
+        if let Some(klass) = cache.get(glyph_id) {
+            return klass as u16;
+        }
+        let klass = glyph_id
+            .checked_sub(u32::from(self.first_glyph))
+            .filter(|&index| index < u32::from(self.n_glyphs))
+            .and_then(|index| {
+                self.data
+                    .read_at::<u8>(self.class_array + index as usize)
+                    .ok()
+            })
+            .map_or(u16::from(aat::class::OUT_OF_BOUNDS), u16::from);
+        cache.set(glyph_id, u32::from(klass));
+        klass
+    }
+
+    fn entry(&self, state: u16, class: u16) -> Result<StateEntry<T>, ReadError> {
+        let class = if class >= self.n_classes {
+            u16::from(aat::class::OUT_OF_BOUNDS)
+        } else {
+            class
+        };
+        let state_index = usize::from(self.state_array)
+            + usize::from(state) * usize::from(self.n_classes)
+            + usize::from(class);
+        let entry_index = usize::from(self.data.read_at::<u8>(state_index)?);
+        let offset = self.entry_table + entry_index * self.entry_size();
+        let new_state: u16 = self.data.read_at(offset)?;
+        let flags = self.data.read_at(offset + 2)?;
+        let payload = if self.payload_len == 0 {
+            <T as bytemuck::Zeroable>::zeroed()
+        } else {
+            *self.data.read_ref_at::<T>(offset + 4)?
+        };
+        // The new state is a byte offset into the state array.
+        let new_state =
+            (i32::from(new_state) - i32::from(self.state_array)) / i32::from(self.n_classes);
+        let mut entry = StateEntry {
+            new_state: new_state.try_into().map_err(|_| ReadError::OutOfBounds)?,
+            flags,
+            payload,
+        };
+        (self.convert)(&mut entry);
+        Ok(entry)
+    }
+}
+
+fn keep_entry<T>(_: &mut StateEntry<T>) {}
+
+/// Converts a byte offset from the start of a subtable into an index into
+/// the array of `size` byte elements at `array`.
+fn offset_to_index(offset: u32, array: u32, size: u32) -> Option<u32> {
+    Some(offset.checked_sub(array)? / size)
+}
+
+#[derive(Clone)]
+struct ObsoleteContextual<'a> {
+    machine: ObsoleteStateTable<'a, ContextualEntryData>,
+    data: FontData<'a>,
+    substitutions: u32,
+}
+
+impl<'a> ObsoleteContextual<'a> {
+    fn new(data: FontData<'a>) -> Result<Self, ReadError> {
+        Ok(Self {
+            machine: ObsoleteStateTable::new(data, ContextualEntryData::RAW_BYTE_LEN, keep_entry)?,
+            data,
+            substitutions: u32::from(data.read_at::<u16>(8)?),
+        })
+    }
+}
+
+impl ContextualSubstitutions for ObsoleteContextual<'_> {
+    fn substitute(&self, index: u16, glyph: GlyphId16) -> Result<Option<u16>, ReadError> {
+        // The entry holds a word offset from the start of the subtable, to
+        // which the glyph is added.
+        let offset = (u32::from(index) + u32::from(glyph.to_u16())) * 2;
+        let Some(index) = offset_to_index(offset, self.substitutions, 2) else {
+            return Ok(None);
+        };
+        let offset = self.substitutions + index * 2;
+        Ok(self
+            .data
+            .read_at::<u16>(offset as usize)
+            .ok()
+            .filter(|&glyph| glyph != 0))
+    }
+}
+
+#[derive(Clone)]
+struct ObsoleteLigature<'a> {
+    machine: ObsoleteStateTable<'a, BigEndian<u16>>,
+    data: FontData<'a>,
+    actions: u32,
+    components: u32,
+    ligatures: u32,
+}
+
+impl<'a> ObsoleteLigature<'a> {
+    const SET_COMPONENT: u16 = 0x8000;
+    const DONT_ADVANCE: u16 = 0x4000;
+    const OFFSET: u16 = 0x3FFF;
+    // The morx flag.
+    const PERFORM_ACTION: u16 = 0x2000;
+
+    fn new(data: FontData<'a>) -> Result<Self, ReadError> {
+        Ok(Self {
+            machine: ObsoleteStateTable::new(data, 0, Self::convert_entry)?,
+            data,
+            actions: u32::from(data.read_at::<u16>(8)?),
+            components: u32::from(data.read_at::<u16>(10)?),
+            ligatures: u32::from(data.read_at::<u16>(12)?),
+        })
+    }
+
+    /// Moves the action offset from the flags into the payload, like in a
+    /// `morx` ligature entry.
+    fn convert_entry(entry: &mut StateEntry<BigEndian<u16>>) {
+        let offset = entry.flags & Self::OFFSET;
+        entry.payload = offset.into();
+        entry.flags &= Self::SET_COMPONENT | Self::DONT_ADVANCE;
+        if offset != 0 {
+            entry.flags |= Self::PERFORM_ACTION;
+        }
+    }
+}
+
+impl LigatureArrays for ObsoleteLigature<'_> {
+    fn action_index(&self, entry_index: u16) -> usize {
+        offset_to_index(u32::from(entry_index), self.actions, 4).map_or(usize::MAX, |i| i as usize)
+    }
+
+    fn action(&self, index: usize) -> Option<u32> {
+        let offset = index.checked_mul(4)?.checked_add(self.actions as usize)?;
+        self.data.read_at(offset).ok()
+    }
+
+    fn component(&self, glyph: u32, offset: i32) -> Option<u16> {
+        // The component offset is in words from the start of the subtable.
+        let offset = glyph.wrapping_add(offset as u32).wrapping_mul(2);
+        let index = offset_to_index(offset, self.components, 2)?;
+        self.data
+            .read_at((self.components + index * 2) as usize)
+            .ok()
+    }
+
+    fn ligature_index(&self, components: u32) -> u32 {
+        offset_to_index(components, self.ligatures, 2).unwrap_or(u32::MAX)
+    }
+
+    fn ligature(&self, index: u32) -> Option<u16> {
+        let offset = index.checked_mul(2)?.checked_add(self.ligatures)?;
+        self.data.read_at(offset as usize).ok()
+    }
+}
+
+fn insertion_machine(
+    data: FontData<'_>,
+) -> Result<ObsoleteStateTable<'_, InsertionEntryData>, ReadError> {
+    // Insertion lists are byte offsets from the start of the subtable, with
+    // zero for none. Convert them to indices into the subtable read as an
+    // array of glyphs.
+    fn convert_entry(entry: &mut StateEntry<InsertionEntryData>) {
+        let to_index = |offset: BigEndian<u16>| match offset.get() {
+            0 | 0xFFFF => 0xFFFF.into(),
+            offset => (offset / 2).into(),
+        };
+        entry.payload.current_insert_index = to_index(entry.payload.current_insert_index);
+        entry.payload.marked_insert_index = to_index(entry.payload.marked_insert_index);
+    }
+    ObsoleteStateTable::new(data, InsertionEntryData::RAW_BYTE_LEN, convert_entry)
+}
+
+fn start_end_safe_to_break<T, Ctx: DriverContext<T>>(
+    machine: Result<ObsoleteStateTable<'_, T>, ReadError>,
+) -> u64
+where
+    T: bytemuck::AnyBitPattern + FixedSize,
+{
+    machine.map_or(0, |machine| {
+        collect_start_end_safe_to_break::<T, Ctx>(&machine)
+    })
+}
+
+pub(crate) struct MortSubtableCache {
+    chain_index: u32,
+    /// The index of the subtable within its chain.
+    #[cfg(feature = "profile")]
+    subtable_index: u32,
+    kind: u16,
+    coverage: u32,
+    sub_feature_flags: u32,
+    data_start: u32,
+    data_end: u32,
+    start_end_safe_to_break: u64,
+    class_cache: ClassCache,
+}
+
+impl MortSubtableCache {
+    /// Returns the caches of all subtables of a `mort` table, in order.
+    pub(crate) fn new_all(mort: FontData<'_>) -> Vec<Self> {
+        let base = mort.as_bytes().as_ptr() as usize;
+        let mut caches = Vec::new();
+        for (chain_index, chain) in chains(mort).enumerate() {
+            for (_subtable_index, subtable) in chain.subtables().enumerate() {
+                let data = subtable.data;
+                let start_end_safe_to_break = match subtable.kind() {
+                    REARRANGEMENT => {
+                        start_end_safe_to_break::<_, RearrangementCtx>(ObsoleteStateTable::<
+                            NoPayload,
+                        >::new(
+                            data, 0, keep_entry
+                        ))
+                    }
+                    CONTEXTUAL => start_end_safe_to_break::<_, ContextualCtx<ObsoleteContextual>>(
+                        ObsoleteContextual::new(data).map(|table| table.machine),
+                    ),
+                    LIGATURE => start_end_safe_to_break::<_, LigatureCtx<ObsoleteLigature>>(
+                        ObsoleteLigature::new(data).map(|table| table.machine),
+                    ),
+                    INSERTION => {
+                        start_end_safe_to_break::<_, InsertionCtx>(insertion_machine(data))
+                    }
+                    _ => 0,
+                };
+                let start = data.as_bytes().as_ptr() as usize - base;
+                caches.push(Self {
+                    chain_index: chain_index as u32,
+                    #[cfg(feature = "profile")]
+                    subtable_index: _subtable_index as u32,
+                    kind: subtable.kind(),
+                    coverage: subtable.morx_coverage(),
+                    sub_feature_flags: subtable.sub_feature_flags,
+                    data_start: start as u32,
+                    data_end: (start + data.len()) as u32,
+                    start_end_safe_to_break,
+                    class_cache: ClassCache::new(),
+                });
+            }
+        }
+        caches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hb::aat::layout::DELETED_GLYPH;
+    use crate::hb::buffer::hb_buffer_t;
+    use crate::hb::face::Scale;
+    use crate::{Direction, FontRef, ShapePlan, ShaperData, UnicodeBuffer};
+
+    fn words(values: &[u16]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    }
+
+    fn subtable(coverage: u16, sub_feature_flags: u32, data: &[u8]) -> Vec<u8> {
+        let mut subtable = words(&[8 + data.len() as u16, coverage]);
+        subtable.extend(sub_feature_flags.to_be_bytes());
+        subtable.extend(data);
+        subtable
+    }
+
+    // Maps glyphs 1 and 2 to 5 and 6.
+    fn noncontextual() -> Vec<u8> {
+        words(&[8, 1, 2, 5, 6])
+    }
+
+    // Forms glyph 3 from glyphs 1 and 2.
+    fn ligature() -> Vec<u8> {
+        let mut data = words(&[
+            6,  // nClasses
+            14, // classTable
+            20, // stateArray
+            38, // entryTable
+            50, // ligActionTable
+            58, // componentTable
+            66, // ligatureTable
+            // Class table: glyphs 1 and 2 are classes 4 and 5.
+            1, 2, 0x0405,
+        ]);
+        // States 0 and 1 wait for glyph 1, state 2 for glyph 2.
+        data.extend([0, 0, 0, 0, 1, 0]);
+        data.extend([0, 0, 0, 0, 1, 0]);
+        data.extend([0, 0, 0, 0, 1, 2]);
+        data.extend(words(&[
+            // Entries.
+            20, 0, 32, 0x8000, 20, 0x8032, // Set component, perform the actions at 50.
+            // Actions; components are addressed as words from glyph 29.
+            0, 29, 0x8000, 29, // Components of glyphs 0 to 3: glyph 2 selects the ligature.
+            0, 0, 66, 0, // Ligatures.
+            3,
+        ]));
+        data
+    }
+
+    fn apply(kind: u16, data: &[u8], glyphs: &[char]) -> Vec<u32> {
+        let font_data = include_bytes!("../../../tests/fonts/text-rendering-tests/TestMORXOne.ttf");
+        let font = FontRef::new(font_data).unwrap();
+        let shaper_data = ShaperData::new(&font);
+        let shaper = shaper_data.shaper(&font).build();
+        let plan = ShapePlan::new(&shaper, Direction::LeftToRight, None, None, &[]);
+
+        let mut unicode_buffer = UnicodeBuffer::new();
+        for (cluster, &glyph) in glyphs.iter().enumerate() {
+            unicode_buffer.add(glyph, cluster as u32);
+        }
+        let mut buffer: hb_buffer_t = unicode_buffer.0;
+        let class_cache = ClassCache::new();
+        let mut c = AatApplyContext::new(&plan, &shaper, Scale::default(), &mut buffer);
+        c.machine_class_cache = Some(&class_cache);
+        apply_subtable(kind, FontData::new(data), &mut c);
+        buffer.info[..buffer.len]
+            .iter()
+            .map(|info| info.glyph_id)
+            .collect()
+    }
+
+    #[test]
+    fn parse_chains_and_subtables() {
+        let mut subtables = subtable(0x2000 | NONCONTEXTUAL, 1, &noncontextual());
+        subtables.extend(subtable(LIGATURE, 2, &ligature()));
+        let mut chain = 3u32.to_be_bytes().to_vec();
+        chain.extend((24 + subtables.len() as u32).to_be_bytes());
+        chain.extend(words(&[1, 2]));
+        chain.extend(words(&[1, 0]));
+        chain.extend(1u32.to_be_bytes());
+        chain.extend((!1u32).to_be_bytes());
+        chain.extend(subtables);
+        let mut mort = 0x0001_0000u32.to_be_bytes().to_vec();
+        mort.extend(1u32.to_be_bytes());
+        mort.extend(chain);
+        let mort = FontData::new(&mort);
+
+        let chain = chains(mort).next().unwrap();
+        assert_eq!(chain.default_flags, 3);
+        let features: Vec<_> = chain
+            .features()
+            .map(|feature| (feature.kind, feature.setting, feature.enable_flags))
+            .collect();
+        assert_eq!(features, [(1, 0, 1)]);
+
+        let caches = MortSubtableCache::new_all(mort);
+        assert_eq!(caches.len(), 2);
+        assert_eq!(caches[0].kind, NONCONTEXTUAL);
+        assert_eq!(caches[0].coverage, 0x2000_0000);
+        assert_eq!(caches[0].sub_feature_flags, 1);
+        assert_eq!(caches[1].kind, LIGATURE);
+        assert_eq!(caches[1].coverage, 0);
+        assert_eq!(caches[1].sub_feature_flags, 2);
+        let data = mort.slice(caches[0].data_start as usize..caches[0].data_end as usize);
+        assert_eq!(data.unwrap().as_bytes(), noncontextual());
+    }
+
+    #[test]
+    fn apply_noncontextual_subtable() {
+        let glyphs = apply(
+            NONCONTEXTUAL,
+            &noncontextual(),
+            &['\u{1}', '\u{2}', '\u{4}'],
+        );
+        assert_eq!(glyphs, [5, 6, 4]);
+    }
+
+    #[test]
+    fn apply_ligature_subtable() {
+        let glyphs = apply(LIGATURE, &ligature(), &['\u{1}', '\u{2}']);
+        assert_eq!(glyphs, [3, DELETED_GLYPH]);
+    }
+}
@@ -4,17 +4,21 @@ use crate::hb::aat::layout_common::{
     get_class, AatApplyContext, ClassCache, TypedCollectGlyphs, START_OF_TEXT,
 };
 use crate::hb::ot_layout::MAX_CONTEXT_LENGTH;
+#[cfg(feature = "profile")]
+use crate::hb::profile::LookupProfile;
 use crate::hb::{hb_font_t, GlyphInfo};
 use crate::U32Set;
 use alloc::vec;
+#[cfg(feature = "profile")]
+use alloc::vec::Vec;
 use read_fonts::tables::aat;
-use read_fonts::tables::aat::{ExtendedStateTable, NoPayload, StateEntry};
+use read_fonts::tables::aat::{ExtendedStateTable, LookupU16, NoPayload, StateEntry};
 use read_fonts::tables::morx::{
     ContextualEntryData, ContextualSubtable, InsertionEntryData, LigatureSubtable, Subtable,
     SubtableKind, SubtableParts,
 };
-use read_fonts::types::{BigEndian, FixedSize, GlyphId16};
-use read_fonts::FontData;
+use read_fonts::types::{BigEndian, FixedSize, GlyphId, GlyphId16};
+use read_fonts::{FontData, ReadError};
 
 // Chain::compile_flags in harfbuzz
 pub fn compile_flags(face: &hb_font_t, builder: &AatMapBuilder, map: &mut AatMap) -> Option<()> {
@@ -31,7 +35,14 @@ pub fn compile_flags(face: &hb_font_t, builder: &AatMapBuilder, map: &mut AatMap
             .is_ok()
     };
 
-    let chains = face.aat_tables.morx.as_ref()?.0.chains();
+    let Some((morx, ..)) = face.aat_tables.morx.as_ref() else {
+        #[cfg(feature = "mort")]
+        if let Some((mort, _)) = face.aat_tables.mort.as_ref() {
+            return super::layout_mort_table::compile_flags(mort, builder, map, has_feature);
+        }
+        return None;
+    };
+    let chains = morx.chains();
     let chain_len = chains.iter().count();
     map.chain_flags.resize(chain_len, vec![]);
 
@@ -39,32 +50,14 @@ pub fn compile_flags(face: &hb_font_t, builder: &AatMapBuilder, map: &mut AatMap
         let Ok(chain) = chain else {
             continue;
         };
-        let mut flags = chain.default_flags();
-        for feature in chain.features() {
-            // Check whether this type/setting pair was requested in the map,
-            // and if so, apply its flags.
-
-            if has_feature(feature.feature_type(), feature.feature_settings()) {
-                flags &= feature.disable_flags();
-                flags |= feature.enable_flags();
-            } else if feature.feature_type() == FEATURE_TYPE_LETTER_CASE as u16
-                && feature.feature_settings() == u16::from(FEATURE_SELECTOR_SMALL_CAPS)
-            {
-                // Deprecated. https://github.com/harfbuzz/harfbuzz/issues/1342
-                let ok = has_feature(
-                    FEATURE_TYPE_LOWER_CASE as u16,
-                    u16::from(FEATURE_SELECTOR_LOWER_CASE_SMALL_CAPS),
-                );
-                if ok {
-                    flags &= feature.disable_flags();
-                    flags |= feature.enable_flags();
-                }
-            }
-            // TODO: Port the following commit: https://github.com/harfbuzz/harfbuzz/commit/2124ad890
-        }
-
+        let features = chain.features().iter().map(|feature| ChainFeature {
+            kind: feature.feature_type(),
+            setting: feature.feature_settings(),
+            enable_flags: feature.enable_flags(),
+            disable_flags: feature.disable_flags(),
+        });
         chain_flags.push(RangeFlags {
-            flags,
+            flags: compile_chain_flags(chain.default_flags(), features, has_feature),
             cluster_first: builder.range_first as u32,
             cluster_last: builder.range_last as u32,
         });
@@ -73,8 +66,51 @@ pub fn compile_flags(face: &hb_font_t, builder: &AatMapBuilder, map: &mut AatMap
     Some(())
 }
 
+/// A feature entry of a `morx` or `mort` chain.
+pub(crate) struct ChainFeature {
+    pub(crate) kind: u16,
+    pub(crate) setting: u16,
+    pub(crate) enable_flags: u32,
+    pub(crate) disable_flags: u32,
+}
+
+pub(crate) fn compile_chain_flags(
+    default_flags: u32,
+    features: impl Iterator<Item = ChainFeature>,
+    has_feature: impl Fn(u16, u16) -> bool,
+) -> u32 {
+    let mut flags = default_flags;
+    for feature in features {
+        // Check whether this type/setting pair was requested in the map,
+        // and if so, apply its flags.
+
+        if has_feature(feature.kind, feature.setting) {
+            flags &= feature.disable_flags;
+            flags |= feature.enable_flags;
+        } else if feature.kind == FEATURE_TYPE_LETTER_CASE as u16
+            && feature.setting == u16::from(FEATURE_SELECTOR_SMALL_CAPS)
+        {
+            // Deprecated. https://github.com/harfbuzz/harfbuzz/issues/1342
+            let ok = has_feature(
+                FEATURE_TYPE_LOWER_CASE as u16,
+                u16::from(FEATURE_SELECTOR_LOWER_CASE_SMALL_CAPS),
+            );
+            if ok {
+                flags &= feature.disable_flags;
+                flags |= feature.enable_flags;
+            }
+        }
+        // TODO: Port the following commit: https://github.com/harfbuzz/harfbuzz/commit/2124ad890
+    }
+    flags
+}
+
 // Chain::apply in harfbuzz
-pub fn apply<'a>(c: &mut AatApplyContext<'a>, map: &'a AatMap) -> Option<()> {
+pub fn apply<'a>(
+    c: &mut AatApplyContext<'a>,
+    map: &'a AatMap,
+    #[cfg(feature = "profile")] mut profile: Option<&mut LookupProfile>,
+) -> Option<()> {
     c.buffer.unsafe_to_concat(None, None);
 
     c.setup_buffer_glyph_set();
@@ -84,6 +120,8 @@ pub fn apply<'a>(c: &mut AatApplyContext<'a>, map: &'a AatMap) -> Option<()> {
 
     let mut last_chain_index = u32::MAX;
     let mut chain_flags = None;
+    #[cfg(feature = "profile")]
+    let (mut chain_start, mut glyphs_before) = (0, Vec::new());
 
     for (subtable_idx, desc) in descriptors.iter().enumerate() {
         if desc.chain_index != last_chain_index {
@@ -92,6 +130,10 @@ pub fn apply<'a>(c: &mut AatApplyContext<'a>, map: &'a AatMap) -> Option<()> {
                 c.reverse_buffer();
             }
             last_chain_index = desc.chain_index;
+            #[cfg(feature = "profile")]
+            {
+                chain_start = subtable_idx;
+            }
             chain_flags = map.chain_flags.get(desc.chain_index as usize);
         }
         let Some(chain_flags) = chain_flags else {
@@ -142,6 +184,17 @@ pub fn apply<'a>(c: &mut AatApplyContext<'a>, map: &'a AatMap) -> Option<()> {
             continue;
         };
         if let Ok(kind) = SubtableKind::from_parts(FontData::new(data), &subtable_cache.parts) {
+            #[cfg(feature = "profile")]
+            if let Some(profile) = profile.as_deref_mut() {
+                profile.aat_subtable(
+                    desc.chain_index as usize,
+                    subtable_idx - chain_start,
+                    &mut glyphs_before,
+                    c,
+                    |c| apply_subtable(kind, c),
+                );
+                continue;
+            }
             apply_subtable(kind, c);
         }
     }
@@ -185,10 +238,9 @@ fn collect_initial_glyphs<T, Ctx: DriverContext<T>>(
     class_table.collect_glyphs_filtered(glyphs, num_glyphs, filter);
 }
 
-fn collect_start_end_safe_to_break<T, Ctx: DriverContext<T>>(machine: &ExtendedStateTable<T>) -> u64
-where
-    T: FixedSize + bytemuck::AnyBitPattern,
-{
+pub(crate) fn collect_start_end_safe_to_break<T, Ctx: DriverContext<T>>(
+    machine: &impl StateMachine<T>,
+) -> u64 {
     let mut result = 0u64;
     for state in 0..64 {
         let bit = if let Ok(entry) = machine.entry(state, aat::class::END_OF_TEXT as u16) {
@@ -203,6 +255,25 @@ where
     result
 }
 
+/// A state table that [`drive`] can run: the extended state tables of
+/// `morx`, or the obsolete ones of `mort`.
+pub(crate) trait StateMachine<T> {
+    fn class(&self, glyph_id: GlyphId, cache: &ClassCache) -> u16;
+    fn entry(&self, state: u16, class: u16) -> Result<StateEntry<T>, ReadError>;
+}
+
+impl<T: bytemuck::AnyBitPattern + FixedSize> StateMachine<T> for ExtendedStateTable<'_, T> {
+    #[inline(always)]
+    fn class(&self, glyph_id: GlyphId, cache: &ClassCache) -> u16 {
+        get_class(self, glyph_id, cache)
+    }
+
+    #[inline(always)]
+    fn entry(&self, state: u16, class: u16) -> Result<StateEntry<T>, ReadError> {
+        ExtendedStateTable::entry(self, state, class)
+    }
+}
+
 pub(crate) trait DriverContext<T> {
     fn in_place() -> bool;
     fn can_advance(entry: &StateEntry<T>) -> bool;
@@ -211,8 +282,8 @@ pub(crate) trait DriverContext<T> {
     fn transition(&mut self, entry: &StateEntry<T>, ac: &mut AatApplyContext) -> Option<()>;
 }
 
-fn drive<T: bytemuck::AnyBitPattern + FixedSize + core::fmt::Debug, Ctx: DriverContext<T>>(
-    machine: &ExtendedStateTable<'_, T>,
+pub(crate) fn drive<T, Ctx: DriverContext<T>>(
+    machine: &impl StateMachine<T>,
     c: &mut Ctx,
     ac: &mut AatApplyContext,
 ) {
@@ -265,11 +336,7 @@ fn drive<T: bytemuck::AnyBitPattern + FixedSize + core::fmt::Debug, Ctx: DriverC
         }
 
         let class = if ac.buffer.idx < ac.buffer.len {
-            get_class(
-                machine,
-                ac.buffer.cur(0).as_glyph(),
-                ac.machine_class_cache.unwrap(),
-            )
+            machine.class(ac.buffer.cur(0).as_glyph(), ac.machine_class_cache.unwrap())
         } else {
             u16::from(aat::class::END_OF_TEXT)
         };
@@ -299,11 +366,7 @@ fn drive<T: bytemuck::AnyBitPattern + FixedSize + core::fmt::Debug, Ctx: DriverC
                 ac.buffer.next_glyph();
 
                 let new_class = if ac.buffer.idx < ac.buffer.len {
-                    get_class(
-                        machine,
-                        ac.buffer.cur(0).as_glyph(),
-                        ac.machine_class_cache.unwrap(),
-                    )
+                    machine.class(ac.buffer.cur(0).as_glyph(), ac.machine_class_cache.unwrap())
                 } else {
                     u16::from(aat::class::END_OF_TEXT)
                 };
@@ -418,78 +481,67 @@ fn drive<T: bytemuck::AnyBitPattern + FixedSize + core::fmt::Debug, Ctx: DriverC
 fn apply_subtable<'a>(kind: SubtableKind<'a>, ac: &mut AatApplyContext<'a>) {
     match kind {
         SubtableKind::Rearrangement(table) => {
-            let mut c = RearrangementCtx { start: 0, end: 0 };
+            let mut c = RearrangementCtx::default();
             drive(&table, &mut c, ac);
         }
         SubtableKind::Contextual(table) => {
-            let mut c = ContextualCtx {
-                mark_set: false,
-                mark: 0,
-                table: table.clone(),
-            };
+            let mut c = ContextualCtx::new(table.clone());
             drive(&table.state_table, &mut c, ac);
         }
         SubtableKind::Ligature(table) => {
-            let mut c = LigatureCtx {
-                table: table.clone(),
-                match_length: 0,
-                match_positions: [0; LIGATURE_MAX_MATCHES],
-            };
+            let mut c = LigatureCtx::new(table.clone());
             drive(&table.state_table, &mut c, ac);
         }
-        SubtableKind::NonContextual(ref lookup) => {
-            let mut last_range = ac.range_flags.as_ref().and_then(|rf| {
-                if rf.len() > 1 {
-                    rf.first().map(|_| 0usize)
-                } else {
-                    // If there's only one range, we already checked the flag.
-                    None
-                }
-            });
+        SubtableKind::NonContextual(ref lookup) => apply_noncontextual(lookup, ac),
+        SubtableKind::Insertion(table) => {
+            let mut c = InsertionCtx::new(table.glyphs);
+            drive(&table.state_table, &mut c, ac);
+        }
+    }
+}
 
-            for i in 0..ac.buffer.len {
-                // This block copied from StateTableDriver::drive. Keep in sync.
-                if let Some(range_flags) = ac.range_flags.as_ref() {
-                    if let Some(last_range) = last_range.as_mut() {
-                        let mut range = *last_range;
-                        if ac.buffer.idx < ac.buffer.len {
-                            // We need to access info
-                            let cluster = ac.buffer.cur(0).cluster;
-                            while cluster < range_flags[range].cluster_first {
-                                range -= 1;
-                            }
-
-                            while cluster > range_flags[range].cluster_last {
-                                range += 1;
-                            }
-
-                            *last_range = range;
-                        }
+pub(crate) fn apply_noncontextual(lookup: &LookupU16, ac: &mut AatApplyContext) {
+    let mut last_range = ac.range_flags.as_ref().and_then(|rf| {
+        if rf.len() > 1 {
+            rf.first().map(|_| 0usize)
+        } else {
+            // If there's only one range, we already checked the flag.
+            None
+        }
+    });
 
-                        if range_flags[range].flags & ac.subtable_flags == 0 {
-                            continue;
-                        }
-                    }
+    for i in 0..ac.buffer.len {
+        // This block copied from StateTableDriver::drive. Keep in sync.
+        if let Some(range_flags) = ac.range_flags.as_ref() {
+            if let Some(last_range) = last_range.as_mut() {
+                let mut range = *last_range;
+                let cluster = ac.buffer.info[i].cluster;
+                while cluster < range_flags[range].cluster_first {
+                    range -= 1;
                 }
 
-                if let Some(glyph) = ac.buffer.info[i].as_gid16() {
-                    if let Ok(replacement) = lookup.value(glyph.to_u16()) {
-                        ac.replace_glyph_inplace(i, replacement.into());
-                    }
+                while cluster > range_flags[range].cluster_last {
+                    range += 1;
+                }
+
+                *last_range = range;
+
+                if range_flags[range].flags & ac.subtable_flags == 0 {
+                    continue;
                 }
             }
         }
-        SubtableKind::Insertion(table) => {
-            let mut c = InsertionCtx {
-                mark: 0,
-                glyphs: table.glyphs,
-            };
-            drive(&table.state_table, &mut c, ac);
+
+        if let Some(glyph) = ac.buffer.info[i].as_gid16() {
+            if let Ok(replacement) = lookup.value(glyph.to_u16()) {
+                ac.replace_glyph_inplace(i, replacement.into());
+            }
         }
     }
 }
 
-struct RearrangementCtx {
+#[derive(Default)]
+pub(crate) struct RearrangementCtx {
     start: usize,
     end: usize,
 }
@@ -607,18 +659,39 @@ impl DriverContext<NoPayload> for RearrangementCtx {
     }
 }
 
-struct ContextualCtx<'a> {
+/// The substitution tables of a contextual subtable.
+pub(crate) trait ContextualSubstitutions {
+    /// Returns the substitute for `glyph` from the substitution at `index`.
+    fn substitute(&self, index: u16, glyph: GlyphId16) -> Result<Option<u16>, ReadError>;
+}
+
+impl ContextualSubstitutions for ContextualSubtable<'_> {
+    fn substitute(&self, index: u16, glyph: GlyphId16) -> Result<Option<u16>, ReadError> {
+        let lookup = self.lookups.get(usize::from(index))?;
+        Ok(lookup.value(glyph.to_u16()).ok())
+    }
+}
+
+pub(crate) struct ContextualCtx<S> {
     mark_set: bool,
     mark: usize,
-    table: ContextualSubtable<'a>,
+    table: S,
 }
 
-impl ContextualCtx<'_> {
+impl<S> ContextualCtx<S> {
     const SET_MARK: u16 = 0x8000;
     const DONT_ADVANCE: u16 = 0x4000;
+
+    pub(crate) fn new(table: S) -> Self {
+        Self {
+            mark_set: false,
+            mark: 0,
+            table,
+        }
+    }
 }
 
-impl DriverContext<ContextualEntryData> for ContextualCtx<'_> {
+impl<S: ContextualSubstitutions> DriverContext<ContextualEntryData> for ContextualCtx<S> {
     fn in_place() -> bool {
         true
     }
@@ -650,13 +723,11 @@ impl DriverContext<ContextualEntryData> for ContextualCtx<'_> {
         let mut replacement = None;
 
         if entry.payload.mark_index.get() != 0xFFFF {
-            let lookup = self
-                .table
-                .lookups
-                .get(usize::from(entry.payload.mark_index.get()))
-                .ok()?;
             if let Some(gid) = ac.buffer.info[self.mark].as_gid16() {
-                replacement = lookup.value(gid.to_u16()).ok();
+                replacement = self
+                    .table
+                    .substitute(entry.payload.mark_index.get(), gid)
+                    .ok()?;
             }
         }
 
@@ -671,13 +742,11 @@ impl DriverContext<ContextualEntryData> for ContextualCtx<'_> {
         replacement = None;
         let idx = ac.buffer.idx.min(ac.buffer.len - 1);
         if entry.payload.current_index.get() != 0xFFFF {
-            let lookup = self
-                .table
-                .lookups
-                .get(usize::from(entry.payload.current_index.get()))
-                .ok()?;
             if let Some(gid) = ac.buffer.info[idx].as_gid16() {
-                replacement = lookup.value(gid.to_u16()).ok();
+                replacement = self
+                    .table
+                    .substitute(entry.payload.current_index.get(), gid)
+                    .ok()?;
             }
         }
 
@@ -694,18 +763,22 @@ impl DriverContext<ContextualEntryData> for ContextualCtx<'_> {
     }
 }
 
-struct InsertionCtx<'a> {
+pub(crate) struct InsertionCtx<'a> {
     mark: u32,
     glyphs: &'a [BigEndian<GlyphId16>],
 }
 
-impl InsertionCtx<'_> {
+impl<'a> InsertionCtx<'a> {
     const SET_MARK: u16 = 0x8000;
     const DONT_ADVANCE: u16 = 0x4000;
     const CURRENT_INSERT_BEFORE: u16 = 0x0800;
     const MARKED_INSERT_BEFORE: u16 = 0x0400;
     const CURRENT_INSERT_COUNT: u16 = 0x03E0;
     const MARKED_INSERT_COUNT: u16 = 0x001F;
+
+    pub(crate) fn new(glyphs: &'a [BigEndian<GlyphId16>]) -> Self {
+        Self { mark: 0, glyphs }
+    }
 }
 
 impl DriverContext<InsertionEntryData> for InsertionCtx<'_> {
@@ -833,13 +906,46 @@ impl DriverContext<InsertionEntryData> for InsertionCtx<'_> {
 
 const LIGATURE_MAX_MATCHES: usize = 64;
 
-struct LigatureCtx<'a> {
-    table: LigatureSubtable<'a>,
+/// The action, component and ligature arrays of a ligature subtable.
+///
+/// Entries hold the index of their first ligature action.
+pub(crate) trait LigatureArrays {
+    /// Returns the index of the action an entry refers to.
+    fn action_index(&self, entry_index: u16) -> usize {
+        usize::from(entry_index)
+    }
+    fn action(&self, index: usize) -> Option<u32>;
+    /// Returns the component for `glyph` and the offset of an action.
+    fn component(&self, glyph: u32, offset: i32) -> Option<u16>;
+    /// Returns the index of the ligature for a sum of components.
+    fn ligature_index(&self, components: u32) -> u32 {
+        components
+    }
+    fn ligature(&self, index: u32) -> Option<u16>;
+}
+
+impl LigatureArrays for LigatureSubtable<'_> {
+    fn action(&self, index: usize) -> Option<u32> {
+        self.ligature_actions.get(index).map(|v| v.get())
+    }
+
+    fn component(&self, glyph: u32, offset: i32) -> Option<u16> {
+        let index = (glyph as i32).wrapping_add(offset) as usize;
+        self.components.get(index).map(|v| v.get())
+    }
+
+    fn ligature(&self, index: u32) -> Option<u16> {
+        self.ligatures.get(index as usize).map(|v| v.get().to_u16())
+    }
+}
+
+pub(crate) struct LigatureCtx<L> {
+    table: L,
     match_length: usize,
     match_positions: [usize; LIGATURE_MAX_MATCHES],
 }
 
-impl LigatureCtx<'_> {
+impl<L> LigatureCtx<L> {
     const SET_COMPONENT: u16 = 0x8000;
     const DONT_ADVANCE: u16 = 0x4000;
     const PERFORM_ACTION: u16 = 0x2000;
@@ -847,9 +953,17 @@ impl LigatureCtx<'_> {
     const LIG_ACTION_LAST: u32 = 0x8000_0000;
     const LIG_ACTION_STORE: u32 = 0x4000_0000;
     const LIG_ACTION_OFFSET: u32 = 0x3FFF_FFFF;
+
+    pub(crate) fn new(table: L) -> Self {
+        Self {
+            table,
+            match_length: 0,
+            match_positions: [0; LIGATURE_MAX_MATCHES],
+        }
+    }
 }
 
-impl DriverContext<BigEndian<u16>> for LigatureCtx<'_> {
+impl<L: LigatureArrays> DriverContext<BigEndian<u16>> for LigatureCtx<L> {
     fn in_place() -> bool {
         false
     }
@@ -898,8 +1012,8 @@ impl DriverContext<BigEndian<u16>> for LigatureCtx<'_> {
 
             let mut cursor = self.match_length;
 
-            let mut ligature_actions_index = entry.payload.get();
-            let mut ligature_idx = 0;
+            let mut ligature_actions_index = self.table.action_index(entry.payload.get());
+            let mut ligature_idx = 0u32;
             loop {
                 if cursor == 0 {
                     // Stack underflow. Clear the stack.
@@ -917,13 +1031,8 @@ impl DriverContext<BigEndian<u16>> for LigatureCtx<'_> {
 
                 // We cannot use ? in this loop, because we must call
                 // ac.buffer.move_to(end) in the end.
-                let action = match self
-                    .table
-                    .ligature_actions
-                    .get(usize::from(ligature_actions_index))
-                {
-                    Some(v) => v.get(),
-                    None => break,
+                let Some(action) = self.table.action(ligature_actions_index) else {
+                    break;
                 };
 
                 let mut uoffset = action & Self::LIG_ACTION_OFFSET;
@@ -932,19 +1041,19 @@ impl DriverContext<BigEndian<u16>> for LigatureCtx<'_> {
                 }
 
                 let offset = uoffset as i32;
-                let component_idx = (ac.buffer.cur(0).glyph_id as i32 + offset) as usize;
-                ligature_idx += match self.table.components.get(component_idx) {
-                    Some(v) => v.get(),
-                    None => break,
+                let Some(component) = self.table.component(ac.buffer.cur(0).glyph_id, offset)
+                else {
+                    break;
                 };
+                ligature_idx = ligature_idx.wrapping_add(u32::from(component));
 
                 if (action & (Self::LIG_ACTION_STORE | Self::LIG_ACTION_LAST)) != 0 {
-                    let lig = match self.table.ligatures.get(usize::from(ligature_idx)) {
-                        Some(v) => v.get(),
-                        None => break,
+                    ligature_idx = self.table.ligature_index(ligature_idx);
+                    let Some(lig) = self.table.ligature(ligature_idx) else {
+                        break;
                     };
 
-                    ac.replace_glyph(u32::from(lig.to_u16()));
+                    ac.replace_glyph(u32::from(lig));
 
                     let lig_end =
                         self.match_positions[(self.match_length - 1) % LIGATURE_MAX_MATCHES] + 1;
@@ -1041,18 +1150,22 @@ impl MorxSubtableCache {
                     );
                 }
                 SubtableKind::Contextual(table) => {
-                    start_end_safe_to_break =
-                        collect_start_end_safe_to_break::<_, ContextualCtx>(&table.state_table);
-                    collect_initial_glyphs::<_, ContextualCtx>(
+                    start_end_safe_to_break = collect_start_end_safe_to_break::<
+                        _,
+                        ContextualCtx<ContextualSubtable>,
+                    >(&table.state_table);
+                    collect_initial_glyphs::<_, ContextualCtx<ContextualSubtable>>(
                         &table.state_table,
                         &mut glyph_set,
                         num_glyphs,
                     );
                 }
                 SubtableKind::Ligature(table) => {
-                    start_end_safe_to_break =
-                        collect_start_end_safe_to_break::<_, LigatureCtx>(&table.state_table);
-                    collect_initial_glyphs::<_, LigatureCtx>(
+                    start_end_safe_to_break = collect_start_end_safe_to_break::<
+                        _,
+                        LigatureCtx<LigatureSubtable>,
+                    >(&table.state_table);
+                    collect_initial_glyphs::<_, LigatureCtx<LigatureSubtable>>(
                         &table.state_table,
                         &mut glyph_set,
                         num_glyphs,
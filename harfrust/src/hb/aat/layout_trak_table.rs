@@ -14,6 +14,7 @@ pub fn apply(
     face: &hb_font_t,
     scale: Scale,
     point_size: Option<f32>,
+    track: f32,
     buffer: &mut hb_buffer_t,
 ) -> Option<()> {
     let trak = face.aat_tables.trak.as_ref()?;
@@ -27,9 +28,9 @@ pub fn apply(
     }
 
     let advance_to_add = if buffer.direction.is_horizontal() {
-        scale.scale_x_f(trak.get_h_tracking(ptem, 0.0))
+        scale.scale_x_f(trak.get_h_tracking(ptem, track))
     } else {
-        scale.scale_y_f(trak.get_v_tracking(ptem, 0.0))
+        scale.scale_y_f(trak.get_v_tracking(ptem, track))
     };
 
     foreach_grapheme!(buffer, start, end, {
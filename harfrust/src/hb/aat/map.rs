@@ -1,5 +1,5 @@
 use crate::hb::common::{HB_FEATURE_GLOBAL_END, HB_FEATURE_GLOBAL_START};
-use crate::Feature;
+use crate::{AatFeature, Feature};
 use alloc::vec;
 use alloc::vec::Vec;
 use core::cmp::Ordering;
@@ -117,6 +117,37 @@ impl AatMapBuilder {
         Some(())
     }
 
+    /// Adds a raw feature type and selector, if the font's `feat` table
+    /// declares it.
+    pub fn add_aat_feature(&mut self, face: &hb_font_t, feature: &AatFeature) -> Option<()> {
+        let feat = face.aat_tables.feat.as_ref()?;
+        let feature_name = feat.find(feature.feature_type)?;
+        let settings = feature_name.setting_table(feat.offset_data()).ok()?;
+        let is_exclusive = feature_name.is_exclusive();
+
+        // Non-exclusive settings are declared by their "on" selector; the
+        // odd selector after it turns the setting off.
+        let declared = settings.settings().iter().any(|setting| {
+            setting.setting() == feature.selector
+                || (!is_exclusive && setting.setting() == (feature.selector & !1))
+        });
+        if !declared {
+            return None;
+        }
+
+        self.features.push(FeatureRange {
+            start: feature.start,
+            end: feature.end,
+            info: FeatureInfo {
+                kind: feature.feature_type,
+                setting: feature.selector,
+                is_exclusive,
+            },
+        });
+
+        Some(())
+    }
+
     pub fn compile(&mut self, face: &hb_font_t, m: &mut AatMap) {
         // Compute active features per range, and compile each.
         let mut feature_events = vec![];
@@ -1,11 +1,16 @@
 pub mod layout;
 pub mod layout_common;
+pub mod layout_feat_table;
 pub mod layout_kerx_table;
+#[cfg(feature = "mort")]
+pub mod layout_mort_table;
 pub mod layout_morx_table;
 pub mod layout_trak_table;
 pub mod map;
 
 use crate::hb::aat::layout_kerx_table::KerxSubtableCache;
+#[cfg(feature = "mort")]
+use crate::hb::aat::layout_mort_table::MortSubtableCache;
 use crate::hb::aat::layout_morx_table::{MorxSubtableCache, MorxSubtableDescriptor};
 use crate::hb::kerning::KernSubtableCache;
 use crate::hb::ot::OtTables;
@@ -15,11 +20,15 @@ use read_fonts::{
     tables::{ankr::Ankr, feat::Feat, kern::Kern, kerx::Kerx, morx::Morx, trak::Trak},
     FontRef, TableProvider,
 };
+#[cfg(feature = "mort")]
+use read_fonts::{types::Tag, FontData};
 
 #[derive(Default)]
 pub struct AatCache {
     pub morx: Vec<MorxSubtableCache>,
     pub morx_descriptors: Vec<MorxSubtableDescriptor>,
+    #[cfg(feature = "mort")]
+    pub mort: Vec<MortSubtableCache>,
     pub kern: Vec<KernSubtableCache>,
     pub kerx: Vec<KerxSubtableCache>,
 }
@@ -52,6 +61,10 @@ impl AatCache {
                 }
             }
         }
+        #[cfg(feature = "mort")]
+        if let Some(mort) = font.data_for_tag(Tag::new(b"mort")) {
+            cache.mort = MortSubtableCache::new_all(mort);
+        }
         if let Ok(kern) = font.kern() {
             for subtable in kern.subtables() {
                 let Ok(subtable) = subtable else {
@@ -83,6 +96,9 @@ pub struct AatTables<'a> {
         &'a [MorxSubtableCache],
         &'a [MorxSubtableDescriptor],
     )>,
+    /// The legacy `mort` table, used when there is no `morx` table.
+    #[cfg(feature = "mort")]
+    pub mort: Option<(FontData<'a>, &'a [MortSubtableCache])>,
     pub ankr: Option<Ankr<'a>>,
     pub kern: Option<(Kern<'a>, &'a [KernSubtableCache])>,
     pub kerx: Option<(Kerx<'a>, &'a [KerxSubtableCache])>,
@@ -120,6 +136,11 @@ impl<'a> AatTables<'a> {
                 )
             })
         };
+        #[cfg(feature = "mort")]
+        let mort = table_ranges
+            .mort
+            .resolve_data(font)
+            .map(|data| (data, cache.mort.as_slice()));
         let ankr = table_ranges.ankr.resolve_table(font);
         let kern = table_ranges
             .kern
@@ -133,6 +154,8 @@ impl<'a> AatTables<'a> {
         let feat = table_ranges.feat.resolve_table(font);
         Self {
             morx,
+            #[cfg(feature = "mort")]
+            mort,
             ankr,
             kern,
             kerx,
@@ -168,6 +191,10 @@ impl<'a> AatTables<'a> {
         } else {
             None
         };
+        #[cfg(feature = "mort")]
+        let mort = font
+            .data_for_tag(Tag::new(b"mort"))
+            .map(|data| (data, cache.mort.as_slice()));
         let ankr = font.ankr().ok();
         let kern = font.kern().ok().map(|table| (table, cache.kern.as_slice()));
         let kerx = font.kerx().ok().map(|table| (table, cache.kerx.as_slice()));
@@ -175,6 +202,8 @@ impl<'a> AatTables<'a> {
         let feat = font.feat().ok();
         Self {
             morx,
+            #[cfg(feature = "mort")]
+            mort,
             ankr,
             kern,
             kerx,
@@ -182,4 +211,13 @@ impl<'a> AatTables<'a> {
             feat,
         }
     }
+
+    /// HB: hb_aat_layout_has_substitution
+    pub fn has_substitution(&self) -> bool {
+        #[cfg(feature = "mort")]
+        if self.mort.is_some() {
+            return true;
+        }
+        self.morx.is_some()
+    }
 }
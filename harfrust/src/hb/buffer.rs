@@ -5,7 +5,8 @@ use crate::hb::glyph_metrics::GlyphMetrics;
 use crate::hb::glyph_names::GlyphNames;
 use crate::hb::set_digest::hb_set_digest_t;
 use crate::hb::tables::TableRanges;
-use crate::unicode::{CharExt, Codepoint};
+use crate::hb::unicode_funcs::{UnicodeFuncs, UnicodeFuncsDispatch};
+use crate::unicode::Codepoint;
 use crate::U32Set;
 use crate::{script, BufferClusterLevel, BufferFlags, Direction, Language, Script, SerializeFlags};
 use alloc::{string::String, vec::Vec};
@@ -36,6 +37,27 @@ pub struct GlyphPosition {
     pub(crate) var: u32,
 }
 
+/// Holds the positions of the glyph as floating point values.
+///
+/// These are only produced when shaping with
+/// [`ShapeOptions::float_positions`](crate::ShapeOptions::float_positions).
+/// The fields have the same meaning as those of [`GlyphPosition`].
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct GlyphPositionF32 {
+    /// How much the line advances after drawing this glyph when setting text in
+    /// horizontal direction.
+    pub x_advance: f32,
+    /// How much the line advances after drawing this glyph when setting text in
+    /// vertical direction.
+    pub y_advance: f32,
+    /// How much the glyph moves on the X-axis before drawing it, this should
+    /// not affect how much the line advances.
+    pub x_offset: f32,
+    /// How much the glyph moves on the Y-axis before drawing it, this should
+    /// not affect how much the line advances.
+    pub y_offset: f32,
+}
+
 impl GlyphPosition {
     #[inline]
     pub(crate) fn attach_chain(&self) -> i16 {
@@ -78,10 +100,7 @@ impl GlyphPosition {
 pub struct GlyphInfo {
     // NOTE: Stores a Unicode codepoint before shaping and a glyph ID after.
     //       Just like harfbuzz, we are using the same variable for two purposes.
-    //       Occupies u32 as a codepoint and u16 as a glyph id.
     /// A selected glyph.
-    ///
-    /// Guarantee to be <= `u16::MAX`.
     pub glyph_id: u32,
     pub(crate) mask: hb_mask_t,
     /// An index to the start of the grapheme cluster in the original string.
@@ -406,13 +425,17 @@ impl GlyphInfo {
         Some(gid.into())
     }
 
-    pub(crate) fn init_unicode_props(&mut self, scratch_flags: &mut hb_buffer_scratch_flags_t) {
+    pub(crate) fn init_unicode_props(
+        &mut self,
+        unicode: UnicodeFuncsDispatch,
+        scratch_flags: &mut hb_buffer_scratch_flags_t,
+    ) {
         let u = self.as_codepoint();
-        let gc = u.general_category();
+        let gc = unicode.general_category(u);
         let mut props = gc.0 as u16;
 
         if u >= 0x80 {
-            if u.is_default_ignorable() {
+            if unicode.is_default_ignorable(u) {
                 props |= UnicodeProps::IGNORABLE.bits();
                 *scratch_flags |= HB_BUFFER_SCRATCH_FLAG_HAS_DEFAULT_IGNORABLES;
 
@@ -447,7 +470,7 @@ impl GlyphInfo {
             if gc.is_mark() {
                 *scratch_flags |= HB_BUFFER_SCRATCH_FLAG_HAS_CONTINUATIONS;
                 props |= UnicodeProps::CONTINUATION.bits();
-                props |= (u.modified_combining_class() as u16) << 8;
+                props |= (unicode.modified_combining_class(u) as u16) << 8;
             }
         }
 
@@ -495,6 +518,10 @@ pub struct hb_buffer_t {
 
     pub info: Vec<GlyphInfo>,
     pub pos: Vec<GlyphPosition>,
+    /// Unrounded positions, filled in at the end of shaping when float
+    /// positions are requested.
+    pub(crate) pos_f32: Vec<GlyphPositionF32>,
+    pub(crate) have_float_positions: bool,
 
     // Text before / after the main buffer contents.
     // Always in Unicode, and ordered outward.
@@ -547,6 +574,8 @@ impl hb_buffer_t {
             out_len: 0,
             info: Vec::new(),
             pos: Vec::new(),
+            pos_f32: Vec::new(),
+            have_float_positions: false,
             have_separate_output: false,
             allocated_var_bits: 0,
             serial: 0,
@@ -742,6 +771,8 @@ impl hb_buffer_t {
         self.idx = 0;
         self.info.clear();
         self.pos.clear();
+        self.pos_f32.clear();
+        self.have_float_positions = false;
         self.len = 0;
         self.out_len = 0;
         self.have_separate_output = false;
@@ -866,10 +897,10 @@ impl hb_buffer_t {
         }
     }
 
-    pub fn guess_segment_properties(&mut self) {
+    pub fn guess_segment_properties(&mut self, unicode: UnicodeFuncsDispatch) {
         if self.script.is_none() {
             for info in &self.info {
-                match info.as_codepoint().script() {
+                match unicode.script(info.as_codepoint()) {
                     script::COMMON | script::INHERITED | script::UNKNOWN => {}
                     s => {
                         self.script = Some(s);
@@ -2038,7 +2069,16 @@ impl UnicodeBuffer {
     /// current buffer.
     #[inline]
     pub fn guess_segment_properties(&mut self) {
-        self.0.guess_segment_properties();
+        self.0
+            .guess_segment_properties(UnicodeFuncsDispatch::default());
+    }
+
+    /// Guess the segment properties (direction, language, script) for the
+    /// current buffer, using the given Unicode property functions.
+    #[inline]
+    pub fn guess_segment_properties_with(&mut self, unicode_funcs: &dyn UnicodeFuncs) {
+        self.0
+            .guess_segment_properties(UnicodeFuncsDispatch::new(Some(unicode_funcs)));
     }
 
     /// Set the flags for this buffer.
@@ -2087,6 +2127,33 @@ impl UnicodeBuffer {
     pub fn clear(&mut self) {
         self.0.clear();
     }
+
+    /// Returns a copy of the buffer text, context and segment properties,
+    /// for callers that need to shape the same input more than once.
+    pub(crate) fn duplicate(&self) -> UnicodeBuffer {
+        let src = &self.0;
+        let mut buffer = UnicodeBuffer::with_properties_of(src);
+        buffer.0.info.extend_from_slice(&src.info[..src.len]);
+        buffer.0.pos.resize(src.len, GlyphPosition::default());
+        buffer.0.len = src.len;
+        buffer
+    }
+
+    /// Returns an empty buffer with the context and segment properties
+    /// of `src`.
+    pub(crate) fn with_properties_of(src: &hb_buffer_t) -> UnicodeBuffer {
+        let mut buffer = hb_buffer_t::new();
+        buffer.flags = src.flags;
+        buffer.cluster_level = src.cluster_level;
+        buffer.invisible = src.invisible;
+        buffer.not_found_variation_selector = src.not_found_variation_selector;
+        buffer.direction = src.direction;
+        buffer.script = src.script;
+        buffer.language.clone_from(&src.language);
+        buffer.context = src.context;
+        buffer.context_len = src.context_len;
+        UnicodeBuffer(buffer)
+    }
 }
 
 impl core::fmt::Debug for UnicodeBuffer {
@@ -2138,6 +2205,17 @@ impl GlyphBuffer {
         &self.0.pos[0..self.0.len]
     }
 
+    /// Get the unrounded glyph positions.
+    ///
+    /// Returns `None` unless the buffer was shaped with
+    /// [`ShapeOptions::float_positions`](crate::ShapeOptions::float_positions).
+    #[inline]
+    pub fn glyph_positions_f32(&self) -> Option<&[GlyphPositionF32]> {
+        self.0
+            .have_float_positions
+            .then(|| &self.0.pos_f32[0..self.0.len])
+    }
+
     /// Clears the content of the glyph buffer and returns an empty
     /// `UnicodeBuffer` reusing the existing allocation.
     #[inline]
@@ -7,6 +7,8 @@ use read_fonts::{
     FontRef, TableProvider,
 };
 
+/// Codepoint to glyph cache. Glyph ids that don't fit in the 19 value bits
+/// are never stored and are looked up in the cmap every time.
 pub type cache_t = hb_cache_t<21, 19, 256, 32>;
 
 #[derive(Clone)]
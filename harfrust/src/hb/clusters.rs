@@ -0,0 +1,437 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use read_fonts::types::GlyphId;
+
+use super::buffer::GlyphBuffer;
+use crate::unicode::{CharExt, GeneralCategory};
+use crate::Shaper;
+
+/// A group of glyphs together with the text they were shaped from.
+///
+/// Yielded by [`GlyphBuffer::clusters`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GlyphCluster {
+    /// Range of the text, in cluster values, the glyphs were shaped from.
+    pub text_range: Range<usize>,
+    /// Range of the glyphs in the buffer.
+    pub glyph_range: Range<usize>,
+    /// Sum of the advances of the glyphs along the buffer direction.
+    pub advance: i32,
+}
+
+impl GlyphBuffer {
+    /// Returns the glyph clusters of the buffer, in buffer order.
+    ///
+    /// Each cluster is the smallest group of consecutive glyphs that maps
+    /// to a contiguous range of text no other glyph maps to. With the
+    /// monotone cluster levels this is a run of glyphs sharing a cluster
+    /// value; with [`BufferClusterLevel::Characters`](crate::BufferClusterLevel::Characters)
+    /// out-of-order clusters are joined. For backward directions glyphs are
+    /// in reverse logical order, so the text ranges decrease along the
+    /// buffer.
+    ///
+    /// Cluster values are taken as text offsets, which is the case for
+    /// text added with [`UnicodeBuffer::push_str`](crate::UnicodeBuffer::push_str).
+    /// `text_len` is the end of the text range of the last cluster in
+    /// logical order.
+    pub fn clusters(&self, text_len: usize) -> impl Iterator<Item = GlyphCluster> {
+        let infos = self.glyph_infos();
+        let positions = self.glyph_positions();
+        let len = infos.len();
+        let backward = self.0.direction.is_backward();
+        let horizontal = self.0.direction.is_horizontal();
+        let logical = |i: usize| if backward { len - 1 - i } else { i };
+        let cluster = |i: usize| infos[logical(i)].cluster as usize;
+
+        // Smallest cluster value at or after each logical index.
+        let mut min_after = alloc::vec![usize::MAX; len + 1];
+        for i in (0..len).rev() {
+            min_after[i] = min_after[i + 1].min(cluster(i));
+        }
+
+        let mut clusters = Vec::new();
+        let mut start = 0;
+        let mut max = 0;
+        for i in 0..len {
+            max = max.max(cluster(i));
+            if max >= min_after[i + 1] {
+                continue;
+            }
+            let text_start = min_after[start];
+            let text_end = if i + 1 == len {
+                text_len.max(text_start)
+            } else {
+                min_after[i + 1]
+            };
+            let glyph_range = if backward {
+                len - 1 - i..len - start
+            } else {
+                start..i + 1
+            };
+            let advance = positions[glyph_range.clone()]
+                .iter()
+                .map(|pos| {
+                    if horizontal {
+                        pos.x_advance
+                    } else {
+                        pos.y_advance
+                    }
+                })
+                .sum();
+            clusters.push(GlyphCluster {
+                text_range: text_start..text_end,
+                glyph_range,
+                advance,
+            });
+            start = i + 1;
+            max = 0;
+        }
+        if backward {
+            clusters.reverse();
+        }
+        clusters.into_iter()
+    }
+
+    /// Returns the position of the caret placed before the character at
+    /// `offset` in `text`.
+    ///
+    /// `text` is the text the buffer was shaped from, added with
+    /// [`UnicodeBuffer::push_str`](crate::UnicodeBuffer::push_str).
+    /// Positions are measured from the start of the first glyph in buffer
+    /// order, in the units of the glyph advances, so for backward
+    /// directions the start of the text is at the end of the run. For
+    /// vertical directions positions are distances down the run, so they
+    /// are positive even though the vertical advances are negative.
+    ///
+    /// Carets inside a cluster, such as a ligature, are placed by splitting
+    /// its advance evenly between its graphemes. Graphemes are found with
+    /// the approximation used for shaping rather than full Unicode text
+    /// segmentation, so for instance a Hangul syllable spelled with
+    /// conjoining jamo or an Indic conjunct may get carets inside it.
+    pub fn offset_to_x(&self, text: &str, offset: usize) -> i32 {
+        self.offset_to_x_impl(text, offset, None)
+    }
+
+    /// Returns the text offset of the caret position closest to `x`.
+    ///
+    /// This is the inverse of [`Self::offset_to_x`]; the returned offset is
+    /// always at a grapheme boundary. Positions before the start or past
+    /// the end of the run map to the corresponding end of the text.
+    pub fn x_to_offset(&self, text: &str, x: i32) -> usize {
+        self.x_to_offset_impl(text, x, None)
+    }
+
+    /// Like [`Self::offset_to_x`], but places carets inside ligatures at
+    /// the positions the font defines for them, if any.
+    ///
+    /// Font carets are only used for horizontal directions; vertical runs
+    /// split ligatures evenly, as [`Self::offset_to_x`] does.
+    ///
+    /// `shaper` and `scale` must be the ones the buffer was shaped with.
+    /// See [`Shaper::ligature_carets`].
+    pub fn offset_to_x_with_carets(
+        &self,
+        text: &str,
+        offset: usize,
+        shaper: &Shaper,
+        scale: Option<(i32, i32)>,
+    ) -> i32 {
+        self.offset_to_x_impl(text, offset, Some((shaper, scale)))
+    }
+
+    /// Like [`Self::x_to_offset`], but uses the ligature caret positions
+    /// the font defines, if any.
+    ///
+    /// `shaper` and `scale` must be the ones the buffer was shaped with.
+    /// See [`Shaper::ligature_carets`].
+    pub fn x_to_offset_with_carets(
+        &self,
+        text: &str,
+        x: i32,
+        shaper: &Shaper,
+        scale: Option<(i32, i32)>,
+    ) -> usize {
+        self.x_to_offset_impl(text, x, Some((shaper, scale)))
+    }
+
+    fn offset_to_x_impl(&self, text: &str, offset: usize, carets: Option<CaretSource>) -> i32 {
+        let mut x = 0;
+        for cluster in self.clusters(text.len()) {
+            if cluster.text_range.contains(&offset) {
+                let starts = grapheme_starts(text, &cluster.text_range);
+                let index = starts.iter().rposition(|&start| start <= offset);
+                let boundaries = self.boundaries(&cluster, starts.len(), carets);
+                return x + boundaries[index.unwrap_or(0)];
+            }
+            x += self.along_run(cluster.advance);
+        }
+        if self.0.direction.is_backward() {
+            0
+        } else {
+            x
+        }
+    }
+
+    fn x_to_offset_impl(&self, text: &str, x: i32, carets: Option<CaretSource>) -> usize {
+        let mut start = 0;
+        for cluster in self.clusters(text.len()) {
+            let end = start + self.along_run(cluster.advance);
+            if x < end {
+                let starts = grapheme_starts(text, &cluster.text_range);
+                let boundaries = self.boundaries(&cluster, starts.len(), carets);
+                // Pick the nearest grapheme boundary.
+                let index = boundaries
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, &boundary)| (i64::from(x - start) - i64::from(boundary)).abs())
+                    .map_or(0, |(index, _)| index);
+                return starts.get(index).copied().unwrap_or(cluster.text_range.end);
+            }
+            start = end;
+        }
+        if self.0.direction.is_backward() {
+            0
+        } else {
+            text.len()
+        }
+    }
+
+    /// Returns the position of each of the `count + 1` grapheme boundaries
+    /// of a cluster in logical order, relative to the start of the cluster
+    /// in buffer order.
+    fn boundaries(
+        &self,
+        cluster: &GlyphCluster,
+        count: usize,
+        carets: Option<CaretSource>,
+    ) -> Vec<i32> {
+        let backward = self.0.direction.is_backward();
+        let advance = self.along_run(cluster.advance);
+        match carets.and_then(|carets| self.font_carets(cluster, count, carets)) {
+            Some(carets) => {
+                let mut boundaries: Vec<i32> = core::iter::once(0)
+                    .chain(carets)
+                    .chain(core::iter::once(advance))
+                    .collect();
+                if backward {
+                    boundaries.reverse();
+                }
+                boundaries
+            }
+            None => (0..=count)
+                .map(|index| {
+                    let part = split_advance(advance, index, count);
+                    if backward {
+                        advance - part
+                    } else {
+                        part
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the `count - 1` interior caret positions of a cluster from
+    /// the first of its glyphs that has exactly that many ligature carets,
+    /// in increasing order. Vertical carets are design-space coordinates
+    /// that depend on the vertical origin of the glyph, so they are not
+    /// used.
+    fn font_carets(
+        &self,
+        cluster: &GlyphCluster,
+        count: usize,
+        (shaper, scale): CaretSource,
+    ) -> Option<Vec<i32>> {
+        if count < 2 || !self.0.direction.is_horizontal() {
+            return None;
+        }
+        let mut origin = 0;
+        for i in cluster.glyph_range.clone() {
+            let glyph = GlyphId::new(self.0.info[i].glyph_id);
+            let mut carets = shaper.ligature_carets(glyph, self.0.direction, scale);
+            if carets.len() == count - 1 {
+                carets.sort_unstable();
+                return Some(carets.into_iter().map(|caret| origin + caret).collect());
+            }
+            origin += self.0.pos[i].x_advance;
+        }
+        None
+    }
+
+    /// Converts an advance along the buffer direction to a distance along
+    /// the run, which is positive for vertical directions too.
+    fn along_run(&self, value: i32) -> i32 {
+        if self.0.direction.is_horizontal() {
+            value
+        } else {
+            -value
+        }
+    }
+}
+
+type CaretSource<'a, 'b> = (&'a Shaper<'b>, Option<(i32, i32)>);
+
+fn split_advance(advance: i32, index: usize, count: usize) -> i32 {
+    (i64::from(advance) * index as i64 / count.max(1) as i64) as i32
+}
+
+/// Returns the offsets at which graphemes start in `range` of `text`.
+///
+/// This is an approximation of extended grapheme clusters, the one used for
+/// shaping: marks, emoji modifiers, ZWJ and a following
+/// Extended_Pictographic, the second of a pair of regional indicators and
+/// the remaining Other_Grapheme_Extend characters continue the preceding
+/// grapheme. Hangul syllable and Indic conjunct rules and prepend
+/// characters are not handled.
+fn grapheme_starts(text: &str, range: &Range<usize>) -> Vec<usize> {
+    let mut starts = alloc::vec![range.start];
+    let Some(slice) = text.get(range.clone()) else {
+        return starts;
+    };
+    let mut prev: Option<(char, bool)> = None;
+    for (i, c) in slice.char_indices() {
+        let u = c as u32;
+        let continuation = match prev {
+            None => false,
+            Some((prev, prev_continuation)) => {
+                u.general_category().is_mark()
+                    || (u.general_category() == GeneralCategory::MODIFIER_SYMBOL
+                        && matches!(u, 0x1F3FB..=0x1F3FF))
+                    || (matches!(u, 0x1F1E6..=0x1F1FF)
+                        && matches!(prev as u32, 0x1F1E6..=0x1F1FF)
+                        && !prev_continuation)
+                    || u == 0x200D
+                    || (prev == '\u{200D}' && u.is_emoji_extended_pictographic())
+                    || matches!(u, 0xFF9E..=0xFF9F | 0xE0020..=0xE007F)
+            }
+        };
+        if prev.is_some() && !continuation {
+            starts.push(range.start + i);
+        }
+        prev = Some((c, continuation));
+    }
+    starts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hb::buffer::hb_buffer_t;
+    use crate::{Direction, GlyphInfo, GlyphPosition};
+
+    fn glyph_buffer(direction: Direction, glyphs: &[(u32, i32)]) -> GlyphBuffer {
+        let mut buffer = hb_buffer_t::new();
+        buffer.direction = direction;
+        for &(cluster, advance) in glyphs {
+            buffer.info.push(GlyphInfo {
+                cluster,
+                ..GlyphInfo::default()
+            });
+            buffer.pos.push(if direction.is_horizontal() {
+                GlyphPosition {
+                    x_advance: advance,
+                    ..GlyphPosition::default()
+                }
+            } else {
+                GlyphPosition {
+                    y_advance: advance,
+                    ..GlyphPosition::default()
+                }
+            });
+        }
+        buffer.len = glyphs.len();
+        GlyphBuffer(buffer)
+    }
+
+    #[test]
+    fn ltr_ligature() {
+        // "affix" with an "ffi" ligature.
+        let text = "affix";
+        let buffer = glyph_buffer(Direction::LeftToRight, &[(0, 100), (1, 300), (4, 100)]);
+        let clusters: Vec<_> = buffer.clusters(text.len()).collect();
+        assert_eq!(
+            clusters,
+            [
+                GlyphCluster {
+                    text_range: 0..1,
+                    glyph_range: 0..1,
+                    advance: 100
+                },
+                GlyphCluster {
+                    text_range: 1..4,
+                    glyph_range: 1..2,
+                    advance: 300
+                },
+                GlyphCluster {
+                    text_range: 4..5,
+                    glyph_range: 2..3,
+                    advance: 100
+                },
+            ]
+        );
+        let xs: Vec<_> = (0..=5).map(|i| buffer.offset_to_x(text, i)).collect();
+        assert_eq!(xs, [0, 100, 200, 300, 400, 500]);
+        for (offset, x) in xs.iter().enumerate() {
+            assert_eq!(buffer.x_to_offset(text, *x), offset);
+        }
+        assert_eq!(buffer.x_to_offset(text, 240), 2);
+        assert_eq!(buffer.x_to_offset(text, -10), 0);
+        assert_eq!(buffer.x_to_offset(text, 600), 5);
+    }
+
+    #[test]
+    fn rtl_with_marks() {
+        // Three two-byte letters, the second carrying a two-byte mark.
+        let text = "\u{0628}\u{0628}\u{064E}\u{0628}";
+        let buffer = glyph_buffer(
+            Direction::RightToLeft,
+            &[(6, 100), (2, 0), (2, 200), (0, 300)],
+        );
+        let clusters: Vec<_> = buffer.clusters(text.len()).collect();
+        assert_eq!(
+            clusters
+                .iter()
+                .map(|cluster| (cluster.text_range.clone(), cluster.glyph_range.clone()))
+                .collect::<Vec<_>>(),
+            [(6..8, 0..1), (2..6, 1..3), (0..2, 3..4)]
+        );
+        assert_eq!(buffer.offset_to_x(text, 0), 600);
+        assert_eq!(buffer.offset_to_x(text, 2), 300);
+        assert_eq!(buffer.offset_to_x(text, 6), 100);
+        assert_eq!(buffer.offset_to_x(text, 8), 0);
+        assert_eq!(buffer.x_to_offset(text, 0), 8);
+        assert_eq!(buffer.x_to_offset(text, 290), 2);
+        assert_eq!(buffer.x_to_offset(text, 310), 2);
+        assert_eq!(buffer.x_to_offset(text, 700), 0);
+    }
+
+    #[test]
+    fn ttb_ligature() {
+        // "affix" with an "ffi" ligature, with downward advances.
+        let text = "affix";
+        let buffer = glyph_buffer(Direction::TopToBottom, &[(0, -100), (1, -300), (4, -100)]);
+        let xs: Vec<_> = (0..=5).map(|i| buffer.offset_to_x(text, i)).collect();
+        assert_eq!(xs, [0, 100, 200, 300, 400, 500]);
+        for (offset, x) in xs.iter().enumerate() {
+            assert_eq!(buffer.x_to_offset(text, *x), offset);
+        }
+        assert_eq!(buffer.x_to_offset(text, 240), 2);
+        assert_eq!(buffer.x_to_offset(text, -10), 0);
+        assert_eq!(buffer.x_to_offset(text, 600), 5);
+    }
+
+    #[test]
+    fn out_of_order_clusters() {
+        let text = "abc";
+        let buffer = glyph_buffer(Direction::LeftToRight, &[(0, 100), (2, 100), (1, 100)]);
+        let clusters: Vec<_> = buffer.clusters(text.len()).collect();
+        assert_eq!(
+            clusters
+                .iter()
+                .map(|cluster| (cluster.text_range.clone(), cluster.glyph_range.clone()))
+                .collect::<Vec<_>>(),
+            [(0..1, 0..1), (1..3, 1..3)]
+        );
+    }
+}
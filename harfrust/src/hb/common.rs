@@ -616,18 +616,7 @@ pub struct Feature {
 impl Feature {
     /// Create a new `Feature` struct.
     pub fn new(tag: Tag, value: u32, range: impl RangeBounds<usize>) -> Feature {
-        let max = u32::MAX as usize;
-        let start = match range.start_bound() {
-            Bound::Included(&included) => included.min(max) as u32,
-            Bound::Excluded(&excluded) => excluded.min(max - 1) as u32 + 1,
-            Bound::Unbounded => 0,
-        };
-        let end = match range.end_bound() {
-            Bound::Included(&included) => included.min(max) as u32,
-            Bound::Excluded(&excluded) => excluded.saturating_sub(1).min(max) as u32,
-            Bound::Unbounded => max as u32,
-        };
-
+        let (start, end) = cluster_bounds(range);
         Feature {
             tag,
             value,
@@ -641,6 +630,55 @@ impl Feature {
     }
 }
 
+fn cluster_bounds(range: impl RangeBounds<usize>) -> (u32, u32) {
+    let max = u32::MAX as usize;
+    let start = match range.start_bound() {
+        Bound::Included(&included) => included.min(max) as u32,
+        Bound::Excluded(&excluded) => excluded.min(max - 1) as u32 + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&included) => included.min(max) as u32,
+        Bound::Excluded(&excluded) => excluded.saturating_sub(1).min(max) as u32,
+        Bound::Unbounded => max as u32,
+    };
+    (start, end)
+}
+
+/// An AAT feature setting, given as a feature type and selector from the
+/// font's `feat` table, with an accompanying range specifying on which
+/// subslice of `shape`s input it should be applied.
+///
+/// This allows requesting `morx` features that have no OpenType
+/// equivalent. Settings the font's `feat` table does not declare are
+/// ignored. For non-exclusive features, even selectors turn a setting on
+/// and the following odd selectors turn it off.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct AatFeature {
+    pub feature_type: u16,
+    pub selector: u16,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl AatFeature {
+    /// Create a new `AatFeature` struct.
+    pub fn new(feature_type: u16, selector: u16, range: impl RangeBounds<usize>) -> AatFeature {
+        let (start, end) = cluster_bounds(range);
+        AatFeature {
+            feature_type,
+            selector,
+            start,
+            end,
+        }
+    }
+
+    pub(crate) fn is_global(&self) -> bool {
+        self.start == 0 && self.end == u32::MAX
+    }
+}
+
 impl FromStr for Feature {
     type Err = &'static str;
 
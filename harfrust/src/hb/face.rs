@@ -1,5 +1,6 @@
 use alloc::boxed::Box;
-use read_fonts::types::{F2Dot14, Fixed};
+use alloc::vec::Vec;
+use read_fonts::types::{F2Dot14, Fixed, GlyphId, Tag};
 use read_fonts::{FontRef, TableProvider};
 use smallvec::SmallVec;
 
@@ -9,16 +10,27 @@ use smallvec::SmallVec;
 use core_maths::CoreFloat as _;
 
 use super::aat::AatTables;
+use super::buffer::hb_buffer_t;
 use super::charmap::{cache_t as cmap_cache_t, Charmap};
+use super::fallback_shape::fallback_shape;
 use super::font_funcs::FontFuncsDispatch;
 use super::glyph_metrics::GlyphMetrics;
 use super::glyph_names::GlyphNames;
 use super::ot::{LayoutTable, OtCache, OtTables};
 use super::ot_layout::TableIndex;
 use super::ot_shape::OtShapeContext;
+use super::ot_shape_plan::{LayoutPreference, NormalizationMode};
 use crate::hb::aat::AatCache;
-use crate::hb::tables::TableRanges;
-use crate::{script, Feature, GlyphBuffer, NormalizedCoord, ShapePlan, UnicodeBuffer, Variation};
+use crate::hb::tables::{has_broken_layout_tables, TableRanges};
+use crate::{
+    script, AatFeature, Direction, Feature, GlyphBuffer, GlyphPositionF32, NormalizedCoord,
+    ShapePlan, UnicodeBuffer, Variation,
+};
+
+#[cfg(feature = "profile")]
+use super::profile::LookupProfile;
+use super::script_shaper::ShaperRegistry;
+use super::unicode_funcs::{UnicodeFuncs, UnicodeFuncsDispatch};
 
 pub use super::font_funcs::{
     AdvanceWidthBatch, BuiltinFontFuncs, FontFuncs, NominalGlyphBatch, RawAdvanceWidthBatch,
@@ -33,6 +45,9 @@ pub struct ShaperData {
     cmap_cache: cmap_cache_t,
     // True if a font has both trak and STAT tables.
     apply_trak: bool,
+    // True if the font's layout tables can't be read, in which case the
+    // fallback shaper is used by default.
+    layout_tables_broken: bool,
 }
 
 impl ShaperData {
@@ -43,12 +58,14 @@ impl ShaperData {
         let table_ranges = TableRanges::new(font);
         let cmap_cache = cmap_cache_t::new();
         let apply_trak = font.trak().is_ok() && font.stat().is_ok();
+        let layout_tables_broken = has_broken_layout_tables(font);
         Self {
             table_ranges,
             ot_cache,
             aat_cache,
             cmap_cache,
             apply_trak,
+            layout_tables_broken,
         }
     }
 
@@ -59,12 +76,14 @@ impl ShaperData {
         let table_ranges = TableRanges::from_tables(&tables);
         let cmap_cache = cmap_cache_t::new();
         let apply_trak = tables.trak_data().is_some() && tables.stat_data().is_some();
+        let layout_tables_broken = has_broken_layout_tables(&tables);
         Self {
             table_ranges,
             ot_cache,
             aat_cache,
             cmap_cache,
             apply_trak,
+            layout_tables_broken,
         }
     }
 
@@ -75,6 +94,7 @@ impl ShaperData {
             data: self,
             font: font.clone(),
             instance: None,
+            script_shapers: None,
         }
     }
 }
@@ -90,6 +110,13 @@ const MAX_INLINE_COORDS: usize = 11;
 pub struct ShaperInstance {
     coords: SmallVec<[F2Dot14; MAX_INLINE_COORDS]>,
     pub(crate) feature_variations: [Option<u32>; 2],
+    // User space settings the coordinates were derived from, kept so that
+    // the optical size can be applied on top of them.
+    variations: SmallVec<[Variation; 4]>,
+    // Whether `opsz` was set explicitly, either by a variation setting or
+    // by normalized coordinates.
+    explicit_opsz: bool,
+    optical_size: Option<f32>,
     // TODO: this is a good place to hang variation specific caches
 }
 
@@ -132,31 +159,29 @@ impl ShaperInstance {
     }
 
     /// Resets the instance for the given font and variation settings.
+    ///
+    /// An `opsz` setting in `variations` takes precedence over the
+    /// [optical size](Self::set_optical_size).
     pub fn set_variations<V>(&mut self, font: &FontRef, variations: V)
     where
         V: IntoIterator,
         V::Item: Into<Variation>,
     {
-        self.coords.clear();
-        if let Ok(fvar) = font.fvar() {
-            self.coords
-                .resize(fvar.axis_count() as usize, F2Dot14::ZERO);
-            fvar.user_to_normalized(
-                font.avar().ok().as_ref(),
-                variations
-                    .into_iter()
-                    .map(Into::into)
-                    .map(|var| (var.tag, Fixed::from_f64(var.value as _))),
-                self.coords.as_mut_slice(),
-            );
-            self.check_default();
-            self.set_feature_variations(font);
-        }
+        self.variations.clear();
+        self.variations
+            .extend(variations.into_iter().map(Into::into));
+        self.explicit_opsz = self.variations.iter().any(|var| var.tag == OPSZ);
+        self.update_coords(font);
     }
 
     /// Resets the instance for the given font and normalized coordinates.
+    ///
+    /// The coordinates are used as is, so the
+    /// [optical size](Self::set_optical_size) is ignored.
     pub fn set_coords(&mut self, font: &FontRef, coords: impl IntoIterator<Item = F2Dot14>) {
         self.coords.clear();
+        self.variations.clear();
+        self.explicit_opsz = true;
         if let Ok(fvar) = font.fvar() {
             let count = fvar.axis_count() as usize;
             self.coords.reserve(count);
@@ -168,23 +193,74 @@ impl ShaperInstance {
 
     /// Resets the instance for the given font using the variation
     /// position from the named instance at the specified index.
+    ///
+    /// The named instance's `opsz` value is replaced by the
+    /// [optical size](Self::set_optical_size), if one is set.
     pub fn set_named_instance(&mut self, font: &FontRef, index: usize) {
         self.coords.clear();
+        self.variations.clear();
+        self.explicit_opsz = false;
         if let Ok(fvar) = font.fvar() {
             if let Ok((axes, instance)) = fvar
                 .axis_instance_arrays()
                 .and_then(|arrays| Ok((arrays.axes(), arrays.instances().get(index)?)))
             {
-                self.set_variations(
-                    font,
+                self.variations.extend(
                     axes.iter()
                         .zip(instance.coordinates)
-                        .map(|(axis, coord)| (axis.axis_tag(), coord.get().to_f32())),
+                        .map(|(axis, coord)| (axis.axis_tag(), coord.get().to_f32()).into()),
                 );
+                self.update_coords(font);
             }
         }
     }
 
+    /// Sets the point size that selects the optical size of the font, or
+    /// `None` to stop doing so.
+    ///
+    /// If the font has an `opsz` axis that was not set explicitly, its
+    /// user space value is set to `point_size`, as CoreText and CSS
+    /// `font-optical-sizing: auto` do. This is usually the same value as
+    /// [`ShapeOptions::point_size`].
+    ///
+    /// The optical size is kept when the variation settings or the named
+    /// instance are changed.
+    pub fn set_optical_size(&mut self, font: &FontRef, point_size: Option<f32>) {
+        self.optical_size = point_size;
+        if !self.explicit_opsz {
+            self.update_coords(font);
+        }
+    }
+
+    /// Returns the point size that selects the optical size of the font.
+    pub fn optical_size(&self) -> Option<f32> {
+        self.optical_size
+    }
+
+    fn update_coords(&mut self, font: &FontRef) {
+        self.coords.clear();
+        if let Ok(fvar) = font.fvar() {
+            self.coords
+                .resize(fvar.axis_count() as usize, F2Dot14::ZERO);
+            let optical_size = match self.optical_size {
+                Some(value) if !self.explicit_opsz => Some(Variation { tag: OPSZ, value }),
+                _ => None,
+            };
+            // Later settings win, so the optical size overrides a named
+            // instance's `opsz` value.
+            fvar.user_to_normalized(
+                font.avar().ok().as_ref(),
+                self.variations
+                    .iter()
+                    .chain(optical_size.as_ref())
+                    .map(|var| (var.tag, Fixed::from_f64(var.value as _))),
+                self.coords.as_mut_slice(),
+            );
+            self.check_default();
+            self.set_feature_variations(font);
+        }
+    }
+
     fn set_feature_variations(&mut self, font: &FontRef) {
         self.feature_variations = [None; 2];
         if self.coords.is_empty() {
@@ -207,11 +283,14 @@ impl ShaperInstance {
     }
 }
 
+const OPSZ: Tag = Tag::new(b"opsz");
+
 /// Builder type for constructing a [`Shaper`](crate::Shaper).
 pub struct ShaperBuilder<'a> {
     data: &'a ShaperData,
     font: FontRef<'a>,
     instance: Option<&'a ShaperInstance>,
+    script_shapers: Option<&'a ShaperRegistry>,
 }
 
 impl<'a> ShaperBuilder<'a> {
@@ -223,6 +302,13 @@ impl<'a> ShaperBuilder<'a> {
         self
     }
 
+    /// Sets optional script shapers that replace the built-in shaper for
+    /// the scripts they are registered for.
+    pub fn script_shapers(mut self, script_shapers: Option<&'a ShaperRegistry>) -> Self {
+        self.script_shapers = script_shapers;
+        self
+    }
+
     /// Builds the shaper with the current configuration.
     pub fn build(self) -> crate::Shaper<'a> {
         let font = self.font;
@@ -248,23 +334,57 @@ impl<'a> ShaperBuilder<'a> {
         });
         hb_font_t {
             font,
+            data: self.data,
             units_per_em,
             cmap_cache: &self.data.cmap_cache,
             ot_tables,
             aat_tables,
             apply_trak: self.data.apply_trak,
+            script_shapers: self.script_shapers,
         }
     }
 }
 
+/// The shaping implementation to use.
+///
+/// This is the equivalent of HarfBuzz's shaper list.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ShapeEngine {
+    /// Use the OpenType shaper, unless the font's layout tables can't be
+    /// read, in which case the fallback shaper is used.
+    #[default]
+    Auto,
+    /// Use the OpenType shaper, which also handles AAT fonts.
+    OpenType,
+    /// Use the fallback shaper.
+    ///
+    /// It maps each character to its nominal glyph and positions glyphs by
+    /// their advances, without applying any font features. Clusters,
+    /// direction and default-ignorable characters are handled as usual.
+    Fallback,
+}
+
 /// Options which can be used to configure shaping.
 #[derive(Default)]
 pub struct ShapeOptions<'a> {
-    plan: Option<&'a ShapePlan>,
-    scale: Option<(i32, i32)>,
-    point_size: Option<f32>,
-    features: &'a [Feature],
-    font_funcs: Option<&'a mut (dyn FontFuncs + 'a)>,
+    pub(crate) plan: Option<&'a ShapePlan>,
+    pub(crate) engine: ShapeEngine,
+    pub(crate) layout_preference: LayoutPreference,
+    pub(crate) normalization_mode: NormalizationMode,
+    pub(crate) scale: Option<(i32, i32)>,
+    pub(crate) point_size: Option<f32>,
+    pub(crate) track: Option<f32>,
+    pub(crate) letter_spacing: Option<f32>,
+    pub(crate) synthetic_bold: (f32, f32, bool),
+    pub(crate) synthetic_slant: f32,
+    pub(crate) float_positions: bool,
+    pub(crate) features: &'a [Feature],
+    pub(crate) aat_features: &'a [AatFeature],
+    pub(crate) font_funcs: Option<&'a mut (dyn FontFuncs + 'a)>,
+    pub(crate) unicode_funcs: Option<&'a (dyn UnicodeFuncs + 'a)>,
+    #[cfg(feature = "profile")]
+    pub(crate) profile: Option<&'a mut LookupProfile>,
 }
 
 impl<'a> ShapeOptions<'a> {
@@ -317,23 +437,189 @@ impl<'a> ShapeOptions<'a> {
         self
     }
 
+    /// Sets whether to also produce glyph positions as floating point
+    /// values, without rounding them to the scale.
+    ///
+    /// Metrics and positioning adjustments are otherwise rounded to the
+    /// scale one by one, which adds up over long runs of text at small
+    /// scales. With this set, shaping happens at a finer internal scale
+    /// and the positions are converted to the requested scale once, at the
+    /// end. They are available from
+    /// [`GlyphBuffer::glyph_positions_f32`], while
+    /// [`GlyphBuffer::glyph_positions`] holds the same values rounded.
+    ///
+    /// Values returned by [font functions](FontFuncs) are still expected
+    /// in the requested scale. This defaults to `false`.
+    pub fn float_positions(mut self, float_positions: bool) -> Self {
+        self.float_positions = float_positions;
+        self
+    }
+
     /// Sets the size used for application of the tracking table.
+    ///
+    /// This doesn't select the optical size of a variable font; use
+    /// [`ShaperInstance::set_optical_size`] for that.
     pub fn point_size(mut self, point_size: Option<f32>) -> Self {
         self.point_size = point_size;
         self
     }
 
+    /// Sets the track used for application of the tracking table.
+    ///
+    /// Fonts commonly define a normal track at `0.0` and tight and loose
+    /// tracks at `-1.0` and `1.0`; tracking is interpolated between the
+    /// tracks surrounding the given value.
+    ///
+    /// This defaults to `None`, which selects the normal track.
+    pub fn track(mut self, track: Option<f32>) -> Self {
+        self.track = track;
+        self
+    }
+
+    /// Sets an amount of letter-spacing, in font units, to add after each
+    /// grapheme.
+    ///
+    /// Unlike the tracking table this applies to any font, and on top of
+    /// the font's own tracking. The amount is scaled like the rest of the
+    /// positions and added to the advance of the first glyph of every
+    /// grapheme in logical order, like tracking, so that marks stay attached
+    /// to their base.
+    pub fn letter_spacing(mut self, letter_spacing: Option<f32>) -> Self {
+        self.letter_spacing = letter_spacing;
+        self
+    }
+
+    /// Sets the strength of synthetic emboldening.
+    ///
+    /// `x_embolden` and `y_embolden` are fractions of the font scale, for
+    /// example `0.02`. Unless `in_place` is set, advances of glyphs that have
+    /// one are increased by the emboldening strength and vertical origins
+    /// are shifted to make room for it. Glyph extents used for positioning
+    /// grow by the strength either way.
+    ///
+    /// This only adjusts positions; emboldening the outlines is up to the
+    /// renderer. This defaults to no emboldening.
+    ///
+    /// This is HarfBuzz's `hb_font_set_synthetic_bold`.
+    pub fn synthetic_bold(mut self, x_embolden: f32, y_embolden: f32, in_place: bool) -> Self {
+        self.synthetic_bold = (x_embolden, y_embolden, in_place);
+        self
+    }
+
+    /// Sets the synthetic slant, as the ratio of horizontal to vertical
+    /// displacement; `0.2` slants glyphs to the right.
+    ///
+    /// Horizontal offsets of glyphs that are offset vertically, such as
+    /// marks, are shifted to follow the slant, and glyph extents used for
+    /// positioning are widened to cover the slanted outline.
+    ///
+    /// This only adjusts positions; slanting the outlines is up to the
+    /// renderer. This defaults to no slant.
+    ///
+    /// This is HarfBuzz's `hb_font_set_synthetic_slant`.
+    pub fn synthetic_slant(mut self, slant: f32) -> Self {
+        self.synthetic_slant = slant;
+        self
+    }
+
     /// Sets the features to apply during shaping.
     pub fn features(mut self, features: &'a [Feature]) -> Self {
         self.features = features;
         self
     }
 
+    /// Sets raw AAT feature settings to apply during shaping, in addition
+    /// to the AAT equivalents of [`features`](Self::features).
+    ///
+    /// These only affect fonts shaped with `morx`.
+    pub fn aat_features(mut self, aat_features: &'a [AatFeature]) -> Self {
+        self.aat_features = aat_features;
+        self
+    }
+
     /// Sets optional font functions used for shaping.
     pub fn font_funcs(mut self, funcs: Option<&'a mut (dyn FontFuncs + 'a)>) -> Self {
         self.font_funcs = funcs;
         self
     }
+
+    /// Sets optional Unicode property functions used for shaping.
+    ///
+    /// Without them, the Unicode data built into HarfRust is used.
+    pub fn unicode_funcs(mut self, funcs: Option<&'a (dyn UnicodeFuncs + 'a)>) -> Self {
+        self.unicode_funcs = funcs;
+        self
+    }
+
+    /// Sets the shaping implementation to use.
+    ///
+    /// The default is [`ShapeEngine::Auto`]. A shape plan, if set, is
+    /// ignored by the fallback shaper.
+    pub fn engine(mut self, engine: ShapeEngine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Sets which layout tables to use when the font has both AAT and
+    /// OpenType tables, and whether to use the legacy `kern` table.
+    ///
+    /// This is only used when no plan is set; a plan uses the preference it
+    /// was created with.
+    pub fn layout_preference(mut self, layout_preference: LayoutPreference) -> Self {
+        self.layout_preference = layout_preference;
+        self
+    }
+
+    /// Sets how characters are composed and decomposed before shaping.
+    ///
+    /// The default is [`NormalizationMode::Auto`]. The mode is adjusted
+    /// where the shaper needs a different one; use
+    /// [`ShapePlan::normalization_mode`] to find out which mode is used.
+    pub fn normalization_mode(mut self, mode: NormalizationMode) -> Self {
+        self.normalization_mode = mode;
+        self
+    }
+
+    /// Sets a profile that records which lookups are tried and applied.
+    ///
+    /// Counts are added to the profile, so the same profile can be passed
+    /// to many shaping calls to profile a whole corpus. See
+    /// [`LookupProfile`] for what is recorded.
+    ///
+    /// Only available with the `profile` cargo feature, so that shaping
+    /// without it doesn't check for a profile in its inner loops.
+    #[cfg(feature = "profile")]
+    pub fn profile(mut self, profile: Option<&'a mut LookupProfile>) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Returns a copy of the options borrowing the font functions, so that
+    /// the same options can be used for more than one shaping call.
+    pub(crate) fn reborrow(&mut self) -> ShapeOptions<'_> {
+        ShapeOptions {
+            plan: self.plan,
+            engine: self.engine,
+            layout_preference: self.layout_preference,
+            normalization_mode: self.normalization_mode,
+            scale: self.scale,
+            point_size: self.point_size,
+            track: self.track,
+            letter_spacing: self.letter_spacing,
+            synthetic_bold: self.synthetic_bold,
+            synthetic_slant: self.synthetic_slant,
+            float_positions: self.float_positions,
+            features: self.features,
+            aat_features: self.aat_features,
+            font_funcs: match self.font_funcs {
+                Some(ref mut funcs) => Some(&mut **funcs),
+                None => None,
+            },
+            unicode_funcs: self.unicode_funcs,
+            #[cfg(feature = "profile")]
+            profile: self.profile.as_deref_mut(),
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -431,6 +717,227 @@ impl Scale {
     }
 }
 
+/// Synthetic emboldening and slant, resolved against the font scale.
+///
+/// hb_font_t::mults_changed: <https://github.com/harfbuzz/harfbuzz/blob/88adc6437ef561486a5adf1822410297ef4a852b/src/hb-font.hh>
+#[derive(Copy, Clone, Default)]
+pub(crate) struct Synthetic {
+    x_strength: i32,
+    y_strength: i32,
+    x_neg: bool,
+    y_neg: bool,
+    in_place: bool,
+    slant_xy: f32,
+}
+
+#[allow(clippy::cast_precision_loss)]
+impl Synthetic {
+    pub(crate) fn new(options: &ShapeOptions, scale: Option<(i32, i32)>, upem: i32) -> Self {
+        let (x_scale, y_scale) = scale.unwrap_or((upem, upem));
+        let (x_embolden, y_embolden, in_place) = options.synthetic_bold;
+        Self {
+            x_strength: (x_scale as f32 * x_embolden).round().abs() as i32,
+            y_strength: (y_scale as f32 * y_embolden).round().abs() as i32,
+            x_neg: x_scale < 0,
+            y_neg: y_scale < 0,
+            in_place,
+            slant_xy: if y_scale != 0 {
+                options.synthetic_slant * x_scale as f32 / y_scale as f32
+            } else {
+                0.0
+            },
+        }
+    }
+
+    /// Adjusts a horizontal advance for emboldening.
+    #[inline(always)]
+    pub(crate) fn h_advance(&self, advance: i32) -> i32 {
+        if self.in_place || advance == 0 {
+            advance
+        } else {
+            advance.saturating_add(self.x_shift())
+        }
+    }
+
+    /// Adjusts a vertical advance for emboldening. Vertical advances grow
+    /// downwards.
+    #[inline(always)]
+    pub(crate) fn v_advance(&self, advance: i32) -> i32 {
+        if self.in_place || advance == 0 {
+            advance
+        } else {
+            advance.saturating_sub(self.y_shift())
+        }
+    }
+
+    /// Adjusts a vertical origin for emboldening. Slant doesn't move the
+    /// origin.
+    ///
+    /// hb_font_t::get_glyph_v_origin: <https://github.com/harfbuzz/harfbuzz/blob/88adc6437ef561486a5adf1822410297ef4a852b/src/hb-font.hh>
+    pub(crate) fn v_origin(&self, (x, y): (i32, i32)) -> (i32, i32) {
+        if self.in_place {
+            return (x, y);
+        }
+        (
+            x.saturating_add(self.x_shift()),
+            y.saturating_add(self.y_shift()),
+        )
+    }
+
+    /// Grows the extents to cover the slanted and emboldened outline.
+    ///
+    /// hb_font_t::synthetic_glyph_extents: <https://github.com/harfbuzz/harfbuzz/blob/88adc6437ef561486a5adf1822410297ef4a852b/src/hb-font.hh>
+    pub(crate) fn extents(&self, mut extents: GlyphExtents) -> GlyphExtents {
+        if self.slant_xy != 0.0 {
+            let x1 = extents.x_bearing as f32;
+            let y1 = extents.y_bearing as f32;
+            let x2 = x1 + extents.width as f32;
+            let y2 = y1 + extents.height as f32;
+            let x1 = x1 + (y1 * self.slant_xy).min(y2 * self.slant_xy).floor();
+            let x2 = x2 + (y1 * self.slant_xy).max(y2 * self.slant_xy).ceil();
+            extents.x_bearing = x1 as i32;
+            extents.width = (x2 - x1) as i32;
+        }
+        if self.x_strength != 0 || self.y_strength != 0 {
+            let y_shift = self.y_shift();
+            extents.y_bearing = extents.y_bearing.saturating_add(y_shift);
+            extents.height = extents.height.saturating_sub(y_shift);
+            let x_shift = self.x_shift();
+            if self.in_place {
+                extents.x_bearing = extents.x_bearing.saturating_sub(x_shift / 2);
+            }
+            extents.width = extents.width.saturating_add(x_shift);
+        }
+        extents
+    }
+
+    /// Returns the horizontal offset that follows the slant for a glyph
+    /// offset vertically by `y_offset`.
+    #[inline(always)]
+    pub(crate) fn slant_offset(&self, y_offset: i32) -> i32 {
+        (self.slant_xy * y_offset as f32).round() as i32
+    }
+
+    #[inline(always)]
+    pub(crate) fn has_slant(&self) -> bool {
+        self.slant_xy != 0.0
+    }
+
+    #[inline(always)]
+    pub(crate) fn has_bold(&self) -> bool {
+        self.x_strength != 0 || self.y_strength != 0
+    }
+
+    fn x_shift(&self) -> i32 {
+        if self.x_neg {
+            -self.x_strength
+        } else {
+            self.x_strength
+        }
+    }
+
+    fn y_shift(&self) -> i32 {
+        if self.y_neg {
+            -self.y_strength
+        } else {
+            self.y_strength
+        }
+    }
+}
+
+// Float positions are shaped at this many internal units per font unit,
+// unless the requested scale is finer.
+const FLOAT_POSITIONS_UNITS_PER_FONT_UNIT: i32 = 256;
+
+/// Conversion from the internal scale that float positions are shaped at
+/// to the requested scale.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct FloatPositions {
+    x_mult: f64,
+    y_mult: f64,
+}
+
+impl FloatPositions {
+    /// Returns the scale to shape at, along with the conversion to apply
+    /// afterwards if float positions are requested.
+    pub(crate) fn resolve(options: &ShapeOptions, upem: i32) -> (Option<(i32, i32)>, Option<Self>) {
+        if !options.float_positions || upem == 0 {
+            return (options.scale, None);
+        }
+        let (x_scale, y_scale) = options.scale.unwrap_or((upem, upem));
+        let fine = upem.saturating_mul(FLOAT_POSITIONS_UNITS_PER_FONT_UNIT);
+        let internal = |scale: i32| {
+            if scale.unsigned_abs() >= fine.unsigned_abs() {
+                scale
+            } else if scale < 0 {
+                -fine
+            } else {
+                fine
+            }
+        };
+        let (x_internal, y_internal) = (internal(x_scale), internal(y_scale));
+        (
+            Some((x_internal, y_internal)),
+            Some(Self {
+                x_mult: f64::from(x_scale) / f64::from(x_internal),
+                y_mult: f64::from(y_scale) / f64::from(y_internal),
+            }),
+        )
+    }
+
+    /// Converts a horizontal value in the requested scale, as returned by
+    /// font functions, to the internal scale.
+    #[inline(always)]
+    pub(crate) fn from_output_x(&self, x: i32) -> i32 {
+        Self::from_output(x, self.x_mult)
+    }
+
+    /// Converts a vertical value in the requested scale, as returned by
+    /// font functions, to the internal scale.
+    #[inline(always)]
+    pub(crate) fn from_output_y(&self, y: i32) -> i32 {
+        Self::from_output(y, self.y_mult)
+    }
+
+    /// Converts extents in the requested scale to the internal scale.
+    pub(crate) fn from_output_extents(&self, extents: GlyphExtents) -> GlyphExtents {
+        GlyphExtents {
+            x_bearing: self.from_output_x(extents.x_bearing),
+            y_bearing: self.from_output_y(extents.y_bearing),
+            width: self.from_output_x(extents.width),
+            height: self.from_output_y(extents.height),
+        }
+    }
+
+    /// Converts the shaped positions to the requested scale, keeping the
+    /// unrounded values alongside the rounded ones.
+    pub(crate) fn finish(&self, buffer: &mut hb_buffer_t) {
+        let positions = &mut buffer.pos[..buffer.len];
+        buffer.pos_f32.clear();
+        buffer.pos_f32.extend(positions.iter_mut().map(|pos| {
+            let pos_f32 = GlyphPositionF32 {
+                x_advance: (f64::from(pos.x_advance) * self.x_mult) as f32,
+                y_advance: (f64::from(pos.y_advance) * self.y_mult) as f32,
+                x_offset: (f64::from(pos.x_offset) * self.x_mult) as f32,
+                y_offset: (f64::from(pos.y_offset) * self.y_mult) as f32,
+            };
+            pos.x_advance = pos_f32.x_advance.round() as i32;
+            pos.y_advance = pos_f32.y_advance.round() as i32;
+            pos.x_offset = pos_f32.x_offset.round() as i32;
+            pos.y_offset = pos_f32.y_offset.round() as i32;
+            pos_f32
+        }));
+        buffer.have_float_positions = true;
+    }
+
+    fn from_output(value: i32, mult: f64) -> i32 {
+        if mult == 0.0 {
+            return 0;
+        }
+        (f64::from(value) / mult).round() as i32
+    }
+}
+
 /// Shapes the buffer content using provided options.
 ///
 /// Consumes the buffer. You can then run [`GlyphBuffer::clear`] to get the [`UnicodeBuffer`] back
@@ -490,11 +997,13 @@ pub struct BasicFontMetrics {
 #[derive(Clone)]
 pub struct hb_font_t<'a> {
     pub(crate) font: FontKind<'a>,
+    pub(crate) data: &'a ShaperData,
     pub(crate) units_per_em: u16,
     pub(crate) cmap_cache: &'a cmap_cache_t,
     pub(crate) ot_tables: OtTables<'a>,
     pub(crate) aat_tables: AatTables<'a>,
     pub(crate) apply_trak: bool,
+    pub(crate) script_shapers: Option<&'a ShaperRegistry>,
 }
 
 impl<'a> crate::Shaper<'a> {
@@ -517,11 +1026,13 @@ impl<'a> crate::Shaper<'a> {
         let aat_tables = AatTables::from_tables(&tables, &ot_tables, &data.aat_cache);
         Some(Self {
             font: FontKind::FontInstance(font, metrics),
+            data,
             units_per_em: data.table_ranges.units_per_em,
             cmap_cache: &data.cmap_cache,
             ot_tables,
             aat_tables,
             apply_trak: data.apply_trak,
+            script_shapers: None,
         })
     }
 
@@ -536,6 +1047,37 @@ impl<'a> crate::Shaper<'a> {
         self.ot_tables.coords
     }
 
+    /// Returns the caret positions of a ligature glyph from the GDEF
+    /// `LigCaretList`, at the current variation instance.
+    ///
+    /// Positions are relative to the glyph origin, along `direction`, and
+    /// are scaled by `scale`, which has the same meaning as in
+    /// [`ShapeOptions::scale_separate`]. The list is empty for glyphs the
+    /// font defines no carets for. Carets defined by a contour point are
+    /// left out, so a ligature may get fewer carets than it has components.
+    /// Variation deltas are applied, but not hinting device deltas.
+    ///
+    /// This is HarfBuzz's `hb_ot_layout_get_ligature_carets`.
+    pub fn ligature_carets(
+        &self,
+        glyph: GlyphId,
+        direction: Direction,
+        scale: Option<(i32, i32)>,
+    ) -> Vec<i32> {
+        let scale = Scale::new(scale, self.units_per_em as i32);
+        self.ot_tables
+            .ligature_carets(glyph)
+            .into_iter()
+            .map(|caret| {
+                if direction.is_horizontal() {
+                    scale.scale_x_f(caret)
+                } else {
+                    scale.scale_y_f(caret)
+                }
+            })
+            .collect()
+    }
+
     /// Shapes the buffer content using provided options.
     ///
     /// Consumes the buffer. You can then run [`GlyphBuffer::clear`] to get the [`UnicodeBuffer`] back
@@ -549,15 +1091,18 @@ impl<'a> crate::Shaper<'a> {
     /// Will panic when debugging assertions are enabled if the buffer and plan have mismatched
     /// properties.    
     pub fn shape(&self, buffer: UnicodeBuffer, options: ShapeOptions<'_>) -> GlyphBuffer {
-        if let Some(plan) = options.plan {
+        if self.resolve_engine(options.engine) == ShapeEngine::Fallback {
+            self.shape_fallback(buffer, options)
+        } else if let Some(plan) = options.plan {
             self.shape_with_plan(plan, buffer, options)
         } else {
-            let plan = ShapePlan::new(
+            let plan = ShapePlan::with_layout_preference(
                 self,
                 buffer.0.direction,
                 buffer.0.script,
                 buffer.0.language.as_ref(),
                 options.features,
+                options.layout_preference,
             );
             self.shape_with_plan(&plan, buffer, options)
         }
@@ -588,18 +1133,82 @@ impl<'a> crate::Shaper<'a> {
         if buffer.len > 0 {
             // Save the original direction, we use it later.
             let target_direction = buffer.direction;
-            let scale = Scale::new(options.scale, self.units_per_em as i32);
-            let mut font_funcs = FontFuncsDispatch::new(self, scale, options.font_funcs);
+            let upem = self.units_per_em as i32;
+            let (scale, float_positions) = FloatPositions::resolve(&options, upem);
+            let synthetic = Synthetic::new(&options, scale, upem);
+            let mut font_funcs = FontFuncsDispatch::new(
+                self,
+                Scale::new(scale, upem),
+                synthetic,
+                float_positions,
+                options.font_funcs,
+                UnicodeFuncsDispatch::new(options.unicode_funcs),
+            );
             OtShapeContext {
                 plan,
                 face: self,
                 buffer: &mut buffer,
                 target_direction,
                 features: options.features,
+                aat_features: options.aat_features,
                 point_size: options.point_size,
+                track: options.track,
+                letter_spacing: options.letter_spacing,
+                normalization_mode: plan.normalization_mode(options.normalization_mode),
                 font_funcs: &mut font_funcs,
+                #[cfg(feature = "profile")]
+                profile: options.profile,
             }
             .shape_internal();
+
+            if let Some(float_positions) = float_positions {
+                float_positions.finish(&mut buffer);
+            }
+        }
+
+        buffer.leave();
+
+        GlyphBuffer(buffer)
+    }
+
+    /// Returns the shaping implementation that [`ShapeEngine::Auto`]
+    /// selects for this font.
+    pub fn default_engine(&self) -> ShapeEngine {
+        if self.data.layout_tables_broken {
+            ShapeEngine::Fallback
+        } else {
+            ShapeEngine::OpenType
+        }
+    }
+
+    fn resolve_engine(&self, engine: ShapeEngine) -> ShapeEngine {
+        match engine {
+            ShapeEngine::Auto => self.default_engine(),
+            engine => engine,
+        }
+    }
+
+    fn shape_fallback(&self, buffer: UnicodeBuffer, options: ShapeOptions<'_>) -> GlyphBuffer {
+        let mut buffer = buffer.0;
+        buffer.enter();
+
+        if buffer.len > 0 {
+            let upem = self.units_per_em as i32;
+            let (scale, float_positions) = FloatPositions::resolve(&options, upem);
+            let synthetic = Synthetic::new(&options, scale, upem);
+            let mut font_funcs = FontFuncsDispatch::new(
+                self,
+                Scale::new(scale, upem),
+                synthetic,
+                float_positions,
+                options.font_funcs,
+                UnicodeFuncsDispatch::new(options.unicode_funcs),
+            );
+            fallback_shape(&mut buffer, &mut font_funcs);
+
+            if let Some(float_positions) = float_positions {
+                float_positions.finish(&mut buffer);
+            }
         }
 
         buffer.leave();
@@ -693,4 +1302,111 @@ mod tests {
         assert_eq!(scaled.width, i32::MAX);
         assert_eq!(scaled.height, i32::MIN);
     }
+
+    #[test]
+    fn synthetic_bold_grows_advances_and_extents() {
+        let options = ShapeOptions::new()
+            .scale(Some(2000))
+            .synthetic_bold(0.02, 0.01, false);
+        let synthetic = Synthetic::new(&options, options.scale, 1000);
+        assert_eq!(synthetic.h_advance(500), 540);
+        assert_eq!(synthetic.h_advance(0), 0);
+        assert_eq!(synthetic.v_advance(-1000), -1020);
+        assert_eq!(synthetic.v_origin((250, 880)), (290, 900));
+        let extents = synthetic.extents(GlyphExtents {
+            x_bearing: 10,
+            y_bearing: 700,
+            width: 400,
+            height: -700,
+        });
+        assert_eq!(extents.x_bearing, 10);
+        assert_eq!(extents.y_bearing, 720);
+        assert_eq!(extents.width, 440);
+        assert_eq!(extents.height, -720);
+    }
+
+    #[test]
+    fn synthetic_bold_follows_negative_scale() {
+        let options = ShapeOptions::new().synthetic_bold(0.02, 0.01, false);
+        let synthetic = Synthetic::new(&options, Some((-2000, -2000)), 1000);
+        assert_eq!(synthetic.h_advance(-500), -540);
+        assert_eq!(synthetic.v_advance(1000), 1020);
+        assert_eq!(synthetic.v_origin((-250, -880)), (-290, -900));
+    }
+
+    #[test]
+    fn synthetic_bold_in_place_keeps_advances() {
+        let options = ShapeOptions::new().synthetic_bold(0.02, 0.02, true);
+        let synthetic = Synthetic::new(&options, options.scale, 1000);
+        assert_eq!(synthetic.h_advance(500), 500);
+        assert_eq!(synthetic.v_advance(-1000), -1000);
+        assert_eq!(synthetic.v_origin((250, 880)), (250, 880));
+        let extents = synthetic.extents(GlyphExtents {
+            x_bearing: 10,
+            y_bearing: 700,
+            width: 400,
+            height: -700,
+        });
+        assert_eq!(extents.x_bearing, 0);
+        assert_eq!(extents.width, 420);
+    }
+
+    #[test]
+    fn synthetic_slant_widens_extents() {
+        let options = ShapeOptions::new().synthetic_slant(0.25);
+        let synthetic = Synthetic::new(&options, options.scale, 1000);
+        assert_eq!(synthetic.slant_offset(-30), -8);
+        assert_eq!(synthetic.slant_offset(400), 100);
+        let extents = synthetic.extents(GlyphExtents {
+            x_bearing: 10,
+            y_bearing: 700,
+            width: 400,
+            height: -900,
+        });
+        assert_eq!(extents.x_bearing, -40);
+        assert_eq!(extents.width, 625);
+    }
+
+    fn cvar_gvar_font() -> FontRef<'static> {
+        let font_data =
+            include_bytes!("../../tests/fonts/text-rendering-tests/TestCVARGVAROne.ttf");
+        FontRef::new(font_data).unwrap()
+    }
+
+    fn variations(opsz: Option<f32>) -> Vec<Variation> {
+        let mut variations = vec![(Tag::new(b"wght"), 94.0).into()];
+        variations.extend(opsz.map(|value| Variation { tag: OPSZ, value }));
+        variations
+    }
+
+    #[test]
+    fn optical_size_sets_implicit_opsz() {
+        let font = cvar_gvar_font();
+        let expected = ShaperInstance::from_variations(&font, variations(Some(72.0)));
+        let mut instance = ShaperInstance::from_variations(&font, variations(None));
+        assert_ne!(instance.coords(), expected.coords());
+        instance.set_optical_size(&font, Some(72.0));
+        assert_eq!(instance.coords(), expected.coords());
+        assert_eq!(instance.feature_variations, expected.feature_variations);
+        // The optical size survives a change of variations...
+        instance.set_variations(&font, variations(None));
+        assert_eq!(instance.coords(), expected.coords());
+        // ...and can be turned off again.
+        instance.set_optical_size(&font, None);
+        let default = ShaperInstance::from_variations(&font, variations(None));
+        assert_eq!(instance.coords(), default.coords());
+    }
+
+    #[test]
+    fn optical_size_keeps_explicit_opsz() {
+        let font = cvar_gvar_font();
+        let mut instance = ShaperInstance::from_variations(&font, variations(Some(10.0)));
+        let expected = instance.coords().to_vec();
+        instance.set_optical_size(&font, Some(72.0));
+        assert_eq!(instance.coords(), expected);
+
+        let mut instance = ShaperInstance::from_coords(&font, expected.iter().copied());
+        instance.set_optical_size(&font, Some(72.0));
+        assert_eq!(instance.coords(), expected);
+    }
 }
@@ -0,0 +1,87 @@
+use super::buffer::{hb_buffer_t, GlyphFlags, GlyphInfo};
+use super::font_funcs::{AdvanceWidthBatch, FontFuncsDispatch};
+use super::ot_shape::form_clusters;
+use crate::BufferFlags;
+
+// _hb_fallback_shape: <https://github.com/harfbuzz/harfbuzz/blob/main/src/hb-fallback-shape.cc>
+pub(crate) fn fallback_shape(buffer: &mut hb_buffer_t, font_funcs: &mut FontFuncsDispatch<'_, '_>) {
+    buffer.allocate_unicode_vars();
+
+    let len = buffer.len;
+    let unicode = font_funcs.unicode();
+    for info in &mut buffer.info[..len] {
+        info.init_unicode_props(unicode, &mut buffer.scratch_flags);
+    }
+
+    form_clusters(buffer);
+
+    // Unlike HarfBuzz, which always replaces default-ignorables with the
+    // space glyph here, honor the buffer flags the same way the OpenType
+    // shaper does.
+    let hide_default_ignorables = !buffer
+        .flags
+        .contains(BufferFlags::PRESERVE_DEFAULT_IGNORABLES);
+    let invisible = if hide_default_ignorables
+        && !buffer
+            .flags
+            .contains(BufferFlags::REMOVE_DEFAULT_IGNORABLES)
+    {
+        buffer
+            .invisible
+            .or_else(|| font_funcs.nominal_glyph(u32::from(' ')))
+    } else {
+        None
+    };
+
+    for info in &mut buffer.info[..len] {
+        if hide_default_ignorables && info.is_default_ignorable() {
+            if let Some(invisible) = invisible {
+                info.glyph_id = invisible.to_u32();
+                continue;
+            }
+        }
+        info.glyph_id = font_funcs
+            .nominal_glyph(info.glyph_id)
+            .map_or(0, |glyph| glyph.to_u32());
+    }
+
+    if hide_default_ignorables && invisible.is_none() {
+        buffer.delete_glyphs_inplace(GlyphInfo::is_default_ignorable);
+    }
+
+    buffer.clear_positions();
+
+    let len = buffer.len;
+    if buffer.direction.is_horizontal() {
+        font_funcs.populate_advance_widths(AdvanceWidthBatch::new(buffer));
+    } else {
+        for (info, pos) in buffer.info[..len].iter().zip(&mut buffer.pos[..len]) {
+            let glyph = info.as_glyph();
+            pos.y_advance = font_funcs.advance_height(glyph);
+            let (x, y) = font_funcs.vertical_origin(glyph);
+            pos.x_offset = pos.x_offset.saturating_sub(x);
+            pos.y_offset = pos.y_offset.saturating_sub(y);
+        }
+    }
+
+    if hide_default_ignorables {
+        for (info, pos) in buffer.info[..len].iter().zip(&mut buffer.pos[..len]) {
+            if info.is_default_ignorable() {
+                pos.x_advance = 0;
+                pos.y_advance = 0;
+                pos.x_offset = 0;
+                pos.y_offset = 0;
+            }
+        }
+    }
+
+    if buffer.direction.is_backward() {
+        buffer.reverse();
+    }
+
+    for info in &mut buffer.info[..len] {
+        info.mask &= !GlyphFlags::DEFINED_BITS;
+    }
+
+    buffer.deallocate_unicode_vars();
+}
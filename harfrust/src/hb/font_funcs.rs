@@ -7,11 +7,12 @@ use read_fonts::types::GlyphId;
 
 use crate::hb::charmap::Charmap;
 use crate::hb::face::FontKind;
-use crate::hb::face::Scale;
+use crate::hb::face::{FloatPositions, Scale, Synthetic};
 use crate::hb::glyph_metrics::GlyphMetrics;
 
 use super::buffer::{hb_buffer_t, GlyphInfo, GlyphPosition};
 use super::face::{hb_font_t, GlyphExtents};
+use super::unicode_funcs::UnicodeFuncsDispatch;
 
 /// Raw C-style view over a batch of glyph ids and advance widths.
 #[derive(Clone, Copy, Debug)]
@@ -396,22 +397,36 @@ pub trait FontFuncs {
 pub(crate) struct FontFuncsDispatch<'a, 'u> {
     builtin: BuiltinFontFuncs<'a>,
     scale: Scale,
+    synthetic: Synthetic,
+    float_positions: Option<FloatPositions>,
     funcs: Option<&'u mut (dyn FontFuncs + 'u)>,
+    unicode: UnicodeFuncsDispatch<'u>,
 }
 
 impl<'a, 'u> FontFuncsDispatch<'a, 'u> {
     pub(crate) fn new(
         face: &'a hb_font_t<'a>,
         scale: Scale,
+        synthetic: Synthetic,
+        float_positions: Option<FloatPositions>,
         funcs: Option<&'u mut (dyn FontFuncs + 'u)>,
+        unicode: UnicodeFuncsDispatch<'u>,
     ) -> Self {
         Self {
             builtin: BuiltinFontFuncs::new(face),
             scale,
+            synthetic,
+            float_positions,
             funcs,
+            unicode,
         }
     }
 
+    #[inline(always)]
+    pub(crate) fn unicode(&self) -> UnicodeFuncsDispatch<'u> {
+        self.unicode
+    }
+
     #[inline(always)]
     pub(crate) fn font(&self) -> &'a hb_font_t<'a> {
         self.builtin.face
@@ -422,6 +437,11 @@ impl<'a, 'u> FontFuncsDispatch<'a, 'u> {
         &self.scale
     }
 
+    #[inline(always)]
+    pub(crate) fn synthetic(&self) -> &Synthetic {
+        &self.synthetic
+    }
+
     #[inline(always)]
     fn scale_x(&self, value: i32) -> i32 {
         self.scale.scale_x(value)
@@ -493,52 +513,87 @@ impl<'a, 'u> FontFuncsDispatch<'a, 'u> {
         }
     }
 
+    // Synthetic bold and slant are applied on top of the font functions,
+    // like HarfBuzz's hb_font_t does. Font functions return values in the
+    // requested scale, which differs from the internal one when float
+    // positions are requested.
+
     #[inline(always)]
     pub(crate) fn advance_width(&mut self, glyph: GlyphId) -> i32 {
-        if let Some(funcs) = &mut self.funcs {
-            funcs.advance_width(&self.builtin, glyph)
+        let advance = if let Some(funcs) = &mut self.funcs {
+            let advance = funcs.advance_width(&self.builtin, glyph);
+            self.float_positions
+                .map_or(advance, |float| float.from_output_x(advance))
         } else {
             self.scale_x(self.builtin.advance_width(glyph))
-        }
+        };
+        self.synthetic.h_advance(advance)
     }
 
     #[inline(always)]
     pub(crate) fn advance_height(&mut self, glyph: GlyphId) -> i32 {
-        if let Some(funcs) = &mut self.funcs {
-            funcs.advance_height(&self.builtin, glyph)
+        let advance = if let Some(funcs) = &mut self.funcs {
+            let advance = funcs.advance_height(&self.builtin, glyph);
+            self.float_positions
+                .map_or(advance, |float| float.from_output_y(advance))
         } else {
             self.scale_y(self.builtin.advance_height(glyph))
-        }
+        };
+        self.synthetic.v_advance(advance)
     }
 
     #[inline(always)]
     pub(crate) fn vertical_origin(&mut self, glyph: GlyphId) -> (i32, i32) {
-        if let Some(funcs) = &mut self.funcs {
-            funcs.vertical_origin(&self.builtin, glyph)
+        let origin = if let Some(funcs) = &mut self.funcs {
+            let (x, y) = funcs.vertical_origin(&self.builtin, glyph);
+            self.float_positions.map_or((x, y), |float| {
+                (float.from_output_x(x), float.from_output_y(y))
+            })
         } else {
             self.scale_point(self.builtin.vertical_origin(glyph))
-        }
+        };
+        self.synthetic.v_origin(origin)
     }
 
     #[inline(always)]
     pub(crate) fn extents(&mut self, glyph: GlyphId) -> Option<GlyphExtents> {
-        if let Some(funcs) = &mut self.funcs {
-            funcs.extents(&self.builtin, glyph)
+        let extents = if let Some(funcs) = &mut self.funcs {
+            let extents = funcs.extents(&self.builtin, glyph)?;
+            self.float_positions
+                .map_or(extents, |float| float.from_output_extents(extents))
         } else {
-            Some(self.scale_extents(self.builtin.extents(glyph)?))
-        }
+            self.scale_extents(self.builtin.extents(glyph)?)
+        };
+        Some(self.synthetic.extents(extents))
     }
 
     pub(crate) fn populate_advance_widths(&mut self, batch: AdvanceWidthBatch<'_>) {
+        let AdvanceWidthBatch { infos, positions } = batch;
         if let Some(funcs) = &mut self.funcs {
-            funcs.populate_advance_widths(&self.builtin, batch);
+            funcs.populate_advance_widths(
+                &self.builtin,
+                AdvanceWidthBatch {
+                    infos,
+                    positions: &mut *positions,
+                },
+            );
+            if let Some(float) = self.float_positions {
+                for pos in &mut *positions {
+                    pos.x_advance = float.from_output_x(pos.x_advance);
+                }
+            }
         } else {
             self.builtin.glyph_metrics().populate_advance_widths(
-                batch.infos,
-                batch.positions,
+                infos,
+                positions,
                 self.builtin.coords(),
                 self.scale,
             );
         }
+        if self.synthetic.has_bold() {
+            for pos in positions {
+                pos.x_advance = self.synthetic.h_advance(pos.x_advance);
+            }
+        }
     }
 }
@@ -0,0 +1,394 @@
+//! Glyph bounds computed from outlines, for the cases where the bounding box
+//! stored in the `glyf` table can't be used: variable `glyf` fonts and
+//! CFF/CFF2 fonts.
+
+use alloc::vec::Vec;
+use read_fonts::{
+    ps::{cff::CffFontRef, cs::CommandSink},
+    tables::{
+        glyf::{Anchor, CompositeGlyphFlags, Glyf, Glyph},
+        gvar::{GlyphVariationData, Gvar},
+        loca::Loca,
+    },
+    types::{BoundingBox, F2Dot14, Fixed, GlyphId, Point},
+};
+
+// libm used for f32::round()
+#[cfg(not(feature = "std"))]
+#[allow(unused_imports)]
+use core_maths::CoreFloat as _;
+
+// HB_MAX_NESTING_LEVEL
+const MAX_NESTING_LEVEL: usize = 64;
+
+/// Accumulates the bounds of a set of points.
+#[derive(Default)]
+struct BoundsBuilder {
+    bounds: Option<BoundingBox<f32>>,
+}
+
+impl BoundsBuilder {
+    fn add(&mut self, x: f32, y: f32) {
+        let bounds = self.bounds.get_or_insert(BoundingBox {
+            x_min: x,
+            y_min: y,
+            x_max: x,
+            y_max: y,
+        });
+        bounds.x_min = bounds.x_min.min(x);
+        bounds.y_min = bounds.y_min.min(y);
+        bounds.x_max = bounds.x_max.max(x);
+        bounds.y_max = bounds.y_max.max(y);
+    }
+
+    /// Rounds the bounds the way HarfBuzz rounds glyph extents. Empty or
+    /// degenerate outlines have empty bounds.
+    fn finish(self) -> BoundingBox<i32> {
+        let Some(bounds) = self.bounds else {
+            return BoundingBox::default();
+        };
+        let (x_min, x_max) = if bounds.x_min < bounds.x_max {
+            (bounds.x_min.round() as i32, bounds.x_max.round() as i32)
+        } else {
+            (0, 0)
+        };
+        let (y_min, y_max) = if bounds.y_min < bounds.y_max {
+            (bounds.y_min.round() as i32, bounds.y_max.round() as i32)
+        } else {
+            (0, 0)
+        };
+        BoundingBox {
+            x_min,
+            y_min,
+            x_max,
+            y_max,
+        }
+    }
+}
+
+/// Accumulates charstring bounds in font units.
+///
+/// Unlike drawing through an outline pen, this keeps the fractional part of
+/// the charstring coordinates, like HarfBuzz does.
+#[derive(Default)]
+struct CffBoundsSink {
+    builder: BoundsBuilder,
+    // Moves only count once something is drawn from them.
+    pending_move: Option<(Fixed, Fixed)>,
+}
+
+impl CffBoundsSink {
+    fn add(&mut self, x: Fixed, y: Fixed) {
+        if let Some((x, y)) = self.pending_move.take() {
+            self.builder.add(x.to_f32(), y.to_f32());
+        }
+        self.builder.add(x.to_f32(), y.to_f32());
+    }
+}
+
+impl CommandSink for CffBoundsSink {
+    fn move_to(&mut self, x: Fixed, y: Fixed) {
+        self.pending_move = Some((x, y));
+    }
+
+    fn line_to(&mut self, x: Fixed, y: Fixed) {
+        self.add(x, y);
+    }
+
+    fn curve_to(&mut self, cx0: Fixed, cy0: Fixed, cx1: Fixed, cy1: Fixed, x: Fixed, y: Fixed) {
+        self.add(cx0, cy0);
+        self.add(cx1, cy1);
+        self.add(x, y);
+    }
+
+    fn close(&mut self) {}
+}
+
+/// Returns the bounds of a CFF or CFF2 glyph, including its control points.
+pub(crate) fn cff_bounds(
+    cff: &CffFontRef,
+    gid: GlyphId,
+    coords: &[F2Dot14],
+) -> Option<BoundingBox<i32>> {
+    let subfont = cff.subfont(cff.subfont_index(gid)?, coords).ok()?;
+    let mut sink = CffBoundsSink::default();
+    cff.evaluate_charstring(&subfont, gid, coords, &mut sink)
+        .ok()?;
+    Some(sink.builder.finish())
+}
+
+/// Returns the bounds of a `glyf` glyph with variations applied.
+///
+/// `x_shift` is added to all points.
+pub(crate) fn glyf_var_bounds(
+    glyf: &Glyf,
+    loca: &Loca,
+    gvar: &Gvar,
+    gid: GlyphId,
+    coords: &[F2Dot14],
+    x_shift: f32,
+) -> Option<BoundingBox<i32>> {
+    let mut points = Vec::new();
+    let ctx = GlyfContext {
+        glyf,
+        loca,
+        gvar,
+        coords,
+    };
+    ctx.append_points(gid, &mut points, 0)?;
+    let mut builder = BoundsBuilder::default();
+    for point in &points {
+        builder.add(point.x + x_shift, point.y);
+    }
+    Some(builder.finish())
+}
+
+struct GlyfContext<'a> {
+    glyf: &'a Glyf<'a>,
+    loca: &'a Loca<'a>,
+    gvar: &'a Gvar<'a>,
+    coords: &'a [F2Dot14],
+}
+
+impl<'a> GlyfContext<'a> {
+    /// Appends the varied outline points of the glyph to `points`.
+    fn append_points(
+        &self,
+        gid: GlyphId,
+        points: &mut Vec<Point<f32>>,
+        depth: usize,
+    ) -> Option<()> {
+        if depth > MAX_NESTING_LEVEL {
+            return None;
+        }
+        let Some(glyph) = self.loca.get_glyf(gid, self.glyf).ok()? else {
+            return Some(());
+        };
+        let var_data = self.gvar.glyph_variation_data(gid).ok().flatten();
+        match glyph {
+            Glyph::Simple(simple) => {
+                let start = points.len();
+                points.extend(
+                    simple
+                        .points()
+                        .map(|point| Point::new(f32::from(point.x), f32::from(point.y))),
+                );
+                if let Some(var_data) = var_data {
+                    let ends = simple.end_pts_of_contours();
+                    let ends = ends.iter().map(|end| usize::from(end.get()));
+                    self.apply_point_deltas(&mut points[start..], ends, &var_data);
+                }
+            }
+            Glyph::Composite(composite) => {
+                let components: Vec<_> = composite.components().collect();
+                let mut offsets: Vec<Point<f32>> = components
+                    .iter()
+                    .map(|component| match component.anchor {
+                        Anchor::Offset { x, y } => Point::new(f32::from(x), f32::from(y)),
+                        Anchor::Point { .. } => Point::default(),
+                    })
+                    .collect();
+                if let Some(var_data) = var_data {
+                    for (tuple, scalar) in var_data.active_tuples_at(self.coords) {
+                        for delta in tuple.deltas() {
+                            if let Some(offset) = offsets.get_mut(delta.position as usize) {
+                                *offset += delta.apply_scalar::<f32>(scalar);
+                            }
+                        }
+                    }
+                }
+                let base = points.len();
+                for (component, mut offset) in components.iter().zip(offsets) {
+                    let start = points.len();
+                    self.append_points(component.glyph.into(), points, depth + 1)?;
+                    let transform = &component.transform;
+                    let matrix = [
+                        transform.xx.to_f32(),
+                        transform.yx.to_f32(),
+                        transform.xy.to_f32(),
+                        transform.yy.to_f32(),
+                    ];
+                    let apply_matrix = |p: Point<f32>| {
+                        Point::new(
+                            p.x * matrix[0] + p.y * matrix[2],
+                            p.x * matrix[1] + p.y * matrix[3],
+                        )
+                    };
+                    let flags = component.flags;
+                    if let Anchor::Point {
+                        base: base_point,
+                        component: component_point,
+                    } = component.anchor
+                    {
+                        for point in &mut points[start..] {
+                            *point = apply_matrix(*point);
+                        }
+                        let base_point = points.get(base + usize::from(base_point))?;
+                        let component_point = points.get(start + usize::from(component_point))?;
+                        offset = *base_point - *component_point;
+                        for point in &mut points[start..] {
+                            *point += offset;
+                        }
+                        continue;
+                    }
+                    if flags.contains(CompositeGlyphFlags::ROUND_XY_TO_GRID) {
+                        offset = Point::new(offset.x.round(), offset.y.round());
+                    }
+                    let scaled_offset = flags
+                        .contains(CompositeGlyphFlags::SCALED_COMPONENT_OFFSET)
+                        && !flags.contains(CompositeGlyphFlags::UNSCALED_COMPONENT_OFFSET);
+                    for point in &mut points[start..] {
+                        *point = if scaled_offset {
+                            apply_matrix(*point + offset)
+                        } else {
+                            apply_matrix(*point) + offset
+                        };
+                    }
+                }
+            }
+        }
+        Some(())
+    }
+
+    /// Applies the deltas of all active tuples to the points of a simple
+    /// glyph, inferring the deltas of untouched points.
+    fn apply_point_deltas(
+        &self,
+        points: &mut [Point<f32>],
+        ends: impl Iterator<Item = usize> + Clone,
+        var_data: &GlyphVariationData<'a>,
+    ) {
+        let orig: Vec<_> = points.to_vec();
+        let mut deltas = alloc::vec![Point::<f32>::default(); points.len()];
+        let mut touched = alloc::vec![false; points.len()];
+        for (tuple, scalar) in var_data.active_tuples_at(self.coords) {
+            if tuple.has_deltas_for_all_points() {
+                for delta in tuple.deltas() {
+                    if let Some(point) = points.get_mut(delta.position as usize) {
+                        *point += delta.apply_scalar::<f32>(scalar);
+                    }
+                }
+                continue;
+            }
+            deltas.fill(Point::default());
+            touched.fill(false);
+            for delta in tuple.deltas() {
+                let idx = delta.position as usize;
+                if idx < points.len() {
+                    deltas[idx] = delta.apply_scalar::<f32>(scalar);
+                    touched[idx] = true;
+                }
+            }
+            let mut start = 0;
+            for end in ends.clone() {
+                if end < start || end >= points.len() {
+                    break;
+                }
+                infer_deltas(&orig, &mut deltas, &touched, start, end);
+                start = end + 1;
+            }
+            for (point, delta) in points.iter_mut().zip(&deltas) {
+                *point += *delta;
+            }
+        }
+    }
+}
+
+/// Interpolates the deltas of the untouched points of the contour
+/// `start..=end` from the deltas of the touched points around them.
+fn infer_deltas(
+    orig: &[Point<f32>],
+    deltas: &mut [Point<f32>],
+    touched: &[bool],
+    start: usize,
+    end: usize,
+) {
+    let Some(first) = (start..=end).find(|&i| touched[i]) else {
+        return;
+    };
+    let next = |i: usize| if i == end { start } else { i + 1 };
+    let mut prev = first;
+    loop {
+        let mut cur = next(prev);
+        while !touched[cur] {
+            cur = next(cur);
+        }
+        let mut i = next(prev);
+        while i != cur {
+            deltas[i] = Point::new(
+                interpolate(
+                    orig[i].x,
+                    (orig[prev].x, deltas[prev].x),
+                    (orig[cur].x, deltas[cur].x),
+                ),
+                interpolate(
+                    orig[i].y,
+                    (orig[prev].y, deltas[prev].y),
+                    (orig[cur].y, deltas[cur].y),
+                ),
+            );
+            i = next(i);
+        }
+        if cur == first {
+            break;
+        }
+        prev = cur;
+    }
+}
+
+/// Interpolates the delta of a coordinate from two reference points, given
+/// as `(coordinate, delta)` pairs.
+// Coordinates are integral, so exact comparison is intended.
+#[allow(clippy::float_cmp)]
+fn interpolate(x: f32, (x1, d1): (f32, f32), (x2, d2): (f32, f32)) -> f32 {
+    if x1 == x2 {
+        return if d1 == d2 { d1 } else { 0.0 };
+    }
+    let ((x1, d1), (x2, d2)) = if x1 < x2 {
+        ((x1, d1), (x2, d2))
+    } else {
+        ((x2, d2), (x1, d1))
+    };
+    if x <= x1 {
+        d1
+    } else if x >= x2 {
+        d2
+    } else {
+        d1 + (x - x1) * (d2 - d1) / (x2 - x1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infer_deltas_interpolates_between_touched_points() {
+        let orig = [
+            Point::new(0.0, 0.0),
+            Point::new(50.0, 10.0),
+            Point::new(100.0, 20.0),
+            Point::new(150.0, 30.0),
+        ];
+        let mut deltas = [
+            Point::new(10.0, 0.0),
+            Point::default(),
+            Point::new(20.0, 4.0),
+            Point::default(),
+        ];
+        let touched = [true, false, true, false];
+        infer_deltas(&orig, &mut deltas, &touched, 0, 3);
+        assert_eq!(deltas[1], Point::new(15.0, 2.0));
+        // Outside the range of the reference points, the delta of the
+        // nearest one is used.
+        assert_eq!(deltas[3], Point::new(20.0, 4.0));
+    }
+
+    #[test]
+    fn infer_deltas_shifts_contour_with_single_touched_point() {
+        let orig = [Point::new(0.0, 0.0), Point::new(10.0, 20.0)];
+        let mut deltas = [Point::default(), Point::new(3.0, -2.0)];
+        infer_deltas(&orig, &mut deltas, &[false, true], 0, 1);
+        assert_eq!(deltas[0], Point::new(3.0, -2.0));
+    }
+}
@@ -1,12 +1,16 @@
 use crate::{
     hb::{
         face::{BasicFontMetrics, Scale},
+        glyph_bounds,
         tables::TableRanges,
     },
     GlyphExtents, GlyphInfo, GlyphPosition, Tag,
 };
 use read_fonts::{
+    ps::cff::CffFontRef,
     tables::{
+        cff::Cff,
+        cff2::Cff2,
         glyf::Glyf,
         gvar::Gvar,
         hmtx::{Hmtx, LongMetric},
@@ -18,7 +22,7 @@ use read_fonts::{
         vvar::Vvar,
     },
     types::{BoundingBox, F2Dot14, Fixed, GlyphId, Point},
-    FontRef, TableProvider,
+    FontRef, TableProvider, TopLevelTable,
 };
 
 #[derive(Clone)]
@@ -30,6 +34,7 @@ pub struct GlyphMetrics<'a> {
     vvar: Option<Vvar<'a>>,
     vorg: Option<Vorg<'a>>,
     glyf: Option<GlyfTables<'a>>,
+    cff: Option<CffFontRef<'a>>,
     mvar: Option<Mvar<'a>>,
     num_glyphs: u32,
     upem: u16,
@@ -74,6 +79,17 @@ impl<'a> GlyphMetrics<'a> {
         } else {
             None
         };
+        let cff = load_cff(
+            table_ranges
+                .cff2
+                .resolve_data(font)
+                .map(|data| data.as_bytes()),
+            table_ranges
+                .cff
+                .resolve_data(font)
+                .map(|data| data.as_bytes()),
+            upem,
+        );
         let mvar = table_ranges.mvar.resolve_table(font);
         let ascent = table_ranges.ascent;
         let descent = table_ranges.descent;
@@ -85,6 +101,7 @@ impl<'a> GlyphMetrics<'a> {
             vvar,
             vorg,
             glyf,
+            cff,
             mvar,
             num_glyphs,
             upem,
@@ -111,6 +128,11 @@ impl<'a> GlyphMetrics<'a> {
         } else {
             None
         };
+        let cff = load_cff(
+            font.data_for_tag(Cff2::TAG).map(|data| data.as_bytes()),
+            font.data_for_tag(Cff::TAG).map(|data| data.as_bytes()),
+            metrics.units_per_em,
+        );
         let mvar = font.mvar().ok();
         Self {
             _hmtx: hmtx,
@@ -120,6 +142,7 @@ impl<'a> GlyphMetrics<'a> {
             vvar,
             vorg,
             glyf,
+            cff,
             mvar,
             num_glyphs: metrics.num_glyphs,
             upem: metrics.units_per_em,
@@ -228,7 +251,7 @@ impl<'a> GlyphMetrics<'a> {
             .and_then(|vmtx| vmtx.advance(gid))
             .map(|advance| advance as i32)
         else {
-            return Some(self.ascent as i32 - self.descent as i32);
+            return Some(self.ascender(coords).saturating_sub(self.descender(coords)));
         };
         if !coords.is_empty() {
             if let Some(vvar) = self.vvar.as_ref() {
@@ -258,7 +281,14 @@ impl<'a> GlyphMetrics<'a> {
                 bearing = bearing
                     .saturating_add(vvar.tsb_delta(gid, coords).unwrap_or_default().to_i32());
             } else if let Some(deltas) = self.phantom_deltas(gid, coords) {
-                bearing = bearing.saturating_add(deltas[3].y.to_i32());
+                // The top phantom point varies independently of the outline,
+                // so measure the bearing from the varied bounds.
+                let header_y_max = self.glyf_header_bounds(gid)?.y_max;
+                let y_max = self.bounds(gid, coords)?.y_max;
+                bearing = bearing
+                    .saturating_add(header_y_max)
+                    .saturating_add(deltas[3].y.to_i32())
+                    .saturating_sub(y_max);
             }
         }
         Some(bearing)
@@ -299,49 +329,72 @@ impl<'a> GlyphMetrics<'a> {
             if let Some(origin) = origin {
                 origin
             } else {
-                let mut advance = self.ascent as i32 - self.descent as i32;
-                if let Some(mvar) = self.mvar.as_ref() {
-                    advance = advance
-                        .saturating_add(
-                            mvar.metric_delta(Tag::new(b"hasc"), coords)
-                                .unwrap_or_default()
-                                .to_i32(),
-                        )
-                        .saturating_sub(
-                            mvar.metric_delta(Tag::new(b"hdsc"), coords)
-                                .unwrap_or_default()
-                                .to_i32(),
-                        );
-                }
+                let advance = self.ascender(coords).saturating_sub(self.descender(coords));
                 let height = extents.y_max.saturating_sub(extents.y_min);
                 let diff = advance.saturating_sub(height);
                 extents.y_max.saturating_add(diff >> 1)
             }
         } else {
-            let mut ascent = self.ascent as i32;
-            if let Some(mvar) = self.mvar.as_ref() {
-                ascent = ascent.saturating_add(
-                    mvar.metric_delta(Tag::new(b"hasc"), coords)
-                        .unwrap_or_default()
-                        .to_i32(),
-                );
-            }
-            ascent
+            self.ascender(coords)
         };
         Some(origin)
     }
 
+    /// Returns the font ascender, with variations applied. Like HarfBuzz,
+    /// the ascender is always positive and the descender always negative.
+    fn ascender(&self, coords: &[F2Dot14]) -> i32 {
+        i32::from(self.ascent)
+            .saturating_add(self.metric_delta(Tag::new(b"hasc"), coords))
+            .saturating_abs()
+    }
+
+    /// Returns the font descender, with variations applied.
+    fn descender(&self, coords: &[F2Dot14]) -> i32 {
+        i32::from(self.descent)
+            .saturating_add(self.metric_delta(Tag::new(b"hdsc"), coords))
+            .saturating_abs()
+            .saturating_neg()
+    }
+
+    fn metric_delta(&self, tag: Tag, coords: &[F2Dot14]) -> i32 {
+        if coords.is_empty() {
+            return 0;
+        }
+        self.mvar
+            .as_ref()
+            .and_then(|mvar| mvar.metric_delta(tag, coords).ok())
+            .map_or(0, |delta| delta.to_i32())
+    }
+
+    #[allow(clippy::cast_precision_loss)]
     fn bounds(&self, gid: impl Into<GlyphId>, coords: &[F2Dot14]) -> Option<BoundingBox<i32>> {
         let gid = gid.into();
+        let Some(glyf) = self.glyf.as_ref() else {
+            return glyph_bounds::cff_bounds(self.cff.as_ref()?, gid, coords);
+        };
+        let header_bounds = self.glyf_header_bounds(gid)?;
+        let Some(gvar) = glyf.gvar.as_ref().filter(|_| !coords.is_empty()) else {
+            return Some(header_bounds);
+        };
+        // HarfBuzz shifts the varied outline so that the left side bearing
+        // phantom point is at the origin.
+        let mut x_shift = self
+            .phantom_deltas(gid, coords)
+            .map_or(0.0, |deltas| -deltas[0].x.to_f32());
+        if let Some(lsb) = self._hmtx.as_ref().and_then(|hmtx| hmtx.side_bearing(gid)) {
+            x_shift += (i32::from(lsb) - header_bounds.x_min) as f32;
+        }
+        glyph_bounds::glyf_var_bounds(&glyf.glyf, &glyf.loca, gvar, gid, coords, x_shift)
+    }
+
+    /// Returns the bounding box stored in the `glyf` table, ignoring variations.
+    fn glyf_header_bounds(&self, gid: GlyphId) -> Option<BoundingBox<i32>> {
         let glyf = self.glyf.as_ref()?;
         let glyph = glyf.loca.get_glyf(gid, &glyf.glyf).ok()?;
         let Some(glyph) = glyph else {
             // Return empty extents for empty glyph
             return Some(BoundingBox::default());
         };
-        if !coords.is_empty() {
-            return None; // TODO https://github.com/harfbuzz/harfrust/pull/52#issuecomment-2878117808
-        }
         Some(BoundingBox {
             x_min: glyph.x_min() as i32,
             y_min: glyph.y_min() as i32,
@@ -355,21 +408,12 @@ impl<'a> GlyphMetrics<'a> {
         gid: impl Into<GlyphId>,
         coords: &[F2Dot14],
     ) -> Option<GlyphExtents> {
-        let gid = gid.into();
-        let glyf = self.glyf.as_ref()?;
-        let glyph = glyf.loca.get_glyf(gid, &glyf.glyf).ok()?;
-        let Some(glyph) = glyph else {
-            // Return empty extents for empty glyph
-            return Some(GlyphExtents::default());
-        };
-        if !coords.is_empty() {
-            return None; // TODO https://github.com/harfbuzz/harfrust/pull/52#issuecomment-2878117808
-        }
+        let bounds = self.bounds(gid, coords)?;
         Some(GlyphExtents {
-            x_bearing: glyph.x_min() as i32,
-            y_bearing: glyph.y_max() as i32,
-            width: glyph.x_max() as i32 - glyph.x_min() as i32,
-            height: glyph.y_min() as i32 - glyph.y_max() as i32,
+            x_bearing: bounds.x_min,
+            y_bearing: bounds.y_max,
+            width: bounds.x_max - bounds.x_min,
+            height: bounds.y_min - bounds.y_max,
         })
     }
 
@@ -380,3 +424,14 @@ impl<'a> GlyphMetrics<'a> {
             .ok()?
     }
 }
+
+/// Loads the CFF2 table, or the CFF table if there is no CFF2 table.
+fn load_cff<'a>(
+    cff2: Option<&'a [u8]>,
+    cff: Option<&'a [u8]>,
+    upem: u16,
+) -> Option<CffFontRef<'a>> {
+    let upem = Some(i32::from(upem));
+    cff2.and_then(|data| CffFontRef::new_cff2(data, upem).ok())
+        .or_else(|| cff.and_then(|data| CffFontRef::new_cff(data, 0, upem).ok()))
+}
@@ -0,0 +1,585 @@
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::ops::Range;
+
+use read_fonts::types::{F2Dot14, Fixed, Tag};
+use read_fonts::{FontRef, TableProvider};
+use smallvec::SmallVec;
+
+use super::face::FontKind;
+use crate::algs::solve_itp;
+use crate::unicode::{CharExt, GeneralCategory};
+use crate::{
+    BufferFlags, GlyphBuffer, GlyphInfo, ShapeOptions, ShapePlan, ShaperInstance, UnicodeBuffer,
+    Variation,
+};
+
+/// Axes searched, in order, when no justification axis is requested.
+const JUSTIFY_AXES: [Tag; 2] = [Tag::new(b"jstf"), Tag::new(b"wdth")];
+
+struct JustifyAxis {
+    index: usize,
+    tag: Tag,
+    min_value: f64,
+    default_value: f64,
+    max_value: f64,
+}
+
+impl JustifyAxis {
+    fn find(font: &FontRef, tag: Option<Tag>) -> Option<Self> {
+        let axes = font.fvar().ok()?.axes().ok()?;
+        let find = |tag: Tag| {
+            axes.iter()
+                .enumerate()
+                .find(|(_, axis)| axis.axis_tag() == tag)
+                .map(|(index, axis)| Self {
+                    index,
+                    tag,
+                    min_value: axis.min_value().to_f64(),
+                    default_value: axis.default_value().to_f64(),
+                    max_value: axis.max_value().to_f64(),
+                })
+        };
+        match tag {
+            Some(tag) => find(tag),
+            None => JUSTIFY_AXES.into_iter().find_map(find),
+        }
+    }
+}
+
+/// The adjustments made by [`Shaper::shape_justify`](crate::Shaper::shape_justify).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Justification {
+    /// The value, in user space, of the axis varied to justify the text,
+    /// if the instance was changed.
+    pub variation: Option<Variation>,
+    /// The letter spacing, in font units, added on top of the one in the
+    /// shaping options, if tracking was needed to reach the target.
+    pub letter_spacing: Option<f32>,
+}
+
+// hb_shape_justify: <https://github.com/harfbuzz/harfbuzz/blob/main/src/hb-shape.cc>
+impl crate::Shaper<'_> {
+    /// Shapes the buffer content, varying a font axis and then the letter
+    /// spacing so that the total advance of the result falls between
+    /// `min_target_advance` and `max_target_advance`.
+    ///
+    /// The axis used is `axis` if given, otherwise the first of `jstf` and
+    /// `wdth` present in the font. The buffer is shaped at the current
+    /// instance first; if that already fits, that result is returned
+    /// unchanged. Otherwise the axis is searched between its default and
+    /// its maximum (to expand) or minimum (to shrink), shaping once per
+    /// step.
+    ///
+    /// If the font has no suitable axis, or the target is out of the range
+    /// the axis can reach, the text is tracked instead, starting from the
+    /// axis extreme closest to the target: letter spacing is added to every
+    /// grapheme, as with [`ShapeOptions::letter_spacing`], and solved for
+    /// the middle of the target range. Since the spacing is rounded per
+    /// grapheme, the result may miss a very narrow range by a few units.
+    ///
+    /// Other axes keep their values from the current instance. Advances are
+    /// measured along the buffer direction, as magnitudes, and use the same
+    /// scale as the glyph positions, so the targets must be given in that
+    /// scale too. The targets may be given in either order, and a NaN
+    /// target is ignored; if both are NaN, the buffer is shaped as is.
+    ///
+    /// Varying the axis requires a shaper built with
+    /// [`ShaperData::shaper`](crate::ShaperData::shaper); other shapers are
+    /// justified with tracking only. The shaper's script shapers are kept
+    /// for every instance. Since the plan depends on the feature variations
+    /// selected by the instance, a plan in `options` is rebuilt for each
+    /// instance from its direction, script, language, features and layout
+    /// preference.
+    ///
+    /// This is HarfBuzz's `hb_shape_justify`, extended with tracking.
+    pub fn shape_justify(
+        &self,
+        buffer: UnicodeBuffer,
+        options: ShapeOptions<'_>,
+        min_target_advance: f32,
+        max_target_advance: f32,
+        axis: Option<Tag>,
+    ) -> (GlyphBuffer, Justification) {
+        // `min` and `max` return the other value for a NaN.
+        let (min_target, max_target) = (
+            f64::from(min_target_advance.min(max_target_advance)),
+            f64::from(min_target_advance.max(max_target_advance)),
+        );
+        if min_target.is_nan() {
+            return (self.shape(buffer, options), Justification::default());
+        }
+        let fits = |advance: f64| min_target <= advance && advance <= max_target;
+
+        let font = match &self.font {
+            FontKind::FontRef(data) => Some(&data.font),
+            FontKind::FontInstance(..) => None,
+        };
+        let mut justifier = Justifier {
+            shaper: self,
+            text: buffer.duplicate(),
+            options,
+            axis: font.and_then(|font| Some((font, JustifyAxis::find(font, axis)?))),
+        };
+        let glyphs = self.shape(buffer, justifier.options.reborrow());
+        let advance = buffer_advance(&glyphs);
+        if fits(advance) {
+            return (glyphs, Justification::default());
+        }
+
+        let Some((_, axis)) = &justifier.axis else {
+            return justifier.track(None, glyphs, advance, min_target, max_target);
+        };
+        let (tag, min_value, default_value, max_value) =
+            (axis.tag, axis.min_value, axis.default_value, axis.max_value);
+
+        // Prepare for running the solver.
+        let (a, b, ya, yb) = if advance < min_target {
+            // Need to expand. Shape at maximum expansion to use as the
+            // other starting point for the solver.
+            let glyphs = justifier.shape_at(Some(max_value), None);
+            let yb = buffer_advance(&glyphs);
+            // If the maximum expansion is less than the min target, the
+            // rest has to come from tracking.
+            if yb < min_target {
+                return justifier.track(Some(max_value), glyphs, yb, min_target, max_target);
+            }
+            if yb <= max_target {
+                return (glyphs, Justification::from_axis(tag, max_value));
+            }
+            (default_value, max_value, advance, yb)
+        } else {
+            // Need to shrink.
+            let glyphs = justifier.shape_at(Some(min_value), None);
+            let ya = buffer_advance(&glyphs);
+            // If the maximum shrinking is more than the max target, the
+            // rest has to come from tracking.
+            if ya > max_target {
+                return justifier.track(Some(min_value), glyphs, ya, min_target, max_target);
+            }
+            if ya >= min_target {
+                return (glyphs, Justification::from_axis(tag, min_value));
+            }
+            (min_value, default_value, ya, advance)
+        };
+
+        // Run the solver to find an axis value that fits the target range.
+        let epsilon = (b - a) / f64::from(1 << 14);
+        let mut last: Option<(f64, GlyphBuffer)> = None;
+        let (value, _) = solve_itp(
+            |x| {
+                let glyphs = justifier.shape_at(Some(x), None);
+                let advance = buffer_advance(&glyphs);
+                last = Some((x, glyphs));
+                advance
+            },
+            a,
+            b,
+            epsilon,
+            min_target,
+            max_target,
+            ya,
+            yb,
+        );
+
+        let glyphs = match last {
+            Some((x, glyphs)) if x.to_bits() == value.to_bits() => glyphs,
+            _ => justifier.shape_at(Some(value), None),
+        };
+        (glyphs, Justification::from_axis(tag, value))
+    }
+}
+
+impl Justification {
+    fn from_axis(tag: Tag, value: f64) -> Self {
+        Self {
+            variation: Some(Variation::from((tag, value as f32))),
+            letter_spacing: None,
+        }
+    }
+}
+
+struct Justifier<'a, 'b, 'o> {
+    shaper: &'a crate::Shaper<'b>,
+    text: UnicodeBuffer,
+    options: ShapeOptions<'o>,
+    axis: Option<(&'a FontRef<'a>, JustifyAxis)>,
+}
+
+impl Justifier<'_, '_, '_> {
+    /// Shapes the text at an axis value, in user space, and with extra
+    /// letter spacing, in font units.
+    fn shape_at(&mut self, value: Option<f64>, extra_spacing: Option<f64>) -> GlyphBuffer {
+        let instance = match (value, &self.axis) {
+            (Some(value), Some((font, axis))) => Some((
+                *font,
+                ShaperInstance::from_coords(font, self.coords_at(font, axis, value)),
+            )),
+            _ => None,
+        };
+        let mut options = self.options.reborrow();
+        if let Some(extra) = extra_spacing {
+            let spacing = f64::from(options.letter_spacing.unwrap_or(0.0)) + extra;
+            options = options.letter_spacing(Some(spacing as f32));
+        }
+        match instance {
+            Some((font, instance)) => {
+                let shaper = self
+                    .shaper
+                    .data
+                    .shaper(font)
+                    .instance(Some(&instance))
+                    .script_shapers(self.shaper.script_shapers)
+                    .build();
+                // The plan depends on the feature variations selected by the
+                // instance, so it can't be reused across axis values.
+                let plan = options.plan.map(|plan| {
+                    ShapePlan::with_layout_preference(
+                        &shaper,
+                        plan.direction,
+                        plan.script,
+                        plan.language.as_ref(),
+                        &plan.user_features,
+                        plan.layout_preference,
+                    )
+                });
+                shaper.shape(self.text.duplicate(), options.plan(plan.as_ref()))
+            }
+            None => self.shaper.shape(self.text.duplicate(), options),
+        }
+    }
+
+    /// Tracks the text shaped at `value` until its advance reaches the
+    /// middle of the target range.
+    #[allow(clippy::cast_precision_loss)]
+    fn track(
+        &mut self,
+        value: Option<f64>,
+        glyphs: GlyphBuffer,
+        advance: f64,
+        min_target: f64,
+        max_target: f64,
+    ) -> (GlyphBuffer, Justification) {
+        let variation = value
+            .zip(self.axis.as_ref())
+            .map(|(value, (_, axis))| Variation::from((axis.tag, value as f32)));
+        // With an unbounded side, aim for the bounded one.
+        let target = match (min_target.is_finite(), max_target.is_finite()) {
+            (true, true) => f64::midpoint(min_target, max_target),
+            (true, false) => min_target,
+            (false, true) => max_target,
+            (false, false) => {
+                return (
+                    glyphs,
+                    Justification {
+                        variation,
+                        letter_spacing: None,
+                    },
+                )
+            }
+        };
+        // Spacing is added once per grapheme, so start by assuming one
+        // glyph per grapheme, then correct with the measured slope. The
+        // advance is linear in the spacing apart from rounding.
+        let mult = self.scale_mult(&glyphs);
+        let mut slope = glyphs.len().max(1) as f64 * mult;
+        let mut best = (glyphs, advance, 0.0);
+        for _ in 0..3 {
+            let extra = (target - advance) / slope;
+            let glyphs = self.shape_at(value, Some(extra));
+            let tracked = buffer_advance(&glyphs);
+            let is_better = (tracked - target).abs() < (best.1 - target).abs();
+            if is_better {
+                best = (glyphs, tracked, extra);
+            }
+            if min_target <= tracked && tracked <= max_target {
+                break;
+            }
+            let measured = (tracked - advance) / extra;
+            if !measured.is_finite() || measured <= 0.0 {
+                break;
+            }
+            slope = measured;
+        }
+        let (glyphs, _, extra) = best;
+        let letter_spacing = (extra != 0.0).then_some(extra as f32);
+        (
+            glyphs,
+            Justification {
+                variation,
+                letter_spacing,
+            },
+        )
+    }
+
+    /// Returns the advance, in output units, of one font unit along the
+    /// buffer direction.
+    fn scale_mult(&self, glyphs: &GlyphBuffer) -> f64 {
+        let upem = self.shaper.units_per_em();
+        match self.options.scale {
+            Some((x_scale, y_scale)) if upem != 0 => {
+                let scale = if glyphs.0.direction.is_horizontal() {
+                    x_scale
+                } else {
+                    y_scale
+                };
+                (f64::from(scale) / f64::from(upem)).abs()
+            }
+            _ => 1.0,
+        }
+    }
+
+    fn coords_at(&self, font: &FontRef, axis: &JustifyAxis, value: f64) -> SmallVec<[F2Dot14; 8]> {
+        let mut coords = SmallVec::new();
+        let Ok(fvar) = font.fvar() else {
+            return coords;
+        };
+        coords.resize(fvar.axis_count() as usize, F2Dot14::ZERO);
+        fvar.user_to_normalized(
+            font.avar().ok().as_ref(),
+            [(axis.tag, Fixed::from_f64(value))],
+            &mut coords,
+        );
+        let normalized = coords.get(axis.index).copied().unwrap_or_default();
+        for (coord, current) in coords.iter_mut().zip(
+            self.shaper
+                .coords()
+                .iter()
+                .copied()
+                .chain(core::iter::repeat(F2Dot14::ZERO)),
+        ) {
+            *coord = current;
+        }
+        if let Some(coord) = coords.get_mut(axis.index) {
+            *coord = normalized;
+        }
+        coords
+    }
+}
+
+/// Returns the magnitude of the advance of the buffer along its direction.
+fn buffer_advance(glyphs: &GlyphBuffer) -> f64 {
+    let horizontal = glyphs.0.direction.is_horizontal();
+    glyphs
+        .glyph_positions()
+        .iter()
+        .map(|pos| {
+            if horizontal {
+                f64::from(pos.x_advance)
+            } else {
+                f64::from(pos.y_advance)
+            }
+        })
+        .sum::<f64>()
+        .abs()
+}
+
+/// U+0640 ARABIC TATWEEL.
+const TATWEEL: char = '\u{0640}';
+/// U+200C ZERO WIDTH NON-JOINER.
+const ZWNJ: char = '\u{200C}';
+
+struct TatweelPoint {
+    /// Byte range of the word in the text.
+    word: Range<usize>,
+    /// Byte offset of the character the tatweels are inserted before.
+    offset: usize,
+    priority: u8,
+    count: usize,
+}
+
+impl crate::Shaper<'_> {
+    /// Justifies shaped text by inserting U+0640 ARABIC TATWEEL characters,
+    /// so that its advance grows by about `extra_width`.
+    ///
+    /// `text` must be the text `glyphs` was shaped from, added with
+    /// [`UnicodeBuffer::push_str`] so that clusters are byte offsets into
+    /// it, and shaping must have been done with
+    /// [`BufferFlags::PRODUCE_SAFE_TO_INSERT_TATWEEL`](crate::BufferFlags::PRODUCE_SAFE_TO_INSERT_TATWEEL)
+    /// set. `options` should be the ones used for shaping `glyphs`.
+    ///
+    /// Insertion points are taken from glyphs flagged with
+    /// [`GlyphFlags::SAFE_TO_INSERT_TATWEEL`](crate::GlyphFlags::SAFE_TO_INSERT_TATWEEL).
+    /// Words are delimited by whitespace, punctuation and U+200C ZERO WIDTH
+    /// NON-JOINER, so each joined part of a word split by a non-joiner
+    /// counts as its own word. At most one point is used per word, chosen
+    /// by the letters it joins:
+    /// after seen and sad first, then before heh, teh marbuta and dal, then
+    /// before alef, lam, kaf and gaf, then before reh and waw, then
+    /// anywhere else. As many tatweels as fit in `extra_width` are spread
+    /// over the words, higher-priority points receiving the remainder.
+    ///
+    /// Only the affected words are shaped again, with the tatweels added
+    /// and the rest of the text as context, so that features such as
+    /// `stch` and `jalt` apply to them. The inserted tatweels get the
+    /// cluster of the character they precede. The buffer is returned
+    /// unchanged if the font has no tatweel glyph or no point is found.
+    pub fn justify_with_tatweel(
+        &self,
+        text: &str,
+        glyphs: GlyphBuffer,
+        mut options: ShapeOptions<'_>,
+        extra_width: f32,
+    ) -> GlyphBuffer {
+        let Some(tatweel_advance) = self.tatweel_advance(&glyphs, options.reborrow()) else {
+            return glyphs;
+        };
+        let count = (f64::from(extra_width) / tatweel_advance).floor() as usize;
+        if count == 0 {
+            return glyphs;
+        }
+
+        let mut points = tatweel_points(text, &glyphs);
+        if points.is_empty() {
+            return glyphs;
+        }
+        // Stable, so points of equal priority stay in text order.
+        points.sort_by_key(|point| point.priority);
+        let len = points.len();
+        for (i, point) in points.iter_mut().enumerate() {
+            point.count = count / len + usize::from(i < count % len);
+        }
+
+        let mut replacements = Vec::new();
+        for point in points.iter().filter(|point| point.count != 0) {
+            let Some(glyph_range) = word_glyph_range(&glyphs, &point.word) else {
+                continue;
+            };
+            let mut buffer = UnicodeBuffer::with_properties_of(&glyphs.0);
+            for (i, c) in text[point.word.clone()].char_indices() {
+                let cluster = point.word.start + i;
+                if cluster == point.offset {
+                    for _ in 0..point.count {
+                        buffer.add(TATWEEL, cluster as u32);
+                    }
+                }
+                buffer.add(c, cluster as u32);
+            }
+            if point.word.start != 0 {
+                buffer.set_pre_context(&text[..point.word.start]);
+                buffer.0.flags.remove(BufferFlags::BEGINNING_OF_TEXT);
+            }
+            if point.word.end != text.len() {
+                buffer.set_post_context(&text[point.word.end..]);
+                buffer.0.flags.remove(BufferFlags::END_OF_TEXT);
+            }
+            replacements.push((glyph_range, self.shape(buffer, options.reborrow())));
+        }
+        replacements.sort_by_key(|(range, _)| range.start);
+
+        let mut buffer = glyphs.0;
+        let mut info = Vec::with_capacity(buffer.len + count);
+        let mut pos = Vec::with_capacity(buffer.len + count);
+        let mut start = 0;
+        for (range, shaped) in &replacements {
+            info.extend_from_slice(&buffer.info[start..range.start]);
+            pos.extend_from_slice(&buffer.pos[start..range.start]);
+            info.extend_from_slice(shaped.glyph_infos());
+            pos.extend_from_slice(shaped.glyph_positions());
+            start = range.end;
+        }
+        info.extend_from_slice(&buffer.info[start..buffer.len]);
+        pos.extend_from_slice(&buffer.pos[start..buffer.len]);
+        buffer.len = info.len();
+        buffer.info = info;
+        buffer.pos = pos;
+        GlyphBuffer(buffer)
+    }
+
+    fn tatweel_advance(&self, glyphs: &GlyphBuffer, options: ShapeOptions<'_>) -> Option<f64> {
+        let mut buffer = UnicodeBuffer::with_properties_of(&glyphs.0);
+        buffer.add(TATWEEL, 0);
+        buffer.0.context_len = [0; 2];
+        let shaped = self.shape(buffer, options);
+        if shaped.glyph_infos().iter().any(|info| info.glyph_id == 0) {
+            return None;
+        }
+        let advance = buffer_advance(&shaped);
+        (advance > 0.0).then_some(advance)
+    }
+}
+
+/// Collects the best tatweel insertion point of each word.
+fn tatweel_points(text: &str, glyphs: &GlyphBuffer) -> Vec<TatweelPoint> {
+    let is_mark = |c: char| (c as u32).general_category().is_mark();
+    let mut points = Vec::new();
+    for info in glyphs.glyph_infos() {
+        if !info.safe_to_insert_tatweel() {
+            continue;
+        }
+        let offset = info.cluster as usize;
+        let (Some(before), Some(after)) = (text.get(..offset), text.get(offset..)) else {
+            continue;
+        };
+        let Some(next) = after.chars().next() else {
+            continue;
+        };
+        let Some(prev) = before.chars().rev().find(|&c| !is_mark(c)) else {
+            continue;
+        };
+        if is_mark(next) || is_word_break(next) || is_word_break(prev) {
+            continue;
+        }
+        let start = before
+            .char_indices()
+            .rev()
+            .find(|&(_, c)| is_word_break(c))
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let end = after.find(is_word_break).map_or(text.len(), |i| offset + i);
+        points.push(TatweelPoint {
+            word: start..end,
+            offset,
+            priority: tatweel_priority(prev, next),
+            count: 0,
+        });
+    }
+    // Keep the highest priority point of each word, preferring the last
+    // one in logical order.
+    points.sort_by_key(|point| (point.word.start, point.priority, Reverse(point.offset)));
+    points.dedup_by_key(|point| point.word.start);
+    points
+}
+
+fn is_word_break(c: char) -> bool {
+    c.is_whitespace()
+        || c == ZWNJ
+        || matches!(
+            (c as u32).general_category(),
+            GeneralCategory::CONNECT_PUNCTUATION
+                | GeneralCategory::DASH_PUNCTUATION
+                | GeneralCategory::CLOSE_PUNCTUATION
+                | GeneralCategory::FINAL_PUNCTUATION
+                | GeneralCategory::INITIAL_PUNCTUATION
+                | GeneralCategory::OTHER_PUNCTUATION
+                | GeneralCategory::OPEN_PUNCTUATION
+        )
+}
+
+/// Ranks an insertion point between `prev` and `next`; lower is better.
+fn tatweel_priority(prev: char, next: char) -> u8 {
+    match (prev, next) {
+        // After seen, sheen, sad and dad.
+        ('\u{0633}'..='\u{0636}', _) => 0,
+        // Before heh, teh marbuta, dal and thal.
+        (_, '\u{0629}' | '\u{062F}' | '\u{0630}' | '\u{0647}' | '\u{06C1}' | '\u{06D5}') => 1,
+        // Before alef, lam, kaf and gaf.
+        (
+            _,
+            '\u{0622}' | '\u{0623}' | '\u{0625}' | '\u{0627}' | '\u{0643}' | '\u{0644}'
+            | '\u{06A9}' | '\u{06AF}',
+        ) => 2,
+        // Before reh, zain and waw.
+        (_, '\u{0631}' | '\u{0632}' | '\u{0648}' | '\u{0698}') => 3,
+        _ => 4,
+    }
+}
+
+/// Returns the glyphs of a word, if they are contiguous in the buffer.
+fn word_glyph_range(glyphs: &GlyphBuffer, word: &Range<usize>) -> Option<Range<usize>> {
+    let infos = glyphs.glyph_infos();
+    let in_word = |info: &GlyphInfo| word.contains(&(info.cluster as usize));
+    let start = infos.iter().position(in_word)?;
+    let end = infos.iter().rposition(in_word)? + 1;
+    infos[start..end].iter().all(in_word).then_some(start..end)
+}
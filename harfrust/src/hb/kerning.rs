@@ -24,8 +24,9 @@ pub(crate) fn get_class(machine: &aat::StateTable, glyph_id: GlyphId, cache: &Cl
     if let Some(klass) = cache.get(glyph_id.to_u32()) {
         return klass as u8;
     }
-    let klass = machine
-        .class(GlyphId16::new(glyph_id.to_u32() as u16))
+    let klass = u16::try_from(glyph_id.to_u32())
+        .ok()
+        .and_then(|gid| machine.class(GlyphId16::new(gid)).ok())
         .unwrap_or(aat::class::OUT_OF_BOUNDS);
     cache.set(glyph_id.to_u32(), klass as u32);
     klass
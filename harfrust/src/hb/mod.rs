@@ -22,23 +22,30 @@
 
 #[macro_use]
 pub mod buffer;
-mod aat;
+pub(crate) mod aat;
 mod cache;
 mod charmap;
+pub mod clusters;
 pub mod common;
 pub mod face;
+mod fallback_shape;
 mod font_funcs;
+mod glyph_bounds;
 mod glyph_metrics;
 mod glyph_names;
+pub mod justify;
 mod kerning;
 mod machine_cursor;
 mod ot;
+pub mod ot_feature_names;
 mod ot_layout;
 mod ot_layout_common;
 mod ot_layout_gpos_table;
 mod ot_layout_gsub_table;
 mod ot_layout_gsubgpos;
 mod ot_map;
+pub mod ot_metrics;
+mod ot_name;
 mod ot_shape;
 mod ot_shape_fallback;
 mod ot_shape_normalize;
@@ -65,12 +72,17 @@ mod ot_shaper_use_machine;
 mod ot_shaper_use_table;
 #[allow(clippy::collapsible_match)]
 mod ot_shaper_vowel_constraints;
+#[cfg(feature = "profile")]
+pub mod profile;
+pub mod script_shaper;
 pub(crate) mod set_digest;
+pub mod shape_cache;
 mod tables;
 mod tag;
 #[allow(clippy::collapsible_match)]
 mod tag_table;
 mod text_parser;
+pub mod unicode_funcs;
 
 use read_fonts::types::Tag as hb_tag_t;
 
@@ -0,0 +1,113 @@
+//! Version 2 GSUB, GPOS and GDEF headers, with 24-bit offsets, from the
+//! [boring expansion](https://github.com/harfbuzz/boring-expansion-spec/blob/main/beyond-64k.md)
+//! of the OpenType spec.
+//!
+//! read-fonts reads these as version 1 headers, so their offsets are read
+//! directly from the table data here. The tables they point to are the ones
+//! of version 1, except for the lookup list, whose offsets to the lookups are
+//! also 24-bit.
+
+use crate::hb::ot::gsub::beyond_64k::{read_u16, read_u24};
+use read_fonts::tables::gdef::{LigCaretList, MarkGlyphSets};
+use read_fonts::tables::gsub::{FeatureList, FeatureVariations, ScriptList};
+use read_fonts::tables::variations::ItemVariationStore;
+use read_fonts::{FontData, FontRead};
+
+/// Returns the table at the 24-bit offset at `offset`, or `None` for a null
+/// offset.
+fn offset24_table<'a, T: FontRead<'a>>(data: &FontData<'a>, offset: usize) -> Option<T> {
+    match read_u24(data, offset)? {
+        0 => None,
+        target => T::read(data.split_off(target as usize)?).ok(),
+    }
+}
+
+/// Returns the table at the 32-bit offset at `offset`, or `None` for a null
+/// offset.
+fn offset32_table<'a, T: FontRead<'a>>(data: &FontData<'a>, offset: usize) -> Option<T> {
+    match data.read_at::<u32>(offset).ok()? {
+        0 => None,
+        target => T::read(data.split_off(target as usize)?).ok(),
+    }
+}
+
+/// The header of a version 2 GSUB or GPOS table.
+#[derive(Clone, Copy)]
+pub(crate) struct LayoutHeader2<'a> {
+    data: FontData<'a>,
+}
+
+impl<'a> LayoutHeader2<'a> {
+    /// Returns the header if `data` is a version 2 GSUB or GPOS table.
+    pub fn new(data: FontData<'a>) -> Option<Self> {
+        (read_u16(&data, 0)? == 2 && data.len() >= 17).then_some(Self { data })
+    }
+
+    pub fn script_list(&self) -> Option<ScriptList<'a>> {
+        offset24_table(&self.data, 4)
+    }
+
+    pub fn feature_list(&self) -> Option<FeatureList<'a>> {
+        offset24_table(&self.data, 7)
+    }
+
+    pub fn feature_variations(&self) -> Option<FeatureVariations<'a>> {
+        offset32_table(&self.data, 13)
+    }
+
+    fn lookup_list_offset(&self) -> Option<usize> {
+        read_u24(&self.data, 10)
+            .filter(|&offset| offset != 0)
+            .map(|offset| offset as usize)
+    }
+
+    pub fn lookup_count(&self) -> u16 {
+        self.lookup_list_offset()
+            .and_then(|list| read_u16(&self.data, list))
+            .unwrap_or_default()
+    }
+
+    /// Returns the offset of the lookup at `index` from the start of the
+    /// table.
+    pub fn lookup_offset(&self, index: u16) -> Option<usize> {
+        let list = self.lookup_list_offset()?;
+        if index >= read_u16(&self.data, list)? {
+            return None;
+        }
+        let offset = read_u24(&self.data, list + 2 + usize::from(index) * 3)?;
+        Some(list + offset as usize)
+    }
+}
+
+/// The header of a version 2 GDEF table.
+#[derive(Clone, Copy)]
+pub(crate) struct GdefHeader2<'a> {
+    data: FontData<'a>,
+}
+
+impl<'a> GdefHeader2<'a> {
+    /// Returns the header if `data` is a version 2 GDEF table.
+    pub fn new(data: FontData<'a>) -> Option<Self> {
+        (read_u16(&data, 0)? == 2 && data.len() >= 23).then_some(Self { data })
+    }
+
+    pub fn glyph_class_def_offset(&self) -> u32 {
+        read_u24(&self.data, 4).unwrap_or_default()
+    }
+
+    pub fn lig_caret_list(&self) -> Option<LigCaretList<'a>> {
+        offset24_table(&self.data, 10)
+    }
+
+    pub fn mark_attach_class_def_offset(&self) -> u32 {
+        read_u24(&self.data, 13).unwrap_or_default()
+    }
+
+    pub fn mark_glyph_sets_def(&self) -> Option<MarkGlyphSets<'a>> {
+        offset24_table(&self.data, 16)
+    }
+
+    pub fn item_var_store(&self) -> Option<ItemVariationStore<'a>> {
+        offset32_table(&self.data, 19)
+    }
+}
@@ -8,15 +8,19 @@ use crate::hb::ot_layout_gsubgpos::{
     ContextFormat2Cache, SubtableExternalCache, SubtableExternalCacheMode, WouldApply,
     WouldApplyContext,
 };
+use core::marker::PhantomData;
 use read_fonts::tables::gsub::ClassDef;
 use read_fonts::tables::layout::{
     ChainedClassSequenceRule, ChainedSequenceContextFormat1, ChainedSequenceContextFormat2,
     ChainedSequenceContextFormat3, ChainedSequenceRule, ClassSequenceRule, SequenceContextFormat1,
     SequenceContextFormat2, SequenceContextFormat3, SequenceLookupRecord, SequenceRule,
 };
-use read_fonts::types::{BigEndian, FixedSize, GlyphId, Offset16};
+use read_fonts::types::{BigEndian, FixedSize, GlyphId, Offset16, Scalar};
 use read_fonts::FontData;
 
+#[cfg(feature = "beyond_64k")]
+pub mod beyond_64k;
+
 impl WouldApply for SequenceContextFormat1<'_> {
     fn would_apply(&self, ctx: &WouldApplyContext) -> bool {
         coverage_index(self.coverage(), ctx.glyphs[0])
@@ -50,7 +54,7 @@ impl Apply for SequenceContextFormat1<'_> {
         let glyph = ctx.buffer.cur(0).as_glyph();
         let index = self.coverage().ok()?.get(glyph)? as usize;
         let set = self.seq_rule_sets().get(index)?.ok()?;
-        apply_context_rules(ctx, set.offset_data(), set.seq_rule_offsets(), match_glyph)
+        apply_context_rules::<u16>(ctx, set.offset_data(), set.seq_rule_offsets(), match_glyph)
     }
 }
 
@@ -101,7 +105,7 @@ impl Apply for SequenceContextFormat2<'_> {
         let input_class = |gid| cache.input.class(&offset_data, gid);
         let index = input_class(glyph) as usize;
         let set = self.class_seq_rule_sets().get(index)?.ok()?;
-        apply_context_rules(
+        apply_context_rules::<u16>(
             ctx,
             set.offset_data(),
             set.class_seq_rule_offsets(),
@@ -127,7 +131,7 @@ impl Apply for SequenceContextFormat2<'_> {
         let input_class = |gid| cache.input.class(&offset_data, gid);
         let index = get_class_cached(&input_class, &mut ctx.buffer.info[ctx.buffer.idx]) as usize;
         let set = self.class_seq_rule_sets().get(index)?.ok()?;
-        apply_context_rules(
+        apply_context_rules::<u16>(
             ctx,
             set.offset_data(),
             set.class_seq_rule_offsets(),
@@ -235,7 +239,7 @@ impl Apply for ChainedSequenceContextFormat1<'_> {
         let glyph = ctx.buffer.cur(0).as_glyph();
         let index = self.coverage().ok()?.get(glyph)? as usize;
         let set = self.chained_seq_rule_sets().get(index)?.ok()?;
-        apply_chain_context_rules(
+        apply_chain_context_rules::<u16, _, _, _>(
             ctx,
             set.offset_data(),
             set.chained_seq_rule_offsets(),
@@ -362,7 +366,7 @@ impl Apply for ChainedSequenceContextFormat2<'_> {
         )?;
         let index = cache.input.class(&offset_data, glyph) as usize;
         let set = self.chained_class_seq_rule_sets().get(index)?.ok()?;
-        apply_chain_context_rules(
+        apply_chain_context_rules::<u16, _, _, _>(
             ctx,
             set.offset_data(),
             set.chained_class_seq_rule_offsets(),
@@ -392,7 +396,7 @@ impl Apply for ChainedSequenceContextFormat2<'_> {
         let lookahead_class = |gid| cache.lookahead.class(&offset_data, gid);
         let index = get_class_cached2(&input_class, &mut ctx.buffer.info[ctx.buffer.idx]) as usize;
         let set = self.chained_class_seq_rule_sets().get(index)?.ok()?;
-        apply_chain_context_rules(
+        apply_chain_context_rules::<u16, _, _, _>(
             ctx,
             set.offset_data(),
             set.chained_class_seq_rule_offsets(),
@@ -529,26 +533,47 @@ impl Apply for ChainedSequenceContextFormat3<'_> {
     }
 }
 
+/// A glyph id or class in a context rule.
+///
+/// These are 16-bit in the standard formats and 24-bit in the formats from
+/// the beyond-64k expansion, which otherwise share the rule layout.
+trait RuleValue: Scalar + Copy + Default + 'static {
+    fn rule_value(self) -> u32;
+}
+
+impl RuleValue for u16 {
+    fn rule_value(self) -> u32 {
+        self.into()
+    }
+}
+
+#[cfg(feature = "beyond_64k")]
+impl RuleValue for read_fonts::types::Uint24 {
+    fn rule_value(self) -> u32 {
+        self.to_u32()
+    }
+}
+
 /// All of a context rule's fields, parsed in a single pass.
 ///
 /// The generated getters re-derive the positions of all preceding fields on
 /// every call, so fetching fields individually in the rule-matching loops
 /// re-reads the leading counts many times over; this parses the whole rule
-/// once instead. Glyph ids and classes are both read as raw u16s.
+/// once instead. Glyph ids and classes are both read as raw `V`s.
 #[derive(Clone, Copy, Default)]
-struct ParsedRule<'a> {
-    backtrack: &'a [BigEndian<u16>],
-    input: &'a [BigEndian<u16>],
-    lookahead: &'a [BigEndian<u16>],
+struct ParsedRule<'a, V: RuleValue = u16> {
+    backtrack: &'a [BigEndian<V>],
+    input: &'a [BigEndian<V>],
+    lookahead: &'a [BigEndian<V>],
     records: &'a [SequenceLookupRecord],
 }
 
-impl<'a> ParsedRule<'a> {
+impl<'a, V: RuleValue> ParsedRule<'a, V> {
     /// Parse a SequenceRule or ClassSequenceRule.
     fn from_rule_data(data: FontData<'a>) -> Option<Self> {
         let glyph_count = usize::from(data.read_at::<u16>(0).ok()?);
         let record_count = usize::from(data.read_at::<u16>(2).ok()?);
-        let input_end = 4 + glyph_count.saturating_sub(1) * u16::RAW_BYTE_LEN;
+        let input_end = 4 + glyph_count.saturating_sub(1) * V::RAW_BYTE_LEN;
         let records_end = input_end + record_count * SequenceLookupRecord::RAW_BYTE_LEN;
         Some(ParsedRule {
             input: data.read_array(4..input_end).ok()?,
@@ -560,11 +585,11 @@ impl<'a> ParsedRule<'a> {
     /// Parse a ChainedSequenceRule or ChainedClassSequenceRule.
     fn from_chain_rule_data(data: FontData<'a>) -> Option<Self> {
         let backtrack_count = usize::from(data.read_at::<u16>(0).ok()?);
-        let backtrack_end = 2 + backtrack_count * u16::RAW_BYTE_LEN;
+        let backtrack_end = 2 + backtrack_count * V::RAW_BYTE_LEN;
         let input_count = usize::from(data.read_at::<u16>(backtrack_end).ok()?);
-        let input_end = backtrack_end + 2 + input_count.saturating_sub(1) * u16::RAW_BYTE_LEN;
+        let input_end = backtrack_end + 2 + input_count.saturating_sub(1) * V::RAW_BYTE_LEN;
         let lookahead_count = usize::from(data.read_at::<u16>(input_end).ok()?);
-        let lookahead_end = input_end + 2 + lookahead_count * u16::RAW_BYTE_LEN;
+        let lookahead_end = input_end + 2 + lookahead_count * V::RAW_BYTE_LEN;
         let record_count = usize::from(data.read_at::<u16>(lookahead_end).ok()?);
         let records_end = lookahead_end + 2 + record_count * SequenceLookupRecord::RAW_BYTE_LEN;
         Some(ParsedRule {
@@ -588,7 +613,7 @@ impl<'a> ParsedRule<'a> {
         let match_func = |info: &mut GlyphInfo, index| {
             inputs
                 .get(index as usize)
-                .is_some_and(|value| match_func(info, value.get() as u32))
+                .is_some_and(|value| match_func(info, value.get().rule_value()))
         };
 
         let mut match_end = 0;
@@ -608,59 +633,63 @@ impl<'a> ParsedRule<'a> {
 /// The glyph count is at offset 0 and the input sequence starts at offset 4,
 /// after the lookup record count. (The input sequence's first entry covers
 /// the *second* glyph of the matched sequence.)
-fn plain_rule_first_input(data: &FontData) -> Option<u16> {
+fn plain_rule_first_input<V: RuleValue>(data: &FontData) -> Option<u32> {
     let glyph_count: u16 = data.read_at(0).ok()?;
     if glyph_count <= 1 {
         return None;
     }
-    data.read_at(4).ok()
+    data.read_at::<V>(4).ok().map(V::rule_value)
 }
 
 /// `second_input` for SequenceRule/ClassSequenceRule; see
 /// [`plain_rule_first_input`] for the layout.
-fn plain_rule_second_input(data: &FontData) -> Option<u16> {
+fn plain_rule_second_input<V: RuleValue>(data: &FontData) -> Option<u32> {
     let glyph_count: u16 = data.read_at(0).ok()?;
     if glyph_count <= 2 {
         return None;
     }
-    data.read_at(6).ok()
+    data.read_at::<V>(4 + V::RAW_BYTE_LEN)
+        .ok()
+        .map(V::rule_value)
 }
 
 /// `first_input` for ChainedSequenceRule/ChainedClassSequenceRule.
 ///
 /// The input glyph count follows the variable-length backtrack sequence, and
 /// the input sequence follows it directly.
-fn chain_rule_first_input(data: &FontData) -> Option<u16> {
+fn chain_rule_first_input<V: RuleValue>(data: &FontData) -> Option<u32> {
     let backtrack_count = usize::from(data.read_at::<u16>(0).ok()?);
-    let count_pos = 2 + backtrack_count * u16::RAW_BYTE_LEN;
+    let count_pos = 2 + backtrack_count * V::RAW_BYTE_LEN;
     let input_count: u16 = data.read_at(count_pos).ok()?;
     if input_count <= 1 {
         return None;
     }
-    data.read_at(count_pos + 2).ok()
+    data.read_at::<V>(count_pos + 2).ok().map(V::rule_value)
 }
 
 /// Positional probes into a chain rule's raw data, reading only the values
 /// the rule pre-match needs instead of parsing the whole rule.
-struct ChainRuleProbe<'a> {
+struct ChainRuleProbe<'a, V> {
     data: FontData<'a>,
     count_pos: usize,
     input_end: usize,
     input_count: u16,
+    value: PhantomData<V>,
 }
 
-impl<'a> ChainRuleProbe<'a> {
+impl<'a, V: RuleValue> ChainRuleProbe<'a, V> {
     fn new(data: FontData<'a>) -> Option<Self> {
         let backtrack_count = usize::from(data.read_at::<u16>(0).ok()?);
-        let count_pos = 2 + backtrack_count * u16::RAW_BYTE_LEN;
+        let count_pos = 2 + backtrack_count * V::RAW_BYTE_LEN;
         let input_count: u16 = data.read_at(count_pos).ok()?;
         let input_end =
-            count_pos + 2 + usize::from(input_count).saturating_sub(1) * u16::RAW_BYTE_LEN;
+            count_pos + 2 + usize::from(input_count).saturating_sub(1) * V::RAW_BYTE_LEN;
         Some(Self {
             data,
             count_pos,
             input_end,
             input_count,
+            value: PhantomData,
         })
     }
 
@@ -670,12 +699,14 @@ impl<'a> ChainRuleProbe<'a> {
         usize::from(self.input_count).max(1)
     }
 
+    fn value_at(&self, pos: usize) -> Option<u32> {
+        self.data.read_at::<V>(pos).ok().map(V::rule_value)
+    }
+
     /// The input value at `index`. Callers must keep `index` within the
     /// input array (`index + 1 < input_count`).
-    fn input(&self, index: usize) -> Option<u16> {
-        self.data
-            .read_at(self.count_pos + 2 + index * u16::RAW_BYTE_LEN)
-            .ok()
+    fn input(&self, index: usize) -> Option<u32> {
+        self.value_at(self.count_pos + 2 + index * V::RAW_BYTE_LEN)
     }
 
     fn lookahead_len(&self) -> usize {
@@ -684,10 +715,8 @@ impl<'a> ChainRuleProbe<'a> {
 
     /// The lookahead value at `index`. Callers must keep `index` within
     /// `lookahead_len()`.
-    fn lookahead(&self, index: usize) -> Option<u16> {
-        self.data
-            .read_at(self.input_end + 2 + index * u16::RAW_BYTE_LEN)
-            .ok()
+    fn lookahead(&self, index: usize) -> Option<u32> {
+        self.value_at(self.input_end + 2 + index * V::RAW_BYTE_LEN)
     }
 }
 
@@ -708,10 +737,10 @@ fn plain_rule_data_at<'a>(
 }
 
 #[inline]
-fn parse_plain_rule_at<'a>(
+fn parse_plain_rule_at<'a, V: RuleValue>(
     set_data: FontData<'a>,
     off: &BigEndian<Offset16>,
-) -> Option<ParsedRule<'a>> {
+) -> Option<ParsedRule<'a, V>> {
     plain_rule_data_at(set_data, off).map(|d| ParsedRule::from_rule_data(d).unwrap_or_default())
 }
 
@@ -726,15 +755,15 @@ fn chain_rule_data_at<'a>(
 }
 
 #[inline]
-fn parse_chain_rule_at<'a>(
+fn parse_chain_rule_at<'a, V: RuleValue>(
     set_data: FontData<'a>,
     off: &BigEndian<Offset16>,
-) -> Option<ParsedRule<'a>> {
+) -> Option<ParsedRule<'a, V>> {
     chain_rule_data_at(set_data, off)
         .map(|d| ParsedRule::from_chain_rule_data(d).unwrap_or_default())
 }
 
-fn apply_context_rules(
+fn apply_context_rules<V: RuleValue>(
     ctx: &mut hb_ot_apply_context_t,
     set_data: FontData<'_>,
     rule_offsets: &[BigEndian<Offset16>],
@@ -766,7 +795,7 @@ fn apply_context_rules(
             // Can't use the fast path if eg. the next char is a default-ignorable
             // or other skippable.
             for off in rule_offsets {
-                let Some(rule) = parse_plain_rule_at(set_data, off) else {
+                let Some(rule) = parse_plain_rule_at::<V>(set_data, off) else {
                     continue;
                 };
                 if rule.apply(ctx, &match_func).is_some() {
@@ -781,7 +810,7 @@ fn apply_context_rules(
         // Failed to match a next glyph. Only try applying rules that have no
         // further impact.
         for off in rule_offsets {
-            let Some(rule) = parse_plain_rule_at(set_data, off) else {
+            let Some(rule) = parse_plain_rule_at::<V>(set_data, off) else {
                 continue;
             };
             if rule.input.len() <= 1 && rule.apply(ctx, &match_func).is_some() {
@@ -799,7 +828,7 @@ fn apply_context_rules(
             // Can't use the fast path if eg. the next char is a default-ignorable
             // or other skippable.
             for off in rule_offsets {
-                let Some(rule) = parse_plain_rule_at(set_data, off) else {
+                let Some(rule) = parse_plain_rule_at::<V>(set_data, off) else {
                     continue;
                 };
                 if rule.apply(ctx, &match_func).is_some() {
@@ -818,13 +847,13 @@ fn apply_context_rules(
         // Probe the first two input values without parsing the whole rule;
         // most visited rules are rejected on these probes and never pay for
         // a full parse — nor for constructing a rule table.
-        let first_value = plain_rule_first_input(&data);
-        if first_value.is_none_or(|v| match_func(&mut ctx.buffer.info[first], u32::from(v))) {
+        let first_value = plain_rule_first_input::<V>(&data);
+        if first_value.is_none_or(|v| match_func(&mut ctx.buffer.info[first], v)) {
             if second.is_none()
-                || plain_rule_second_input(&data)
-                    .is_none_or(|v| match_func(&mut ctx.buffer.info[second.unwrap()], u32::from(v)))
+                || plain_rule_second_input::<V>(&data)
+                    .is_none_or(|v| match_func(&mut ctx.buffer.info[second.unwrap()], v))
             {
-                if ParsedRule::from_rule_data(data)
+                if ParsedRule::<V>::from_rule_data(data)
                     .unwrap_or_default()
                     .apply(ctx, &match_func)
                     .is_some()
@@ -854,7 +883,7 @@ fn apply_context_rules(
                 let Some(d) = plain_rule_data_at(set_data, off) else {
                     continue;
                 };
-                if plain_rule_first_input(&d) != Some(first_glyph_value) {
+                if plain_rule_first_input::<V>(&d) != Some(first_glyph_value) {
                     break;
                 }
             }
@@ -868,19 +897,20 @@ fn apply_context_rules(
 }
 
 fn apply_chain_with_sequences<
+    V: RuleValue,
     F1: Fn(&mut GlyphInfo, u32) -> bool,
     F2: Fn(&mut GlyphInfo, u32) -> bool,
     F3: Fn(&mut GlyphInfo, u32) -> bool,
 >(
     ctx: &mut hb_ot_apply_context_t,
-    rule: &ParsedRule<'_>,
+    rule: &ParsedRule<'_, V>,
     match_funcs: &(F1, F2, F3),
 ) -> Option<()> {
     let input = rule.input;
     let f3 = |info: &mut GlyphInfo, index| {
         input
             .get(index as usize)
-            .is_some_and(|value| match_funcs.1(info, value.get() as u32))
+            .is_some_and(|value| match_funcs.1(info, value.get().rule_value()))
     };
 
     let mut end_index = ctx.buffer.idx;
@@ -900,7 +930,7 @@ fn apply_chain_with_sequences<
     let f2 = |info: &mut GlyphInfo, index| {
         lookahead
             .get(index as usize)
-            .is_some_and(|value| match_funcs.2(info, value.get() as u32))
+            .is_some_and(|value| match_funcs.2(info, value.get().rule_value()))
     };
 
     if !match_lookahead(ctx, lookahead.len() as u16, f2, match_end, &mut end_index) {
@@ -915,7 +945,7 @@ fn apply_chain_with_sequences<
     let f1 = |info: &mut GlyphInfo, index| {
         backtrack
             .get(index as usize)
-            .is_some_and(|value| match_funcs.0(info, value.get() as u32))
+            .is_some_and(|value| match_funcs.0(info, value.get().rule_value()))
     };
 
     if !match_backtrack(ctx, backtrack.len() as u16, f1, &mut start_index) {
@@ -932,6 +962,7 @@ fn apply_chain_with_sequences<
 }
 
 fn apply_chain_context_rules<
+    V: RuleValue,
     F1: Fn(&mut GlyphInfo, u32) -> bool,
     F2: Fn(&mut GlyphInfo, u32) -> bool,
     F3: Fn(&mut GlyphInfo, u32) -> bool,
@@ -963,7 +994,7 @@ fn apply_chain_context_rules<
             // Can't use the fast path if eg. the next char is a default-ignorable
             // or other skippable.
             for off in rule_offsets {
-                let Some(rule) = parse_chain_rule_at(set_data, off) else {
+                let Some(rule) = parse_chain_rule_at::<V>(set_data, off) else {
                     continue;
                 };
                 if apply_chain_with_sequences(ctx, &rule, &match_funcs).is_some() {
@@ -978,7 +1009,7 @@ fn apply_chain_context_rules<
         // Failed to match a next glyph. Only try applying rules that have no
         // further impact.
         for off in rule_offsets {
-            let Some(rule) = parse_chain_rule_at(set_data, off) else {
+            let Some(rule) = parse_chain_rule_at::<V>(set_data, off) else {
                 continue;
             };
             if rule.input.len() <= 1
@@ -999,7 +1030,7 @@ fn apply_chain_context_rules<
             // Can't use the fast path if eg. the next char is a default-ignorable
             // or other skippable.
             for off in rule_offsets {
-                let Some(rule) = parse_chain_rule_at(set_data, off) else {
+                let Some(rule) = parse_chain_rule_at::<V>(set_data, off) else {
                     continue;
                 };
                 if apply_chain_with_sequences(ctx, &rule, &match_funcs).is_some() {
@@ -1018,10 +1049,10 @@ fn apply_chain_context_rules<
         // Probe the values the pre-match needs without parsing the whole
         // rule; most visited rules are rejected on these probes and never
         // pay for a full parse — nor for constructing a rule table.
-        let Some(probe) = ChainRuleProbe::new(data) else {
+        let Some(probe) = ChainRuleProbe::<V>::new(data) else {
             // Unreadable rule header; the full parse treats it as an empty
             // rule, same as the parse-first code did.
-            let rule = ParsedRule::from_chain_rule_data(data).unwrap_or_default();
+            let rule = ParsedRule::<V>::from_chain_rule_data(data).unwrap_or_default();
             if apply_chain_with_sequences(ctx, &rule, &match_funcs).is_some() {
                 if let Some(unsafe_to) = unsafe_to {
                     ctx.buffer
@@ -1036,30 +1067,30 @@ fn apply_chain_context_rules<
         let matched_first = if len_p1 > 1 {
             probe
                 .input(0)
-                .is_some_and(|v| match_funcs.1(&mut ctx.buffer.info[first], u32::from(v)))
+                .is_some_and(|v| match_funcs.1(&mut ctx.buffer.info[first], v))
         } else {
             probe.lookahead_len() == 0
                 || probe
                     .lookahead(0)
-                    .is_some_and(|v| match_funcs.2(&mut ctx.buffer.info[first], u32::from(v)))
+                    .is_some_and(|v| match_funcs.2(&mut ctx.buffer.info[first], v))
         };
         if matched_first {
             let matched_second = if let Some(second) = second {
                 if len_p1 > 2 {
                     probe
                         .input(1)
-                        .is_some_and(|v| match_funcs.1(&mut ctx.buffer.info[second], u32::from(v)))
+                        .is_some_and(|v| match_funcs.1(&mut ctx.buffer.info[second], v))
                 } else {
                     (probe.lookahead_len() <= 2 - len_p1)
-                        || probe.lookahead(2 - len_p1).is_some_and(|v| {
-                            match_funcs.2(&mut ctx.buffer.info[second], u32::from(v))
-                        })
+                        || probe
+                            .lookahead(2 - len_p1)
+                            .is_some_and(|v| match_funcs.2(&mut ctx.buffer.info[second], v))
                 }
             } else {
                 true
             };
             if matched_second {
-                let rule = ParsedRule::from_chain_rule_data(data).unwrap_or_default();
+                let rule = ParsedRule::<V>::from_chain_rule_data(data).unwrap_or_default();
                 if apply_chain_with_sequences(ctx, &rule, &match_funcs).is_some() {
                     if let Some(unsafe_to) = unsafe_to {
                         ctx.buffer
@@ -1088,7 +1119,7 @@ fn apply_chain_context_rules<
                     let Some(d) = chain_rule_data_at(set_data, off) else {
                         continue;
                     };
-                    if chain_rule_first_input(&d) != Some(first_glyph_value) {
+                    if chain_rule_first_input::<V>(&d) != Some(first_glyph_value) {
                         break;
                     }
                 }
@@ -0,0 +1,196 @@
+//! Context and chained context subtable formats with 24-bit glyph ids,
+//! classes and offsets, from the
+//! [boring expansion](https://github.com/harfbuzz/boring-expansion-spec/blob/main/beyond-64k.md)
+//! of the OpenType spec.
+//!
+//! The rule sets keep their 16-bit offsets to the rules, and the rules only
+//! differ from the standard ones in the width of their glyph ids and classes,
+//! so they are matched by the same code as formats 1 and 2.
+
+use super::{apply_chain_context_rules, apply_context_rules, ParsedRule};
+use crate::hb::buffer::GlyphInfo;
+use crate::hb::ot::gsub::beyond_64k::{offset24_target, read_u16, read_u24, subtable};
+use crate::hb::ot::{ClassDefInfo, CoverageInfo};
+use crate::hb::ot_layout_gsubgpos::OT::hb_ot_apply_context_t;
+use crate::hb::ot_layout_gsubgpos::{match_glyph, Apply, WouldApply, WouldApplyContext};
+use read_fonts::types::{BigEndian, GlyphId, Offset16, Uint24};
+use read_fonts::{FontData, FontRead, ReadError};
+
+subtable!(
+    /// Context substitution or positioning by glyph sequences, with 24-bit
+    /// offsets and glyph ids.
+    ContextFormat4,
+    4,
+    7
+);
+subtable!(
+    /// Context substitution or positioning by class sequences, with 24-bit
+    /// offsets.
+    ContextFormat5,
+    5,
+    10
+);
+subtable!(
+    /// Chained context substitution or positioning by glyph sequences, with
+    /// 24-bit offsets and glyph ids.
+    ChainContextFormat4,
+    4,
+    7
+);
+subtable!(
+    /// Chained context substitution or positioning by class sequences, with
+    /// 24-bit offsets.
+    ChainContextFormat5,
+    5,
+    16
+);
+
+/// A rule set: its data and the 16-bit offsets to its rules.
+type RuleSet<'a> = (FontData<'a>, &'a [BigEndian<Offset16>]);
+
+/// Returns the `index`th rule set of the `Array16Of<Offset24To<RuleSet>>`
+/// at `offset`.
+fn rule_set<'a>(data: &FontData<'a>, offset: usize, index: usize) -> Option<RuleSet<'a>> {
+    let set = offset24_target(data, offset, index)?;
+    let count = read_u16(&set, 0)? as usize;
+    let offsets = set.read_array(2..2 + count * 2).ok()?;
+    Some((set, offsets))
+}
+
+/// Returns the class definition at the 24-bit offset at `offset`.
+fn class_def(data: &FontData, offset: usize) -> Option<ClassDefInfo> {
+    ClassDefInfo::new_large(data, read_u24(data, offset)?)
+}
+
+fn class(class_def: Option<&ClassDefInfo>, data: &FontData, gid: GlyphId) -> u16 {
+    class_def.map_or(0, |class_def| class_def.class(data, gid))
+}
+
+/// Whether any rule of the set matches `ctx.glyphs` exactly.
+fn rule_set_would_apply(
+    ctx: &WouldApplyContext,
+    (set, offsets): RuleSet,
+    is_chain: bool,
+    match_func: impl Fn(&mut GlyphInfo, u32) -> bool,
+) -> bool {
+    offsets.iter().any(|off| {
+        let Some(data) = set.split_off(off.get().to_u32() as usize) else {
+            return false;
+        };
+        let rule = if is_chain {
+            ParsedRule::<Uint24>::from_chain_rule_data(data)
+        } else {
+            ParsedRule::<Uint24>::from_rule_data(data)
+        };
+        rule.is_some_and(|rule| {
+            (!ctx.zero_context || (rule.backtrack.is_empty() && rule.lookahead.is_empty()))
+                && ctx.glyphs.len() == rule.input.len() + 1
+                && rule.input.iter().enumerate().all(|(i, value)| {
+                    let mut info = GlyphInfo {
+                        glyph_id: ctx.glyphs[i + 1].into(),
+                        ..GlyphInfo::default()
+                    };
+                    match_func(&mut info, value.get().to_u32())
+                })
+        })
+    })
+}
+
+impl WouldApply for ContextFormat4<'_> {
+    fn would_apply(&self, ctx: &WouldApplyContext) -> bool {
+        self.coverage_index(ctx.glyphs[0])
+            .and_then(|index| rule_set(&self.data, 5, index))
+            .is_some_and(|set| rule_set_would_apply(ctx, set, false, match_glyph))
+    }
+}
+
+impl Apply for ContextFormat4<'_> {
+    fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
+        let index = self.coverage_index(ctx.buffer.cur(0).as_glyph())?;
+        let (set, offsets) = rule_set(&self.data, 5, index)?;
+        apply_context_rules::<Uint24>(ctx, set, offsets, match_glyph)
+    }
+}
+
+impl WouldApply for ContextFormat5<'_> {
+    fn would_apply(&self, ctx: &WouldApplyContext) -> bool {
+        let class_def = class_def(&self.data, 5);
+        let input_class = |gid| class(class_def.as_ref(), &self.data, gid);
+        rule_set(&self.data, 8, input_class(ctx.glyphs[0]).into()).is_some_and(|set| {
+            rule_set_would_apply(ctx, set, false, |info, value| {
+                u32::from(input_class(info.as_glyph())) == value
+            })
+        })
+    }
+}
+
+impl Apply for ContextFormat5<'_> {
+    fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
+        let glyph = ctx.buffer.cur(0).as_glyph();
+        self.coverage_index(glyph)?;
+        let class_def = class_def(&self.data, 5);
+        let input_class = |gid| class(class_def.as_ref(), &self.data, gid);
+        let (set, offsets) = rule_set(&self.data, 8, input_class(glyph).into())?;
+        apply_context_rules::<Uint24>(ctx, set, offsets, |info, value| {
+            u32::from(input_class(info.as_glyph())) == value
+        })
+    }
+}
+
+impl WouldApply for ChainContextFormat4<'_> {
+    fn would_apply(&self, ctx: &WouldApplyContext) -> bool {
+        self.coverage_index(ctx.glyphs[0])
+            .and_then(|index| rule_set(&self.data, 5, index))
+            .is_some_and(|set| rule_set_would_apply(ctx, set, true, match_glyph))
+    }
+}
+
+impl Apply for ChainContextFormat4<'_> {
+    fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
+        let index = self.coverage_index(ctx.buffer.cur(0).as_glyph())?;
+        let (set, offsets) = rule_set(&self.data, 5, index)?;
+        apply_chain_context_rules::<Uint24, _, _, _>(
+            ctx,
+            set,
+            offsets,
+            (match_glyph, match_glyph, match_glyph),
+        )
+    }
+}
+
+impl WouldApply for ChainContextFormat5<'_> {
+    fn would_apply(&self, ctx: &WouldApplyContext) -> bool {
+        let class_def = class_def(&self.data, 8);
+        let input_class = |gid| class(class_def.as_ref(), &self.data, gid);
+        rule_set(&self.data, 14, input_class(ctx.glyphs[0]).into()).is_some_and(|set| {
+            rule_set_would_apply(ctx, set, true, |info, value| {
+                u32::from(input_class(info.as_glyph())) == value
+            })
+        })
+    }
+}
+
+impl Apply for ChainContextFormat5<'_> {
+    fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
+        let glyph = ctx.buffer.cur(0).as_glyph();
+        self.coverage_index(glyph)?;
+        let data = &self.data;
+        let [backtrack, input, lookahead] = [5, 8, 11].map(|offset| class_def(data, offset));
+        let input_class = |gid| class(input.as_ref(), data, gid);
+        let (set, offsets) = rule_set(data, 14, input_class(glyph).into())?;
+        apply_chain_context_rules::<Uint24, _, _, _>(
+            ctx,
+            set,
+            offsets,
+            (
+                |info: &mut GlyphInfo, value| {
+                    u32::from(class(backtrack.as_ref(), data, info.as_glyph())) == value
+                },
+                |info: &mut GlyphInfo, value| u32::from(input_class(info.as_glyph())) == value,
+                |info: &mut GlyphInfo, value| {
+                    u32::from(class(lookahead.as_ref(), data, info.as_glyph())) == value
+                },
+            ),
+        )
+    }
+}
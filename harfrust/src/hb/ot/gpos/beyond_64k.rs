@@ -0,0 +1,179 @@
+//! GPOS subtable formats with 24-bit glyph ids and offsets, from the
+//! [boring expansion](https://github.com/harfbuzz/boring-expansion-spec/blob/main/beyond-64k.md)
+//! of the OpenType spec.
+//!
+//! read-fonts doesn't parse these formats, so their headers are read directly
+//! from the subtable data here. The mark, base and ligature arrays they point
+//! to keep their 16-bit offsets and are parsed by read-fonts.
+
+use super::mark::{apply_mark_base, apply_mark_lig, apply_mark_mark};
+use super::pair::{apply_pair, find_pair_record};
+use crate::hb::ot::gsub::beyond_64k::{offset24_target, read_u16, read_u24, subtable};
+use crate::hb::ot::{ClassDefInfo, CoverageInfo};
+use crate::hb::ot_layout_gsubgpos::Apply;
+use crate::hb::ot_layout_gsubgpos::OT::hb_ot_apply_context_t;
+use read_fonts::tables::gpos::{BaseArray, LigatureArray, Mark2Array, MarkArray, ValueFormat};
+use read_fonts::types::GlyphId;
+use read_fonts::{FontData, FontRead, FontReadWithArgs, ReadError};
+
+subtable!(
+    /// Pair adjustment by glyph pairs, with 24-bit offsets and glyph ids.
+    PairPosFormat3,
+    3,
+    11
+);
+subtable!(
+    /// Pair adjustment by glyph classes, with 24-bit offsets.
+    PairPosFormat4,
+    4,
+    19
+);
+subtable!(
+    /// Mark-to-base attachment with 24-bit offsets.
+    MarkBasePosFormat2,
+    2,
+    16
+);
+subtable!(
+    /// Mark-to-ligature attachment with 24-bit offsets.
+    MarkLigPosFormat2,
+    2,
+    16
+);
+subtable!(
+    /// Mark-to-mark attachment with 24-bit offsets.
+    MarkMarkPosFormat2,
+    2,
+    16
+);
+
+/// Reads the two value formats, which follow the coverage offset in both
+/// pair adjustment formats.
+fn value_formats(data: &FontData) -> Option<(ValueFormat, ValueFormat)> {
+    Some((
+        ValueFormat::from_bits_truncate(read_u16(data, 5)?),
+        ValueFormat::from_bits_truncate(read_u16(data, 7)?),
+    ))
+}
+
+/// Returns the data pointed to by the 24-bit offset at `offset`.
+fn offset24_data<'a>(data: &FontData<'a>, offset: usize) -> Option<FontData<'a>> {
+    match read_u24(data, offset)? {
+        0 => None,
+        target => data.split_off(target as usize),
+    }
+}
+
+impl Apply for PairPosFormat3<'_> {
+    fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
+        let index = self.coverage_index(ctx.buffer.cur(0).as_glyph())?;
+        let (format1, format2) = value_formats(&self.data)?;
+        let record_size = 3 + format1.record_byte_len() + format2.record_byte_len();
+        apply_pair(ctx, format1, format2, |second_glyph| {
+            let set_data = offset24_target(&self.data, 9, index)?;
+            let record_offset = find_pair_record(&set_data, second_glyph, record_size, |offset| {
+                read_u24(&set_data, offset)
+            })?;
+            Some((set_data, record_offset + 3))
+        })
+    }
+}
+
+impl PairPosFormat4<'_> {
+    fn class(&self, class_def_offset: usize, gid: GlyphId) -> u16 {
+        read_u24(&self.data, class_def_offset)
+            .and_then(|offset| ClassDefInfo::new_large(&self.data, offset))
+            .map_or(0, |class_def| class_def.class(&self.data, gid))
+    }
+}
+
+impl Apply for PairPosFormat4<'_> {
+    fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
+        let first_glyph = ctx.buffer.cur(0).as_glyph();
+        self.coverage_index(first_glyph)?;
+        let (format1, format2) = value_formats(&self.data)?;
+        let record_size = format1.record_byte_len() + format2.record_byte_len();
+        apply_pair(ctx, format1, format2, |second_glyph| {
+            let class1 = self.class(9, first_glyph);
+            let class2 = self.class(12, second_glyph);
+            let class1_count = read_u16(&self.data, 15)?;
+            let class2_count = read_u16(&self.data, 17)?;
+            if class1 >= class1_count || class2 >= class2_count {
+                return None;
+            }
+            let record = class1 as usize * class2_count as usize + class2 as usize;
+            Some((self.data, 19 + record * record_size))
+        })
+    }
+}
+
+/// The mark attachment formats share their layout: the mark coverage, the
+/// coverage of the glyphs marks attach to, the mark class count, the mark
+/// array and the array of anchors for the glyphs marks attach to.
+macro_rules! mark_attachment {
+    ($name:ident) => {
+        impl<'a> $name<'a> {
+            fn attach_coverage(&self) -> Option<CoverageInfo> {
+                CoverageInfo::new_large(&self.data, read_u24(&self.data, 5)?)
+            }
+
+            fn mark_class_count(&self) -> Option<u16> {
+                read_u16(&self.data, 8)
+            }
+
+            fn mark_array(&self) -> Option<MarkArray<'a>> {
+                MarkArray::read(offset24_data(&self.data, 10)?).ok()
+            }
+
+            fn attach_array<T: FontReadWithArgs<'a, Args = u16>>(&self) -> Option<T> {
+                let data = offset24_data(&self.data, 13)?;
+                T::read_with_args(data, &self.mark_class_count()?).ok()
+            }
+        }
+    };
+}
+
+mark_attachment!(MarkBasePosFormat2);
+mark_attachment!(MarkLigPosFormat2);
+mark_attachment!(MarkMarkPosFormat2);
+
+impl Apply for MarkBasePosFormat2<'_> {
+    fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
+        let mark_index = self.coverage_index(ctx.buffer.cur(0).as_glyph())?;
+        let base_coverage = self.attach_coverage()?;
+        apply_mark_base(
+            ctx,
+            mark_index,
+            |gid| base_coverage.index(&self.data, gid),
+            || self.mark_array(),
+            || self.attach_array::<BaseArray>(),
+        )
+    }
+}
+
+impl Apply for MarkLigPosFormat2<'_> {
+    fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
+        let mark_index = self.coverage_index(ctx.buffer.cur(0).as_glyph())?;
+        let ligature_coverage = self.attach_coverage()?;
+        apply_mark_lig(
+            ctx,
+            mark_index,
+            |gid| ligature_coverage.index(&self.data, gid),
+            || self.attach_array::<LigatureArray>(),
+            || self.mark_array(),
+        )
+    }
+}
+
+impl Apply for MarkMarkPosFormat2<'_> {
+    fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
+        let mark1_index = self.coverage_index(ctx.buffer.cur(0).as_glyph())?;
+        apply_mark_mark(
+            ctx,
+            mark1_index,
+            |gid| self.attach_coverage()?.index(&self.data, gid),
+            || self.mark_array(),
+            || self.attach_array::<Mark2Array>(),
+        )
+    }
+}
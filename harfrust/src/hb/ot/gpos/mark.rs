@@ -4,8 +4,10 @@ use crate::hb::ot_layout_gpos_table::attach_type;
 use crate::hb::ot_layout_gsubgpos::OT::hb_ot_apply_context_t;
 use crate::hb::ot_layout_gsubgpos::{match_t, skipping_iterator_t, Apply, MatchSource};
 use read_fonts::tables::gpos::{
-    AnchorTable, MarkArray, MarkBasePosFormat1, MarkLigPosFormat1, MarkMarkPosFormat1,
+    AnchorTable, BaseArray, LigatureArray, Mark2Array, MarkArray, MarkBasePosFormat1,
+    MarkLigPosFormat1, MarkMarkPosFormat1,
 };
+use read_fonts::types::GlyphId;
 
 fn resolve_cross_offset(
     pos: &[GlyphPosition],
@@ -94,79 +96,96 @@ impl MarkArrayExt for MarkArray<'_> {
 impl Apply for MarkBasePosFormat1<'_> {
     fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
         let mark_glyph = ctx.buffer.cur(0).as_glyph();
-        let mark_index = self.mark_coverage().ok()?.get(mark_glyph)?;
-
+        let mark_index = self.mark_coverage().ok()?.get(mark_glyph)? as usize;
         let base_coverage = self.base_coverage().ok()?;
-        let last_base_until = ctx.last_base_until;
-        let mut last_base = ctx.last_base;
-
-        // Due to borrowing rules, we have this piece of code before creating the
-        // iterator, unlike in harfbuzz.
-        if ctx.last_base_until > ctx.buffer.idx as u32 {
-            ctx.last_base_until = 0;
-            ctx.last_base = -1;
-        }
+        apply_mark_base(
+            ctx,
+            mark_index,
+            |gid| base_coverage.get(gid),
+            || self.mark_array().ok(),
+            || self.base_array().ok(),
+        )
+    }
+}
 
-        // Now we search backwards for a non-mark glyph
-        // We don't use skippy_iter.prev() to avoid O(n^2) behavior.
-        let mut iter = skipping_iterator_t::new(ctx, false);
-        iter.set_lookup_props(u32::from(lookup_flags::IGNORE_MARKS));
-
-        let mut j = iter.buffer.idx;
-        while j > last_base_until as usize {
-            let mut _match = iter.match_at(j - 1, MatchSource::Info);
-            if _match == match_t::MATCH {
-                // https://github.com/harfbuzz/harfbuzz/issues/4124
-                if !accept(iter.buffer, j - 1)
-                    && base_coverage
-                        .get(iter.buffer.info[j - 1].as_glyph())
-                        .is_none()
-                {
-                    _match = match_t::SKIP;
-                }
-            }
+/// Attaches the current mark glyph, with the given index in the mark
+/// coverage, to the preceding base glyph.
+///
+/// `base_coverage` returns the index of a glyph in the base coverage.
+pub(super) fn apply_mark_base<'a>(
+    ctx: &mut hb_ot_apply_context_t,
+    mark_index: usize,
+    base_coverage: impl Fn(GlyphId) -> Option<u16>,
+    mark_array: impl FnOnce() -> Option<MarkArray<'a>>,
+    base_array: impl FnOnce() -> Option<BaseArray<'a>>,
+) -> Option<()> {
+    let last_base_until = ctx.last_base_until;
+    let mut last_base = ctx.last_base;
+
+    // Due to borrowing rules, we have this piece of code before creating the
+    // iterator, unlike in harfbuzz.
+    if ctx.last_base_until > ctx.buffer.idx as u32 {
+        ctx.last_base_until = 0;
+        ctx.last_base = -1;
+    }
 
-            if _match == match_t::MATCH {
-                last_base = j as i32 - 1;
-                break;
+    // Now we search backwards for a non-mark glyph
+    // We don't use skippy_iter.prev() to avoid O(n^2) behavior.
+    let mut iter = skipping_iterator_t::new(ctx, false);
+    iter.set_lookup_props(u32::from(lookup_flags::IGNORE_MARKS));
+
+    let mut j = iter.buffer.idx;
+    while j > last_base_until as usize {
+        let mut _match = iter.match_at(j - 1, MatchSource::Info);
+        if _match == match_t::MATCH {
+            // https://github.com/harfbuzz/harfbuzz/issues/4124
+            if !accept(iter.buffer, j - 1)
+                && base_coverage(iter.buffer.info[j - 1].as_glyph()).is_none()
+            {
+                _match = match_t::SKIP;
             }
-
-            j -= 1;
         }
-        ctx.last_base_until = ctx.buffer.idx as u32;
-        ctx.last_base = last_base;
 
-        if ctx.last_base == -1 {
-            ctx.buffer
-                .unsafe_to_concat_from_outbuffer(Some(0), Some(ctx.buffer.idx + 1));
-            return None;
+        if _match == match_t::MATCH {
+            last_base = j as i32 - 1;
+            break;
         }
 
-        let idx = ctx.last_base as u32;
+        j -= 1;
+    }
+    ctx.last_base_until = ctx.buffer.idx as u32;
+    ctx.last_base = last_base;
 
-        let info = &ctx.buffer.info;
+    if ctx.last_base == -1 {
+        ctx.buffer
+            .unsafe_to_concat_from_outbuffer(Some(0), Some(ctx.buffer.idx + 1));
+        return None;
+    }
 
-        // Checking that matched glyph is actually a base glyph by GDEF is too strong; disabled
-        let base_glyph = info[idx as usize].as_glyph();
-        let Some(base_index) = base_coverage.get(base_glyph) else {
-            ctx.buffer
-                .unsafe_to_concat_from_outbuffer(Some(idx as usize), Some(ctx.buffer.idx + 1));
-            return None;
-        };
+    let idx = ctx.last_base as u32;
 
-        let mark_array = self.mark_array().ok()?;
-        let mark_record = mark_array.mark_records().get(mark_index as usize)?;
-        let mark_anchor = mark_record.mark_anchor(mark_array.offset_data()).ok()?;
+    let info = &ctx.buffer.info;
 
-        let base_array = self.base_array().ok()?;
-        let base_record = base_array.base_records().get(base_index as usize).ok()?;
-        let base_anchor = base_record
-            .base_anchors(base_array.offset_data())
-            .get(mark_record.mark_class() as usize)?
-            .ok()?;
+    // Checking that matched glyph is actually a base glyph by GDEF is too strong; disabled
+    let base_glyph = info[idx as usize].as_glyph();
+    let Some(base_index) = base_coverage(base_glyph) else {
+        ctx.buffer
+            .unsafe_to_concat_from_outbuffer(Some(idx as usize), Some(ctx.buffer.idx + 1));
+        return None;
+    };
 
-        mark_array.apply(ctx, &base_anchor, &mark_anchor, idx as usize)
-    }
+    let mark_array = mark_array()?;
+    let mark_record = mark_array.mark_records().get(mark_index)?;
+    let mark_anchor = mark_record.mark_anchor(mark_array.offset_data()).ok()?;
+
+    let base_array = base_array()?;
+    let base_record = base_array.base_records().get(base_index as usize).ok()?;
+    let base_anchor = base_record
+        .base_anchors(base_array.offset_data())
+        .get(mark_record.mark_class() as usize)?
+        .ok()?;
+
+    mark_array.apply(ctx, &base_anchor, &mark_anchor, idx as usize)
 }
 
 fn accept(buffer: &hb_buffer_t, idx: usize) -> bool {
@@ -187,64 +206,84 @@ fn accept(buffer: &hb_buffer_t, idx: usize) -> bool {
 impl Apply for MarkMarkPosFormat1<'_> {
     fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
         let mark1_glyph = ctx.buffer.cur(0).as_glyph();
-        let mark1_index = self.mark1_coverage().ok()?.get(mark1_glyph)?;
-        let lookup_props = ctx.lookup_props;
-        // Now we search backwards for a suitable mark glyph until a non-mark glyph
-        let mut iter = skipping_iterator_t::new(ctx, false);
-        iter.reset_fast(iter.buffer.idx);
-        iter.set_lookup_props(lookup_props & !u32::from(lookup_flags::IGNORE_FLAGS));
-
-        let mut unsafe_from = 0;
-        if !iter.prev(Some(&mut unsafe_from)) {
-            iter.buffer
-                .unsafe_to_concat_from_outbuffer(Some(unsafe_from), Some(iter.buffer.idx + 1));
-            return None;
-        }
+        let mark1_index = self.mark1_coverage().ok()?.get(mark1_glyph)? as usize;
+        apply_mark_mark(
+            ctx,
+            mark1_index,
+            |gid| self.mark2_coverage().ok()?.get(gid),
+            || self.mark1_array().ok(),
+            || self.mark2_array().ok(),
+        )
+    }
+}
 
-        let iter_idx = iter.index();
-        if !ctx.buffer.info[iter_idx].is_mark() {
-            ctx.buffer
-                .unsafe_to_concat_from_outbuffer(Some(iter_idx), Some(ctx.buffer.idx + 1));
-            return None;
-        }
+/// Attaches the current mark glyph, with the given index in the mark1
+/// coverage, to the preceding mark glyph.
+///
+/// `mark2_coverage` returns the index of a glyph in the mark2 coverage.
+pub(super) fn apply_mark_mark<'a>(
+    ctx: &mut hb_ot_apply_context_t,
+    mark1_index: usize,
+    mark2_coverage: impl FnOnce(GlyphId) -> Option<u16>,
+    mark1_array: impl FnOnce() -> Option<MarkArray<'a>>,
+    mark2_array: impl FnOnce() -> Option<Mark2Array<'a>>,
+) -> Option<()> {
+    let lookup_props = ctx.lookup_props;
+    // Now we search backwards for a suitable mark glyph until a non-mark glyph
+    let mut iter = skipping_iterator_t::new(ctx, false);
+    iter.reset_fast(iter.buffer.idx);
+    iter.set_lookup_props(lookup_props & !u32::from(lookup_flags::IGNORE_FLAGS));
+
+    let mut unsafe_from = 0;
+    if !iter.prev(Some(&mut unsafe_from)) {
+        iter.buffer
+            .unsafe_to_concat_from_outbuffer(Some(unsafe_from), Some(iter.buffer.idx + 1));
+        return None;
+    }
 
-        let id1 = ctx.buffer.cur(0).lig_id();
-        let id2 = ctx.buffer.info[iter_idx].lig_id();
-        let comp1 = ctx.buffer.cur(0).lig_comp();
-        let comp2 = ctx.buffer.info[iter_idx].lig_comp();
+    let iter_idx = iter.index();
+    if !ctx.buffer.info[iter_idx].is_mark() {
+        ctx.buffer
+            .unsafe_to_concat_from_outbuffer(Some(iter_idx), Some(ctx.buffer.idx + 1));
+        return None;
+    }
 
-        let matches = if id1 == id2 {
-            // Marks belonging to the same base
-            // or marks belonging to the same ligature component.
-            id1 == 0 || comp1 == comp2
-        } else {
-            // If ligature ids don't match, it may be the case that one of the marks
-            // itself is a ligature.  In which case match.
-            (id1 > 0 && comp1 == 0) || (id2 > 0 && comp2 == 0)
-        };
-
-        if !matches {
-            ctx.buffer
-                .unsafe_to_concat_from_outbuffer(Some(iter_idx), Some(ctx.buffer.idx + 1));
-            return None;
-        }
+    let id1 = ctx.buffer.cur(0).lig_id();
+    let id2 = ctx.buffer.info[iter_idx].lig_id();
+    let comp1 = ctx.buffer.cur(0).lig_comp();
+    let comp2 = ctx.buffer.info[iter_idx].lig_comp();
 
-        let mark2_glyph = ctx.buffer.info[iter_idx].as_glyph();
-        let mark2_index = self.mark2_coverage().ok()?.get(mark2_glyph)?;
+    let matches = if id1 == id2 {
+        // Marks belonging to the same base
+        // or marks belonging to the same ligature component.
+        id1 == 0 || comp1 == comp2
+    } else {
+        // If ligature ids don't match, it may be the case that one of the marks
+        // itself is a ligature.  In which case match.
+        (id1 > 0 && comp1 == 0) || (id2 > 0 && comp2 == 0)
+    };
 
-        let mark1_array = self.mark1_array().ok()?;
-        let mark1_record = mark1_array.mark_records().get(mark1_index as usize)?;
-        let mark1_anchor = mark1_record.mark_anchor(mark1_array.offset_data()).ok()?;
+    if !matches {
+        ctx.buffer
+            .unsafe_to_concat_from_outbuffer(Some(iter_idx), Some(ctx.buffer.idx + 1));
+        return None;
+    }
 
-        let base_array = self.mark2_array().ok()?;
-        let base_record = base_array.mark2_records().get(mark2_index as usize).ok()?;
-        let base_anchor = base_record
-            .mark2_anchors(base_array.offset_data())
-            .get(mark1_record.mark_class() as usize)?
-            .ok()?;
+    let mark2_glyph = ctx.buffer.info[iter_idx].as_glyph();
+    let mark2_index = mark2_coverage(mark2_glyph)?;
 
-        mark1_array.apply(ctx, &base_anchor, &mark1_anchor, iter_idx)
-    }
+    let mark1_array = mark1_array()?;
+    let mark1_record = mark1_array.mark_records().get(mark1_index)?;
+    let mark1_anchor = mark1_record.mark_anchor(mark1_array.offset_data()).ok()?;
+
+    let base_array = mark2_array()?;
+    let base_record = base_array.mark2_records().get(mark2_index as usize).ok()?;
+    let base_anchor = base_record
+        .mark2_anchors(base_array.offset_data())
+        .get(mark1_record.mark_class() as usize)?
+        .ok()?;
+
+    mark1_array.apply(ctx, &base_anchor, &mark1_anchor, iter_idx)
 }
 
 impl Apply for MarkLigPosFormat1<'_> {
@@ -252,102 +291,118 @@ impl Apply for MarkLigPosFormat1<'_> {
         let mark_glyph = ctx.buffer.cur(0).as_glyph();
         let mark_index = self.mark_coverage().ok()?.get(mark_glyph)? as usize;
         let ligature_coverage = self.ligature_coverage().ok()?;
+        apply_mark_lig(
+            ctx,
+            mark_index,
+            |gid| ligature_coverage.get(gid),
+            || self.ligature_array().ok(),
+            || self.mark_array().ok(),
+        )
+    }
+}
 
-        // Due to borrowing rules, we have this piece of code before creating the
-        // iterator, unlike in harfbuzz.
-        if ctx.last_base_until > ctx.buffer.idx as u32 {
-            ctx.last_base_until = 0;
-            ctx.last_base = -1;
-        }
+/// Attaches the current mark glyph, with the given index in the mark
+/// coverage, to a component of the preceding ligature glyph.
+///
+/// `ligature_coverage` returns the index of a glyph in the ligature
+/// coverage.
+pub(super) fn apply_mark_lig<'a>(
+    ctx: &mut hb_ot_apply_context_t,
+    mark_index: usize,
+    ligature_coverage: impl Fn(GlyphId) -> Option<u16>,
+    ligature_array: impl FnOnce() -> Option<LigatureArray<'a>>,
+    mark_array: impl FnOnce() -> Option<MarkArray<'a>>,
+) -> Option<()> {
+    // Due to borrowing rules, we have this piece of code before creating the
+    // iterator, unlike in harfbuzz.
+    if ctx.last_base_until > ctx.buffer.idx as u32 {
+        ctx.last_base_until = 0;
+        ctx.last_base = -1;
+    }
 
-        let last_base_until = ctx.last_base_until;
-        let mut last_base = ctx.last_base;
-
-        // Now we search backwards for a non-mark glyph
-        let mut iter = skipping_iterator_t::new(ctx, false);
-        iter.set_lookup_props(u32::from(lookup_flags::IGNORE_MARKS));
-
-        let mut j = iter.buffer.idx;
-        while j > last_base_until as usize {
-            let mut _match = iter.match_at(j - 1, MatchSource::Info);
-            if _match == match_t::MATCH
-                && !accept_mark_ligature(iter.buffer, j - 1)
-                && ligature_coverage
-                    .get(iter.buffer.info[j - 1].as_glyph())
-                    .is_none()
-            {
-                _match = match_t::SKIP;
-            }
-            if _match == match_t::MATCH {
-                last_base = j as i32 - 1;
-                break;
-            }
-            j -= 1;
+    let last_base_until = ctx.last_base_until;
+    let mut last_base = ctx.last_base;
+
+    // Now we search backwards for a non-mark glyph
+    let mut iter = skipping_iterator_t::new(ctx, false);
+    iter.set_lookup_props(u32::from(lookup_flags::IGNORE_MARKS));
+
+    let mut j = iter.buffer.idx;
+    while j > last_base_until as usize {
+        let mut _match = iter.match_at(j - 1, MatchSource::Info);
+        if _match == match_t::MATCH
+            && !accept_mark_ligature(iter.buffer, j - 1)
+            && ligature_coverage(iter.buffer.info[j - 1].as_glyph()).is_none()
+        {
+            _match = match_t::SKIP;
         }
+        if _match == match_t::MATCH {
+            last_base = j as i32 - 1;
+            break;
+        }
+        j -= 1;
+    }
 
-        ctx.last_base_until = ctx.buffer.idx as u32;
-        ctx.last_base = last_base;
+    ctx.last_base_until = ctx.buffer.idx as u32;
+    ctx.last_base = last_base;
 
-        if ctx.last_base == -1 {
-            ctx.buffer
-                .unsafe_to_concat_from_outbuffer(Some(0), Some(ctx.buffer.idx + 1));
-            return None;
-        }
+    if ctx.last_base == -1 {
+        ctx.buffer
+            .unsafe_to_concat_from_outbuffer(Some(0), Some(ctx.buffer.idx + 1));
+        return None;
+    }
 
-        let idx = ctx.last_base as usize;
-
-        // Checking that matched glyph is actually a ligature by GDEF is too strong; disabled
-
-        let lig_glyph = ctx.buffer.info[idx].as_glyph();
-        let Some(lig_index) = ligature_coverage.get(lig_glyph) else {
-            ctx.buffer
-                .unsafe_to_concat_from_outbuffer(Some(idx), Some(ctx.buffer.idx + 1));
-            return None;
-        };
-        let lig_attach = self
-            .ligature_array()
-            .ok()?
-            .ligature_attaches()
-            .get(lig_index as usize)
-            .ok()?;
-
-        // Find component to attach to
-        let comp_count = lig_attach.component_count();
-        if comp_count == 0 {
-            ctx.buffer
-                .unsafe_to_concat_from_outbuffer(Some(idx), Some(ctx.buffer.idx + 1));
-            return None;
-        }
+    let idx = ctx.last_base as usize;
 
-        // We must now check whether the ligature ID of the current mark glyph
-        // is identical to the ligature ID of the found ligature.  If yes, we
-        // can directly use the component index.  If not, we attach the mark
-        // glyph to the last component of the ligature.
-        let lig_id = ctx.buffer.info[idx].lig_id();
-        let mark_id = ctx.buffer.cur(0).lig_id();
-        let mark_comp = u16::from(ctx.buffer.cur(0).lig_comp());
-        let matches = lig_id != 0 && lig_id == mark_id && mark_comp > 0;
-        let comp_index = if matches {
-            mark_comp.min(comp_count)
-        } else {
-            comp_count
-        } - 1;
-
-        let mark_array = self.mark_array().ok()?;
-        let mark_record = mark_array.mark_records().get(mark_index)?;
-        let mark_anchor = mark_record.mark_anchor(mark_array.offset_data()).ok()?;
-
-        let base_record = lig_attach
-            .component_records()
-            .get(comp_index as usize)
-            .ok()?;
-        let base_anchor = base_record
-            .ligature_anchors(lig_attach.offset_data())
-            .get(mark_record.mark_class() as usize)?
-            .ok()?;
-
-        mark_array.apply(ctx, &base_anchor, &mark_anchor, idx)
+    // Checking that matched glyph is actually a ligature by GDEF is too strong; disabled
+
+    let lig_glyph = ctx.buffer.info[idx].as_glyph();
+    let Some(lig_index) = ligature_coverage(lig_glyph) else {
+        ctx.buffer
+            .unsafe_to_concat_from_outbuffer(Some(idx), Some(ctx.buffer.idx + 1));
+        return None;
+    };
+    let lig_attach = ligature_array()?
+        .ligature_attaches()
+        .get(lig_index as usize)
+        .ok()?;
+
+    // Find component to attach to
+    let comp_count = lig_attach.component_count();
+    if comp_count == 0 {
+        ctx.buffer
+            .unsafe_to_concat_from_outbuffer(Some(idx), Some(ctx.buffer.idx + 1));
+        return None;
     }
+
+    // We must now check whether the ligature ID of the current mark glyph
+    // is identical to the ligature ID of the found ligature.  If yes, we
+    // can directly use the component index.  If not, we attach the mark
+    // glyph to the last component of the ligature.
+    let lig_id = ctx.buffer.info[idx].lig_id();
+    let mark_id = ctx.buffer.cur(0).lig_id();
+    let mark_comp = u16::from(ctx.buffer.cur(0).lig_comp());
+    let matches = lig_id != 0 && lig_id == mark_id && mark_comp > 0;
+    let comp_index = if matches {
+        mark_comp.min(comp_count)
+    } else {
+        comp_count
+    } - 1;
+
+    let mark_array = mark_array()?;
+    let mark_record = mark_array.mark_records().get(mark_index)?;
+    let mark_anchor = mark_record.mark_anchor(mark_array.offset_data()).ok()?;
+
+    let base_record = lig_attach
+        .component_records()
+        .get(comp_index as usize)
+        .ok()?;
+    let base_anchor = base_record
+        .ligature_anchors(lig_attach.offset_data())
+        .get(mark_record.mark_class() as usize)?
+        .ok()?;
+
+    mark_array.apply(ctx, &base_anchor, &mark_anchor, idx)
 }
 
 fn accept_mark_ligature(buffer: &hb_buffer_t, idx: usize) -> bool {
@@ -6,6 +6,8 @@ use read_fonts::{
     FontData,
 };
 
+#[cfg(feature = "beyond_64k")]
+pub mod beyond_64k;
 mod cursive;
 mod mark;
 mod pair;
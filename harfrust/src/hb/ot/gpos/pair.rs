@@ -6,7 +6,9 @@ use crate::hb::ot_layout_gsubgpos::{
     PairPosFormat2SmallCache, SubtableExternalCache, SubtableExternalCacheMode,
 };
 use alloc::boxed::Box;
-use read_fonts::tables::gpos::{PairPosFormat1, PairPosFormat2};
+use read_fonts::tables::gpos::{PairPosFormat1, PairPosFormat2, ValueFormat};
+use read_fonts::types::GlyphId;
+use read_fonts::FontData;
 
 impl Apply for PairPosFormat1<'_> {
     fn apply_with_external_cache(
@@ -28,101 +30,21 @@ impl Apply for PairPosFormat1<'_> {
             _ => coverage_index(self.coverage(), first_glyph)?,
         };
 
-        let mut iter = skipping_iterator_t::new(ctx, false);
-        iter.reset(iter.buffer.idx);
-
-        let mut unsafe_to = 0;
-        if !iter.next(Some(&mut unsafe_to)) {
-            ctx.buffer
-                .unsafe_to_concat(Some(ctx.buffer.idx), Some(unsafe_to));
-            return None;
-        }
-
-        let second_glyph_index = iter.index();
-        let second_glyph = iter.buffer.info[second_glyph_index].as_glyph();
-
-        let finish = |ctx: &mut hb_ot_apply_context_t, iter_index: &mut usize, has_record2| {
-            if has_record2 {
-                *iter_index += 1;
-                // https://github.com/harfbuzz/harfbuzz/issues/3824
-                // https://github.com/harfbuzz/harfbuzz/issues/3888#issuecomment-1326781116
-                ctx.buffer
-                    .unsafe_to_break(Some(ctx.buffer.idx), Some(*iter_index + 1));
-            }
-
-            ctx.buffer.idx = *iter_index;
-
-            Some(())
-        };
-
-        let boring = |ctx: &mut hb_ot_apply_context_t, iter_index: &mut usize, has_record2| {
-            ctx.buffer
-                .unsafe_to_concat(Some(ctx.buffer.idx), Some(second_glyph_index + 1));
-            finish(ctx, iter_index, has_record2)
-        };
-
-        let success =
-            |ctx: &mut hb_ot_apply_context_t, iter_index: &mut usize, flag1, flag2, has_record2| {
-                if flag1 || flag2 {
-                    ctx.buffer
-                        .unsafe_to_break(Some(ctx.buffer.idx), Some(second_glyph_index + 1));
-                    finish(ctx, iter_index, has_record2)
-                } else {
-                    boring(ctx, iter_index, has_record2)
-                }
-            };
-
-        let mut buf_idx = iter.buf_idx;
-        let set_offset = self
-            .pair_set_offsets()
-            .get(first_glyph_coverage_index as usize)?
-            .get()
-            .to_u32() as usize;
         let format1 = self.value_format1();
-        let format1_len = format1.record_byte_len();
         let format2 = self.value_format2();
-        let record_size = format1_len + format2.record_byte_len() + 2;
-        let data = self.offset_data();
-        let set_data = data.split_off(set_offset)?;
-        let pair_count = set_data.read_at::<u16>(0).ok()? as usize;
-        let mut hi = pair_count;
-        let mut lo = 0;
-        while lo < hi {
-            // This recommends using usize::midpoint which expands to u128.
-            // We definitely do not want to do that here since the input values
-            // are 16-bit.
-            #[allow(clippy::manual_midpoint)]
-            let mid = (lo + hi) / 2;
-            let record_offset = 2 + mid * record_size;
-            let glyph_id = set_data
-                .read_at::<read_fonts::types::GlyphId16>(record_offset)
-                .ok()?;
-            if glyph_id < second_glyph {
-                lo = mid + 1;
-            } else if glyph_id > second_glyph {
-                hi = mid;
-            } else {
-                let has_record2 = !format2.is_empty();
-                let worked1 = !format1.is_empty()
-                    && super::apply_value(
-                        ctx,
-                        ctx.buffer.idx,
-                        &set_data,
-                        record_offset + 2,
-                        format1,
-                    ) == Some(true);
-                let worked2 = has_record2
-                    && super::apply_value(
-                        ctx,
-                        second_glyph_index,
-                        &set_data,
-                        record_offset + format1_len + 2,
-                        format2,
-                    ) == Some(true);
-                return success(ctx, &mut buf_idx, worked1, worked2, has_record2);
-            }
-        }
-        None
+        let record_size = format1.record_byte_len() + format2.record_byte_len() + 2;
+        apply_pair(ctx, format1, format2, |second_glyph| {
+            let set_offset = self
+                .pair_set_offsets()
+                .get(first_glyph_coverage_index as usize)?
+                .get()
+                .to_u32() as usize;
+            let set_data = self.offset_data().split_off(set_offset)?;
+            let record_offset = find_pair_record(&set_data, second_glyph, record_size, |offset| {
+                set_data.read_at::<u16>(offset).ok().map(u32::from)
+            })?;
+            Some((set_data, record_offset + 2))
+        })
     }
 
     fn external_cache_create(&self, mode: SubtableExternalCacheMode) -> SubtableExternalCache {
@@ -164,93 +86,39 @@ impl Apply for PairPosFormat2<'_> {
             }
             _ => coverage_index(self.coverage(), first_glyph)?,
         };
-        let mut iter = skipping_iterator_t::new(ctx, false);
-        iter.reset(iter.buffer.idx);
-
-        let mut unsafe_to = 0;
-        if !iter.next(Some(&mut unsafe_to)) {
-            ctx.buffer
-                .unsafe_to_concat(Some(ctx.buffer.idx), Some(unsafe_to));
-            return None;
-        }
-
-        let second_glyph_index = iter.index();
-        let second_glyph = iter.buffer.info[second_glyph_index].as_glyph();
-
-        let finish = |ctx: &mut hb_ot_apply_context_t, iter_index: &mut usize, has_record2| {
-            if has_record2 {
-                *iter_index += 1;
-                // https://github.com/harfbuzz/harfbuzz/issues/3824
-                // https://github.com/harfbuzz/harfbuzz/issues/3888#issuecomment-1326781116
-                ctx.buffer
-                    .unsafe_to_break(Some(ctx.buffer.idx), Some(*iter_index + 1));
-            }
-
-            ctx.buffer.idx = *iter_index;
-
-            Some(())
-        };
-
-        let boring = |ctx: &mut hb_ot_apply_context_t, iter_index: &mut usize, has_record2| {
-            ctx.buffer
-                .unsafe_to_concat(Some(ctx.buffer.idx), Some(second_glyph_index + 1));
-            finish(ctx, iter_index, has_record2)
-        };
-
-        let success =
-            |ctx: &mut hb_ot_apply_context_t, iter_index: &mut usize, flag1, flag2, has_record2| {
-                if flag1 || flag2 {
-                    ctx.buffer
-                        .unsafe_to_break(Some(ctx.buffer.idx), Some(second_glyph_index + 1));
-                    finish(ctx, iter_index, has_record2)
-                } else {
-                    boring(ctx, iter_index, has_record2)
-                }
-            };
-        let data = self.offset_data();
-        let (class1, class2) = match external_cache {
-            SubtableExternalCache::PairPosFormat2Cache(cache) => (
-                glyph_class_cached(
-                    |gid| glyph_class(self.class_def1(), gid),
-                    first_glyph,
-                    &cache.first,
-                ),
-                glyph_class_cached(
-                    |gid| glyph_class(self.class_def2(), gid),
-                    second_glyph,
-                    &cache.second,
-                ),
-            ),
-            SubtableExternalCache::PairPosFormat2SmallCache(cache) => (
-                cache.first.class(&data, first_glyph),
-                cache.second.class(&data, second_glyph),
-            ),
-            _ => (
-                glyph_class(self.class_def1(), first_glyph),
-                glyph_class(self.class_def2(), second_glyph),
-            ),
-        };
-        let mut buf_idx = iter.buf_idx;
         let format1 = self.value_format1();
-        let format1_len = format1.record_byte_len();
         let format2 = self.value_format2();
-        let record_size = format1_len + format2.record_byte_len();
-        // Compute an offset into the 2D array of positioning records
-        let record_offset = (class1 as usize * record_size * self.class2_count() as usize)
-            + (class2 as usize * record_size)
-            + self.class1_records_byte_range().start;
-        let has_record2 = !format2.is_empty();
-        let worked1 = !format1.is_empty()
-            && super::apply_value(ctx, ctx.buffer.idx, &data, record_offset, format1) == Some(true);
-        let worked2 = has_record2
-            && super::apply_value(
-                ctx,
-                second_glyph_index,
-                &data,
-                record_offset + format1_len,
-                format2,
-            ) == Some(true);
-        success(ctx, &mut buf_idx, worked1, worked2, has_record2)
+        let record_size = format1.record_byte_len() + format2.record_byte_len();
+        apply_pair(ctx, format1, format2, |second_glyph| {
+            let data = self.offset_data();
+            let (class1, class2) = match external_cache {
+                SubtableExternalCache::PairPosFormat2Cache(cache) => (
+                    glyph_class_cached(
+                        |gid| glyph_class(self.class_def1(), gid),
+                        first_glyph,
+                        &cache.first,
+                    ),
+                    glyph_class_cached(
+                        |gid| glyph_class(self.class_def2(), gid),
+                        second_glyph,
+                        &cache.second,
+                    ),
+                ),
+                SubtableExternalCache::PairPosFormat2SmallCache(cache) => (
+                    cache.first.class(&data, first_glyph),
+                    cache.second.class(&data, second_glyph),
+                ),
+                _ => (
+                    glyph_class(self.class_def1(), first_glyph),
+                    glyph_class(self.class_def2(), second_glyph),
+                ),
+            };
+            // Compute an offset into the 2D array of positioning records
+            let record_offset = (class1 as usize * record_size * self.class2_count() as usize)
+                + (class2 as usize * record_size)
+                + self.class1_records_byte_range().start;
+            Some((data, record_offset))
+        })
     }
 
     fn external_cache_create(&self, mode: SubtableExternalCacheMode) -> SubtableExternalCache {
@@ -277,3 +145,109 @@ impl Apply for PairPosFormat2<'_> {
         }
     }
 }
+
+/// Applies the value records of a pair to the current glyph and the next
+/// glyph that isn't skipped.
+///
+/// `find_values` returns the data holding the value records for the second
+/// glyph and the offset of the first of them, or `None` if the pair isn't
+/// positioned by the subtable.
+pub(super) fn apply_pair<'a>(
+    ctx: &mut hb_ot_apply_context_t,
+    format1: ValueFormat,
+    format2: ValueFormat,
+    find_values: impl FnOnce(GlyphId) -> Option<(FontData<'a>, usize)>,
+) -> Option<()> {
+    let mut iter = skipping_iterator_t::new(ctx, false);
+    iter.reset(iter.buffer.idx);
+
+    let mut unsafe_to = 0;
+    if !iter.next(Some(&mut unsafe_to)) {
+        ctx.buffer
+            .unsafe_to_concat(Some(ctx.buffer.idx), Some(unsafe_to));
+        return None;
+    }
+
+    let second_glyph_index = iter.index();
+    let second_glyph = iter.buffer.info[second_glyph_index].as_glyph();
+    let mut buf_idx = iter.buf_idx;
+
+    let finish = |ctx: &mut hb_ot_apply_context_t, iter_index: &mut usize, has_record2| {
+        if has_record2 {
+            *iter_index += 1;
+            // https://github.com/harfbuzz/harfbuzz/issues/3824
+            // https://github.com/harfbuzz/harfbuzz/issues/3888#issuecomment-1326781116
+            ctx.buffer
+                .unsafe_to_break(Some(ctx.buffer.idx), Some(*iter_index + 1));
+        }
+
+        ctx.buffer.idx = *iter_index;
+
+        Some(())
+    };
+
+    let boring = |ctx: &mut hb_ot_apply_context_t, iter_index: &mut usize, has_record2| {
+        ctx.buffer
+            .unsafe_to_concat(Some(ctx.buffer.idx), Some(second_glyph_index + 1));
+        finish(ctx, iter_index, has_record2)
+    };
+
+    let success =
+        |ctx: &mut hb_ot_apply_context_t, iter_index: &mut usize, flag1, flag2, has_record2| {
+            if flag1 || flag2 {
+                ctx.buffer
+                    .unsafe_to_break(Some(ctx.buffer.idx), Some(second_glyph_index + 1));
+                finish(ctx, iter_index, has_record2)
+            } else {
+                boring(ctx, iter_index, has_record2)
+            }
+        };
+
+    let (data, record_offset) = find_values(second_glyph)?;
+    let format1_len = format1.record_byte_len();
+    let has_record2 = !format2.is_empty();
+    let worked1 = !format1.is_empty()
+        && super::apply_value(ctx, ctx.buffer.idx, &data, record_offset, format1) == Some(true);
+    let worked2 = has_record2
+        && super::apply_value(
+            ctx,
+            second_glyph_index,
+            &data,
+            record_offset + format1_len,
+            format2,
+        ) == Some(true);
+    success(ctx, &mut buf_idx, worked1, worked2, has_record2)
+}
+
+/// Finds the record for `second_glyph` in a PairSet table, whose records of
+/// `record_size` bytes start with the glyph id read by `read_glyph`.
+///
+/// Returns the offset of the record.
+pub(super) fn find_pair_record(
+    set_data: &FontData,
+    second_glyph: GlyphId,
+    record_size: usize,
+    read_glyph: impl Fn(usize) -> Option<u32>,
+) -> Option<usize> {
+    let second_glyph = second_glyph.to_u32();
+    let pair_count = set_data.read_at::<u16>(0).ok()? as usize;
+    let mut hi = pair_count;
+    let mut lo = 0;
+    while lo < hi {
+        // This recommends using usize::midpoint which expands to u128.
+        // We definitely do not want to do that here since the input values
+        // are 16-bit.
+        #[allow(clippy::manual_midpoint)]
+        let mid = (lo + hi) / 2;
+        let record_offset = 2 + mid * record_size;
+        let glyph_id = read_glyph(record_offset)?;
+        if glyph_id < second_glyph {
+            lo = mid + 1;
+        } else if glyph_id > second_glyph {
+            hi = mid;
+        } else {
+            return Some(record_offset);
+        }
+    }
+    None
+}
@@ -1,34 +1,46 @@
 use crate::hb::ot_layout_gsubgpos::OT::hb_ot_apply_context_t;
 use crate::hb::ot_layout_gsubgpos::{Apply, WouldApply, WouldApplyContext};
 use read_fonts::tables::gsub::{AlternateSet, AlternateSubstFormat1};
+use read_fonts::types::GlyphId;
 
 impl Apply for AlternateSet<'_> {
     fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
         let alternates = self.alternate_glyph_ids();
-        let len = alternates.len() as u16;
-        if len == 0 {
-            return None;
-        }
-
-        let glyph_mask = ctx.buffer.cur(0).mask;
+        apply_alternate(ctx, alternates.len(), |idx| {
+            alternates.get(idx).map(|gid| gid.get().into())
+        })
+    }
+}
 
-        // Note: This breaks badly if two features enabled this lookup together.
-        let shift = ctx.lookup_mask().trailing_zeros();
-        let mut alt_index = (ctx.lookup_mask() & glyph_mask) >> shift;
+/// Replaces the current glyph with the alternate selected by the lookup mask.
+pub(super) fn apply_alternate(
+    ctx: &mut hb_ot_apply_context_t,
+    count: usize,
+    alternate: impl Fn(usize) -> Option<GlyphId>,
+) -> Option<()> {
+    let len = count as u16;
+    if len == 0 {
+        return None;
+    }
 
-        // If alt_index is MAX_VALUE, randomize feature if it is the rand feature.
-        if alt_index == crate::hb::ot_map::hb_ot_map_t::MAX_VALUE && ctx.random {
-            // Maybe we can do better than unsafe-to-break all; but since we are
-            // changing random state, it would be hard to track that.  Good 'nough.
-            ctx.buffer.unsafe_to_break(Some(0), Some(ctx.buffer.len));
-            alt_index = ctx.random_number() % u32::from(len) + 1;
-        }
+    let glyph_mask = ctx.buffer.cur(0).mask;
 
-        let idx = u16::try_from(alt_index).ok()?.checked_sub(1)?;
-        ctx.replace_glyph(alternates.get(idx as usize)?.get().into());
+    // Note: This breaks badly if two features enabled this lookup together.
+    let shift = ctx.lookup_mask().trailing_zeros();
+    let mut alt_index = (ctx.lookup_mask() & glyph_mask) >> shift;
 
-        Some(())
+    // If alt_index is MAX_VALUE, randomize feature if it is the rand feature.
+    if alt_index == crate::hb::ot_map::hb_ot_map_t::MAX_VALUE && ctx.random {
+        // Maybe we can do better than unsafe-to-break all; but since we are
+        // changing random state, it would be hard to track that.  Good 'nough.
+        ctx.buffer.unsafe_to_break(Some(0), Some(ctx.buffer.len));
+        alt_index = ctx.random_number() % u32::from(len) + 1;
     }
+
+    let idx = u16::try_from(alt_index).ok()?.checked_sub(1)?;
+    ctx.replace_glyph(alternate(idx as usize)?);
+
+    Some(())
 }
 
 impl WouldApply for AlternateSubstFormat1<'_> {
@@ -0,0 +1,335 @@
+//! GSUB subtable formats with 24-bit glyph ids and offsets, from the
+//! [boring expansion](https://github.com/harfbuzz/boring-expansion-spec/blob/main/beyond-64k.md)
+//! of the OpenType spec.
+//!
+//! read-fonts doesn't parse these formats, so they are read directly from the
+//! subtable data here.
+
+use super::alternate::apply_alternate;
+use super::ligature::{apply_ligature, ligature_would_apply};
+use super::multiple::apply_sequence;
+use crate::hb::ot::CoverageInfo;
+use crate::hb::ot_layout_gsubgpos::OT::hb_ot_apply_context_t;
+use crate::hb::ot_layout_gsubgpos::{Apply, WouldApply, WouldApplyContext};
+use read_fonts::types::{BigEndian, GlyphId, Uint24};
+use read_fonts::{FontData, FontRead, ReadError};
+
+macro_rules! subtable {
+    ($(#[$attr:meta])* $name:ident, $format:literal, $min_len:literal) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy)]
+        pub struct $name<'a> {
+            data: FontData<'a>,
+        }
+
+        impl<'a> FontRead<'a> for $name<'a> {
+            fn read(data: FontData<'a>) -> Result<Self, ReadError> {
+                if data.read_at::<u16>(0)? != $format {
+                    return Err(ReadError::InvalidFormat($format));
+                }
+                if data.len() < $min_len {
+                    return Err(ReadError::OutOfBounds);
+                }
+                Ok(Self { data })
+            }
+        }
+
+        impl $name<'_> {
+            pub fn coverage(&self) -> Option<CoverageInfo> {
+                CoverageInfo::new_large(&self.data, read_u24(&self.data, 2)?)
+            }
+
+            fn coverage_index(&self, gid: GlyphId) -> Option<usize> {
+                self.coverage()?
+                    .index(&self.data, gid)
+                    .map(usize::from)
+            }
+        }
+    };
+}
+
+pub(crate) use subtable;
+
+subtable!(
+    /// Single substitution by a 24-bit delta.
+    SingleSubstFormat3,
+    3,
+    8
+);
+subtable!(
+    /// Single substitution by a list of 24-bit glyph ids.
+    SingleSubstFormat4,
+    4,
+    7
+);
+subtable!(
+    /// Multiple substitution with 24-bit offsets and glyph ids.
+    MultipleSubstFormat2,
+    2,
+    7
+);
+subtable!(
+    /// Alternate substitution with 24-bit offsets and glyph ids.
+    AlternateSubstFormat2,
+    2,
+    7
+);
+subtable!(
+    /// Ligature substitution with 24-bit offsets and glyph ids.
+    LigatureSubstFormat2,
+    2,
+    7
+);
+
+pub(crate) fn read_u16(data: &FontData, offset: usize) -> Option<u16> {
+    data.read_at::<u16>(offset).ok()
+}
+
+pub(crate) fn read_u24(data: &FontData, offset: usize) -> Option<u32> {
+    data.read_at::<Uint24>(offset).ok().map(Uint24::to_u32)
+}
+
+/// Reads an `Array16Of<HBGlyphID24>` at `offset`.
+fn glyph_array<'a>(data: &FontData<'a>, offset: usize) -> Option<&'a [BigEndian<Uint24>]> {
+    let count = read_u16(data, offset)? as usize;
+    let start = offset + 2;
+    data.read_array(start..start + count * 3).ok()
+}
+
+/// Returns the data pointed to by the `index`th entry of an
+/// `Array16Of<Offset24To<..>>` at `offset`.
+pub(crate) fn offset24_target<'a>(
+    data: &FontData<'a>,
+    offset: usize,
+    index: usize,
+) -> Option<FontData<'a>> {
+    if index >= read_u16(data, offset)? as usize {
+        return None;
+    }
+    match read_u24(data, offset + 2 + index * 3)? {
+        0 => None,
+        target => data.split_off(target as usize),
+    }
+}
+
+fn glyph(glyphs: &[BigEndian<Uint24>], index: usize) -> Option<GlyphId> {
+    glyphs
+        .get(index)
+        .map(|gid| GlyphId::new(gid.get().to_u32()))
+}
+
+fn would_apply_single(coverage_index: Option<usize>, ctx: &WouldApplyContext) -> bool {
+    ctx.glyphs.len() == 1 && coverage_index.is_some()
+}
+
+impl WouldApply for SingleSubstFormat3<'_> {
+    fn would_apply(&self, ctx: &WouldApplyContext) -> bool {
+        would_apply_single(self.coverage_index(ctx.glyphs[0]), ctx)
+    }
+}
+
+impl Apply for SingleSubstFormat3<'_> {
+    fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
+        let glyph = ctx.buffer.cur(0).as_glyph();
+        self.coverage_index(glyph)?;
+        let delta = read_u24(&self.data, 5)?;
+        // The delta wraps around modulo 2^24.
+        let subst = glyph.to_u32().wrapping_add(delta) & 0xFF_FFFF;
+        ctx.replace_glyph(GlyphId::new(subst));
+        Some(())
+    }
+}
+
+impl WouldApply for SingleSubstFormat4<'_> {
+    fn would_apply(&self, ctx: &WouldApplyContext) -> bool {
+        would_apply_single(self.coverage_index(ctx.glyphs[0]), ctx)
+    }
+}
+
+impl Apply for SingleSubstFormat4<'_> {
+    fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
+        let index = self.coverage_index(ctx.buffer.cur(0).as_glyph())?;
+        let subst = glyph(glyph_array(&self.data, 5)?, index)?;
+        ctx.replace_glyph(subst);
+        Some(())
+    }
+}
+
+impl WouldApply for MultipleSubstFormat2<'_> {
+    fn would_apply(&self, ctx: &WouldApplyContext) -> bool {
+        would_apply_single(self.coverage_index(ctx.glyphs[0]), ctx)
+    }
+}
+
+impl Apply for MultipleSubstFormat2<'_> {
+    fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
+        let index = self.coverage_index(ctx.buffer.cur(0).as_glyph())?;
+        let substs = glyph_array(&offset24_target(&self.data, 5, index)?, 0)?;
+        apply_sequence(ctx, substs.len(), |i| glyph(substs, i))
+    }
+}
+
+impl WouldApply for AlternateSubstFormat2<'_> {
+    fn would_apply(&self, ctx: &WouldApplyContext) -> bool {
+        would_apply_single(self.coverage_index(ctx.glyphs[0]), ctx)
+    }
+}
+
+impl Apply for AlternateSubstFormat2<'_> {
+    fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
+        let index = self.coverage_index(ctx.buffer.cur(0).as_glyph())?;
+        let alternates = glyph_array(&offset24_target(&self.data, 5, index)?, 0)?;
+        apply_alternate(ctx, alternates.len(), |i| glyph(alternates, i))
+    }
+}
+
+/// A ligature: a 24-bit ligature glyph followed by a 16-bit component count
+/// and the 24-bit ids of all components but the first.
+struct Ligature24<'a> {
+    glyph: GlyphId,
+    components: &'a [BigEndian<Uint24>],
+}
+
+impl<'a> LigatureSubstFormat2<'a> {
+    /// Returns the ligatures of the set for the given coverage index.
+    ///
+    /// Unlike the other offsets in this subtable, the offsets from a ligature
+    /// set to its ligatures are 16-bit.
+    fn ligatures(&self, index: usize) -> impl Iterator<Item = Ligature24<'a>> {
+        let set = offset24_target(&self.data, 5, index);
+        let count = set.as_ref().and_then(|set| read_u16(set, 0));
+        (0..count.unwrap_or_default() as usize).filter_map(move |i| {
+            let set = set.as_ref()?;
+            let lig = set.split_off(read_u16(set, 2 + i * 2)? as usize)?;
+            let glyph = GlyphId::new(read_u24(&lig, 0)?);
+            let count = read_u16(&lig, 3)?.checked_sub(1)? as usize;
+            let components = lig.read_array(5..5 + count * 3).ok()?;
+            Some(Ligature24 { glyph, components })
+        })
+    }
+}
+
+impl WouldApply for LigatureSubstFormat2<'_> {
+    fn would_apply(&self, ctx: &WouldApplyContext) -> bool {
+        self.coverage_index(ctx.glyphs[0]).is_some_and(|index| {
+            self.ligatures(index).any(|lig| {
+                ligature_would_apply(ctx, lig.components.len(), |i| {
+                    lig.components[i].get().to_u32()
+                })
+            })
+        })
+    }
+}
+
+impl Apply for LigatureSubstFormat2<'_> {
+    fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
+        let index = self.coverage_index(ctx.buffer.cur(0).as_glyph())?;
+        self.ligatures(index).find_map(|lig| {
+            apply_ligature(ctx, lig.glyph, lig.components.len(), |i| {
+                lig.components[i].get().to_u32()
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u24(value: u32) -> [u8; 3] {
+        let bytes = value.to_be_bytes();
+        [bytes[1], bytes[2], bytes[3]]
+    }
+
+    /// A SingleSubstFormat3 subtable followed by a CoverageFormat3 table
+    /// covering glyphs 70000 and 70002.
+    fn single_subst3(delta: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&3u16.to_be_bytes());
+        data.extend_from_slice(&u24(8));
+        data.extend_from_slice(&u24(delta));
+        data.extend_from_slice(&3u16.to_be_bytes());
+        data.extend_from_slice(&2u16.to_be_bytes());
+        data.extend_from_slice(&u24(70000));
+        data.extend_from_slice(&u24(70002));
+        data
+    }
+
+    #[test]
+    fn coverage_format3() {
+        let data = single_subst3(1);
+        let subtable = SingleSubstFormat3::read(FontData::new(&data)).unwrap();
+        assert_eq!(subtable.coverage_index(GlyphId::new(70000)), Some(0));
+        assert_eq!(subtable.coverage_index(GlyphId::new(70001)), None);
+        assert_eq!(subtable.coverage_index(GlyphId::new(70002)), Some(1));
+
+        let digest = subtable.coverage().unwrap().digest(&subtable.data);
+        assert!(digest.may_have(70000));
+        assert!(digest.may_have(70002));
+    }
+
+    #[test]
+    fn coverage_format4() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&4u16.to_be_bytes());
+        data.extend_from_slice(&u24(7));
+        data.extend_from_slice(&0u16.to_be_bytes());
+        // Coverage: glyphs 65530..=65540 and 100000..=100001.
+        data.extend_from_slice(&4u16.to_be_bytes());
+        data.extend_from_slice(&2u16.to_be_bytes());
+        data.extend_from_slice(&u24(65530));
+        data.extend_from_slice(&u24(65540));
+        data.extend_from_slice(&0u16.to_be_bytes());
+        data.extend_from_slice(&u24(100_000));
+        data.extend_from_slice(&u24(100_001));
+        data.extend_from_slice(&11u16.to_be_bytes());
+
+        let subtable = SingleSubstFormat4::read(FontData::new(&data)).unwrap();
+        assert_eq!(subtable.coverage_index(GlyphId::new(65529)), None);
+        assert_eq!(subtable.coverage_index(GlyphId::new(65536)), Some(6));
+        assert_eq!(subtable.coverage_index(GlyphId::new(100_001)), Some(12));
+        assert_eq!(subtable.coverage_index(GlyphId::new(100_002)), None);
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        let data = single_subst3(1);
+        assert!(SingleSubstFormat4::read(FontData::new(&data)).is_err());
+        assert!(SingleSubstFormat3::read(FontData::new(&data[..7])).is_err());
+    }
+
+    #[test]
+    fn ligature_subst_format2_would_apply() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&2u16.to_be_bytes());
+        data.extend_from_slice(&u24(10));
+        data.extend_from_slice(&1u16.to_be_bytes());
+        data.extend_from_slice(&u24(17));
+        // Coverage: glyph 70000.
+        data.extend_from_slice(&3u16.to_be_bytes());
+        data.extend_from_slice(&1u16.to_be_bytes());
+        data.extend_from_slice(&u24(70000));
+        // LigatureSet with a single ligature: 70000 70001 -> 80000.
+        data.extend_from_slice(&1u16.to_be_bytes());
+        data.extend_from_slice(&4u16.to_be_bytes());
+        data.extend_from_slice(&u24(80000));
+        data.extend_from_slice(&2u16.to_be_bytes());
+        data.extend_from_slice(&u24(70001));
+
+        let subtable = LigatureSubstFormat2::read(FontData::new(&data)).unwrap();
+        let would_apply = |glyphs: &[u32]| {
+            let glyphs: Vec<_> = glyphs.iter().copied().map(GlyphId::new).collect();
+            subtable.would_apply(&WouldApplyContext {
+                glyphs: &glyphs,
+                zero_context: false,
+            })
+        };
+        assert!(would_apply(&[70000, 70001]));
+        assert!(!would_apply(&[70000, 70002]));
+        assert!(!would_apply(&[70000]));
+
+        let ligatures: Vec<_> = subtable.ligatures(0).collect();
+        assert_eq!(ligatures.len(), 1);
+        assert_eq!(ligatures[0].glyph, GlyphId::new(80000));
+    }
+}
@@ -19,53 +19,68 @@ const MAX_LIGATURE_CACHE_WORK: usize = 16_384;
 impl WouldApply for Ligature<'_> {
     fn would_apply(&self, ctx: &WouldApplyContext) -> bool {
         let components = self.component_glyph_ids();
-        ctx.glyphs.len() == components.len() + 1
-            && components
-                .iter()
-                .map(|comp| GlyphId::from(comp.get()))
-                .enumerate()
-                .all(|(i, comp)| ctx.glyphs[i + 1] == comp)
+        ligature_would_apply(ctx, components.len(), |i| components[i].get().to_u32())
     }
 }
 
 impl Apply for Ligature<'_> {
     fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
-        // Special-case to make it in-place and not consider this
-        // as a "ligated" substitution.
         let components = self.component_glyph_ids();
-        if components.is_empty() {
-            ctx.replace_glyph(self.ligature_glyph().into());
-            Some(())
-        } else {
-            let f = |info: &mut GlyphInfo, index| {
-                let value = components.get(index as usize).unwrap().get().to_u32();
-                match_glyph(info, value)
-            };
+        apply_ligature(ctx, self.ligature_glyph().into(), components.len(), |i| {
+            components[i].get().to_u32()
+        })
+    }
+}
 
-            let mut match_end = 0;
-            let mut total_component_count = 0;
+/// Whether the glyphs in the context are the first glyph followed by the
+/// `count` components of a ligature.
+pub(super) fn ligature_would_apply(
+    ctx: &WouldApplyContext,
+    count: usize,
+    component: impl Fn(usize) -> u32,
+) -> bool {
+    ctx.glyphs.len() == count + 1 && (0..count).all(|i| ctx.glyphs[i + 1].to_u32() == component(i))
+}
 
-            if !match_input(
-                ctx,
-                components.len() as u16,
-                f,
-                &mut match_end,
-                Some(&mut total_component_count),
-            ) {
-                ctx.buffer
-                    .unsafe_to_concat(Some(ctx.buffer.idx), Some(match_end));
-                return None;
-            }
-            let count = components.len() + 1;
-            ligate_input(
-                ctx,
-                count,
-                match_end,
-                total_component_count,
-                self.ligature_glyph().into(),
-            );
-            Some(())
+/// Forms a ligature if the `count` components follow the current glyph.
+///
+/// `component` must accept every index below `count`.
+pub(super) fn apply_ligature(
+    ctx: &mut hb_ot_apply_context_t,
+    ligature_glyph: GlyphId,
+    count: usize,
+    component: impl Fn(usize) -> u32,
+) -> Option<()> {
+    // Special-case to make it in-place and not consider this
+    // as a "ligated" substitution.
+    if count == 0 {
+        ctx.replace_glyph(ligature_glyph);
+        Some(())
+    } else {
+        let f = |info: &mut GlyphInfo, index| match_glyph(info, component(index as usize));
+
+        let mut match_end = 0;
+        let mut total_component_count = 0;
+
+        if !match_input(
+            ctx,
+            count as u16,
+            f,
+            &mut match_end,
+            Some(&mut total_component_count),
+        ) {
+            ctx.buffer
+                .unsafe_to_concat(Some(ctx.buffer.idx), Some(match_end));
+            return None;
         }
+        ligate_input(
+            ctx,
+            count + 1,
+            match_end,
+            total_component_count,
+            ligature_glyph,
+        );
+        Some(())
     }
 }
 
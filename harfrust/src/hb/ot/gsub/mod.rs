@@ -1,6 +1,8 @@
 //! OpenType GSUB lookups.
 
 mod alternate;
+#[cfg(feature = "beyond_64k")]
+pub mod beyond_64k;
 mod ligature;
 mod multiple;
 mod reverse_chain;
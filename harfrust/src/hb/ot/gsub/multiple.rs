@@ -2,6 +2,7 @@ use crate::hb::buffer::GlyphPropsFlags;
 use crate::hb::ot_layout_gsubgpos::OT::hb_ot_apply_context_t;
 use crate::hb::ot_layout_gsubgpos::{Apply, WouldApply, WouldApplyContext};
 use read_fonts::tables::gsub::MultipleSubstFormat1;
+use read_fonts::types::GlyphId;
 
 impl WouldApply for MultipleSubstFormat1<'_> {
     fn would_apply(&self, ctx: &WouldApplyContext) -> bool {
@@ -17,37 +18,50 @@ impl Apply for MultipleSubstFormat1<'_> {
         let gid = ctx.buffer.cur(0).as_glyph();
         let index = self.coverage().ok()?.get(gid)? as usize;
         let substs = self.sequences().get(index).ok()?.substitute_glyph_ids();
-        match substs.len() {
-            // Spec disallows this, but Uniscribe allows it.
-            // https://github.com/harfbuzz/harfbuzz/issues/253
-            0 => ctx.buffer.delete_glyph(),
+        apply_sequence(ctx, substs.len(), |i| {
+            substs.get(i).map(|gid| gid.get().into())
+        })
+    }
+}
 
-            // Special-case to make it in-place and not consider this
-            // as a "multiplied" substitution.
-            1 => ctx.replace_glyph(substs.first()?.get().into()),
+/// Replaces the current glyph with a sequence of glyphs.
+pub(super) fn apply_sequence(
+    ctx: &mut hb_ot_apply_context_t,
+    count: usize,
+    subst: impl Fn(usize) -> Option<GlyphId>,
+) -> Option<()> {
+    match count {
+        // Spec disallows this, but Uniscribe allows it.
+        // https://github.com/harfbuzz/harfbuzz/issues/253
+        0 => ctx.buffer.delete_glyph(),
 
-            _ => {
-                let class = if ctx.buffer.cur(0).is_ligature() {
-                    GlyphPropsFlags::BASE_GLYPH
-                } else {
-                    GlyphPropsFlags::empty()
-                };
-                let lig_id = ctx.buffer.cur(0).lig_id();
+        // Special-case to make it in-place and not consider this
+        // as a "multiplied" substitution.
+        1 => ctx.replace_glyph(subst(0)?),
 
-                for (i, subst) in substs.iter().enumerate() {
-                    let subst = subst.get().into();
-                    // If is attached to a ligature, don't disturb that.
-                    // https://github.com/harfbuzz/harfbuzz/issues/3069
-                    if lig_id == 0 {
-                        // Index is truncated to 4 bits anway, so we can safely cast to u8.
-                        ctx.buffer.cur_mut(0).set_lig_props_for_component(i as u8);
-                    }
-                    ctx.output_glyph_for_component(subst, class);
-                }
+        _ => {
+            let class = if ctx.buffer.cur(0).is_ligature() {
+                GlyphPropsFlags::BASE_GLYPH
+            } else {
+                GlyphPropsFlags::empty()
+            };
+            let lig_id = ctx.buffer.cur(0).lig_id();
 
-                ctx.buffer.skip_glyph();
+            for i in 0..count {
+                let Some(subst) = subst(i) else {
+                    break;
+                };
+                // If is attached to a ligature, don't disturb that.
+                // https://github.com/harfbuzz/harfbuzz/issues/3069
+                if lig_id == 0 {
+                    // Index is truncated to 4 bits anway, so we can safely cast to u8.
+                    ctx.buffer.cur_mut(0).set_lig_props_for_component(i as u8);
+                }
+                ctx.output_glyph_for_component(subst, class);
             }
+
+            ctx.buffer.skip_glyph();
         }
-        Some(())
     }
+    Some(())
 }
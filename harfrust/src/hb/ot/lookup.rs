@@ -1,3 +1,18 @@
+#[cfg(feature = "beyond_64k")]
+use crate::hb::ot::beyond_64k::LayoutHeader2;
+#[cfg(feature = "beyond_64k")]
+use crate::hb::ot::contextual::beyond_64k::{
+    ChainContextFormat4, ChainContextFormat5, ContextFormat4, ContextFormat5,
+};
+#[cfg(feature = "beyond_64k")]
+use crate::hb::ot::gpos::beyond_64k::{
+    MarkBasePosFormat2, MarkLigPosFormat2, MarkMarkPosFormat2, PairPosFormat3, PairPosFormat4,
+};
+#[cfg(feature = "beyond_64k")]
+use crate::hb::ot::gsub::beyond_64k::{
+    AlternateSubstFormat2, LigatureSubstFormat2, MultipleSubstFormat2, SingleSubstFormat3,
+    SingleSubstFormat4,
+};
 use crate::hb::{
     hb_font_t,
     ot_layout::TableIndex,
@@ -45,12 +60,24 @@ pub trait LookupHost<'a> {
 
 impl<'a> LookupHost<'a> for Gsub<'a> {
     fn lookup_count(&self) -> u16 {
+        #[cfg(feature = "beyond_64k")]
+        if let Some(header) = LayoutHeader2::new(self.offset_data()) {
+            return header.lookup_count();
+        }
         self.lookup_list()
             .map(|list| list.lookup_count())
             .unwrap_or_default()
     }
 
     fn lookup_data(&self, index: u16) -> Result<LookupData<'a>, ReadError> {
+        #[cfg(feature = "beyond_64k")]
+        if let Some(header) = LayoutHeader2::new(self.offset_data()) {
+            return Ok(LookupData {
+                offset: header.lookup_offset(index).ok_or(ReadError::OutOfBounds)?,
+                is_subst: true,
+                table_data: self.offset_data(),
+            });
+        }
         let list = self.lookup_list()?;
         let offset = list
             .lookup_offsets()
@@ -69,12 +96,24 @@ impl<'a> LookupHost<'a> for Gsub<'a> {
 
 impl<'a> LookupHost<'a> for Gpos<'a> {
     fn lookup_count(&self) -> u16 {
+        #[cfg(feature = "beyond_64k")]
+        if let Some(header) = LayoutHeader2::new(self.offset_data()) {
+            return header.lookup_count();
+        }
         self.lookup_list()
             .map(|list| list.lookup_count())
             .unwrap_or_default()
     }
 
     fn lookup_data(&self, index: u16) -> Result<LookupData<'a>, ReadError> {
+        #[cfg(feature = "beyond_64k")]
+        if let Some(header) = LayoutHeader2::new(self.offset_data()) {
+            return Ok(LookupData {
+                offset: header.lookup_offset(index).ok_or(ReadError::OutOfBounds)?,
+                is_subst: false,
+                table_data: self.offset_data(),
+            });
+        }
         let list = self.lookup_list()?;
         let offset = list
             .lookup_offsets()
@@ -254,6 +293,10 @@ impl LookupInfo {
         table_data: &[u8],
         use_hot_subtable_cache: bool,
     ) -> Option<()> {
+        #[cfg(feature = "profile")]
+        if ctx.profile.is_some() {
+            return self.apply_profiled(ctx, table_data, use_hot_subtable_cache);
+        }
         let glyph = ctx.buffer.cur(0).glyph_id;
         if let [subtable_info] = self.subtables.as_slice() {
             // The lookup digest is the union of the subtable digests, so for
@@ -275,6 +318,53 @@ impl LookupInfo {
         None
     }
 
+    /// Same as `apply`, but also records what was tried in `ctx.profile`.
+    #[cfg(feature = "profile")]
+    #[cold]
+    fn apply_profiled(
+        &self,
+        ctx: &mut hb_ot_apply_context_t,
+        table_data: &[u8],
+        use_hot_subtable_cache: bool,
+    ) -> Option<()> {
+        let glyph = ctx.buffer.cur(0).glyph_id;
+        let (table_index, lookup_index) = (ctx.table_index, ctx.lookup_index);
+        let mut applied = None;
+        for (subtable_idx, subtable_info) in self.subtables.iter().enumerate() {
+            let covered = subtable_info.digest.may_have(glyph);
+            if let Some(profile) = ctx.profile.as_deref_mut() {
+                let counts = &mut profile
+                    .lookup(table_index, lookup_index, self.subtables.len())
+                    .subtables[subtable_idx];
+                counts.glyphs_visited += 1;
+                counts.attempts += u64::from(covered);
+            }
+            if !covered {
+                continue;
+            }
+            let is_cached =
+                use_hot_subtable_cache && (self.subtable_cache_user_idx == Some(subtable_idx));
+            applied = subtable_info.apply(ctx, table_data, is_cached);
+            if applied.is_some() {
+                if let Some(profile) = ctx.profile.as_deref_mut() {
+                    profile
+                        .lookup(table_index, lookup_index, self.subtables.len())
+                        .subtables[subtable_idx]
+                        .successes += 1;
+                }
+                break;
+            }
+        }
+        if let Some(profile) = ctx.profile.as_deref_mut() {
+            let counts = &mut profile
+                .lookup(table_index, lookup_index, self.subtables.len())
+                .counts;
+            counts.attempts += 1;
+            counts.successes += u64::from(applied.is_some());
+        }
+        applied
+    }
+
     pub(crate) fn cache_enter(&self, ctx: &mut hb_ot_apply_context_t) -> bool {
         let Some(idx) = self.subtable_cache_user_idx else {
             return false;
@@ -346,6 +436,42 @@ impl LookupInfo {
                 SubtableKind::ReverseChainContext => {
                     ReverseChainSingleSubstFormat1::read(data).map(|t| t.would_apply(ctx))
                 }
+                #[cfg(feature = "beyond_64k")]
+                SubtableKind::SingleSubst3 => {
+                    SingleSubstFormat3::read(data).map(|t| t.would_apply(ctx))
+                }
+                #[cfg(feature = "beyond_64k")]
+                SubtableKind::SingleSubst4 => {
+                    SingleSubstFormat4::read(data).map(|t| t.would_apply(ctx))
+                }
+                #[cfg(feature = "beyond_64k")]
+                SubtableKind::MultipleSubst2 => {
+                    MultipleSubstFormat2::read(data).map(|t| t.would_apply(ctx))
+                }
+                #[cfg(feature = "beyond_64k")]
+                SubtableKind::AlternateSubst2 => {
+                    AlternateSubstFormat2::read(data).map(|t| t.would_apply(ctx))
+                }
+                #[cfg(feature = "beyond_64k")]
+                SubtableKind::LigatureSubst2 => {
+                    LigatureSubstFormat2::read(data).map(|t| t.would_apply(ctx))
+                }
+                #[cfg(feature = "beyond_64k")]
+                SubtableKind::ContextFormat4 => {
+                    ContextFormat4::read(data).map(|t| t.would_apply(ctx))
+                }
+                #[cfg(feature = "beyond_64k")]
+                SubtableKind::ContextFormat5 => {
+                    ContextFormat5::read(data).map(|t| t.would_apply(ctx))
+                }
+                #[cfg(feature = "beyond_64k")]
+                SubtableKind::ChainedContextFormat4 => {
+                    ChainContextFormat4::read(data).map(|t| t.would_apply(ctx))
+                }
+                #[cfg(feature = "beyond_64k")]
+                SubtableKind::ChainedContextFormat5 => {
+                    ChainContextFormat5::read(data).map(|t| t.would_apply(ctx))
+                }
                 SubtableKind::ContextFormat1 => {
                     SequenceContextFormat1::read(data).map(|t| t.would_apply(ctx))
                 }
@@ -472,6 +598,54 @@ apply_fns!(
     rev_chain_single_subst1_cached,
     ReverseChainSingleSubstFormat1
 );
+#[cfg(feature = "beyond_64k")]
+apply_fns!(single_subst3, single_subst3_cached, SingleSubstFormat3);
+#[cfg(feature = "beyond_64k")]
+apply_fns!(single_subst4, single_subst4_cached, SingleSubstFormat4);
+#[cfg(feature = "beyond_64k")]
+apply_fns!(
+    multiple_subst2,
+    multiple_subst2_cached,
+    MultipleSubstFormat2
+);
+#[cfg(feature = "beyond_64k")]
+apply_fns!(
+    alternate_subst2,
+    alternate_subst2_cached,
+    AlternateSubstFormat2
+);
+#[cfg(feature = "beyond_64k")]
+apply_fns!(
+    ligature_subst2,
+    ligature_subst2_cached,
+    LigatureSubstFormat2
+);
+#[cfg(feature = "beyond_64k")]
+apply_fns!(pair_pos3, pair_pos3_cached, PairPosFormat3);
+#[cfg(feature = "beyond_64k")]
+apply_fns!(pair_pos4, pair_pos4_cached, PairPosFormat4);
+#[cfg(feature = "beyond_64k")]
+apply_fns!(mark_base_pos2, mark_base_pos2_cached, MarkBasePosFormat2);
+#[cfg(feature = "beyond_64k")]
+apply_fns!(mark_mark_pos2, mark_mark_pos2_cached, MarkMarkPosFormat2);
+#[cfg(feature = "beyond_64k")]
+apply_fns!(mark_lig_pos2, mark_lig_pos2_cached, MarkLigPosFormat2);
+#[cfg(feature = "beyond_64k")]
+apply_fns!(context4, context4_cached, ContextFormat4);
+#[cfg(feature = "beyond_64k")]
+apply_fns!(context5, context5_cached, ContextFormat5);
+#[cfg(feature = "beyond_64k")]
+apply_fns!(
+    chained_context4,
+    chained_context4_cached,
+    ChainContextFormat4
+);
+#[cfg(feature = "beyond_64k")]
+apply_fns!(
+    chained_context5,
+    chained_context5_cached,
+    ChainContextFormat5
+);
 
 /// All possible subtables in a lookup.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -496,6 +670,34 @@ pub enum SubtableKind {
     ChainedContextFormat2,
     ChainedContextFormat3,
     ReverseChainContext,
+    #[cfg(feature = "beyond_64k")]
+    SingleSubst3,
+    #[cfg(feature = "beyond_64k")]
+    SingleSubst4,
+    #[cfg(feature = "beyond_64k")]
+    MultipleSubst2,
+    #[cfg(feature = "beyond_64k")]
+    AlternateSubst2,
+    #[cfg(feature = "beyond_64k")]
+    LigatureSubst2,
+    #[cfg(feature = "beyond_64k")]
+    PairPos3,
+    #[cfg(feature = "beyond_64k")]
+    PairPos4,
+    #[cfg(feature = "beyond_64k")]
+    MarkBasePos2,
+    #[cfg(feature = "beyond_64k")]
+    MarkMarkPos2,
+    #[cfg(feature = "beyond_64k")]
+    MarkLigPos2,
+    #[cfg(feature = "beyond_64k")]
+    ContextFormat4,
+    #[cfg(feature = "beyond_64k")]
+    ContextFormat5,
+    #[cfg(feature = "beyond_64k")]
+    ChainedContextFormat4,
+    #[cfg(feature = "beyond_64k")]
+    ChainedContextFormat5,
 }
 
 impl SubtableInfo {
@@ -507,6 +709,10 @@ impl SubtableInfo {
         cache_mode: SubtableExternalCacheMode,
     ) -> Option<(Self, u32)> {
         let data = table_data.split_off(subtable_offset as usize)?;
+        #[cfg(feature = "beyond_64k")]
+        if let Some(info) = Self::new_large(data, subtable_offset, is_subst, lookup_type) {
+            return Some((info, 0));
+        }
         let maybe_external_cache = |s: &dyn Apply| s.external_cache_create(cache_mode);
         let (kind, (external_cache, cache_cost, coverage), apply_fns): (
             SubtableKind,
@@ -693,6 +899,88 @@ impl SubtableInfo {
     }
 }
 
+impl SubtableInfo {
+    /// Handles the GSUB and GPOS subtable formats with 24-bit glyph ids and
+    /// offsets.
+    ///
+    /// These formats are never cached.
+    #[cfg(feature = "beyond_64k")]
+    fn new_large(
+        data: FontData,
+        subtable_offset: u32,
+        is_subst: bool,
+        lookup_type: u8,
+    ) -> Option<Self> {
+        // The first coverage of every format, which is the mark coverage of
+        // the mark attachment formats, is at the same offset.
+        fn coverage_digest(data: FontData) -> Option<hb_set_digest_t> {
+            let offset = data.read_at::<read_fonts::types::Uint24>(2).ok()?.to_u32();
+            let coverage = crate::hb::ot::CoverageInfo::new_large(&data, offset)?;
+            Some(coverage.digest(&data))
+        }
+
+        let (kind, apply_fns): (SubtableKind, [SubtableApplyFn; 2]) =
+            match (is_subst, lookup_type, data.read_at::<u16>(0).ok()?) {
+                (true, 1, 3) => (
+                    SubtableKind::SingleSubst3,
+                    [single_subst3, single_subst3_cached],
+                ),
+                (true, 1, 4) => (
+                    SubtableKind::SingleSubst4,
+                    [single_subst4, single_subst4_cached],
+                ),
+                (true, 2, 2) => (
+                    SubtableKind::MultipleSubst2,
+                    [multiple_subst2, multiple_subst2_cached],
+                ),
+                (true, 3, 2) => (
+                    SubtableKind::AlternateSubst2,
+                    [alternate_subst2, alternate_subst2_cached],
+                ),
+                (true, 4, 2) => (
+                    SubtableKind::LigatureSubst2,
+                    [ligature_subst2, ligature_subst2_cached],
+                ),
+                (false, 2, 3) => (SubtableKind::PairPos3, [pair_pos3, pair_pos3_cached]),
+                (false, 2, 4) => (SubtableKind::PairPos4, [pair_pos4, pair_pos4_cached]),
+                (false, 4, 2) => (
+                    SubtableKind::MarkBasePos2,
+                    [mark_base_pos2, mark_base_pos2_cached],
+                ),
+                (false, 5, 2) => (
+                    SubtableKind::MarkLigPos2,
+                    [mark_lig_pos2, mark_lig_pos2_cached],
+                ),
+                (false, 6, 2) => (
+                    SubtableKind::MarkMarkPos2,
+                    [mark_mark_pos2, mark_mark_pos2_cached],
+                ),
+                (true, 5, 4) | (false, 7, 4) => {
+                    (SubtableKind::ContextFormat4, [context4, context4_cached])
+                }
+                (true, 5, 5) | (false, 7, 5) => {
+                    (SubtableKind::ContextFormat5, [context5, context5_cached])
+                }
+                (true, 6, 4) | (false, 8, 4) => (
+                    SubtableKind::ChainedContextFormat4,
+                    [chained_context4, chained_context4_cached],
+                ),
+                (true, 6, 5) | (false, 8, 5) => (
+                    SubtableKind::ChainedContextFormat5,
+                    [chained_context5, chained_context5_cached],
+                ),
+                _ => return None,
+            };
+        Some(SubtableInfo {
+            kind,
+            offset: subtable_offset,
+            digest: coverage_digest(data)?,
+            apply_fns,
+            external_cache: SubtableExternalCache::None,
+        })
+    }
+}
+
 fn cache_enter(ctx: &mut hb_ot_apply_context_t) -> bool {
     if !ctx.buffer.try_allocate_var(GlyphInfo::SYLLABLE_VAR) {
         return false;
@@ -8,9 +8,11 @@ use alloc::vec::Vec;
 use lookup::{LookupCache, LookupInfo};
 use read_fonts::tables::layout::{ClassRangeRecord, RangeRecord};
 use read_fonts::types::GlyphId16;
+#[cfg(feature = "beyond_64k")]
+use read_fonts::types::Uint24;
 use read_fonts::{
     tables::{
-        gdef::Gdef,
+        gdef::{CaretValue, Gdef, LigCaretList, MarkGlyphSets},
         gpos::{AnchorTable, DeviceOrVariationIndex, Gpos},
         gsub::{ClassDef, FeatureList, FeatureVariations, Gsub, ScriptList},
         layout::{Feature, LangSys, Script},
@@ -18,9 +20,11 @@ use read_fonts::{
         variations::{DeltaSetIndex, ItemVariationStore},
     },
     types::{BigEndian, F2Dot14, GlyphId, Offset32},
-    FontData, FontRef, ReadError, ResolveOffset, TableProvider,
+    FontData, FontRef, ReadError, TableProvider,
 };
 
+#[cfg(feature = "beyond_64k")]
+pub mod beyond_64k;
 pub mod contextual;
 pub mod gpos;
 pub mod gsub;
@@ -128,10 +132,12 @@ impl OtCache {
             .unwrap_or_default();
         let mut gdef_mark_set_bitmaps = Vec::new();
         if let Ok(gdef) = font.gdef() {
-            if let Some(Ok(mark_sets)) = gdef.mark_glyph_sets_def() {
+            if let Some(mark_sets) = mark_glyph_sets(&gdef) {
                 gdef_mark_set_bitmaps.extend(mark_sets.coverages().iter().map(|set| {
+                    // Coverage formats that read-fonts can't parse are left
+                    // to `is_mark_glyph_gdef`.
                     set.ok().map_or_else(
-                        || MarkGlyphSetBitmap::Digest(hb_set_digest_t::default()),
+                        || MarkGlyphSetBitmap::Digest(hb_set_digest_t::full()),
                         |coverage| MarkGlyphSetBitmap::from_coverage(&coverage),
                     )
                 }));
@@ -179,30 +185,58 @@ impl crate::hb::ot_layout::LayoutTable for GposTable<'_> {
 #[derive(Clone, Default)]
 pub struct GdefTable<'a> {
     pub(crate) table: Option<Gdef<'a>>,
-    classes: Option<ClassDef<'a>>,
-    mark_classes: Option<ClassDef<'a>>,
+    data: FontData<'a>,
+    classes: Option<ClassDefInfo>,
+    mark_classes: Option<ClassDefInfo>,
     mark_sets: Option<(FontData<'a>, &'a [BigEndian<Offset32>])>,
+    lig_caret_list: Option<LigCaretList<'a>>,
+    var_store: Option<ItemVariationStore<'a>>,
 }
 
 impl<'a> GdefTable<'a> {
     fn new(gdef: Gdef<'a>) -> Self {
-        let classes = gdef.glyph_class_def().transpose().ok().flatten();
-        let mark_classes = gdef.mark_attach_class_def().transpose().ok().flatten();
-        let mark_sets = gdef
-            .mark_glyph_sets_def()
-            .transpose()
-            .ok()
-            .flatten()
-            .map(|sets| (sets.offset_data(), sets.coverage_offsets()));
+        let data = gdef.offset_data();
+        let mark_sets =
+            mark_glyph_sets(&gdef).map(|sets| (sets.offset_data(), sets.coverage_offsets()));
+        #[cfg(feature = "beyond_64k")]
+        if let Some(header) = beyond_64k::GdefHeader2::new(data) {
+            return Self {
+                data,
+                classes: ClassDefInfo::new_large(&data, header.glyph_class_def_offset()),
+                mark_classes: ClassDefInfo::new_large(&data, header.mark_attach_class_def_offset()),
+                mark_sets,
+                lig_caret_list: header.lig_caret_list(),
+                var_store: header.item_var_store(),
+                table: Some(gdef),
+            };
+        }
         Self {
-            table: Some(gdef),
-            classes,
-            mark_classes,
+            data,
+            classes: ClassDefInfo::new_large(
+                &data,
+                gdef.glyph_class_def_offset().offset().to_u32(),
+            ),
+            mark_classes: ClassDefInfo::new_large(
+                &data,
+                gdef.mark_attach_class_def_offset().offset().to_u32(),
+            ),
             mark_sets,
+            lig_caret_list: gdef.lig_caret_list().transpose().ok().flatten(),
+            var_store: gdef.item_var_store().transpose().ok().flatten(),
+            table: Some(gdef),
         }
     }
 }
 
+/// Returns the mark glyph sets of a GDEF table.
+fn mark_glyph_sets<'a>(gdef: &Gdef<'a>) -> Option<MarkGlyphSets<'a>> {
+    #[cfg(feature = "beyond_64k")]
+    if let Some(header) = beyond_64k::GdefHeader2::new(gdef.offset_data()) {
+        return header.mark_glyph_sets_def();
+    }
+    gdef.mark_glyph_sets_def().transpose().ok().flatten()
+}
+
 #[derive(Clone)]
 pub struct OtTables<'a> {
     pub gsub: Option<GsubTable<'a>>,
@@ -256,9 +290,7 @@ impl<'a> OtTables<'a> {
                 .unwrap_or_default()
         };
         let var_store = if !coords.is_empty() {
-            gdef.table
-                .as_ref()
-                .and_then(|gdef| gdef.item_var_store().transpose().ok().flatten())
+            gdef.var_store.clone()
         } else {
             None
         };
@@ -311,9 +343,7 @@ impl<'a> OtTables<'a> {
             GdefTable::default()
         };
         let var_store = if !coords.is_empty() {
-            gdef.table
-                .as_ref()
-                .and_then(|gdef| gdef.item_var_store().transpose().ok().flatten())
+            gdef.var_store.clone()
         } else {
             None
         };
@@ -334,17 +364,15 @@ impl<'a> OtTables<'a> {
     }
 
     pub fn glyph_class(&self, glyph_id: u32) -> u16 {
-        self.gdef
-            .classes
-            .as_ref()
-            .map_or(0, |class_def| class_def.get(glyph_id))
+        self.gdef.classes.as_ref().map_or(0, |class_def| {
+            class_def.class(&self.gdef.data, GlyphId::new(glyph_id))
+        })
     }
 
     pub fn glyph_mark_attachment_class(&self, glyph_id: u32) -> u16 {
-        self.gdef
-            .mark_classes
-            .as_ref()
-            .map_or(0, |class_def| class_def.get(glyph_id))
+        self.gdef.mark_classes.as_ref().map_or(0, |class_def| {
+            class_def.class(&self.gdef.data, GlyphId::new(glyph_id))
+        })
     }
 
     pub(crate) fn glyph_props(&self, glyph: GlyphId) -> u16 {
@@ -375,8 +403,10 @@ impl<'a> OtTables<'a> {
             .mark_sets
             .as_ref()
             .and_then(|(data, offsets)| Some((data, offsets.get(set_index as usize)?.get())))
-            .and_then(|(data, offset)| offset.resolve::<CoverageTable>(*data).ok())
-            .is_some_and(|coverage| coverage.get(glyph_id).is_some())
+            .and_then(|(data, offset)| {
+                Some((data, CoverageInfo::new_large(data, offset.to_u32())?))
+            })
+            .is_some_and(|(data, coverage)| coverage.index(data, GlyphId::new(glyph_id)).is_some())
     }
 
     #[inline(always)]
@@ -416,29 +446,58 @@ impl<'a> OtTables<'a> {
     }
 
     pub(super) fn resolve_anchor(&self, anchor: &AnchorTable) -> (f32, f32) {
-        let mut x = anchor.x_coordinate() as f32;
-        let mut y = anchor.y_coordinate() as f32;
-        if let Some(vs) = self.var_store.as_ref() {
-            // Keep the delta fractional; the caller rounds once when scaling,
-            // matching HarfBuzz's Anchor::get_anchor.
-            let delta = |val: Option<Result<DeviceOrVariationIndex<'_>, ReadError>>| match val {
-                Some(Ok(DeviceOrVariationIndex::VariationIndex(varix))) => {
-                    vs.compute_float_delta(
-                        DeltaSetIndex {
-                            outer: varix.delta_set_outer_index(),
-                            inner: varix.delta_set_inner_index(),
-                        },
-                        self.coords,
-                    )
-                    .unwrap_or_default()
-                    .to_f64() as f32
+        // Keep the delta fractional; the caller rounds once when scaling,
+        // matching HarfBuzz's Anchor::get_anchor.
+        let x = anchor.x_coordinate() as f32 + self.variation_delta(anchor.x_device());
+        let y = anchor.y_coordinate() as f32 + self.variation_delta(anchor.y_device());
+        (x, y)
+    }
+
+    /// Returns the ligature caret values of a glyph in font units, in the
+    /// order they appear in the GDEF `LigCaretList`.
+    ///
+    /// Carets defined by a contour point are skipped, as glyph outlines are
+    /// not loaded. Variation deltas are applied; hinting device deltas are
+    /// not, as there is no ppem, like in GPOS.
+    // OT::LigCaretList::get_lig_carets: <https://github.com/harfbuzz/harfbuzz/blob/main/src/OT/Layout/GDEF/GDEF.hh>
+    pub(crate) fn ligature_carets(&self, glyph_id: GlyphId) -> Vec<f32> {
+        let Some(lig_glyph) = self.gdef.lig_caret_list.as_ref().and_then(|list| {
+            let index = list.coverage().ok()?.get(glyph_id)?;
+            list.lig_glyphs().get(index as usize).ok()
+        }) else {
+            return Vec::new();
+        };
+        lig_glyph
+            .caret_values()
+            .iter()
+            .filter_map(|caret| match caret {
+                Ok(CaretValue::Format1(caret)) => Some(caret.coordinate() as f32),
+                Ok(CaretValue::Format3(caret)) => {
+                    Some(caret.coordinate() as f32 + self.variation_delta(Some(caret.device())))
                 }
-                _ => 0.0,
-            };
-            x += delta(anchor.x_device());
-            y += delta(anchor.y_device());
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn variation_delta(
+        &self,
+        device: Option<Result<DeviceOrVariationIndex<'_>, ReadError>>,
+    ) -> f32 {
+        match (device, self.var_store.as_ref()) {
+            (Some(Ok(DeviceOrVariationIndex::VariationIndex(varix))), Some(vs)) => {
+                vs.compute_float_delta(
+                    DeltaSetIndex {
+                        outer: varix.delta_set_outer_index(),
+                        inner: varix.delta_set_inner_index(),
+                    },
+                    self.coords,
+                )
+                .unwrap_or_default()
+                .to_f64() as f32
+            }
+            _ => 0.0,
         }
-        (x, y)
     }
 }
 
@@ -448,7 +507,19 @@ pub enum LayoutTable<'a> {
 }
 
 impl<'a> LayoutTable<'a> {
+    #[cfg(feature = "beyond_64k")]
+    fn header2(&self) -> Option<beyond_64k::LayoutHeader2<'a>> {
+        beyond_64k::LayoutHeader2::new(match self {
+            Self::Gsub(gsub) => gsub.offset_data(),
+            Self::Gpos(gpos) => gpos.offset_data(),
+        })
+    }
+
     fn script_list(&self) -> Option<ScriptList<'a>> {
+        #[cfg(feature = "beyond_64k")]
+        if let Some(header) = self.header2() {
+            return header.script_list();
+        }
         match self {
             Self::Gsub(gsub) => gsub.script_list().ok(),
             Self::Gpos(gpos) => gpos.script_list().ok(),
@@ -456,6 +527,10 @@ impl<'a> LayoutTable<'a> {
     }
 
     fn feature_list(&self) -> Option<FeatureList<'a>> {
+        #[cfg(feature = "beyond_64k")]
+        if let Some(header) = self.header2() {
+            return header.feature_list();
+        }
         match self {
             Self::Gsub(gsub) => gsub.feature_list().ok(),
             Self::Gpos(gpos) => gpos.feature_list().ok(),
@@ -463,6 +538,10 @@ impl<'a> LayoutTable<'a> {
     }
 
     fn feature_variations(&self) -> Option<FeatureVariations<'a>> {
+        #[cfg(feature = "beyond_64k")]
+        if let Some(header) = self.header2() {
+            return header.feature_variations();
+        }
         match self {
             Self::Gsub(gsub) => gsub.feature_variations(),
             Self::Gpos(gpos) => gpos.feature_variations(),
@@ -494,6 +573,12 @@ impl<'a> LayoutTable<'a> {
         }
     }
 
+    pub(crate) fn langsys_tag(&self, script_index: u16, langsys_index: u16) -> Option<hb_tag_t> {
+        let script = self.script(script_index)?;
+        let record = script.lang_sys_records().get(langsys_index as usize)?;
+        Some(record.lang_sys_tag())
+    }
+
     pub(crate) fn feature(&self, index: u16) -> Option<Feature<'a>> {
         self.feature_list()?
             .get(index)
@@ -583,6 +668,10 @@ impl<'a> LayoutTable<'a> {
     }
 
     pub(crate) fn lookup_count(&self) -> u16 {
+        #[cfg(feature = "beyond_64k")]
+        if let Some(header) = self.header2() {
+            return header.lookup_count();
+        }
         match self {
             Self::Gsub(gsub) => gsub
                 .lookup_list()
@@ -734,18 +823,28 @@ fn glyph_class_cached(
 
 #[derive(Copy, Clone, Default, Debug)]
 pub(crate) struct CoverageInfo {
-    pub offset: u16,
+    pub offset: u32,
     pub format: u16,
     pub count: u16,
 }
 
 impl CoverageInfo {
     pub fn new(parent_data: &FontData, offset: u16) -> Option<Self> {
+        Self::read(parent_data, offset.into(), false)
+    }
+
+    /// Like [`Self::new`], but also accepts formats 3 and 4, with 24-bit
+    /// glyph ids, when the `beyond_64k` feature is enabled.
+    pub fn new_large(parent_data: &FontData, offset: u32) -> Option<Self> {
+        Self::read(parent_data, offset, true)
+    }
+
+    fn read(parent_data: &FontData, offset: u32, large: bool) -> Option<Self> {
         if offset == 0 {
             return None;
         }
         let format = parent_data.read_at::<u16>(offset as usize).ok()?;
-        if format != 1 && format != 2 {
+        if !is_supported_format(format, large) {
             return None;
         }
         let count = parent_data.read_at::<u16>(offset as usize + 2).ok()?;
@@ -763,67 +862,151 @@ impl CoverageInfo {
         let gid = gid.to_u32();
         let data_offset = self.offset as usize + 4;
         let len = self.count as usize;
-        if self.format == 1 {
-            let glyphs = parent_data
-                .read_array::<BigEndian<GlyphId16>>(data_offset..data_offset + len * 2)
-                .ok()?;
-            glyphs
-                .binary_search_by_key(&gid, |g| g.get().to_u32())
+        match self.format {
+            1 => {
+                let glyphs = parent_data
+                    .read_array::<BigEndian<GlyphId16>>(data_offset..data_offset + len * 2)
+                    .ok()?;
+                glyphs
+                    .binary_search_by_key(&gid, |g| g.get().to_u32())
+                    .ok()
+                    .map(|idx| idx as _)
+            }
+            2 => {
+                use core::cmp::Ordering;
+                let records = parent_data
+                    .read_array::<RangeRecord>(
+                        data_offset..data_offset + len * size_of::<RangeRecord>(),
+                    )
+                    .ok()?;
+                records
+                    .binary_search_by(|rec| {
+                        if rec.end_glyph_id().to_u32() < gid {
+                            Ordering::Less
+                        } else if rec.start_glyph_id().to_u32() > gid {
+                            Ordering::Greater
+                        } else {
+                            Ordering::Equal
+                        }
+                    })
+                    .ok()
+                    .map(|idx| {
+                        let rec = &records[idx];
+                        (rec.start_coverage_index() as u32 + gid - rec.start_glyph_id().to_u32())
+                            as u16
+                    })
+            }
+            #[cfg(feature = "beyond_64k")]
+            3 => {
+                let glyphs = parent_data
+                    .read_array::<BigEndian<Uint24>>(data_offset..data_offset + len * 3)
+                    .ok()?;
+                glyphs
+                    .binary_search_by_key(&gid, |g| g.get().to_u32())
+                    .ok()
+                    .map(|idx| idx as _)
+            }
+            #[cfg(feature = "beyond_64k")]
+            4 => {
+                let (first, record) = find_range24(parent_data, data_offset, len, gid)?;
+                let start_index = parent_data.read_at::<u16>(record + 6).ok()?;
+                (u32::from(start_index) + gid - first).try_into().ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns a digest of the covered glyphs.
+    #[cfg(feature = "beyond_64k")]
+    pub fn digest(&self, parent_data: &FontData) -> hb_set_digest_t {
+        let mut digest = hb_set_digest_t::new();
+        let data_offset = self.offset as usize + 4;
+        let len = self.count as usize;
+        let glyph16 = |offset| parent_data.read_at::<u16>(offset).ok().map(u32::from);
+        let glyph24 = |offset| {
+            parent_data
+                .read_at::<Uint24>(offset)
                 .ok()
-                .map(|idx| idx as _)
+                .map(Uint24::to_u32)
+        };
+        let (glyph, glyph_len): (&dyn Fn(usize) -> Option<u32>, usize) = if self.format <= 2 {
+            (&glyph16, 2)
         } else {
-            use core::cmp::Ordering;
-            let records = parent_data
-                .read_array::<RangeRecord>(
-                    data_offset..data_offset + len * size_of::<RangeRecord>(),
-                )
-                .ok()?;
-            records
-                .binary_search_by(|rec| {
-                    if rec.end_glyph_id().to_u32() < gid {
-                        Ordering::Less
-                    } else if rec.start_glyph_id().to_u32() > gid {
-                        Ordering::Greater
-                    } else {
-                        Ordering::Equal
-                    }
-                })
-                .ok()
-                .map(|idx| {
-                    let rec = &records[idx];
-                    (rec.start_coverage_index() as u32 + gid - rec.start_glyph_id().to_u32()) as u16
-                })
+            (&glyph24, 3)
+        };
+        if self.format % 2 == 1 {
+            for i in 0..len {
+                let Some(gid) = glyph(data_offset + i * glyph_len) else {
+                    break;
+                };
+                digest.add(gid);
+            }
+        } else {
+            // Range records: first and last glyph, and a 16-bit value.
+            let record_len = 2 * glyph_len + 2;
+            for i in 0..len {
+                let record = data_offset + i * record_len;
+                let Some((first, last)) = glyph(record).zip(glyph(record + glyph_len)) else {
+                    break;
+                };
+                digest.add_range(first, last);
+            }
         }
+        digest
     }
 }
 
 #[derive(Copy, Clone, Default, Debug)]
 pub(crate) struct ClassDefInfo {
-    pub offset: u16,
+    pub offset: u32,
     pub format: u16,
-    // For format 1 only
-    pub start_glyph_id: u16,
-    pub count: u16,
+    // For formats 1 and 3 only
+    pub start_glyph_id: u32,
+    pub count: u32,
 }
 
 impl ClassDefInfo {
     pub fn new(parent_data: &FontData, offset: u16) -> Option<Self> {
+        Self::read(parent_data, offset.into(), false)
+    }
+
+    /// Like [`Self::new`], but also accepts formats 3 and 4, with 24-bit
+    /// glyph ids, when the `beyond_64k` feature is enabled.
+    pub fn new_large(parent_data: &FontData, offset: u32) -> Option<Self> {
+        Self::read(parent_data, offset, true)
+    }
+
+    fn read(parent_data: &FontData, offset: u32, large: bool) -> Option<Self> {
         if offset == 0 {
             return None;
         }
-        let format = parent_data.read_at::<u16>(offset as usize).ok()?;
-        if format != 1 && format != 2 {
+        let offset_usize = offset as usize;
+        let format = parent_data.read_at::<u16>(offset_usize).ok()?;
+        if !is_supported_format(format, large) {
             return None;
         }
-        let (start_glyph_id, count) = if format == 1 {
-            let start_glyph_id = parent_data.read_at::<u16>(offset as usize + 2).ok()?;
-            let count = parent_data.read_at::<u16>(offset as usize + 4).ok()?;
-            (start_glyph_id, count)
-        } else if format == 2 {
-            let count = parent_data.read_at::<u16>(offset as usize + 2).ok()?;
-            (0, count)
-        } else {
-            return None;
+        let (start_glyph_id, count) = match format {
+            1 => {
+                let start_glyph_id = parent_data.read_at::<u16>(offset_usize + 2).ok()?;
+                let count = parent_data.read_at::<u16>(offset_usize + 4).ok()?;
+                (start_glyph_id.into(), count.into())
+            }
+            2 => {
+                let count = parent_data.read_at::<u16>(offset_usize + 2).ok()?;
+                (0, count.into())
+            }
+            #[cfg(feature = "beyond_64k")]
+            3 => {
+                let start_glyph_id = parent_data.read_at::<Uint24>(offset_usize + 2).ok()?;
+                let count = parent_data.read_at::<Uint24>(offset_usize + 5).ok()?;
+                (start_glyph_id.to_u32(), count.to_u32())
+            }
+            #[cfg(feature = "beyond_64k")]
+            4 => {
+                let count = parent_data.read_at::<Uint24>(offset_usize + 2).ok()?;
+                (0, count.to_u32())
+            }
+            _ => return None,
         };
         Some(Self {
             offset,
@@ -839,37 +1022,80 @@ impl ClassDefInfo {
             return 0;
         }
         let gid = gid.to_u32();
-        if self.format == 1 {
-            let Some(idx) = gid.checked_sub(self.start_glyph_id as u32) else {
-                return 0;
-            };
-            if idx >= self.count as u32 {
-                return 0;
+        match self.format {
+            1 | 3 => {
+                let Some(idx) = gid.checked_sub(self.start_glyph_id) else {
+                    return 0;
+                };
+                if idx >= self.count {
+                    return 0;
+                }
+                let values = if self.format == 1 { 6 } else { 8 };
+                parent_data
+                    .read_at::<u16>(offset + values + idx as usize * 2)
+                    .unwrap_or(0)
             }
-            parent_data
-                .read_at::<u16>(offset + 6 + idx as usize * 2)
-                .unwrap_or(0)
+            2 => {
+                use core::cmp::Ordering;
+                let start = offset + 4;
+                let end = start + self.count as usize * size_of::<ClassRangeRecord>();
+                let Ok(records) = parent_data.read_array::<ClassRangeRecord>(start..end) else {
+                    return 0;
+                };
+                records
+                    .binary_search_by(|rec| {
+                        if rec.end_glyph_id().to_u32() < gid {
+                            Ordering::Less
+                        } else if rec.start_glyph_id().to_u32() > gid {
+                            Ordering::Greater
+                        } else {
+                            Ordering::Equal
+                        }
+                    })
+                    .ok()
+                    .map_or(0, |idx| records[idx].class())
+            }
+            #[cfg(feature = "beyond_64k")]
+            4 => find_range24(parent_data, offset + 5, self.count as usize, gid)
+                .and_then(|(_, record)| parent_data.read_at::<u16>(record + 6).ok())
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+}
+
+/// Whether a Coverage or ClassDef table of `format` can be read. Formats 3
+/// and 4 are only read for `large` tables with the `beyond_64k` feature.
+fn is_supported_format(format: u16, large: bool) -> bool {
+    match format {
+        1 | 2 => true,
+        3 | 4 => large && cfg!(feature = "beyond_64k"),
+        _ => false,
+    }
+}
+
+/// Finds the range record covering `gid` among `count` records with 24-bit
+/// first and last glyph ids and a 16-bit value, starting at `start`.
+///
+/// Returns the first glyph id of the range and the offset of the record.
+#[cfg(feature = "beyond_64k")]
+fn find_range24(data: &FontData, start: usize, count: usize, gid: u32) -> Option<(u32, usize)> {
+    const RECORD_LEN: usize = 8;
+    let (mut lo, mut hi) = (0, count);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let record = start + mid * RECORD_LEN;
+        let first = data.read_at::<Uint24>(record).ok()?.to_u32();
+        let last = data.read_at::<Uint24>(record + 3).ok()?.to_u32();
+        if last < gid {
+            lo = mid + 1;
+        } else if first > gid {
+            hi = mid;
         } else {
-            use core::cmp::Ordering;
-            let start = offset + 4;
-            let end = start + self.count as usize * size_of::<ClassRangeRecord>();
-            let Ok(records) = parent_data.read_array::<ClassRangeRecord>(start..end) else {
-                return 0;
-            };
-            records
-                .binary_search_by(|rec| {
-                    if rec.end_glyph_id().to_u32() < gid {
-                        Ordering::Less
-                    } else if rec.start_glyph_id().to_u32() > gid {
-                        Ordering::Greater
-                    } else {
-                        Ordering::Equal
-                    }
-                })
-                .ok()
-                .map_or(0, |idx| records[idx].class())
+            return Some((first, record));
         }
     }
+    None
 }
 
 use crate::algs::HB_CODEPOINT_ENCODE3 as encode3;
@@ -0,0 +1,154 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use read_fonts::tables::layout::{Feature, FeatureParams};
+use read_fonts::types::{NameId, Tag};
+
+use super::face::hb_font_t;
+use super::tag;
+use crate::{Language, Script};
+
+/// `name` table IDs attached to a stylistic set (`ss01`–`ss20`) or
+/// character variant (`cv01`–`cv99`) feature.
+///
+/// Returned by [`Shaper::feature_name_ids`](crate::Shaper::feature_name_ids).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FeatureNameIds {
+    /// The feature's UI label, e.g. "Single-storey a".
+    pub label: Option<NameId>,
+    /// The feature's tooltip text. Only set for character variants.
+    pub tooltip: Option<NameId>,
+    /// Sample text that illustrates the feature. Only set for character
+    /// variants.
+    pub sample_text: Option<NameId>,
+    /// The label of the first named parameter. Only set for character
+    /// variants.
+    pub first_param_label: Option<NameId>,
+    /// The number of named parameters, whose labels have consecutive IDs
+    /// starting at `first_param_label`.
+    pub num_named_parameters: u16,
+}
+
+impl FeatureNameIds {
+    /// Returns the IDs of the labels of the named parameters, in order.
+    pub fn param_labels(&self) -> impl Iterator<Item = NameId> + '_ {
+        self.first_param_label.into_iter().flat_map(|first| {
+            (0..self.num_named_parameters)
+                .filter_map(move |i| first.to_u16().checked_add(i).map(NameId::new))
+        })
+    }
+}
+
+/// The strings of [`FeatureNameIds`], resolved with
+/// [`Shaper::name`](crate::Shaper::name).
+///
+/// Returned by [`Shaper::feature_names`](crate::Shaper::feature_names).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeatureNames {
+    /// The feature's UI label, e.g. "Single-storey a".
+    pub label: Option<String>,
+    /// The feature's tooltip text.
+    pub tooltip: Option<String>,
+    /// Sample text that illustrates the feature.
+    pub sample_text: Option<String>,
+    /// The labels of the named parameters, in order.
+    pub param_labels: Vec<String>,
+    /// The characters the feature provides glyph variants for.
+    pub characters: Vec<char>,
+}
+
+impl hb_font_t<'_> {
+    /// Returns the `name` table IDs of a stylistic set or character variant
+    /// feature, or `None` if neither `GSUB` nor `GPOS` has the feature or
+    /// it has no feature parameters.
+    ///
+    /// The feature is looked up in the language system selected for
+    /// `script` and `language`, as when shaping, since fonts may define
+    /// a feature tag several times with different parameters.
+    ///
+    /// This is HarfBuzz's `hb_ot_layout_feature_get_name_ids`, with the
+    /// feature index found by `hb_ot_layout_language_find_feature`.
+    pub fn feature_name_ids(
+        &self,
+        feature_tag: Tag,
+        script: Option<Script>,
+        language: Option<&Language>,
+    ) -> Option<FeatureNameIds> {
+        let name_id = |id: NameId| (id.to_u16() != 0).then_some(id);
+        match self.feature_params(feature_tag, script, language)? {
+            FeatureParams::StylisticSet(params) => Some(FeatureNameIds {
+                label: name_id(params.ui_name_id()),
+                ..FeatureNameIds::default()
+            }),
+            FeatureParams::CharacterVariant(params) => Some(FeatureNameIds {
+                label: name_id(params.feat_ui_label_name_id()),
+                tooltip: name_id(params.feat_ui_tooltip_text_name_id()),
+                sample_text: name_id(params.sample_text_name_id()),
+                first_param_label: name_id(params.first_param_ui_label_name_id()),
+                num_named_parameters: params.num_named_parameters(),
+            }),
+            FeatureParams::Size(_) => None,
+        }
+    }
+
+    /// Returns the characters a character variant feature provides glyph
+    /// variants for, or an empty list for any other feature.
+    ///
+    /// The feature is looked up as in
+    /// [`feature_name_ids`](Self::feature_name_ids).
+    ///
+    /// This is HarfBuzz's `hb_ot_layout_feature_get_characters`.
+    pub fn feature_characters(
+        &self,
+        feature_tag: Tag,
+        script: Option<Script>,
+        language: Option<&Language>,
+    ) -> Vec<char> {
+        match self.feature_params(feature_tag, script, language) {
+            Some(FeatureParams::CharacterVariant(params)) => params
+                .character()
+                .iter()
+                .filter_map(|c| char::from_u32(c.get().to_u32()))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the UI strings and characters of a stylistic set or
+    /// character variant feature, resolved through the `name` table.
+    ///
+    /// Returns `None` in the same cases as
+    /// [`feature_name_ids`](Self::feature_name_ids).
+    pub fn feature_names(
+        &self,
+        feature_tag: Tag,
+        script: Option<Script>,
+        language: Option<&Language>,
+    ) -> Option<FeatureNames> {
+        let ids = self.feature_name_ids(feature_tag, script, language)?;
+        let name = |id: Option<NameId>| id.and_then(|id| self.name(id));
+        Some(FeatureNames {
+            label: name(ids.label),
+            tooltip: name(ids.tooltip),
+            sample_text: name(ids.sample_text),
+            param_labels: ids.param_labels().filter_map(|id| self.name(id)).collect(),
+            characters: self.feature_characters(feature_tag, script, language),
+        })
+    }
+
+    fn feature_params(
+        &self,
+        feature_tag: Tag,
+        script: Option<Script>,
+        language: Option<&Language>,
+    ) -> Option<FeatureParams<'_>> {
+        let (script_tags, lang_tags) = tag::tags_from_script_and_language(script, language);
+        self.layout_tables()
+            .filter_map(|(_, table)| {
+                let (_, script_index, _) = table.select_script(&script_tags)?;
+                let lang_index = table.select_script_language(script_index, &lang_tags);
+                let index = table.find_language_feature(script_index, lang_index, feature_tag)?;
+                table.feature(index)
+            })
+            .find_map(|feature: Feature| feature.feature_params()?.ok())
+    }
+}
@@ -1,13 +1,16 @@
 //! OpenType layout.
 
 use core::ops::{Index, IndexMut};
+use read_fonts::types::GlyphId;
 
 use super::buffer::*;
 use super::font_funcs::FontFuncsDispatch;
 use super::ot::lookup::LookupInfo;
-use super::ot_layout_gsubgpos::OT;
+use super::ot_layout_gsubgpos::{WouldApplyContext, OT};
 use super::ot_shape_plan::hb_ot_shape_plan_t;
-use super::{hb_font_t, GlyphInfo};
+#[cfg(feature = "profile")]
+use super::profile::LookupProfile;
+use super::{hb_font_t, hb_tag_t, GlyphInfo};
 use crate::hb::ot_layout_gsubgpos::OT::check_glyph_property;
 use crate::unicode::{hb_unicode_funcs_t, GeneralCategory};
 
@@ -142,6 +145,65 @@ pub fn hb_ot_layout_substitute_start(face: &hb_font_t, buffer: &mut hb_buffer_t)
     _hb_ot_layout_set_glyph_props(face, buffer);
 }
 
+impl hb_font_t<'_> {
+    /// Returns true if any `GSUB` lookup of the feature `feature_tag` would
+    /// substitute the glyph sequence `glyphs`.
+    ///
+    /// The feature is looked up in the script and language system chosen
+    /// for `plan`, taking feature variations of the current instance into
+    /// account. `plan` must have been created for this font.
+    /// If `zero_context` is true, contextual lookups only match when they
+    /// need no backtrack or lookahead glyphs.
+    ///
+    /// This is HarfBuzz's `hb_ot_layout_lookup_would_substitute`, applied to
+    /// every lookup of the feature.
+    pub fn would_substitute(
+        &self,
+        plan: &hb_ot_shape_plan_t,
+        feature_tag: hb_tag_t,
+        glyphs: &[GlyphId],
+        zero_context: bool,
+    ) -> bool {
+        if glyphs.is_empty() {
+            return false;
+        }
+        let (Some(gsub), Some(table)) = (
+            self.ot_tables.gsub.as_ref(),
+            self.layout_table(TableIndex::GSUB),
+        ) else {
+            return false;
+        };
+
+        let Some(script_index) = plan.ot_map.script_index(TableIndex::GSUB) else {
+            return false;
+        };
+        let lang_index = plan.ot_map.language_index(TableIndex::GSUB);
+        let Some(feature_index) =
+            table.find_language_feature(script_index, lang_index, feature_tag)
+        else {
+            return false;
+        };
+        let feature = match self.ot_tables.feature_variations[TableIndex::GSUB] {
+            Some(idx) => table
+                .feature_substitution(idx, feature_index)
+                .or_else(|| table.feature(feature_index)),
+            None => table.feature(feature_index),
+        };
+        let Some(feature) = feature else {
+            return false;
+        };
+
+        let ctx = WouldApplyContext {
+            glyphs,
+            zero_context,
+        };
+        feature.lookup_list_indices().iter().any(|index| {
+            gsub.get_lookup(index.get())
+                .is_some_and(|lookup| lookup.would_apply(self, &ctx) == Some(true))
+        })
+    }
+}
+
 /// Applies the lookups in the given GSUB or GPOS table.
 pub fn apply_layout_table<T: LayoutTable>(
     plan: &hb_ot_shape_plan_t,
@@ -149,8 +211,13 @@ pub fn apply_layout_table<T: LayoutTable>(
     font_funcs: &mut FontFuncsDispatch,
     buffer: &mut hb_buffer_t,
     table: Option<&T>,
+    #[cfg(feature = "profile")] profile: Option<&mut LookupProfile>,
 ) {
     let mut ctx = OT::hb_ot_apply_context_t::new(T::INDEX, face, *font_funcs.scale(), buffer);
+    #[cfg(feature = "profile")]
+    {
+        ctx.profile = profile;
+    }
 
     for (stage_index, stage) in plan.ot_map.stages(T::INDEX).iter().enumerate() {
         if let Some(table) = table {
@@ -240,6 +307,15 @@ fn apply_forward(ctx: &mut OT::hb_ot_apply_context_t, lookup: &LookupInfo) -> bo
             j += 1;
         }
 
+        #[cfg(feature = "profile")]
+        if let Some(profile) = ctx.profile.as_deref_mut() {
+            let visited = j - idx + usize::from(j < infos.len());
+            profile
+                .lookup(ctx.table_index, ctx.lookup_index, lookup.subtables.len())
+                .counts
+                .glyphs_visited += visited as u64;
+        }
+
         if j > idx {
             ctx.buffer.next_glyphs(j - idx);
         }
@@ -282,6 +358,15 @@ fn apply_backward(ctx: &mut OT::hb_ot_apply_context_t, lookup: &LookupInfo) -> b
                 && check_glyph_property(face, info, lookup_props)
         });
 
+        #[cfg(feature = "profile")]
+        if let Some(profile) = ctx.profile.as_deref_mut() {
+            let visited = ctx.buffer.idx + 1 - candidate.unwrap_or(0);
+            profile
+                .lookup(ctx.table_index, ctx.lookup_index, lookup.subtables.len())
+                .counts
+                .glyphs_visited += visited as u64;
+        }
+
         let Some(idx) = candidate else {
             ctx.buffer.idx = 0;
             break;
@@ -3,6 +3,8 @@ use super::font_funcs::FontFuncsDispatch;
 use super::hb_font_t;
 use super::ot_layout::*;
 use super::ot_shape_plan::hb_ot_shape_plan_t;
+#[cfg(feature = "profile")]
+use super::profile::LookupProfile;
 use crate::Direction;
 
 pub fn position(
@@ -10,8 +12,17 @@ pub fn position(
     face: &hb_font_t,
     font_funcs: &mut FontFuncsDispatch,
     buffer: &mut hb_buffer_t,
+    #[cfg(feature = "profile")] profile: Option<&mut LookupProfile>,
 ) {
-    apply_layout_table(plan, face, font_funcs, buffer, face.ot_tables.gpos.as_ref());
+    apply_layout_table(
+        plan,
+        face,
+        font_funcs,
+        buffer,
+        face.ot_tables.gpos.as_ref(),
+        #[cfg(feature = "profile")]
+        profile,
+    );
 }
 
 pub mod attach_type {
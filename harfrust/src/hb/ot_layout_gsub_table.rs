@@ -3,12 +3,23 @@ use super::font_funcs::FontFuncsDispatch;
 use super::hb_font_t;
 use super::ot_layout::*;
 use super::ot_shape_plan::hb_ot_shape_plan_t;
+#[cfg(feature = "profile")]
+use super::profile::LookupProfile;
 
 pub fn substitute(
     plan: &hb_ot_shape_plan_t,
     face: &hb_font_t,
     font_funcs: &mut FontFuncsDispatch,
     buffer: &mut hb_buffer_t,
+    #[cfg(feature = "profile")] profile: Option<&mut LookupProfile>,
 ) {
-    apply_layout_table(plan, face, font_funcs, buffer, face.ot_tables.gsub.as_ref());
+    apply_layout_table(
+        plan,
+        face,
+        font_funcs,
+        buffer,
+        face.ot_tables.gsub.as_ref(),
+        #[cfg(feature = "profile")]
+        profile,
+    );
 }
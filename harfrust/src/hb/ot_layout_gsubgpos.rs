@@ -8,6 +8,8 @@ use super::hb_font_t;
 use super::hb_mask_t;
 use super::ot_layout::*;
 use super::ot_layout_common::*;
+#[cfg(feature = "profile")]
+use super::profile::LookupProfile;
 use super::set_digest::hb_set_digest_t;
 use crate::hb::ot::{ClassDefInfo, CoverageInfo};
 use crate::hb::ot_layout_gsubgpos::OT::check_glyph_property;
@@ -872,6 +874,8 @@ pub mod OT {
         pub(crate) context_matcher: matcher_t,
         pub(crate) match_positions_len: usize,
         pub(crate) match_positions: MatchPositions,
+        #[cfg(feature = "profile")]
+        pub(crate) profile: Option<&'a mut LookupProfile>,
     }
 
     impl<'a> hb_ot_apply_context_t<'a> {
@@ -902,6 +906,8 @@ pub mod OT {
                 context_matcher: matcher_t::default(),
                 match_positions_len: 0,
                 match_positions: MatchPositions::from_elem(0, 1),
+                #[cfg(feature = "profile")]
+                profile: None,
             }
         }
 
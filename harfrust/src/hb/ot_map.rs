@@ -15,9 +15,15 @@ use core::mem::{size_of, size_of_val};
 pub struct hb_ot_map_t {
     found_script: [bool; 2],
     chosen_script: [Option<hb_tag_t>; 2],
+    chosen_language: [Option<hb_tag_t>; 2],
+    script_index: [Option<u16>; 2],
+    lang_index: [Option<u16>; 2],
     global_mask: hb_mask_t,
     features: Vec<feature_map_t>,
     lookups: [Vec<lookup_map_t>; 2],
+    // The feature that added each lookup, kept apart from `lookups` since it
+    // is only used for introspection.
+    lookup_feature_tags: [Vec<hb_tag_t>; 2],
     stages: [Vec<StageMap>; 2],
     feature_variations: [Option<u32>; 2],
 }
@@ -86,6 +92,21 @@ impl hb_ot_map_t {
         self.chosen_script[table_index]
     }
 
+    #[inline]
+    pub fn chosen_language(&self, table_index: TableIndex) -> Option<hb_tag_t> {
+        self.chosen_language[table_index]
+    }
+
+    #[inline]
+    pub fn script_index(&self, table_index: TableIndex) -> Option<u16> {
+        self.script_index[table_index]
+    }
+
+    #[inline]
+    pub fn language_index(&self, table_index: TableIndex) -> Option<u16> {
+        self.lang_index[table_index]
+    }
+
     #[inline]
     pub fn get_global_mask(&self) -> hb_mask_t {
         self.global_mask
@@ -142,6 +163,12 @@ impl hb_ot_map_t {
         &self.lookups[table_index][self.stage_lookup_range(table_index, stage)]
     }
 
+    /// Returns the features that added the lookups of a stage, in the
+    /// order of [`stage_lookups`](Self::stage_lookups).
+    pub fn stage_lookup_feature_tags(&self, table_index: TableIndex, stage: usize) -> &[hb_tag_t] {
+        &self.lookup_feature_tags[table_index][self.stage_lookup_range(table_index, stage)]
+    }
+
     #[inline]
     pub fn stage_lookup_range(&self, table_index: TableIndex, stage: usize) -> Range<usize> {
         let stages = &self.stages[table_index];
@@ -178,6 +205,7 @@ pub struct hb_ot_map_builder_t<'a> {
     found_script: [bool; 2],
     script_index: [Option<u16>; 2],
     chosen_script: [Option<hb_tag_t>; 2],
+    chosen_language: [Option<hb_tag_t>; 2],
     lang_index: [Option<u16>; 2],
     current_stage: [usize; 2],
     feature_infos: Vec<feature_info_t>,
@@ -220,6 +248,7 @@ impl<'a> hb_ot_map_builder_t<'a> {
         let mut found_script = [false; 2];
         let mut script_index = [None; 2];
         let mut chosen_script = [None; 2];
+        let mut chosen_language = [None; 2];
         let mut lang_index = [None; 2];
 
         for (table_index, table) in face.layout_tables() {
@@ -228,8 +257,9 @@ impl<'a> hb_ot_map_builder_t<'a> {
                 found_script[table_index] = found;
                 script_index[table_index] = Some(idx);
 
-                if let Some(idx) = table.select_script_language(idx, &lang_tags) {
-                    lang_index[table_index] = Some(idx);
+                if let Some(lang_idx) = table.select_script_language(idx, &lang_tags) {
+                    lang_index[table_index] = Some(lang_idx);
+                    chosen_language[table_index] = table.langsys_tag(idx, lang_idx);
                 }
             }
         }
@@ -239,6 +269,7 @@ impl<'a> hb_ot_map_builder_t<'a> {
             found_script,
             script_index,
             chosen_script,
+            chosen_language,
             lang_index,
             current_stage: [0, 0],
             feature_infos: Vec::new(),
@@ -334,15 +365,19 @@ impl<'a> hb_ot_map_builder_t<'a> {
         self.add_gsub_pause(None);
         self.add_gpos_pause(None);
 
-        let (lookups, stages) =
-            self.collect_lookup_stages(&features, required_index, required_stage);
+        let (lookups, lookup_feature_tags, stages) =
+            self.collect_lookup_stages(&features, required_index, required_tag, required_stage);
 
         hb_ot_map_t {
             found_script: self.found_script,
             chosen_script: self.chosen_script,
+            chosen_language: self.chosen_language,
+            script_index: self.script_index,
+            lang_index: self.lang_index,
             global_mask,
             features,
             lookups,
+            lookup_feature_tags,
             stages,
             feature_variations: self.face.ot_tables.feature_variations,
         }
@@ -483,9 +518,15 @@ impl<'a> hb_ot_map_builder_t<'a> {
         &self,
         map_features: &[feature_map_t],
         required_feature_index: [Option<u16>; 2],
+        required_feature_tag: [Option<hb_tag_t>; 2],
         required_feature_stage: [usize; 2],
-    ) -> ([Vec<lookup_map_t>; 2], [Vec<StageMap>; 2]) {
-        let mut map_lookups = [Vec::new(), Vec::new()];
+    ) -> (
+        [Vec<lookup_map_t>; 2],
+        [Vec<hb_tag_t>; 2],
+        [Vec<StageMap>; 2],
+    ) {
+        // Lookups paired with the feature that added them.
+        let mut map_lookups: [Vec<(lookup_map_t, hb_tag_t)>; 2] = [Vec::new(), Vec::new()];
         let mut map_stages = [Vec::new(), Vec::new()];
 
         for table_index in TableIndex::iter() {
@@ -496,12 +537,16 @@ impl<'a> hb_ot_map_builder_t<'a> {
             let variation_index = self.face.ot_tables.feature_variations[table_index as usize];
 
             for stage in 0..self.current_stage[table_index] {
-                if let Some(feature_index) = required_feature_index[table_index] {
+                if let (Some(feature_index), Some(feature_tag)) = (
+                    required_feature_index[table_index],
+                    required_feature_tag[table_index],
+                ) {
                     if required_feature_stage[table_index] == stage {
                         self.add_lookups(
                             &mut map_lookups[table_index],
                             table_index,
                             feature_index,
+                            feature_tag,
                             variation_index,
                             GLOBAL_BIT_MASK,
                             true,
@@ -519,6 +564,7 @@ impl<'a> hb_ot_map_builder_t<'a> {
                                 &mut map_lookups[table_index],
                                 table_index,
                                 feature_index,
+                                feature.tag,
                                 variation_index,
                                 feature.mask,
                                 feature.auto_zwnj,
@@ -535,17 +581,19 @@ impl<'a> hb_ot_map_builder_t<'a> {
                 let len = lookups.len();
 
                 if last_lookup + 1 < len {
-                    lookups[last_lookup..].sort();
+                    lookups[last_lookup..].sort_by_key(|(lookup, _)| *lookup);
 
                     let mut j = last_lookup;
                     for i in j + 1..len {
-                        if lookups[i].index != lookups[j].index {
+                        if lookups[i].0.index != lookups[j].0.index {
                             j += 1;
                             lookups[j] = lookups[i];
                         } else {
-                            lookups[j].mask |= lookups[i].mask;
-                            lookups[j].auto_zwnj &= lookups[i].auto_zwnj;
-                            lookups[j].auto_zwj &= lookups[i].auto_zwj;
+                            let other = lookups[i].0;
+                            let lookup = &mut lookups[j].0;
+                            lookup.mask |= other.mask;
+                            lookup.auto_zwnj &= other.auto_zwnj;
+                            lookup.auto_zwj &= other.auto_zwj;
                         }
                     }
 
@@ -567,14 +615,16 @@ impl<'a> hb_ot_map_builder_t<'a> {
             }
         }
 
-        (map_lookups, map_stages)
+        let [gsub, gpos] = map_lookups.map(|lookups| lookups.into_iter().unzip());
+        ([gsub.0, gpos.0], [gsub.1, gpos.1], map_stages)
     }
 
     fn add_lookups(
         &self,
-        lookups: &mut Vec<lookup_map_t>,
+        lookups: &mut Vec<(lookup_map_t, hb_tag_t)>,
         table_index: TableIndex,
         feature_index: u16,
+        feature_tag: hb_tag_t,
         variation_index: Option<u32>,
         mask: hb_mask_t,
         auto_zwnj: bool,
@@ -595,14 +645,15 @@ impl<'a> hb_ot_map_builder_t<'a> {
         for index in feature.lookup_list_indices() {
             let index = index.get();
             if index < lookup_count {
-                lookups.push(lookup_map_t {
+                let lookup = lookup_map_t {
                     index,
                     auto_zwnj,
                     auto_zwj,
                     random,
                     mask,
                     per_syllable,
-                });
+                };
+                lookups.push((lookup, feature_tag));
             }
         }
 
@@ -0,0 +1,229 @@
+use read_fonts::tables::os2::SelectionFlags;
+use read_fonts::types::{F2Dot14, Tag};
+use read_fonts::TableProvider;
+
+// libm used for f32::abs()
+#[cfg(not(feature = "std"))]
+#[allow(unused_imports)]
+use core_maths::CoreFloat as _;
+
+use super::face::{FontKind, Scale};
+
+/// Font-wide metrics that can be queried with
+/// [`Shaper::metric`](crate::Shaper::metric).
+///
+/// Each metric corresponds to an `MVAR` value tag, returned by
+/// [`MetricsTag::tag`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MetricsTag {
+    /// Horizontal ascender, from `OS/2` if `USE_TYPO_METRICS` is set and
+    /// from `hhea` otherwise.
+    HorizontalAscender,
+    /// Horizontal descender, from `OS/2` if `USE_TYPO_METRICS` is set and
+    /// from `hhea` otherwise.
+    HorizontalDescender,
+    /// Horizontal line gap, from `OS/2` if `USE_TYPO_METRICS` is set and
+    /// from `hhea` otherwise.
+    HorizontalLineGap,
+    /// Horizontal clipping ascent (`OS/2.usWinAscent`).
+    HorizontalClippingAscent,
+    /// Horizontal clipping descent (`OS/2.usWinDescent`).
+    HorizontalClippingDescent,
+    /// Vertical ascender (`vhea.ascent`).
+    VerticalAscender,
+    /// Vertical descender (`vhea.descent`).
+    VerticalDescender,
+    /// Vertical line gap (`vhea.lineGap`).
+    VerticalLineGap,
+    /// Horizontal caret rise (`hhea.caretSlopeRise`).
+    HorizontalCaretRise,
+    /// Horizontal caret run (`hhea.caretSlopeRun`).
+    HorizontalCaretRun,
+    /// Horizontal caret offset (`hhea.caretOffset`).
+    HorizontalCaretOffset,
+    /// Vertical caret rise (`vhea.caretSlopeRise`).
+    VerticalCaretRise,
+    /// Vertical caret run (`vhea.caretSlopeRun`).
+    VerticalCaretRun,
+    /// Vertical caret offset (`vhea.caretOffset`).
+    VerticalCaretOffset,
+    /// x-height (`OS/2.sxHeight`).
+    XHeight,
+    /// Cap height (`OS/2.sCapHeight`).
+    CapHeight,
+    /// Subscript em x size (`OS/2.ySubscriptXSize`).
+    SubscriptEmXSize,
+    /// Subscript em y size (`OS/2.ySubscriptYSize`).
+    SubscriptEmYSize,
+    /// Subscript em x offset (`OS/2.ySubscriptXOffset`).
+    SubscriptEmXOffset,
+    /// Subscript em y offset (`OS/2.ySubscriptYOffset`).
+    SubscriptEmYOffset,
+    /// Superscript em x size (`OS/2.ySuperscriptXSize`).
+    SuperscriptEmXSize,
+    /// Superscript em y size (`OS/2.ySuperscriptYSize`).
+    SuperscriptEmYSize,
+    /// Superscript em x offset (`OS/2.ySuperscriptXOffset`).
+    SuperscriptEmXOffset,
+    /// Superscript em y offset (`OS/2.ySuperscriptYOffset`).
+    SuperscriptEmYOffset,
+    /// Strikeout size (`OS/2.yStrikeoutSize`).
+    StrikeoutSize,
+    /// Strikeout offset (`OS/2.yStrikeoutPosition`).
+    StrikeoutOffset,
+    /// Underline size (`post.underlineThickness`).
+    UnderlineSize,
+    /// Underline offset (`post.underlinePosition`).
+    UnderlineOffset,
+}
+
+impl MetricsTag {
+    /// Returns the `MVAR` value tag of the metric.
+    pub const fn tag(self) -> Tag {
+        Tag::new(match self {
+            Self::HorizontalAscender => b"hasc",
+            Self::HorizontalDescender => b"hdsc",
+            Self::HorizontalLineGap => b"hlgp",
+            Self::HorizontalClippingAscent => b"hcla",
+            Self::HorizontalClippingDescent => b"hcld",
+            Self::VerticalAscender => b"vasc",
+            Self::VerticalDescender => b"vdsc",
+            Self::VerticalLineGap => b"vlgp",
+            Self::HorizontalCaretRise => b"hcrs",
+            Self::HorizontalCaretRun => b"hcrn",
+            Self::HorizontalCaretOffset => b"hcof",
+            Self::VerticalCaretRise => b"vcrs",
+            Self::VerticalCaretRun => b"vcrn",
+            Self::VerticalCaretOffset => b"vcof",
+            Self::XHeight => b"xhgt",
+            Self::CapHeight => b"cpht",
+            Self::SubscriptEmXSize => b"sbxs",
+            Self::SubscriptEmYSize => b"sbys",
+            Self::SubscriptEmXOffset => b"sbxo",
+            Self::SubscriptEmYOffset => b"sbyo",
+            Self::SuperscriptEmXSize => b"spxs",
+            Self::SuperscriptEmYSize => b"spys",
+            Self::SuperscriptEmXOffset => b"spxo",
+            Self::SuperscriptEmYOffset => b"spyo",
+            Self::StrikeoutSize => b"strs",
+            Self::StrikeoutOffset => b"stro",
+            Self::UnderlineSize => b"unds",
+            Self::UnderlineOffset => b"undo",
+        })
+    }
+}
+
+impl crate::Shaper<'_> {
+    /// Returns the value of a font-wide metric at the current variation
+    /// instance, or `None` if the table it comes from is missing.
+    ///
+    /// Values are varied by `MVAR` and scaled by `scale`, which has the
+    /// same meaning as in
+    /// [`ShapeOptions::scale_separate`](crate::ShapeOptions::scale_separate),
+    /// so they are consistent with the positions produced by shaping with
+    /// the same scale. Ascenders are always returned as positive values and
+    /// descenders as negative ones.
+    ///
+    /// This is HarfBuzz's `hb_ot_metrics_get_position`.
+    pub fn metric(&self, metric: MetricsTag, scale: Option<(i32, i32)>) -> Option<i32> {
+        let scale = Scale::new(scale, self.units_per_em as i32);
+        let coords = self.coords();
+        match &self.font {
+            FontKind::FontRef(data) => metric_position(&data.font, metric, coords, &scale),
+            FontKind::FontInstance(instance, _) => {
+                metric_position(&instance.tables(), metric, coords, &scale)
+            }
+        }
+    }
+}
+
+// hb_ot_metrics_get_position: <https://github.com/harfbuzz/harfbuzz/blob/main/src/hb-ot-metrics.cc>
+#[allow(clippy::enum_glob_use)]
+fn metric_position<'a>(
+    font: &impl TableProvider<'a>,
+    metric: MetricsTag,
+    coords: &[F2Dot14],
+    scale: &Scale,
+) -> Option<i32> {
+    use MetricsTag::*;
+
+    let os2 = || font.os2().ok();
+    let hhea = || font.hhea().ok();
+    let vhea = || font.vhea().ok();
+    let use_typo_metrics = || {
+        os2().is_some_and(|os2| {
+            os2.fs_selection()
+                .contains(SelectionFlags::USE_TYPO_METRICS)
+        })
+    };
+
+    let mut value: f32 = match metric {
+        HorizontalAscender if use_typo_metrics() => os2()?.s_typo_ascender().into(),
+        HorizontalAscender => hhea()?.ascender().to_i16().into(),
+        HorizontalDescender if use_typo_metrics() => os2()?.s_typo_descender().into(),
+        HorizontalDescender => hhea()?.descender().to_i16().into(),
+        HorizontalLineGap if use_typo_metrics() => os2()?.s_typo_line_gap().into(),
+        HorizontalLineGap => hhea()?.line_gap().to_i16().into(),
+        HorizontalClippingAscent => os2()?.us_win_ascent().into(),
+        HorizontalClippingDescent => os2()?.us_win_descent().into(),
+        VerticalAscender => vhea()?.ascender().to_i16().into(),
+        VerticalDescender => vhea()?.descender().to_i16().into(),
+        VerticalLineGap => vhea()?.line_gap().to_i16().into(),
+        HorizontalCaretRise => hhea()?.caret_slope_rise().into(),
+        HorizontalCaretRun => hhea()?.caret_slope_run().into(),
+        HorizontalCaretOffset => hhea()?.caret_offset().into(),
+        VerticalCaretRise => vhea()?.caret_slope_rise().into(),
+        VerticalCaretRun => vhea()?.caret_slope_run().into(),
+        VerticalCaretOffset => vhea()?.caret_offset().into(),
+        XHeight => os2()?.sx_height()?.into(),
+        CapHeight => os2()?.s_cap_height()?.into(),
+        SubscriptEmXSize => os2()?.y_subscript_x_size().into(),
+        SubscriptEmYSize => os2()?.y_subscript_y_size().into(),
+        SubscriptEmXOffset => os2()?.y_subscript_x_offset().into(),
+        SubscriptEmYOffset => os2()?.y_subscript_y_offset().into(),
+        SuperscriptEmXSize => os2()?.y_superscript_x_size().into(),
+        SuperscriptEmYSize => os2()?.y_superscript_y_size().into(),
+        SuperscriptEmXOffset => os2()?.y_superscript_x_offset().into(),
+        SuperscriptEmYOffset => os2()?.y_superscript_y_offset().into(),
+        StrikeoutSize => os2()?.y_strikeout_size().into(),
+        StrikeoutOffset => os2()?.y_strikeout_position().into(),
+        UnderlineSize => font.post().ok()?.underline_thickness().to_i16().into(),
+        UnderlineOffset => font.post().ok()?.underline_position().to_i16().into(),
+    };
+
+    if !coords.is_empty() {
+        if let Ok(mvar) = font.mvar() {
+            value += mvar
+                .metric_delta(metric.tag(), coords)
+                .map(|delta| delta.to_f64() as f32)
+                .unwrap_or_default();
+        }
+    }
+    // _fix_ascender_descender
+    match metric {
+        HorizontalAscender | VerticalAscender => value = value.abs(),
+        HorizontalDescender | VerticalDescender => value = -value.abs(),
+        _ => {}
+    }
+
+    // Metrics along the x axis use the x scale; HarfBuzz's GET_METRIC_X.
+    let is_x = matches!(
+        metric,
+        VerticalAscender
+            | VerticalDescender
+            | VerticalLineGap
+            | HorizontalCaretRun
+            | HorizontalCaretOffset
+            | VerticalCaretRise
+            | SubscriptEmXSize
+            | SubscriptEmXOffset
+            | SuperscriptEmXSize
+            | SuperscriptEmXOffset
+    );
+    Some(if is_x {
+        scale.scale_x_f(value)
+    } else {
+        scale.scale_y_f(value)
+    })
+}
@@ -0,0 +1,52 @@
+use alloc::string::String;
+use read_fonts::tables::name::{Name, NameId};
+use read_fonts::TableProvider;
+
+use super::face::FontKind;
+
+impl crate::Shaper<'_> {
+    /// Returns the string for an entry of the `name` table.
+    ///
+    /// English records are preferred, Windows ones first; otherwise the
+    /// first record with a decodable encoding is used. Returns `None` if the
+    /// font has no `name` table or no record with the given ID.
+    ///
+    /// This is HarfBuzz's `hb_ot_name_get_utf8` with the default language.
+    pub fn name(&self, name_id: NameId) -> Option<String> {
+        match &self.font {
+            FontKind::FontRef(data) => name_string(&data.font.name().ok()?, name_id),
+            FontKind::FontInstance(instance, _) => {
+                name_string(&instance.tables().name().ok()?, name_id)
+            }
+        }
+    }
+}
+
+// hb_ot_name_get_utf8: <https://github.com/harfbuzz/harfbuzz/blob/main/src/hb-ot-name.cc>
+fn name_string(name: &Name, name_id: NameId) -> Option<String> {
+    let record = name
+        .name_record()
+        .iter()
+        .filter(|record| record.name_id() == name_id)
+        .filter_map(|record| Some((record_rank(record)?, record)))
+        .min_by_key(|(rank, _)| *rank)?
+        .1;
+    let string = record.string(name.string_data()).ok()?;
+    Some(string.chars().collect())
+}
+
+/// Lower is better; `None` for encodings that can't be decoded.
+fn record_rank(record: &read_fonts::tables::name::NameRecord) -> Option<u8> {
+    const MS_ENGLISH_US: u16 = 0x0409;
+    const MAC_ENGLISH: u16 = 0;
+    let language = record.language_id();
+    Some(match (record.platform_id(), record.encoding_id()) {
+        (3, 0 | 1 | 10) if language == MS_ENGLISH_US => 0,
+        (3, 0 | 1 | 10) if language & 0xFF == 0x09 => 1,
+        (0, _) => 2,
+        (1, 0) if language == MAC_ENGLISH => 3,
+        (3, 0 | 1 | 10) => 4,
+        (1, 0) => 5,
+        _ => return None,
+    })
+}
@@ -4,16 +4,22 @@ use super::font_funcs::{AdvanceWidthBatch, FontFuncsDispatch};
 use super::ot_layout::*;
 use super::ot_layout_gpos_table::GPOS;
 use super::ot_map::*;
-use super::ot_shape_plan::hb_ot_shape_plan_t;
+use super::ot_shape_plan::{
+    hb_ot_shape_plan_t, AatPreference, LayoutPreference, NormalizationMode,
+};
 use super::ot_shaper::*;
+#[cfg(feature = "profile")]
+use super::profile::LookupProfile;
+use super::script_shaper::{
+    builtin_shaper, BuiltinShaper, ScriptShaper, ShapePlanner, ShaperBuffer,
+};
 use super::*;
 use super::{hb_font_t, hb_tag_t};
 use crate::hb::aat;
 use crate::hb::buffer::GlyphFlags;
 use crate::unicode::{CharExt, GeneralCategory};
 use crate::BufferFlags;
-use crate::{Direction, Feature, Language, Script};
-use core::ptr;
+use crate::{AatFeature, Direction, Feature, Language, Script};
 
 pub struct hb_ot_shape_planner_t<'a> {
     pub face: &'a hb_font_t<'a>,
@@ -23,9 +29,13 @@ pub struct hb_ot_shape_planner_t<'a> {
     pub ot_map: hb_ot_map_builder_t<'a>,
     pub aat_map: AatMapBuilder,
     pub apply_morx: bool,
+    pub layout_preference: LayoutPreference,
     pub script_zero_marks: bool,
     pub script_fallback_position: bool,
+    pub shaper_kind: ShaperKind,
     pub shaper: &'static hb_ot_shaper_t,
+    pub script_shaper: Option<&'static dyn ScriptShaper>,
+    pub script_shaper_id: Option<usize>,
 }
 
 impl<'a> hb_ot_shape_planner_t<'a> {
@@ -34,30 +44,46 @@ impl<'a> hb_ot_shape_planner_t<'a> {
         direction: Direction,
         script: Option<Script>,
         language: Option<&Language>,
+        layout_preference: LayoutPreference,
     ) -> Self {
         let ot_map = hb_ot_map_builder_t::new(face, script, language);
         let aat_map = AatMapBuilder::default();
 
-        let mut shaper = match script {
+        let mut shaper_kind = match script {
             Some(script) => hb_ot_shape_complex_categorize(
                 script,
                 direction,
                 ot_map.chosen_script(TableIndex::GSUB),
             ),
-            None => &DEFAULT_SHAPER,
+            None => ShaperKind::Default,
         };
 
+        let (script_shaper_id, registered_script_shaper) = script
+            .and_then(|script| face.script_shapers?.get_with_id(script))
+            .unzip();
+        if let Some(script_shaper) = registered_script_shaper {
+            shaper_kind = script_shaper.base(shaper_kind);
+        }
+        let mut shaper = builtin_shaper(shaper_kind);
+
         let script_zero_marks = shaper.zero_width_marks != HB_OT_SHAPE_ZERO_WIDTH_MARKS_NONE;
         let script_fallback_position = shaper.fallback_position;
 
         // https://github.com/harfbuzz/harfbuzz/issues/2124
-        let apply_morx = face.aat_tables.morx.is_some()
-            && (direction.is_horizontal() || face.ot_tables.gsub.is_none());
+        let apply_morx = face.aat_tables.has_substitution()
+            && match layout_preference.aat {
+                AatPreference::Auto => direction.is_horizontal() || face.ot_tables.gsub.is_none(),
+                AatPreference::Prefer => true,
+                AatPreference::Forbid => false,
+            };
 
         // https://github.com/harfbuzz/harfbuzz/issues/1528
-        if apply_morx && !ptr::eq(ptr::from_ref(shaper), ptr::from_ref(&DEFAULT_SHAPER)) {
+        if apply_morx && shaper_kind != ShaperKind::Default {
+            shaper_kind = ShaperKind::Dumber;
             shaper = &DUMBER_SHAPER;
         }
+        // Like the built-in shapers, script shapers are skipped with morx.
+        let script_shaper = registered_script_shaper.filter(|_| !apply_morx);
 
         hb_ot_shape_planner_t {
             face,
@@ -67,9 +93,13 @@ impl<'a> hb_ot_shape_planner_t<'a> {
             ot_map,
             aat_map,
             apply_morx,
+            layout_preference,
             script_zero_marks,
             script_fallback_position,
+            shaper_kind,
             shaper,
+            script_shaper,
+            script_shaper_id,
         }
     }
 
@@ -130,7 +160,13 @@ impl<'a> hb_ot_shape_planner_t<'a> {
         self.ot_map.enable_feature(hb_tag_t::new(b"Harf"), empty, 1); // Considered required.
         self.ot_map.enable_feature(hb_tag_t::new(b"HARF"), empty, 1); // Considered discretionary.
 
-        if let Some(func) = self.shaper.collect_features {
+        if let Some(script_shaper) = self.script_shaper {
+            self.ot_map.is_simple = false;
+            script_shaper.collect_features(
+                BuiltinShaper::from_kind(self.shaper_kind),
+                &mut ShapePlanner::new(self),
+            );
+        } else if let Some(func) = self.shaper.collect_features {
             self.ot_map.is_simple = false;
             func(self);
         }
@@ -168,7 +204,12 @@ impl<'a> hb_ot_shape_planner_t<'a> {
             self.ot_map.add_feature(feature.tag, flags, feature.value);
         }
 
-        if let Some(func) = self.shaper.override_features {
+        if let Some(script_shaper) = self.script_shaper {
+            script_shaper.override_features(
+                BuiltinShaper::from_kind(self.shaper_kind),
+                &mut ShapePlanner::new(self),
+            );
+        } else if let Some(func) = self.shaper.override_features {
             func(self);
         }
     }
@@ -214,13 +255,16 @@ impl<'a> hb_ot_shape_planner_t<'a> {
         let mut apply_kern = false;
 
         // Decide who does positioning. GPOS, kerx, kern, or fallback.
-        let has_kerx = self.face.aat_tables.kerx.is_some();
+        let has_kerx = self.layout_preference.aat != AatPreference::Forbid
+            && self.face.aat_tables.kerx.is_some();
         let has_gsub = !apply_morx && self.face.ot_tables.gsub.is_some();
         let has_gpos = !disable_gpos && self.face.ot_tables.gpos.is_some();
 
-        // Prefer GPOS over kerx if GSUB is present;
+        // Prefer GPOS over kerx if GSUB is present, unless AAT is preferred;
         // https://github.com/harfbuzz/harfbuzz/issues/3008
-        if has_kerx && !(has_gsub && has_gpos) {
+        if has_kerx
+            && (self.layout_preference.aat == AatPreference::Prefer || !(has_gsub && has_gpos))
+        {
             apply_kerx = true;
         } else if has_gpos {
             apply_gpos = true;
@@ -229,7 +273,7 @@ impl<'a> hb_ot_shape_planner_t<'a> {
         if !apply_kerx && (!has_gpos_kern || !apply_gpos) {
             if has_kerx {
                 apply_kerx = true;
-            } else if hb_ot_layout_has_kerning(self.face) {
+            } else if !self.layout_preference.disable_kern && hb_ot_layout_has_kerning(self.face) {
                 apply_kern = self.script_fallback_position;
             }
         }
@@ -257,13 +301,18 @@ impl<'a> hb_ot_shape_planner_t<'a> {
 
         // According to Ned, trak is applied by default for "modern fonts", as detected by presence of STAT table.
         // https://github.com/googlefonts/fontations/issues/1492
-        let apply_trak = self.face.apply_trak;
+        let apply_trak =
+            self.face.apply_trak && self.layout_preference.aat != AatPreference::Forbid;
 
         let mut plan = hb_ot_shape_plan_t {
             direction: self.direction,
             script: self.script,
             language: self.language,
+            shaper_kind: self.shaper_kind,
             shaper: self.shaper,
+            script_shaper: self.script_shaper,
+            script_shaper_id: self.script_shaper_id,
+            layout_preference: self.layout_preference,
             ot_map,
             aat_map,
             data: None,
@@ -303,10 +352,16 @@ pub(crate) struct OtShapeContext<'a, 'u> {
     pub face: &'a hb_font_t<'a>,
     pub buffer: &'a mut hb_buffer_t,
     pub features: &'a [Feature],
+    pub aat_features: &'a [AatFeature],
     // Transient stuff
     pub target_direction: Direction,
     pub point_size: Option<f32>,
+    pub track: Option<f32>,
+    pub letter_spacing: Option<f32>,
+    pub normalization_mode: NormalizationMode,
     pub font_funcs: &'a mut FontFuncsDispatch<'a, 'u>,
+    #[cfg(feature = "profile")]
+    pub profile: Option<&'a mut LookupProfile>,
 }
 
 impl OtShapeContext<'_, '_> {
@@ -330,7 +385,13 @@ impl OtShapeContext<'_, '_> {
 
         ensure_native_direction(self.buffer);
 
-        if let Some(func) = self.plan.shaper.preprocess_text {
+        if let Some(script_shaper) = self.plan.script_shaper {
+            script_shaper.preprocess_text(
+                BuiltinShaper::from_kind(self.plan.shaper_kind),
+                self.plan,
+                &mut ShaperBuffer::new(self.buffer, self.font_funcs),
+            );
+        } else if let Some(func) = self.plan.shaper.preprocess_text {
             func(self.plan, self.font_funcs, self.buffer);
         }
 
@@ -367,7 +428,13 @@ impl OtShapeContext<'_, '_> {
         deal_with_variation_selectors(self.buffer);
         hide_default_ignorables(self.buffer, self.font_funcs);
 
-        if let Some(func) = self.plan.shaper.postprocess_glyphs {
+        if let Some(script_shaper) = self.plan.script_shaper {
+            script_shaper.postprocess_glyphs(
+                BuiltinShaper::from_kind(self.plan.shaper_kind),
+                self.plan,
+                &mut ShaperBuffer::new(self.buffer, self.font_funcs),
+            );
+        } else if let Some(func) = self.plan.shaper.postprocess_glyphs {
             func(self.plan, self.font_funcs, self.buffer);
         }
     }
@@ -384,6 +451,7 @@ impl OtShapeContext<'_, '_> {
             self.buffer,
             self.face,
             self.font_funcs,
+            self.normalization_mode,
         );
 
         self.setup_masks();
@@ -412,7 +480,15 @@ impl OtShapeContext<'_, '_> {
         }
 
         if self.plan.apply_morx {
-            aat::layout::substitute(self.plan, self.face, self.buffer, self.features);
+            aat::layout::substitute(
+                self.plan,
+                self.face,
+                self.buffer,
+                self.features,
+                self.aat_features,
+                #[cfg(feature = "profile")]
+                self.profile.as_deref_mut(),
+            );
             // The digest is only read by the OT lookup-apply loop; without
             // GPOS ahead, nothing consumes it.
             if self.plan.apply_gpos {
@@ -420,7 +496,14 @@ impl OtShapeContext<'_, '_> {
             }
         } else {
             self.buffer.update_digest();
-            ot_layout_gsub_table::substitute(self.plan, self.face, self.font_funcs, self.buffer);
+            ot_layout_gsub_table::substitute(
+                self.plan,
+                self.face,
+                self.font_funcs,
+                self.buffer,
+                #[cfg(feature = "profile")]
+                self.profile.as_deref_mut(),
+            );
         }
     }
 
@@ -512,6 +595,20 @@ impl OtShapeContext<'_, '_> {
                 self.font_funcs,
             );
         }
+
+        // Move glyphs that are raised or lowered, such as marks, along
+        // the synthetic slant.
+        let synthetic = *self.font_funcs.synthetic();
+        if synthetic.has_slant() && self.buffer.direction.is_horizontal() {
+            let len = self.buffer.len;
+            for pos in &mut self.buffer.pos[..len] {
+                if pos.y_offset != 0 {
+                    pos.x_offset = pos
+                        .x_offset
+                        .saturating_add(synthetic.slant_offset(pos.y_offset));
+                }
+            }
+        }
     }
 
     // hb_ot_shape_plan_t::position <https://github.com/harfbuzz/harfbuzz/blob/22ea52f42fa4fc168be91ef4e56aee3affda6e28/src/hb-ot-shape.cc#L271>
@@ -520,7 +617,14 @@ impl OtShapeContext<'_, '_> {
         let face = self.face;
         let buffer = &mut *self.buffer;
         if plan.apply_gpos {
-            ot_layout_gpos_table::position(plan, face, self.font_funcs, buffer);
+            ot_layout_gpos_table::position(
+                plan,
+                face,
+                self.font_funcs,
+                buffer,
+                #[cfg(feature = "profile")]
+                self.profile.as_deref_mut(),
+            );
         } else if plan.apply_kerx {
             aat::layout::position(plan, face, *self.font_funcs.scale(), buffer);
         }
@@ -536,9 +640,14 @@ impl OtShapeContext<'_, '_> {
                 face,
                 *self.font_funcs.scale(),
                 self.point_size,
+                self.track.unwrap_or(0.0),
                 buffer,
             );
         }
+
+        if let Some(letter_spacing) = self.letter_spacing {
+            apply_letter_spacing(*self.font_funcs.scale(), letter_spacing, buffer);
+        }
     }
 
     // hb_ot_shape_initialize_masks: <https://github.com/harfbuzz/harfbuzz/blob/22ea52f42fa4fc168be91ef4e56aee3affda6e28/src/hb-ot-shape.cc#L747>
@@ -551,7 +660,13 @@ impl OtShapeContext<'_, '_> {
     fn setup_masks(&mut self) {
         self.setup_masks_fraction();
 
-        if let Some(func) = self.plan.shaper.setup_masks {
+        if let Some(script_shaper) = self.plan.script_shaper {
+            script_shaper.setup_masks(
+                BuiltinShaper::from_kind(self.plan.shaper_kind),
+                self.plan,
+                &mut ShaperBuffer::new(self.buffer, self.font_funcs),
+            );
+        } else if let Some(func) = self.plan.shaper.setup_masks {
             func(self.plan, self.font_funcs, self.buffer);
         }
 
@@ -639,6 +754,7 @@ impl OtShapeContext<'_, '_> {
     // hb_set_unicode_props: <https://github.com/harfbuzz/harfbuzz/blob/22ea52f42fa4fc168be91ef4e56aee3affda6e28/src/hb-ot-shape.cc#L471>
     fn set_unicode_props(&mut self) {
         let buffer = &mut *self.buffer;
+        let unicode = self.font_funcs.unicode();
         // Implement enough of Unicode Graphemes here that shaping
         // in reverse-direction wouldn't break graphemes.  Namely,
         // we mark all marks and ZWJ and ZWJ,Extended_Pictographic
@@ -652,7 +768,7 @@ impl OtShapeContext<'_, '_> {
         let mut i = 0;
         while i < len {
             let info = &mut buffer.info[i];
-            info.init_unicode_props(&mut buffer.scratch_flags);
+            info.init_unicode_props(unicode, &mut buffer.scratch_flags);
 
             if info.glyph_id < 0x80 {
                 i += 1;
@@ -695,8 +811,8 @@ impl OtShapeContext<'_, '_> {
             } else if info.is_zwj() {
                 info.set_continuation(&mut buffer.scratch_flags);
                 if let Some(next) = buffer.info[..len].get_mut(i + 1) {
-                    if next.as_codepoint().is_emoji_extended_pictographic() {
-                        next.init_unicode_props(&mut buffer.scratch_flags);
+                    if unicode.is_emoji_extended_pictographic(next.as_codepoint()) {
+                        next.init_unicode_props(unicode, &mut buffer.scratch_flags);
                         next.set_continuation(&mut buffer.scratch_flags);
                         i += 1;
                     }
@@ -749,7 +865,7 @@ impl OtShapeContext<'_, '_> {
                 ..GlyphInfo::default()
             };
 
-            info.init_unicode_props(&mut buffer.scratch_flags);
+            info.init_unicode_props(self.font_funcs.unicode(), &mut buffer.scratch_flags);
             buffer.clear_output();
             buffer.output_info(info);
             buffer.sync();
@@ -762,9 +878,10 @@ impl OtShapeContext<'_, '_> {
 
         if self.target_direction.is_backward() {
             let rtlm_mask = self.plan.rtlm_mask;
+            let unicode = self.font_funcs.unicode();
 
             for info in &mut self.buffer.info[..len] {
-                if let Some(c) = info.as_codepoint().mirroring() {
+                if let Some(c) = unicode.mirroring(info.as_codepoint()) {
                     if self.font_funcs.nominal_glyph(c).is_some() {
                         info.glyph_id = c;
                         continue;
@@ -786,7 +903,7 @@ impl OtShapeContext<'_, '_> {
     }
 }
 
-fn form_clusters(buffer: &mut hb_buffer_t) {
+pub(crate) fn form_clusters(buffer: &mut hb_buffer_t) {
     if buffer.scratch_flags & HB_BUFFER_SCRATCH_FLAG_HAS_CONTINUATIONS != 0 {
         foreach_grapheme!(buffer, start, end, {
             buffer.merge_grapheme_clusters(start, end);
@@ -951,6 +1068,34 @@ fn zero_mark_widths_by_gdef(buffer: &mut hb_buffer_t, adjust_offsets: bool) {
     }
 }
 
+fn apply_letter_spacing(scale: face::Scale, letter_spacing: f32, buffer: &mut hb_buffer_t) {
+    if !buffer.have_positions {
+        buffer.clear_positions();
+    }
+
+    let horizontal = buffer.direction.is_horizontal();
+    let advance_to_add = if horizontal {
+        scale.scale_x_f(letter_spacing)
+    } else {
+        // Vertical advances grow downwards.
+        -scale.scale_y_f(letter_spacing)
+    };
+
+    foreach_grapheme!(buffer, start, end, {
+        // Like tracking, add to the first glyph of the grapheme: the buffer
+        // is still in logical order, and attached mark offsets are resolved
+        // afterwards, so marks stay on their base.
+        let pos = &mut buffer.pos[start];
+        if horizontal {
+            pos.x_advance = pos.x_advance.saturating_add(advance_to_add);
+        } else {
+            pos.y_advance = pos.y_advance.saturating_add(advance_to_add);
+        }
+        // Breaking inside a grapheme would change where the spacing goes.
+        buffer.unsafe_to_break(Some(start), Some(end));
+    });
+}
+
 fn hide_default_ignorables(buffer: &mut hb_buffer_t, font_funcs: &mut FontFuncsDispatch<'_, '_>) {
     if buffer.scratch_flags & HB_BUFFER_SCRATCH_FLAG_HAS_DEFAULT_IGNORABLES != 0
         && !buffer
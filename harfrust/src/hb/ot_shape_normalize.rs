@@ -1,8 +1,10 @@
 use super::buffer::*;
 use super::font_funcs::{FontFuncsDispatch, NominalGlyphBatch};
 use super::hb_font_t;
-use super::ot_shape_plan::hb_ot_shape_plan_t;
+use super::ot_shape_plan::{hb_ot_shape_plan_t, NormalizationMode};
 use super::ot_shaper::{ComposeFn, DecomposeFn, MAX_COMBINING_MARKS};
+use super::script_shaper::{BuiltinShaper, Normalizer, ShaperBuffer};
+use super::unicode_funcs::UnicodeFuncsDispatch;
 use crate::unicode::{hb_unicode_funcs_t, CharExt, Codepoint};
 use read_fonts::types::GlyphId;
 
@@ -48,6 +50,9 @@ pub const HB_OT_SHAPE_NORMALIZATION_MODE_DECOMPOSED: i32 = 1;
 pub const HB_OT_SHAPE_NORMALIZATION_MODE_COMPOSED_DIACRITICS: i32 = 2; /* Never composes base-to-base */
 pub const HB_OT_SHAPE_NORMALIZATION_MODE_COMPOSED_DIACRITICS_NO_SHORT_CIRCUIT: i32 = 3; /* Always fully decomposes and then recompose back */
 pub const HB_OT_SHAPE_NORMALIZATION_MODE_AUTO: i32 = 4; /* See hb-ot-shape-normalize.cc for logic. */
+/// Not in HarfBuzz: characters are mapped to glyphs without being
+/// decomposed, reordered or composed.
+pub const NORMALIZATION_MODE_PASS_THROUGH: i32 = 5;
 #[allow(dead_code)]
 pub const HB_OT_SHAPE_NORMALIZATION_MODE_DEFAULT: i32 = HB_OT_SHAPE_NORMALIZATION_MODE_AUTO;
 
@@ -90,28 +95,59 @@ pub const HB_OT_SHAPE_NORMALIZATION_MODE_DEFAULT: i32 = HB_OT_SHAPE_NORMALIZATIO
 //     offload some of their requirements to the normalizer.  For example, the
 //     Indic shaper may want to disallow recomposing of two matras.
 
-fn decompose_unicode(
-    _: &hb_ot_shape_normalize_context_t,
+pub(crate) fn decompose_unicode(
+    ctx: &hb_ot_shape_normalize_context_t,
     ab: Codepoint,
 ) -> Option<(Codepoint, Codepoint)> {
-    crate::unicode::decompose(ab)
+    ctx.font_funcs.unicode().decompose(ab)
 }
 
-fn compose_unicode(
+fn decompose_nothing(
     _: &hb_ot_shape_normalize_context_t,
+    _: Codepoint,
+) -> Option<(Codepoint, Codepoint)> {
+    None
+}
+
+pub(crate) fn compose_unicode(
+    ctx: &hb_ot_shape_normalize_context_t,
     a: Codepoint,
     b: Codepoint,
 ) -> Option<Codepoint> {
-    crate::unicode::compose(a, b)
+    ctx.font_funcs.unicode().compose(a, b)
+}
+
+fn decompose_script_shaper(
+    ctx: &hb_ot_shape_normalize_context_t,
+    ab: Codepoint,
+) -> Option<(Codepoint, Codepoint)> {
+    ctx.plan.script_shaper?.decompose(
+        BuiltinShaper::from_kind(ctx.plan.shaper_kind),
+        &Normalizer::new(ctx),
+        ab,
+    )
 }
 
-fn output_char(buffer: &mut hb_buffer_t, unichar: u32, glyph: u32) {
+fn compose_script_shaper(
+    ctx: &hb_ot_shape_normalize_context_t,
+    a: Codepoint,
+    b: Codepoint,
+) -> Option<Codepoint> {
+    ctx.plan.script_shaper?.compose(
+        BuiltinShaper::from_kind(ctx.plan.shaper_kind),
+        &Normalizer::new(ctx),
+        a,
+        b,
+    )
+}
+
+fn output_char(buffer: &mut hb_buffer_t, unicode: UnicodeFuncsDispatch, unichar: u32, glyph: u32) {
     // This is very confusing indeed.
     buffer.cur_mut(0).set_normalizer_glyph_index(glyph);
     buffer.output_glyph(unichar);
     // TODO: should be _hb_glyph_info_set_unicode_props (&buffer->prev(), buffer);
     let mut flags = buffer.scratch_flags;
-    buffer.prev_mut().init_unicode_props(&mut flags);
+    buffer.prev_mut().init_unicode_props(unicode, &mut flags);
     buffer.scratch_flags = flags;
 }
 
@@ -143,9 +179,9 @@ fn decompose(ctx: &mut hb_ot_shape_normalize_context_t, shortest: bool, ab: Code
     if let Some(a_glyph) = a_glyph {
         if shortest {
             // Output a and b
-            output_char(ctx.buffer, a, u32::from(a_glyph));
+            output_char(ctx.buffer, ctx.font_funcs.unicode(), a, u32::from(a_glyph));
             if let Some(b_glyph) = b_glyph {
-                output_char(ctx.buffer, b, u32::from(b_glyph));
+                output_char(ctx.buffer, ctx.font_funcs.unicode(), b, u32::from(b_glyph));
                 return 2;
             }
             return 1;
@@ -155,16 +191,16 @@ fn decompose(ctx: &mut hb_ot_shape_normalize_context_t, shortest: bool, ab: Code
     let ret = decompose(ctx, shortest, a);
     if ret != 0 {
         if let Some(b_glyph) = b_glyph {
-            output_char(ctx.buffer, b, u32::from(b_glyph));
+            output_char(ctx.buffer, ctx.font_funcs.unicode(), b, u32::from(b_glyph));
             return ret + 1;
         }
         return ret;
     }
 
     if let Some(a_glyph) = a_glyph {
-        output_char(ctx.buffer, a, u32::from(a_glyph));
+        output_char(ctx.buffer, ctx.font_funcs.unicode(), a, u32::from(a_glyph));
         if let Some(b_glyph) = b_glyph {
-            output_char(ctx.buffer, b, u32::from(b_glyph));
+            output_char(ctx.buffer, ctx.font_funcs.unicode(), b, u32::from(b_glyph));
             return 2;
         }
         return 1;
@@ -303,6 +339,7 @@ pub fn _hb_ot_shape_normalize<'a, 'x>(
     buffer: &'x mut hb_buffer_t,
     _face: &'a hb_font_t<'a>,
     font_funcs: &'x mut FontFuncsDispatch<'a, '_>,
+    mode: NormalizationMode,
 ) {
     if buffer.is_empty() {
         return;
@@ -310,7 +347,7 @@ pub fn _hb_ot_shape_normalize<'a, 'x>(
 
     buffer.assert_unicode_vars();
 
-    let mut mode = plan.shaper.normalization_preference;
+    let mut mode = mode.to_hb();
     if mode == HB_OT_SHAPE_NORMALIZATION_MODE_AUTO {
         if plan.has_gpos_mark {
             // https://github.com/harfbuzz/harfbuzz/issues/653#issuecomment-423905920
@@ -321,15 +358,26 @@ pub fn _hb_ot_shape_normalize<'a, 'x>(
         }
     }
 
+    let pass_through = mode == NORMALIZATION_MODE_PASS_THROUGH;
     let mut ctx = hb_ot_shape_normalize_context_t {
         plan,
         buffer,
         font_funcs,
-        decompose: plan.shaper.decompose.unwrap_or(decompose_unicode),
-        compose: plan.shaper.compose.unwrap_or(compose_unicode),
+        decompose: if pass_through {
+            decompose_nothing
+        } else if plan.script_shaper.is_some() {
+            decompose_script_shaper
+        } else {
+            plan.shaper.decompose.unwrap_or(decompose_unicode)
+        },
+        compose: if plan.script_shaper.is_some() {
+            compose_script_shaper
+        } else {
+            plan.shaper.compose.unwrap_or(compose_unicode)
+        },
     };
 
-    let always_short_circuit = mode == HB_OT_SHAPE_NORMALIZATION_MODE_NONE;
+    let always_short_circuit = mode == HB_OT_SHAPE_NORMALIZATION_MODE_NONE || pass_through;
     let might_short_circuit = always_short_circuit
         || (mode != HB_OT_SHAPE_NORMALIZATION_MODE_DECOMPOSED
             && mode != HB_OT_SHAPE_NORMALIZATION_MODE_COMPOSED_DIACRITICS_NO_SHORT_CIRCUIT);
@@ -396,7 +444,7 @@ pub fn _hb_ot_shape_normalize<'a, 'x>(
     }
 
     // Second round, reorder (inplace)
-    if !all_simple {
+    if !all_simple && !pass_through {
         let count = ctx.buffer.len;
         let mut i = 0;
         while i < count {
@@ -414,7 +462,15 @@ pub fn _hb_ot_shape_normalize<'a, 'x>(
             if end - i <= MAX_COMBINING_MARKS {
                 ctx.buffer.sort(i, end, compare_combining_class);
 
-                if let Some(reorder_marks) = ctx.plan.shaper.reorder_marks {
+                if let Some(script_shaper) = ctx.plan.script_shaper {
+                    script_shaper.reorder_marks(
+                        BuiltinShaper::from_kind(ctx.plan.shaper_kind),
+                        ctx.plan,
+                        &mut ShaperBuffer::new(ctx.buffer, ctx.font_funcs),
+                        i,
+                        end,
+                    );
+                } else if let Some(reorder_marks) = ctx.plan.shaper.reorder_marks {
                     reorder_marks(ctx.plan, ctx.buffer, i, end);
                 }
             }
@@ -480,10 +536,11 @@ pub fn _hb_ot_shape_normalize<'a, 'x>(
 
                         // Modify starter and carry on.
                         let mut flags = ctx.buffer.scratch_flags;
+                        let unicode = ctx.font_funcs.unicode();
                         let info = &mut ctx.buffer.out_info_mut()[starter];
                         info.glyph_id = composed;
                         info.set_normalizer_glyph_index(u32::from(glyph_id));
-                        info.init_unicode_props(&mut flags);
+                        info.init_unicode_props(unicode, &mut flags);
                         ctx.buffer.scratch_flags = flags;
 
                         continue;
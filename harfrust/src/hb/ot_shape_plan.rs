@@ -7,17 +7,26 @@ use crate::hb::common::HB_FEATURE_GLOBAL_START;
 use crate::ShaperInstance;
 
 use super::aat::map::*;
+use super::ot_layout::TableIndex;
 use super::ot_map::*;
 use super::ot_shape::*;
+use super::ot_shape_normalize::*;
+pub use super::ot_shaper::ShaperKind;
 use super::ot_shaper::*;
-use super::{hb_font_t, hb_mask_t, Direction, Feature, Language, Script};
+use super::script_shaper::{BuiltinShaper, ScriptShaper, ShaperRegistry};
+use super::{hb_font_t, hb_mask_t, hb_tag_t, Direction, Feature, Language, Script};
 
 /// A reusable plan for shaping a text buffer.
 pub struct hb_ot_shape_plan_t {
     pub(crate) direction: Direction,
     pub(crate) script: Option<Script>,
     pub(crate) language: Option<Language>,
+    pub(crate) shaper_kind: ShaperKind,
     pub(crate) shaper: &'static hb_ot_shaper_t,
+    pub(crate) script_shaper: Option<&'static dyn ScriptShaper>,
+    /// The id of the shaper registered for the script, even if it isn't used.
+    pub(crate) script_shaper_id: Option<usize>,
+    pub(crate) layout_preference: LayoutPreference,
     pub(crate) ot_map: hb_ot_map_t,
     pub(crate) aat_map: AatMap,
     pub(crate) data: Option<Box<dyn Any + Send + Sync>>,
@@ -81,6 +90,27 @@ impl hb_ot_shape_plan_t {
         script: Option<Script>,
         language: Option<&Language>,
         user_features: &[Feature],
+    ) -> Self {
+        Self::with_layout_preference(
+            font,
+            direction,
+            script,
+            language,
+            user_features,
+            LayoutPreference::default(),
+        )
+    }
+
+    /// Returns a plan that can be used for shaping any buffer with the
+    /// provided properties, choosing layout tables according to
+    /// `layout_preference`.
+    pub fn with_layout_preference(
+        font: &impl AnyFont,
+        direction: Direction,
+        script: Option<Script>,
+        language: Option<&Language>,
+        user_features: &[Feature],
+        layout_preference: LayoutPreference,
     ) -> Self {
         assert_ne!(
             direction,
@@ -89,7 +119,8 @@ impl hb_ot_shape_plan_t {
         );
         font.with_font(|font| {
             let font = font.expect("font should be available for shaping");
-            let mut planner = hb_ot_shape_planner_t::new(font, direction, script, language);
+            let mut planner =
+                hb_ot_shape_planner_t::new(font, direction, script, language, layout_preference);
             planner.collect_features(user_features);
             planner.compile(user_features)
         })
@@ -113,6 +144,293 @@ impl hb_ot_shape_plan_t {
     pub fn language(&self) -> Option<&Language> {
         self.language.as_ref()
     }
+
+    /// The script-specific shaper selected for this plan.
+    ///
+    /// If a [`ScriptShaper`] is used, this is its base shaper.
+    pub fn shaper(&self) -> ShaperKind {
+        self.shaper_kind
+    }
+
+    /// Whether a user provided [`ScriptShaper`] is used.
+    pub fn uses_script_shaper(&self) -> bool {
+        self.script_shaper.is_some()
+    }
+
+    /// Returns the mask that enables `feature_tag` with a value of 1, or 0
+    /// if the plan doesn't apply the feature.
+    ///
+    /// Masks of features added with
+    /// [`ShapePlanner::add_feature`](crate::ShapePlanner::add_feature) are
+    /// meant to be set in [`ScriptShaper::setup_masks`].
+    pub fn feature_mask(&self, feature_tag: hb_tag_t) -> u32 {
+        self.ot_map.get_1_mask(feature_tag)
+    }
+
+    /// Returns the normalization mode used when shaping with this plan and
+    /// `requested` set with
+    /// [`ShapeOptions::normalization_mode`](crate::ShapeOptions::normalization_mode).
+    ///
+    /// [`NormalizationMode::Auto`] resolves to the shaper's preference.
+    /// Other modes are adjusted where the shaper can't work with them:
+    /// shapers that prefer [`NormalizationMode::None`], like the Hangul
+    /// shaper which composes and decomposes syllables itself, use it for
+    /// every mode but [`NormalizationMode::PassThrough`], and shapers that
+    /// need text to be fully decomposed, like the Indic shaper, never
+    /// short-circuit decomposition.
+    pub fn normalization_mode(&self, requested: NormalizationMode) -> NormalizationMode {
+        let preference = match self.script_shaper {
+            Some(script_shaper) => script_shaper
+                .normalization_preference(BuiltinShaper::from_kind(self.shaper_kind))
+                .to_hb(),
+            None => self.shaper.normalization_preference,
+        };
+        match requested {
+            NormalizationMode::Auto => NormalizationMode::from_hb(preference),
+            NormalizationMode::PassThrough if preference == HB_OT_SHAPE_NORMALIZATION_MODE_NONE => {
+                NormalizationMode::PassThrough
+            }
+            _ if preference == HB_OT_SHAPE_NORMALIZATION_MODE_NONE => NormalizationMode::None,
+            NormalizationMode::PassThrough
+            | NormalizationMode::None
+            | NormalizationMode::ComposedDiacritics
+                if preference
+                    == HB_OT_SHAPE_NORMALIZATION_MODE_COMPOSED_DIACRITICS_NO_SHORT_CIRCUIT =>
+            {
+                NormalizationMode::ComposedDiacriticsNoShortCircuit
+            }
+            requested => requested,
+        }
+    }
+
+    /// The layout table preference this plan was created with.
+    pub fn layout_preference(&self) -> LayoutPreference {
+        self.layout_preference
+    }
+
+    /// The script tag selected from the `GSUB` table, if the font has one.
+    pub fn gsub_script_tag(&self) -> Option<hb_tag_t> {
+        self.ot_map.chosen_script(TableIndex::GSUB)
+    }
+
+    /// The language system tag selected from the `GSUB` table, or `None`
+    /// if the script's default language system is used.
+    pub fn gsub_language_tag(&self) -> Option<hb_tag_t> {
+        self.ot_map.chosen_language(TableIndex::GSUB)
+    }
+
+    /// The script tag selected from the `GPOS` table, if the font has one.
+    pub fn gpos_script_tag(&self) -> Option<hb_tag_t> {
+        self.ot_map.chosen_script(TableIndex::GPOS)
+    }
+
+    /// The language system tag selected from the `GPOS` table, or `None`
+    /// if the script's default language system is used.
+    pub fn gpos_language_tag(&self) -> Option<hb_tag_t> {
+        self.ot_map.chosen_language(TableIndex::GPOS)
+    }
+
+    /// The `GSUB` lookups of this plan, in the order they are applied.
+    ///
+    /// This is HarfBuzz's `hb_ot_shape_plan_collect_lookups`, with the
+    /// details of how each lookup is applied.
+    pub fn gsub_lookups(&self) -> impl Iterator<Item = PlanLookup> + '_ {
+        self.plan_lookups(TableIndex::GSUB)
+    }
+
+    /// The `GPOS` lookups of this plan, in the order they are applied.
+    ///
+    /// This is HarfBuzz's `hb_ot_shape_plan_collect_lookups`, with the
+    /// details of how each lookup is applied.
+    pub fn gpos_lookups(&self) -> impl Iterator<Item = PlanLookup> + '_ {
+        self.plan_lookups(TableIndex::GPOS)
+    }
+
+    fn plan_lookups(&self, table_index: TableIndex) -> impl Iterator<Item = PlanLookup> + '_ {
+        let map = &self.ot_map;
+        (0..map.stages(table_index).len()).flat_map(move |stage| {
+            let feature_tags = map.stage_lookup_feature_tags(table_index, stage);
+            map.stage_lookups(table_index, stage)
+                .iter()
+                .zip(feature_tags)
+                .map(move |(lookup, &feature_tag)| PlanLookup {
+                    stage,
+                    feature_tag,
+                    lookup_index: lookup.index,
+                    auto_zwj: lookup.auto_zwj,
+                    auto_zwnj: lookup.auto_zwnj,
+                    per_syllable: lookup.per_syllable,
+                })
+        })
+    }
+
+    /// Whether the AAT `morx` table is used for substitution instead of
+    /// `GSUB`.
+    pub fn applies_morx(&self) -> bool {
+        self.apply_morx
+    }
+
+    /// Whether `GPOS` is used for positioning.
+    pub fn applies_gpos(&self) -> bool {
+        self.apply_gpos
+    }
+
+    /// Whether the AAT `kerx` table is used for kerning.
+    pub fn applies_kerx(&self) -> bool {
+        self.apply_kerx
+    }
+
+    /// Whether the `kern` table is used for kerning.
+    pub fn applies_kern(&self) -> bool {
+        self.apply_kern
+    }
+
+    /// Whether the AAT `trak` table is used for tracking.
+    pub fn applies_trak(&self) -> bool {
+        self.apply_trak
+    }
+}
+
+/// How a shape plan chooses between AAT and OpenType layout tables.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AatPreference {
+    /// Use the same rules as HarfBuzz: `morx` is used instead of `GSUB` for
+    /// horizontal text, and `kerx` instead of `GPOS` unless the font also
+    /// has `GSUB`.
+    #[default]
+    Auto,
+    /// Use `morx` and `kerx` whenever the font has them.
+    Prefer,
+    /// Never use the AAT `morx`, `kerx` and `trak` tables.
+    Forbid,
+}
+
+/// How characters are composed and decomposed before shaping.
+///
+/// The mode is set with
+/// [`ShapeOptions::normalization_mode`](crate::ShapeOptions::normalization_mode)
+/// and validated against the shaper; see
+/// [`ShapePlan::normalization_mode`](crate::ShapePlan::normalization_mode).
+///
+/// In all modes but [`NormalizationMode::PassThrough`] combining marks are
+/// reordered by their combining class.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NormalizationMode {
+    /// Use the shaper's preference.
+    #[default]
+    Auto,
+    /// Never compose characters, and only decompose characters the font
+    /// has no glyph for.
+    None,
+    /// Fully decompose characters, whether the font supports them or not.
+    Decomposed,
+    /// Decompose characters the font has no glyph for or that have marks
+    /// the font can't position, then recompose marks with their base where
+    /// the font has a glyph for the result. Bases are never composed with
+    /// each other.
+    ComposedDiacritics,
+    /// Like [`NormalizationMode::ComposedDiacritics`], but always fully
+    /// decompose before recomposing.
+    ComposedDiacriticsNoShortCircuit,
+    /// Map each character to its glyph as is: characters are never
+    /// composed or decomposed, and marks are not reordered. This mode is
+    /// not in HarfBuzz.
+    ///
+    /// Characters the font has no glyph for become `.notdef`, except that
+    /// spaces still fall back to the space glyph. Variation sequences are
+    /// still mapped to their glyphs.
+    PassThrough,
+}
+
+impl NormalizationMode {
+    pub(crate) fn from_hb(mode: hb_ot_shape_normalization_mode_t) -> Self {
+        match mode {
+            NORMALIZATION_MODE_PASS_THROUGH => Self::PassThrough,
+            HB_OT_SHAPE_NORMALIZATION_MODE_NONE => Self::None,
+            HB_OT_SHAPE_NORMALIZATION_MODE_DECOMPOSED => Self::Decomposed,
+            HB_OT_SHAPE_NORMALIZATION_MODE_COMPOSED_DIACRITICS_NO_SHORT_CIRCUIT => {
+                Self::ComposedDiacriticsNoShortCircuit
+            }
+            // HB_OT_SHAPE_NORMALIZATION_MODE_AUTO currently always means
+            // composed diacritics.
+            _ => Self::ComposedDiacritics,
+        }
+    }
+
+    pub(crate) fn to_hb(self) -> hb_ot_shape_normalization_mode_t {
+        match self {
+            Self::Auto => HB_OT_SHAPE_NORMALIZATION_MODE_AUTO,
+            Self::PassThrough => NORMALIZATION_MODE_PASS_THROUGH,
+            Self::None => HB_OT_SHAPE_NORMALIZATION_MODE_NONE,
+            Self::Decomposed => HB_OT_SHAPE_NORMALIZATION_MODE_DECOMPOSED,
+            Self::ComposedDiacritics => HB_OT_SHAPE_NORMALIZATION_MODE_COMPOSED_DIACRITICS,
+            Self::ComposedDiacriticsNoShortCircuit => {
+                HB_OT_SHAPE_NORMALIZATION_MODE_COMPOSED_DIACRITICS_NO_SHORT_CIRCUIT
+            }
+        }
+    }
+}
+
+/// Controls which layout tables a [`ShapePlan`](crate::ShapePlan) uses
+/// when a font has more than one way to do substitution or positioning.
+///
+/// Which tables were chosen can be read back with
+/// [`ShapePlan::applies_morx`](crate::ShapePlan::applies_morx) and the
+/// related methods.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct LayoutPreference {
+    /// The preference for AAT tables over OpenType ones.
+    pub aat: AatPreference,
+    /// Whether to ignore the legacy `kern` table.
+    pub disable_kern: bool,
+}
+
+impl LayoutPreference {
+    /// Creates the default preference, which follows HarfBuzz.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the preference for AAT tables over OpenType ones.
+    ///
+    /// The default is [`AatPreference::Auto`].
+    pub fn aat(mut self, aat: AatPreference) -> Self {
+        self.aat = aat;
+        self
+    }
+
+    /// Sets whether to ignore the legacy `kern` table.
+    ///
+    /// The default is `false`.
+    pub fn disable_kern(mut self, disable_kern: bool) -> Self {
+        self.disable_kern = disable_kern;
+        self
+    }
+}
+
+/// A lookup applied by a [`ShapePlan`](crate::ShapePlan).
+///
+/// Returned by [`ShapePlan::gsub_lookups`](crate::ShapePlan::gsub_lookups)
+/// and [`ShapePlan::gpos_lookups`](crate::ShapePlan::gpos_lookups).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlanLookup {
+    /// The stage the lookup is applied in. Stages are separated by
+    /// shaper-specific pauses.
+    pub stage: usize,
+    /// The feature that added the lookup. If several features share the
+    /// lookup in the same stage, it is applied once and this is one of them.
+    pub feature_tag: hb_tag_t,
+    /// The index of the lookup in the table's lookup list.
+    pub lookup_index: u16,
+    /// Whether ZWJ is skipped when matching.
+    pub auto_zwj: bool,
+    /// Whether ZWNJ is skipped when matching.
+    pub auto_zwnj: bool,
+    /// Whether the lookup is applied one syllable at a time.
+    pub per_syllable: bool,
 }
 
 /// A key used for selecting a shape plan.
@@ -122,6 +440,8 @@ pub struct ShapePlanKey<'a> {
     language: Option<&'a Language>,
     feature_variations: [Option<u32>; 2],
     features: &'a [Feature],
+    layout_preference: LayoutPreference,
+    script_shapers: Option<&'a ShaperRegistry>,
 }
 
 impl<'a> ShapePlanKey<'a> {
@@ -133,6 +453,8 @@ impl<'a> ShapePlanKey<'a> {
             language: None,
             feature_variations: [None; 2],
             features: &[],
+            layout_preference: LayoutPreference::default(),
+            script_shapers: None,
         }
     }
 
@@ -156,13 +478,37 @@ impl<'a> ShapePlanKey<'a> {
         self
     }
 
+    /// Sets the layout table preference to use for this shape plan key.
+    pub fn layout_preference(mut self, layout_preference: LayoutPreference) -> Self {
+        self.layout_preference = layout_preference;
+        self
+    }
+
+    /// Sets the script shapers of the shaper to use for this shape plan key.
+    ///
+    /// This must be the registry passed to
+    /// [`ShaperBuilder::script_shapers`](crate::ShaperBuilder::script_shapers).
+    /// A plan matches if the registry has the same shaper, compared by
+    /// address, for the plan's script as the registry the plan was created
+    /// with.
+    pub fn script_shapers(mut self, script_shapers: Option<&'a ShaperRegistry>) -> Self {
+        self.script_shapers = script_shapers;
+        self
+    }
+
     /// Returns true if this key is a match for the given shape plan.
     pub fn matches(&self, plan: &hb_ot_shape_plan_t) -> bool {
+        let script_shaper_id = self
+            .script
+            .and_then(|script| self.script_shapers?.get_with_id(script))
+            .map(|(id, _)| id);
         self.script == plan.script
             && self.direction == plan.direction
             && self.language == plan.language.as_ref()
             && self.feature_variations == *plan.ot_map.feature_variations()
             && features_equivalent(self.features, &plan.user_features)
+            && self.layout_preference == plan.layout_preference
+            && script_shaper_id == plan.script_shaper_id
     }
 }
 
@@ -184,11 +530,239 @@ fn features_equivalent(features_a: &[Feature], features_b: &[Feature]) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use super::hb_ot_shape_plan_t;
+    use super::{
+        hb_ot_shape_plan_t, AatPreference, LayoutPreference, NormalizationMode, PlanLookup,
+        ShapePlanKey, ShaperKind,
+    };
+    use crate::{script, Direction, FontRef, ShapeOptions, ShaperData, Tag, UnicodeBuffer};
 
     #[test]
     fn test_shape_plan_is_send_and_sync() {
         fn ensure_send_and_sync<T: Send + Sync>() {}
         ensure_send_and_sync::<hb_ot_shape_plan_t>();
     }
+
+    #[test]
+    fn layout_preference_selects_tables() {
+        let font_data = include_bytes!("../../tests/fonts/text-rendering-tests/TestMORXOne.ttf");
+        let font = FontRef::new(font_data).unwrap();
+        let shaper_data = ShaperData::new(&font);
+        let shaper = shaper_data.shaper(&font).build();
+        let plan = |layout_preference| {
+            hb_ot_shape_plan_t::with_layout_preference(
+                &shaper,
+                Direction::LeftToRight,
+                None,
+                None,
+                &[],
+                layout_preference,
+            )
+        };
+
+        assert!(plan(LayoutPreference::default()).applies_morx());
+        let forbid = LayoutPreference::new().aat(AatPreference::Forbid);
+        let forbidden = plan(forbid);
+        assert!(!forbidden.applies_morx());
+        assert_eq!(forbidden.layout_preference(), forbid);
+
+        let key = ShapePlanKey::new(None, Direction::LeftToRight);
+        assert!(!key.matches(&forbidden));
+        assert!(key.layout_preference(forbid).matches(&forbidden));
+    }
+
+    #[test]
+    fn normalization_mode_is_validated_against_shaper() {
+        let font_data = include_bytes!("../../tests/fonts/rb_custom/OpenSans.subset1.ttf");
+        let font = FontRef::new(font_data).unwrap();
+        let shaper_data = ShaperData::new(&font);
+        let shaper = shaper_data.shaper(&font).build();
+        let plan = |script| {
+            hb_ot_shape_plan_t::new(&shaper, Direction::LeftToRight, Some(script), None, &[])
+        };
+
+        let latin = plan(script::LATIN);
+        assert_eq!(
+            latin.normalization_mode(NormalizationMode::Auto),
+            NormalizationMode::ComposedDiacritics
+        );
+        for mode in [
+            NormalizationMode::PassThrough,
+            NormalizationMode::None,
+            NormalizationMode::Decomposed,
+            NormalizationMode::ComposedDiacriticsNoShortCircuit,
+        ] {
+            assert_eq!(latin.normalization_mode(mode), mode);
+        }
+
+        let hangul = plan(script::HANGUL);
+        assert_eq!(
+            hangul.normalization_mode(NormalizationMode::Decomposed),
+            NormalizationMode::None
+        );
+        assert_eq!(
+            hangul.normalization_mode(NormalizationMode::PassThrough),
+            NormalizationMode::PassThrough
+        );
+
+        let devanagari = plan(script::DEVANAGARI);
+        assert_eq!(
+            devanagari.normalization_mode(NormalizationMode::Auto),
+            NormalizationMode::ComposedDiacriticsNoShortCircuit
+        );
+        assert_eq!(
+            devanagari.normalization_mode(NormalizationMode::PassThrough),
+            NormalizationMode::ComposedDiacriticsNoShortCircuit
+        );
+        assert_eq!(
+            devanagari.normalization_mode(NormalizationMode::Decomposed),
+            NormalizationMode::Decomposed
+        );
+    }
+
+    #[test]
+    fn normalization_mode_pass_through_maps_characters_as_is() {
+        const NOTDEF: u32 = 0;
+        const A: u32 = 14;
+        const ACUTE: u32 = 45;
+        const AACUTE: u32 = 51;
+        const DOT_BELOW: u32 = 63;
+
+        // Has a, a-acute and the combining acute and dot below, but no
+        // a-dot-below.
+        let font_data = include_bytes!("../../tests/fonts/text-rendering-tests/TestGPOSOne.ttf");
+        let font = FontRef::new(font_data).unwrap();
+        let shaper_data = ShaperData::new(&font);
+        let shaper = shaper_data.shaper(&font).build();
+        let shape = |text: &str, mode| {
+            let mut buffer = UnicodeBuffer::new();
+            buffer.push_str(text);
+            buffer.guess_segment_properties();
+            let options = ShapeOptions::new().normalization_mode(mode);
+            shaper
+                .shape(buffer, options)
+                .glyph_infos()
+                .iter()
+                .map(|info| info.glyph_id)
+                .collect::<Vec<_>>()
+        };
+
+        let a_acute = "a\u{0301}";
+        assert_eq!(shape(a_acute, NormalizationMode::Auto), [AACUTE]);
+        assert_eq!(shape(a_acute, NormalizationMode::PassThrough), [A, ACUTE]);
+
+        let a_dot_below = "\u{1EA1}";
+        assert_eq!(shape(a_dot_below, NormalizationMode::None), [A, DOT_BELOW]);
+        assert_eq!(shape(a_dot_below, NormalizationMode::PassThrough), [NOTDEF]);
+
+        let unordered_marks = "a\u{0301}\u{0323}";
+        assert_eq!(
+            shape(unordered_marks, NormalizationMode::None),
+            [A, DOT_BELOW, ACUTE]
+        );
+        assert_eq!(
+            shape(unordered_marks, NormalizationMode::PassThrough),
+            [A, ACUTE, DOT_BELOW]
+        );
+    }
+
+    #[test]
+    fn layout_preference_disables_kern() {
+        let font_data = include_bytes!("../../tests/fonts/text-rendering-tests/TestKERNOne.otf");
+        let font = FontRef::new(font_data).unwrap();
+        let shaper_data = ShaperData::new(&font);
+        let shaper = shaper_data.shaper(&font).build();
+        let plan = |disable_kern| {
+            hb_ot_shape_plan_t::with_layout_preference(
+                &shaper,
+                Direction::LeftToRight,
+                None,
+                None,
+                &[],
+                LayoutPreference::new().disable_kern(disable_kern),
+            )
+        };
+
+        assert!(plan(false).applies_kern());
+        assert!(!plan(true).applies_kern());
+    }
+
+    #[test]
+    fn plan_lists_lookups_by_stage() {
+        let font_data = include_bytes!(
+            "../../tests/fonts/in-house/8339c821814d9bad7c77169332327ad8b0f33c81.ttf"
+        );
+        let font = FontRef::new(font_data).unwrap();
+        let shaper_data = ShaperData::new(&font);
+        let shaper = shaper_data.shaper(&font).build();
+        let plan = hb_ot_shape_plan_t::new(
+            &shaper,
+            Direction::RightToLeft,
+            Some(script::ARABIC),
+            None,
+            &[],
+        );
+
+        assert_eq!(plan.shaper(), ShaperKind::Arabic);
+        assert_eq!(plan.gsub_script_tag(), Some(Tag::new(b"arab")));
+        assert_eq!(plan.gsub_language_tag(), None);
+        assert_eq!(plan.gpos_script_tag(), Some(Tag::new(b"arab")));
+        assert_eq!(plan.gpos_language_tag(), None);
+
+        let lookup = |stage, feature_tag: &[u8; 4], lookup_index, auto_zwj, auto_zwnj| PlanLookup {
+            stage,
+            feature_tag: Tag::new(feature_tag),
+            lookup_index,
+            auto_zwj,
+            auto_zwnj,
+            per_syllable: false,
+        };
+        assert_eq!(
+            plan.gsub_lookups().collect::<Vec<_>>(),
+            [
+                lookup(1, b"rand", 0, true, true),
+                lookup(2, b"ccmp", 1, false, true),
+                lookup(2, b"ccmp", 2, false, true),
+                lookup(4, b"fina", 6, false, true),
+                lookup(7, b"medi", 5, false, true),
+                lookup(9, b"init", 4, false, true),
+                lookup(11, b"rlig", 7, false, true),
+            ]
+        );
+        assert_eq!(
+            plan.gpos_lookups().collect::<Vec<_>>(),
+            [
+                lookup(0, b"kern", 0, true, true),
+                lookup(0, b"mark", 1, false, false),
+                lookup(0, b"mark", 2, false, false),
+            ]
+        );
+
+        assert!(!plan.applies_morx());
+        assert!(plan.applies_gpos());
+        assert!(!plan.applies_kerx());
+        assert!(!plan.applies_kern());
+        assert!(!plan.applies_trak());
+    }
+
+    #[test]
+    fn plan_reports_aat_tables() {
+        let font_data = include_bytes!("../../tests/fonts/in-house/TRAK.ttf");
+        let font = FontRef::new(font_data).unwrap();
+        let shaper_data = ShaperData::new(&font);
+        let shaper = shaper_data.shaper(&font).build();
+        let plan = hb_ot_shape_plan_t::new(
+            &shaper,
+            Direction::LeftToRight,
+            Some(script::LATIN),
+            None,
+            &[],
+        );
+
+        assert!(plan.applies_morx());
+        assert!(plan.applies_trak());
+        assert!(!plan.applies_gpos());
+        assert_eq!(plan.shaper(), ShaperKind::Default);
+        assert_eq!(plan.gsub_lookups().count(), 0);
+        assert_eq!(plan.gpos_lookups().count(), 0);
+    }
 }
@@ -108,6 +108,37 @@ pub struct hb_ot_shaper_t {
     pub fallback_position: bool,
 }
 
+/// The script-specific shaper selected for a [`ShapePlan`](crate::ShapePlan).
+///
+/// Returned by [`ShapePlan::shaper`](crate::ShapePlan::shaper).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ShaperKind {
+    /// The default shaper, for scripts without special requirements.
+    Default,
+    /// The default shaper without mark zeroing and fallback positioning,
+    /// used when the AAT `morx` table is applied.
+    Dumber,
+    /// The Arabic shaper, also used for Syriac.
+    Arabic,
+    /// The Hangul shaper.
+    Hangul,
+    /// The Hebrew shaper.
+    Hebrew,
+    /// The Indic shaper.
+    Indic,
+    /// The Khmer shaper.
+    Khmer,
+    /// The Myanmar shaper.
+    Myanmar,
+    /// The shaper for Myanmar text in the Zawgyi encoding.
+    MyanmarZawgyi,
+    /// The Thai and Lao shaper.
+    Thai,
+    /// The Universal Shaping Engine.
+    Use,
+}
+
 // Same as default but no mark advance zeroing / fallback positioning.
 // Dumbest shaper ever, basically.
 pub const DUMBER_SHAPER: hb_ot_shaper_t = hb_ot_shaper_t {
@@ -130,7 +161,7 @@ pub fn hb_ot_shape_complex_categorize(
     script: Script,
     direction: Direction,
     gsub_script: Option<hb_tag_t>,
-) -> &'static hb_ot_shaper_t {
+) -> ShaperKind {
     match script {
         // Unicode-1.1 additions
         script::ARABIC
@@ -146,21 +177,21 @@ pub fn hb_ot_shape_complex_categorize(
             if (gsub_script != Some(hb_tag_t::default_script()) || script == script::ARABIC)
                 && direction.is_horizontal()
             {
-                &crate::hb::ot_shaper_arabic::ARABIC_SHAPER
+                ShaperKind::Arabic
             } else {
-                &DEFAULT_SHAPER
+                ShaperKind::Default
             }
         }
 
         // Unicode-1.1 additions
         script::THAI
-        | script::LAO => &crate::hb::ot_shaper_thai::THAI_SHAPER,
+        | script::LAO => ShaperKind::Thai,
 
         // Unicode-1.1 additions
-        script::HANGUL => &crate::hb::ot_shaper_hangul::HANGUL_SHAPER,
+        script::HANGUL => ShaperKind::Hangul,
 
         // Unicode-1.1 additions
-        script::HEBREW => &crate::hb::ot_shaper_hebrew::HEBREW_SHAPER,
+        script::HEBREW => ShaperKind::Hebrew,
 
         // Unicode-1.1 additions
         script::BENGALI
@@ -179,15 +210,15 @@ pub fn hb_ot_shape_complex_categorize(
             // If it's indy3 tag, send to USE.
             if gsub_script == Some(hb_tag_t::default_script()) ||
                gsub_script == Some(hb_tag_t::new(b"latn")) {
-                &DEFAULT_SHAPER
+                ShaperKind::Default
             } else if gsub_script.is_some_and(|tag| tag.to_be_bytes()[3] == b'3') {
-                &crate::hb::ot_shaper_use::UNIVERSAL_SHAPER
+                ShaperKind::Use
             } else {
-                &crate::hb::ot_shaper_indic::INDIC_SHAPER
+                ShaperKind::Indic
             }
         }
 
-        script::KHMER => &crate::hb::ot_shaper_khmer::KHMER_SHAPER,
+        script::KHMER => ShaperKind::Khmer,
 
         script::MYANMAR => {
             // If the designer designed the font for the 'DFLT' script,
@@ -201,14 +232,14 @@ pub fn hb_ot_shape_complex_categorize(
                gsub_script == Some(hb_tag_t::new(b"latn")) ||
                gsub_script == Some(hb_tag_t::new(b"mymr"))
             {
-                &DEFAULT_SHAPER
+                ShaperKind::Default
             } else {
-                &crate::hb::ot_shaper_myanmar::MYANMAR_SHAPER
+                ShaperKind::Myanmar
             }
         }
 
         // https://github.com/harfbuzz/harfbuzz/issues/1162
-        script::MYANMAR_ZAWGYI => &crate::hb::ot_shaper_myanmar::MYANMAR_ZAWGYI_SHAPER,
+        script::MYANMAR_ZAWGYI => ShaperKind::MyanmarZawgyi,
 
         // Unicode-2.0 additions
         script::TIBETAN
@@ -348,12 +379,12 @@ pub fn hb_ot_shape_complex_categorize(
             // GSUB/GPOS needed, so there may be no scripts found!
             if gsub_script == Some(hb_tag_t::default_script()) ||
                gsub_script == Some(hb_tag_t::new(b"latn")) {
-                &DEFAULT_SHAPER
+                ShaperKind::Default
             } else {
-                &crate::hb::ot_shaper_use::UNIVERSAL_SHAPER
+                ShaperKind::Use
             }
         }
 
-        _ => &DEFAULT_SHAPER
+        _ => ShaperKind::Default
     }
 }
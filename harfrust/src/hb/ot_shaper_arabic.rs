@@ -5,6 +5,7 @@ use super::ot_shape::*;
 use super::ot_shape_normalize::HB_OT_SHAPE_NORMALIZATION_MODE_AUTO;
 use super::ot_shape_plan::hb_ot_shape_plan_t;
 use super::ot_shaper::*;
+use super::unicode_funcs::UnicodeFuncsDispatch;
 use super::{hb_mask_t, hb_tag_t, script, GlyphInfo, Script};
 use crate::unicode::*;
 use crate::Direction;
@@ -301,14 +302,14 @@ pub fn data_create_arabic(plan: &hb_ot_shape_plan_t) -> arabic_shape_plan_t {
     }
 }
 
-fn arabic_joining(buffer: &mut hb_buffer_t) {
+fn arabic_joining(unicode: UnicodeFuncsDispatch, buffer: &mut hb_buffer_t) {
     let mut prev: Option<usize> = None;
     let mut state = 0;
 
     // Check pre-context.
     for i in 0..buffer.context_len[0] {
         let c = buffer.context[0][i] as Codepoint;
-        let this_type = get_joining_type(c, c.general_category());
+        let this_type = get_joining_type(c, unicode.general_category(c));
         if this_type == hb_arabic_joining_type_t::T {
             continue;
         }
@@ -355,7 +356,7 @@ fn arabic_joining(buffer: &mut hb_buffer_t) {
 
     for i in 0..buffer.context_len[1] {
         let c = buffer.context[1][i] as Codepoint;
-        let this_type = get_joining_type(c, c.general_category());
+        let this_type = get_joining_type(c, unicode.general_category(c));
         if this_type == hb_arabic_joining_type_t::T {
             continue;
         }
@@ -392,21 +393,22 @@ fn mongolian_variation_selectors(buffer: &mut hb_buffer_t) {
 
 fn setup_masks_arabic_plan(
     plan: &hb_ot_shape_plan_t,
-    _: &mut FontFuncsDispatch,
+    font_funcs: &mut FontFuncsDispatch,
     buffer: &mut hb_buffer_t,
 ) {
     buffer.allocate_var(GlyphInfo::ARABIC_SHAPING_ACTION_VAR);
 
     let arabic_plan = plan.data::<arabic_shape_plan_t>();
-    setup_masks_inner(arabic_plan, plan.script, buffer);
+    setup_masks_inner(arabic_plan, plan.script, font_funcs.unicode(), buffer);
 }
 
 pub fn setup_masks_inner(
     arabic_plan: &arabic_shape_plan_t,
     script: Option<Script>,
+    unicode: UnicodeFuncsDispatch,
     buffer: &mut hb_buffer_t,
 ) {
-    arabic_joining(buffer);
+    arabic_joining(unicode, buffer);
     if script == Some(script::MONGOLIAN) {
         mongolian_variation_selectors(buffer);
     }
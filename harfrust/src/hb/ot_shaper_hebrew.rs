@@ -3,7 +3,7 @@ use super::ot_shape_normalize::*;
 use super::ot_shaper::*;
 use crate::hb::buffer::hb_buffer_t;
 use crate::hb::ot_shape_plan::hb_ot_shape_plan_t;
-use crate::unicode::{self, combining_class, modified_combining_class, Codepoint};
+use crate::unicode::{combining_class, modified_combining_class, Codepoint};
 
 pub const HEBREW_SHAPER: hb_ot_shaper_t = hb_ot_shaper_t {
     collect_features: None,
@@ -83,7 +83,7 @@ fn compose(ctx: &hb_ot_shape_normalize_context_t, a: Codepoint, b: Codepoint) ->
     // https://bugzilla.mozilla.org/show_bug.cgi?id=728866
     // Hebrew presentation forms with dagesh, for characters U+05D0..05EA;
     // Note that some letters do not have a dagesh presForm encoded.
-    match unicode::compose(a, b) {
+    match ctx.font_funcs.unicode().compose(a, b) {
         Some(c) => Some(c),
         None if !ctx.plan.has_gpos_mark => {
             // Special-case Hebrew presentation forms that are excluded from
@@ -16,8 +16,8 @@ use super::ot_shaper::*;
 use super::ot_shaper_syllabic::*;
 use super::{hb_font_t, hb_mask_t, hb_tag_t, script, GlyphInfo, Script};
 use crate::algs::*;
+use crate::unicode::Codepoint;
 use crate::unicode::GeneralCategory;
-use crate::unicode::{CharExt, Codepoint};
 
 pub const INDIC_SHAPER: hb_ot_shaper_t = hb_ot_shaper_t {
     collect_features: Some(collect_features),
@@ -580,7 +580,10 @@ fn preprocess_text(_: &hb_ot_shape_plan_t, _: &mut FontFuncsDispatch, buffer: &m
     super::ot_shaper_vowel_constraints::preprocess_text_vowel_constraints(buffer);
 }
 
-fn decompose(_: &hb_ot_shape_normalize_context_t, ab: Codepoint) -> Option<(Codepoint, Codepoint)> {
+fn decompose(
+    ctx: &hb_ot_shape_normalize_context_t,
+    ab: Codepoint,
+) -> Option<(Codepoint, Codepoint)> {
     // Don't decompose these.
     match ab {
         0x0931 |               // DEVANAGARI LETTER RRA
@@ -591,12 +594,14 @@ fn decompose(_: &hb_ot_shape_normalize_context_t, ab: Codepoint) -> Option<(Code
         _ => {}
     }
 
-    crate::unicode::decompose(ab)
+    ctx.font_funcs.unicode().decompose(ab)
 }
 
-fn compose(_: &hb_ot_shape_normalize_context_t, a: Codepoint, b: Codepoint) -> Option<Codepoint> {
+fn compose(ctx: &hb_ot_shape_normalize_context_t, a: Codepoint, b: Codepoint) -> Option<Codepoint> {
+    let unicode = ctx.font_funcs.unicode();
+
     // Avoid recomposing split matras.
-    if a.general_category().is_mark() {
+    if unicode.general_category(a).is_mark() {
         return None;
     }
 
@@ -605,7 +610,7 @@ fn compose(_: &hb_ot_shape_normalize_context_t, a: Codepoint, b: Codepoint) -> O
         return Some(0x09DF);
     }
 
-    crate::unicode::compose(a, b)
+    unicode.compose(a, b)
 }
 
 fn setup_masks(_: &hb_ot_shape_plan_t, _: &mut FontFuncsDispatch, buffer: &mut hb_buffer_t) {
@@ -8,7 +8,7 @@ use super::ot_shaper::*;
 use super::ot_shaper_indic::ot_category_t;
 use super::ot_shaper_syllabic::*;
 use super::{hb_mask_t, hb_tag_t, GlyphInfo};
-use crate::unicode::{CharExt, Codepoint};
+use crate::unicode::Codepoint;
 use alloc::boxed::Box;
 
 pub const KHMER_SHAPER: hb_ot_shaper_t = hb_ot_shaper_t {
@@ -290,21 +290,26 @@ fn override_features(planner: &mut hb_ot_shape_planner_t) {
     planner.ot_map.disable_feature(hb_tag_t::new(b"liga"));
 }
 
-fn decompose(_: &hb_ot_shape_normalize_context_t, ab: Codepoint) -> Option<(Codepoint, Codepoint)> {
+fn decompose(
+    ctx: &hb_ot_shape_normalize_context_t,
+    ab: Codepoint,
+) -> Option<(Codepoint, Codepoint)> {
     // Decompose split matras that don't have Unicode decompositions.
     match ab {
         0x17BE | 0x17BF | 0x17C0 | 0x17C4 | 0x17C5 => Some((0x17C1, ab)),
-        _ => crate::unicode::decompose(ab),
+        _ => ctx.font_funcs.unicode().decompose(ab),
     }
 }
 
-fn compose(_: &hb_ot_shape_normalize_context_t, a: Codepoint, b: Codepoint) -> Option<Codepoint> {
+fn compose(ctx: &hb_ot_shape_normalize_context_t, a: Codepoint, b: Codepoint) -> Option<Codepoint> {
+    let unicode = ctx.font_funcs.unicode();
+
     // Avoid recomposing split matras.
-    if a.general_category().is_mark() {
+    if unicode.general_category(a).is_mark() {
         return None;
     }
 
-    crate::unicode::compose(a, b)
+    unicode.compose(a, b)
 }
 
 fn setup_masks(_: &hb_ot_shape_plan_t, _: &mut FontFuncsDispatch, buffer: &mut hb_buffer_t) {
@@ -10,7 +10,7 @@ use super::ot_shaper_arabic::arabic_shape_plan_t;
 use super::ot_shaper_syllabic::*;
 use super::{hb_mask_t, hb_tag_t, script, GlyphInfo, Script};
 use crate::algs::*;
-use crate::unicode::{CharExt, Codepoint};
+use crate::unicode::Codepoint;
 use alloc::boxed::Box;
 
 pub const UNIVERSAL_SHAPER: hb_ot_shaper_t = hb_ot_shaper_t {
@@ -561,21 +561,32 @@ fn preprocess_text(_: &hb_ot_shape_plan_t, _: &mut FontFuncsDispatch, buffer: &m
     super::ot_shaper_vowel_constraints::preprocess_text_vowel_constraints(buffer);
 }
 
-fn compose(_: &hb_ot_shape_normalize_context_t, a: Codepoint, b: Codepoint) -> Option<Codepoint> {
+fn compose(ctx: &hb_ot_shape_normalize_context_t, a: Codepoint, b: Codepoint) -> Option<Codepoint> {
+    let unicode = ctx.font_funcs.unicode();
+
     // Avoid recomposing split matras.
-    if a.general_category().is_mark() {
+    if unicode.general_category(a).is_mark() {
         return None;
     }
 
-    crate::unicode::compose(a, b)
+    unicode.compose(a, b)
 }
 
-fn setup_masks(plan: &hb_ot_shape_plan_t, _: &mut FontFuncsDispatch, buffer: &mut hb_buffer_t) {
+fn setup_masks(
+    plan: &hb_ot_shape_plan_t,
+    font_funcs: &mut FontFuncsDispatch,
+    buffer: &mut hb_buffer_t,
+) {
     let universal_plan = plan.data::<UniversalShapePlan>();
 
     // Do this before allocating use_category().
     if let Some(ref arabic_plan) = universal_plan.arabic_plan {
-        crate::hb::ot_shaper_arabic::setup_masks_inner(arabic_plan, plan.script, buffer);
+        crate::hb::ot_shaper_arabic::setup_masks_inner(
+            arabic_plan,
+            plan.script,
+            font_funcs.unicode(),
+            buffer,
+        );
     }
 
     buffer.allocate_var(GlyphInfo::USE_CATEGORY_VAR);
@@ -0,0 +1,188 @@
+//! Lookup coverage profiling.
+
+use alloc::vec::Vec;
+
+use super::aat::layout_common::AatApplyContext;
+use super::ot_layout::TableIndex;
+
+/// Counters for a lookup, a lookup subtable or a `morx` or `mort` subtable.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LookupCounts {
+    /// The number of times the lookup or subtable was tried.
+    ///
+    /// For lookups this is once per glyph the lookup was applied at,
+    /// including from nested lookups. For lookup subtables it counts
+    /// glyphs that passed the subtable's coverage digest. For `morx` and
+    /// `mort` subtables it is once per buffer the subtable was run on.
+    pub attempts: u64,
+    /// The number of attempts that changed the buffer.
+    pub successes: u64,
+    /// The number of glyphs looked at.
+    ///
+    /// For lookups this includes glyphs skipped by the lookup's coverage
+    /// digest, mask or glyph properties. For lookup subtables it counts
+    /// glyphs that reached the subtable's coverage digest check. For `morx`
+    /// and `mort` subtables it is the length of the buffers the subtable was
+    /// run on.
+    pub glyphs_visited: u64,
+}
+
+impl LookupCounts {
+    fn merge(&mut self, other: &Self) {
+        self.attempts += other.attempts;
+        self.successes += other.successes;
+        self.glyphs_visited += other.glyphs_visited;
+    }
+}
+
+/// The counters of a `GSUB` or `GPOS` lookup.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LookupStats {
+    /// Counters for the lookup as a whole.
+    pub counts: LookupCounts,
+    /// Counters for each subtable of the lookup, in table order.
+    pub subtables: Vec<LookupCounts>,
+}
+
+/// Per-lookup and per-subtable counters accumulated over any number of
+/// shaping calls.
+///
+/// Pass a profile to [`ShapeOptions::profile`](crate::ShapeOptions::profile)
+/// to have shaping record which `GSUB`, `GPOS` and `morx` or `mort` lookups
+/// and subtables fire. Lookups are indexed by their index in the table's lookup
+/// list, so a profile should only be used with one font.
+///
+/// Profiling slows down shaping and is meant for font QA only. It is only
+/// available with the `profile` cargo feature.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LookupProfile {
+    gsub: Vec<LookupStats>,
+    gpos: Vec<LookupStats>,
+    morx: Vec<Vec<LookupCounts>>,
+}
+
+impl LookupProfile {
+    /// Creates an empty profile.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the counters of the `GSUB` lookups, indexed by lookup index.
+    ///
+    /// Lookups after the last one that was looked at are not included.
+    pub fn gsub_lookups(&self) -> &[LookupStats] {
+        &self.gsub
+    }
+
+    /// Returns the counters of the `GPOS` lookups, indexed by lookup index.
+    ///
+    /// Lookups after the last one that was looked at are not included.
+    pub fn gpos_lookups(&self) -> &[LookupStats] {
+        &self.gpos
+    }
+
+    /// Returns the counters of the `morx` subtables, or the `mort` ones if
+    /// the font has no `morx` table, indexed by chain and then by subtable
+    /// index within the chain.
+    pub fn morx_chains(&self) -> &[Vec<LookupCounts>] {
+        &self.morx
+    }
+
+    /// Adds the counters of `other` to this profile.
+    pub fn merge(&mut self, other: &Self) {
+        fn merge_counts(into: &mut Vec<LookupCounts>, from: &[LookupCounts]) {
+            if into.len() < from.len() {
+                into.resize(from.len(), LookupCounts::default());
+            }
+            for (into, from) in into.iter_mut().zip(from) {
+                into.merge(from);
+            }
+        }
+        for (into, from) in [(&mut self.gsub, &other.gsub), (&mut self.gpos, &other.gpos)] {
+            if into.len() < from.len() {
+                into.resize(from.len(), LookupStats::default());
+            }
+            for (into, from) in into.iter_mut().zip(from) {
+                into.counts.merge(&from.counts);
+                merge_counts(&mut into.subtables, &from.subtables);
+            }
+        }
+        if self.morx.len() < other.morx.len() {
+            self.morx.resize(other.morx.len(), Vec::new());
+        }
+        for (into, from) in self.morx.iter_mut().zip(&other.morx) {
+            merge_counts(into, from);
+        }
+    }
+
+    /// Resets all counters.
+    pub fn clear(&mut self) {
+        self.gsub.clear();
+        self.gpos.clear();
+        self.morx.clear();
+    }
+
+    pub(crate) fn lookup(
+        &mut self,
+        table_index: TableIndex,
+        lookup_index: u16,
+        num_subtables: usize,
+    ) -> &mut LookupStats {
+        let lookups = match table_index {
+            TableIndex::GSUB => &mut self.gsub,
+            TableIndex::GPOS => &mut self.gpos,
+        };
+        let index = usize::from(lookup_index);
+        if lookups.len() <= index {
+            lookups.resize(index + 1, LookupStats::default());
+        }
+        let stats = &mut lookups[index];
+        if stats.subtables.len() < num_subtables {
+            stats
+                .subtables
+                .resize(num_subtables, LookupCounts::default());
+        }
+        stats
+    }
+
+    /// Runs `apply` on the buffer of `c` and counts it as a run of a `morx`
+    /// or `mort` subtable, which succeeds if it changes the glyphs.
+    ///
+    /// `glyphs_before` is scratch space reused across subtables.
+    pub(crate) fn aat_subtable<'a>(
+        &mut self,
+        chain: usize,
+        subtable: usize,
+        glyphs_before: &mut Vec<u32>,
+        c: &mut AatApplyContext<'a>,
+        apply: impl FnOnce(&mut AatApplyContext<'a>),
+    ) {
+        glyphs_before.clear();
+        glyphs_before.extend(
+            c.buffer.info[..c.buffer.len]
+                .iter()
+                .map(|info| info.glyph_id),
+        );
+        apply(c);
+        let glyphs_after = c.buffer.info[..c.buffer.len]
+            .iter()
+            .map(|info| info.glyph_id);
+        let changed = !glyphs_after.eq(glyphs_before.iter().copied());
+
+        let counts = self.morx_subtable(chain, subtable);
+        counts.attempts += 1;
+        counts.glyphs_visited += glyphs_before.len() as u64;
+        counts.successes += u64::from(changed);
+    }
+
+    fn morx_subtable(&mut self, chain: usize, subtable: usize) -> &mut LookupCounts {
+        if self.morx.len() <= chain {
+            self.morx.resize(chain + 1, Vec::new());
+        }
+        let chain = &mut self.morx[chain];
+        if chain.len() <= subtable {
+            chain.resize(subtable + 1, LookupCounts::default());
+        }
+        &mut chain[subtable]
+    }
+}
@@ -0,0 +1,501 @@
+//! Custom script-specific shapers.
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use super::buffer::{hb_buffer_t, GlyphInfo, GlyphPosition};
+use super::font_funcs::FontFuncsDispatch;
+use super::ot_map::F_NONE;
+use super::ot_shape::hb_ot_shape_planner_t;
+use super::ot_shape_normalize::{
+    compose_unicode, decompose_unicode, hb_ot_shape_normalize_context_t,
+};
+use super::ot_shape_plan::{hb_ot_shape_plan_t, NormalizationMode};
+use super::ot_shaper::*;
+use super::{hb_mask_t, hb_tag_t, Direction, Script};
+use crate::unicode::GeneralCategory;
+
+/// A script-specific shaper provided by the user.
+///
+/// A script shaper builds on one of the built-in shapers, which is passed
+/// to every callback as `base`. The default implementation of each callback
+/// forwards to `base`, so a shaper that overrides nothing behaves exactly
+/// like its base shaper. The base shaper also decides how marks are zeroed
+/// and whether fallback mark positioning is used.
+///
+/// Register script shapers in a [`ShaperRegistry`] and pass it to
+/// [`ShaperBuilder::script_shapers`](crate::ShaperBuilder::script_shapers).
+/// Like the built-in shapers, script shapers are not used for fonts shaped
+/// with the AAT `morx` table.
+pub trait ScriptShaper: Sync {
+    /// Returns the built-in shaper to build on.
+    ///
+    /// `default` is the shaper HarfRust selects for the script and font.
+    fn base(&self, default: ShaperKind) -> ShaperKind {
+        default
+    }
+
+    /// Adds the script's features to the plan.
+    ///
+    /// Called while planning, before the common features are added.
+    fn collect_features(&self, base: BuiltinShaper, planner: &mut ShapePlanner<'_, '_>) {
+        base.collect_features(planner);
+    }
+
+    /// Overrides features after the common and user features are added.
+    fn override_features(&self, base: BuiltinShaper, planner: &mut ShapePlanner<'_, '_>) {
+        base.override_features(planner);
+    }
+
+    /// Processes the text before substitution.
+    fn preprocess_text(
+        &self,
+        base: BuiltinShaper,
+        plan: &hb_ot_shape_plan_t,
+        buffer: &mut ShaperBuffer<'_, '_, '_>,
+    ) {
+        base.preprocess_text(plan, buffer);
+    }
+
+    /// Returns the normalization mode used for [`NormalizationMode::Auto`].
+    ///
+    /// Like the preference of a built-in shaper, this also adjusts the other
+    /// modes; see
+    /// [`ShapePlan::normalization_mode`](crate::ShapePlan::normalization_mode).
+    fn normalization_preference(&self, base: BuiltinShaper) -> NormalizationMode {
+        base.normalization_preference()
+    }
+
+    /// Decomposes the character `ab` into two characters during
+    /// normalization, or returns `None` if it doesn't decompose.
+    fn decompose(
+        &self,
+        base: BuiltinShaper,
+        normalizer: &Normalizer<'_, '_, '_, '_>,
+        ab: u32,
+    ) -> Option<(u32, u32)> {
+        base.decompose(normalizer, ab)
+    }
+
+    /// Composes the characters `a` and `b` into one during normalization, or
+    /// returns `None` if they don't compose.
+    fn compose(
+        &self,
+        base: BuiltinShaper,
+        normalizer: &Normalizer<'_, '_, '_, '_>,
+        a: u32,
+        b: u32,
+    ) -> Option<u32> {
+        base.compose(normalizer, a, b)
+    }
+
+    /// Reorders the combining marks in `start..end` during normalization,
+    /// after they were sorted by their combining class.
+    fn reorder_marks(
+        &self,
+        base: BuiltinShaper,
+        plan: &hb_ot_shape_plan_t,
+        buffer: &mut ShaperBuffer<'_, '_, '_>,
+        start: usize,
+        end: usize,
+    ) {
+        base.reorder_marks(plan, buffer, start, end);
+    }
+
+    /// Sets the masks of the features added by the shaper.
+    ///
+    /// Shapers should not modify characters here.
+    fn setup_masks(
+        &self,
+        base: BuiltinShaper,
+        plan: &hb_ot_shape_plan_t,
+        buffer: &mut ShaperBuffer<'_, '_, '_>,
+    ) {
+        base.setup_masks(plan, buffer);
+    }
+
+    /// Processes the glyphs after positioning.
+    fn postprocess_glyphs(
+        &self,
+        base: BuiltinShaper,
+        plan: &hb_ot_shape_plan_t,
+        buffer: &mut ShaperBuffer<'_, '_, '_>,
+    ) {
+        base.postprocess_glyphs(plan, buffer);
+    }
+}
+
+/// A set of script shapers, selected by script.
+#[derive(Clone, Default)]
+pub struct ShaperRegistry {
+    /// The shapers, with the id assigned when they were registered.
+    shapers: Vec<(Script, usize, &'static dyn ScriptShaper)>,
+}
+
+/// The id of the next registered shaper.
+///
+/// Plans identify their script shaper by this id rather than by its
+/// address, which isn't unique for zero-sized shapers and doesn't tell
+/// trait objects with different vtables apart reliably.
+static NEXT_SHAPER_ID: AtomicUsize = AtomicUsize::new(0);
+
+impl ShaperRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a shaper for `script`, replacing any shaper registered for
+    /// it before.
+    ///
+    /// Plans created with the shaper registered before don't match a
+    /// [`ShapePlanKey`](crate::ShapePlanKey) for this registry anymore, even
+    /// if the same shaper is registered again.
+    pub fn register(&mut self, script: Script, shaper: &'static dyn ScriptShaper) {
+        let id = NEXT_SHAPER_ID.fetch_add(1, Ordering::Relaxed);
+        match self.shapers.iter_mut().find(|(s, ..)| *s == script) {
+            Some(entry) => *entry = (script, id, shaper),
+            None => self.shapers.push((script, id, shaper)),
+        }
+    }
+
+    /// Removes the shaper registered for `script`, if any.
+    pub fn unregister(&mut self, script: Script) {
+        self.shapers.retain(|(s, ..)| *s != script);
+    }
+
+    /// Returns the shaper registered for `script`.
+    pub fn get(&self, script: Script) -> Option<&'static dyn ScriptShaper> {
+        self.get_with_id(script).map(|(_, shaper)| shaper)
+    }
+
+    /// Returns the shaper registered for `script` and the id assigned when it
+    /// was registered.
+    pub(crate) fn get_with_id(&self, script: Script) -> Option<(usize, &'static dyn ScriptShaper)> {
+        self.shapers
+            .iter()
+            .find(|(s, ..)| *s == script)
+            .map(|&(_, id, shaper)| (id, shaper))
+    }
+}
+
+/// One of the built-in shapers, used as a building block for a
+/// [`ScriptShaper`].
+#[derive(Clone, Copy)]
+pub struct BuiltinShaper {
+    kind: ShaperKind,
+    shaper: &'static hb_ot_shaper_t,
+}
+
+impl BuiltinShaper {
+    /// Returns the built-in shaper of the given kind.
+    pub fn from_kind(kind: ShaperKind) -> Self {
+        Self {
+            kind,
+            shaper: builtin_shaper(kind),
+        }
+    }
+
+    /// Returns which built-in shaper this is.
+    pub fn kind(&self) -> ShaperKind {
+        self.kind
+    }
+
+    /// Runs the shaper's feature collection.
+    pub fn collect_features(&self, planner: &mut ShapePlanner<'_, '_>) {
+        if let Some(func) = self.shaper.collect_features {
+            func(planner.planner);
+        }
+    }
+
+    /// Runs the shaper's feature overrides.
+    pub fn override_features(&self, planner: &mut ShapePlanner<'_, '_>) {
+        if let Some(func) = self.shaper.override_features {
+            func(planner.planner);
+        }
+    }
+
+    /// Runs the shaper's text preprocessing.
+    pub fn preprocess_text(
+        &self,
+        plan: &hb_ot_shape_plan_t,
+        buffer: &mut ShaperBuffer<'_, '_, '_>,
+    ) {
+        if let Some(func) = self.shaper.preprocess_text {
+            func(plan, buffer.font_funcs, buffer.buffer);
+        }
+    }
+
+    /// Returns the shaper's normalization preference.
+    pub fn normalization_preference(&self) -> NormalizationMode {
+        NormalizationMode::from_hb(self.shaper.normalization_preference)
+    }
+
+    /// Runs the shaper's decomposition.
+    pub fn decompose(
+        &self,
+        normalizer: &Normalizer<'_, '_, '_, '_>,
+        ab: u32,
+    ) -> Option<(u32, u32)> {
+        self.shaper.decompose.unwrap_or(decompose_unicode)(normalizer.ctx, ab)
+    }
+
+    /// Runs the shaper's composition.
+    pub fn compose(&self, normalizer: &Normalizer<'_, '_, '_, '_>, a: u32, b: u32) -> Option<u32> {
+        self.shaper.compose.unwrap_or(compose_unicode)(normalizer.ctx, a, b)
+    }
+
+    /// Runs the shaper's mark reordering.
+    pub fn reorder_marks(
+        &self,
+        plan: &hb_ot_shape_plan_t,
+        buffer: &mut ShaperBuffer<'_, '_, '_>,
+        start: usize,
+        end: usize,
+    ) {
+        if let Some(func) = self.shaper.reorder_marks {
+            func(plan, buffer.buffer, start, end);
+        }
+    }
+
+    /// Runs the shaper's mask setup.
+    pub fn setup_masks(&self, plan: &hb_ot_shape_plan_t, buffer: &mut ShaperBuffer<'_, '_, '_>) {
+        if let Some(func) = self.shaper.setup_masks {
+            func(plan, buffer.font_funcs, buffer.buffer);
+        }
+    }
+
+    /// Runs the shaper's glyph postprocessing.
+    pub fn postprocess_glyphs(
+        &self,
+        plan: &hb_ot_shape_plan_t,
+        buffer: &mut ShaperBuffer<'_, '_, '_>,
+    ) {
+        if let Some(func) = self.shaper.postprocess_glyphs {
+            func(plan, buffer.font_funcs, buffer.buffer);
+        }
+    }
+}
+
+pub(crate) fn builtin_shaper(kind: ShaperKind) -> &'static hb_ot_shaper_t {
+    match kind {
+        ShaperKind::Default => &DEFAULT_SHAPER,
+        ShaperKind::Dumber => &DUMBER_SHAPER,
+        ShaperKind::Arabic => &super::ot_shaper_arabic::ARABIC_SHAPER,
+        ShaperKind::Hangul => &super::ot_shaper_hangul::HANGUL_SHAPER,
+        ShaperKind::Hebrew => &super::ot_shaper_hebrew::HEBREW_SHAPER,
+        ShaperKind::Indic => &super::ot_shaper_indic::INDIC_SHAPER,
+        ShaperKind::Khmer => &super::ot_shaper_khmer::KHMER_SHAPER,
+        ShaperKind::Myanmar => &super::ot_shaper_myanmar::MYANMAR_SHAPER,
+        ShaperKind::MyanmarZawgyi => &super::ot_shaper_myanmar::MYANMAR_ZAWGYI_SHAPER,
+        ShaperKind::Thai => &super::ot_shaper_thai::THAI_SHAPER,
+        ShaperKind::Use => &super::ot_shaper_use::UNIVERSAL_SHAPER,
+    }
+}
+
+/// The plan being built, as seen by a [`ScriptShaper`].
+pub struct ShapePlanner<'p, 'a> {
+    planner: &'p mut hb_ot_shape_planner_t<'a>,
+}
+
+impl<'p, 'a> ShapePlanner<'p, 'a> {
+    pub(crate) fn new(planner: &'p mut hb_ot_shape_planner_t<'a>) -> Self {
+        Self { planner }
+    }
+
+    /// The direction of the text.
+    pub fn direction(&self) -> Direction {
+        self.planner.direction
+    }
+
+    /// The script of the text.
+    pub fn script(&self) -> Option<Script> {
+        self.planner.script
+    }
+
+    /// Adds a feature that applies to the whole buffer.
+    pub fn enable_feature(&mut self, tag: hb_tag_t) {
+        self.planner.ot_map.enable_feature(tag, F_NONE, 1);
+    }
+
+    /// Adds a feature that only applies where its mask is set.
+    ///
+    /// Look the mask up with
+    /// [`ShapePlan::feature_mask`](crate::ShapePlan::feature_mask) and set
+    /// it in [`ScriptShaper::setup_masks`].
+    pub fn add_feature(&mut self, tag: hb_tag_t) {
+        self.planner.ot_map.add_feature(tag, F_NONE, 1);
+    }
+
+    /// Disables a feature, including one requested by the user.
+    pub fn disable_feature(&mut self, tag: hb_tag_t) {
+        self.planner.ot_map.disable_feature(tag);
+    }
+
+    /// Starts a new `GSUB` stage. Lookups of features added after the pause
+    /// are applied after the ones added before it.
+    pub fn add_gsub_pause(&mut self) {
+        self.planner.ot_map.add_gsub_pause(None);
+    }
+
+    /// Returns `true` if the font has the feature for the selected script
+    /// and language.
+    pub fn has_feature(&self, tag: hb_tag_t) -> bool {
+        self.planner.ot_map.has_feature(tag)
+    }
+}
+
+/// The normalizer, as seen by a [`ScriptShaper`] that composes or
+/// decomposes characters.
+pub struct Normalizer<'n, 'a, 'x, 'u> {
+    ctx: &'n hb_ot_shape_normalize_context_t<'a, 'x, 'u>,
+}
+
+impl<'n, 'a, 'x, 'u> Normalizer<'n, 'a, 'x, 'u> {
+    pub(crate) fn new(ctx: &'n hb_ot_shape_normalize_context_t<'a, 'x, 'u>) -> Self {
+        Self { ctx }
+    }
+
+    /// The plan the text is shaped with.
+    pub fn plan(&self) -> &'a hb_ot_shape_plan_t {
+        self.ctx.plan
+    }
+
+    /// Returns the canonical decomposition of `ab` from the Unicode
+    /// functions.
+    pub fn decompose_unicode(&self, ab: u32) -> Option<(u32, u32)> {
+        decompose_unicode(self.ctx, ab)
+    }
+
+    /// Returns the canonical composition of `a` and `b` from the Unicode
+    /// functions.
+    pub fn compose_unicode(&self, a: u32, b: u32) -> Option<u32> {
+        compose_unicode(self.ctx, a, b)
+    }
+}
+
+/// The buffer being shaped, as seen by a [`ScriptShaper`].
+pub struct ShaperBuffer<'b, 'a, 'u> {
+    buffer: &'b mut hb_buffer_t,
+    font_funcs: &'b mut FontFuncsDispatch<'a, 'u>,
+}
+
+impl<'b, 'a, 'u> ShaperBuffer<'b, 'a, 'u> {
+    pub(crate) fn new(
+        buffer: &'b mut hb_buffer_t,
+        font_funcs: &'b mut FontFuncsDispatch<'a, 'u>,
+    ) -> Self {
+        Self { buffer, font_funcs }
+    }
+
+    /// Returns the number of characters or glyphs in the buffer.
+    pub fn len(&self) -> usize {
+        self.buffer.len
+    }
+
+    /// Returns `true` if the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.len == 0
+    }
+
+    /// The direction the buffer is shaped in.
+    pub fn direction(&self) -> Direction {
+        self.buffer.direction
+    }
+
+    /// The script of the buffer.
+    pub fn script(&self) -> Option<Script> {
+        self.buffer.script
+    }
+
+    /// Returns the buffer contents.
+    ///
+    /// Before substitution, `glyph_id` holds the character.
+    pub fn glyph_infos(&self) -> &[GlyphInfo] {
+        &self.buffer.info[..self.buffer.len]
+    }
+
+    /// Returns the general category of the character at `index`.
+    pub fn general_category(&self, index: usize) -> GeneralCategory {
+        self.buffer.info[index].general_category()
+    }
+
+    /// Enables the features of `mask` for the entry at `index`.
+    pub fn add_mask(&mut self, index: usize, mask: hb_mask_t) {
+        self.buffer.info[index].mask |= mask;
+    }
+
+    /// Merges the clusters of the entries in `start..end`.
+    pub fn merge_clusters(&mut self, start: usize, end: usize) {
+        self.buffer.merge_clusters(start, end);
+    }
+
+    /// Marks the entries in `start..end` as unsafe to break between.
+    pub fn unsafe_to_break(&mut self, start: usize, end: usize) {
+        self.buffer.unsafe_to_break(Some(start), Some(end));
+    }
+
+    /// Returns `true` if the font has a glyph for the character.
+    pub fn has_glyph(&mut self, c: u32) -> bool {
+        self.font_funcs.nominal_glyph(c).is_some()
+    }
+
+    /// Replaces the character at `index` with `c` and updates its Unicode
+    /// properties.
+    ///
+    /// Only characters can be replaced, so this is meant for
+    /// [`ScriptShaper::preprocess_text`].
+    pub fn replace(&mut self, index: usize, c: u32) {
+        let mut flags = self.buffer.scratch_flags;
+        let info = &mut self.buffer.info[index];
+        info.glyph_id = c;
+        info.init_unicode_props(self.font_funcs.unicode(), &mut flags);
+        self.buffer.scratch_flags = flags;
+    }
+
+    /// Inserts the character `c` before the entry at `index`, or at the end
+    /// if `index` is the length of the buffer.
+    ///
+    /// The new character takes the cluster and mask of the entry it is
+    /// inserted before, or of the last entry at the end. Nothing is inserted
+    /// into an empty buffer. Like [`ShaperBuffer::replace`], this is meant
+    /// for [`ScriptShaper::preprocess_text`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length of the buffer.
+    pub fn insert(&mut self, index: usize, c: u32) {
+        let len = self.buffer.len;
+        assert!(index <= len, "index {index} out of bounds");
+        if len == 0 || !self.buffer.ensure(len + 1) {
+            return;
+        }
+
+        let mut info = self.buffer.info[index.min(len - 1)];
+        info.glyph_id = c;
+        let mut flags = self.buffer.scratch_flags;
+        info.init_unicode_props(self.font_funcs.unicode(), &mut flags);
+        self.buffer.scratch_flags = flags;
+
+        self.buffer.info.copy_within(index..len, index + 1);
+        self.buffer.pos.copy_within(index..len, index + 1);
+        self.buffer.info[index] = info;
+        self.buffer.pos[index] = GlyphPosition::default();
+        self.buffer.len += 1;
+    }
+
+    /// Moves the entry at `from` to `to`, shifting the entries in between,
+    /// and merges the clusters of all entries from `from` to `to`.
+    pub fn move_entry(&mut self, from: usize, to: usize) {
+        let (start, end) = (from.min(to), from.max(to) + 1);
+        self.buffer.merge_clusters(start, end);
+        let positions = &mut self.buffer.pos[start..end];
+        if from < to {
+            self.buffer.info[start..end].rotate_left(1);
+            positions.rotate_left(1);
+        } else {
+            self.buffer.info[start..end].rotate_right(1);
+            positions.rotate_right(1);
+        }
+    }
+}
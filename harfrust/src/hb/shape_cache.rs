@@ -0,0 +1,437 @@
+//! Word-level cache of shaping results.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use read_fonts::TableProvider;
+
+use super::buffer::{hb_buffer_t, GlyphFlags, GlyphInfo, GlyphPosition};
+use super::face::{FontKind, ShapeEngine, ShapeOptions};
+use super::ot_shape_plan::{AatPreference, LayoutPreference};
+use crate::{BufferFlags, Direction, GlyphBuffer, UnicodeBuffer};
+
+// Segments longer than this are shaped but not stored, so that the memory
+// used by an entry stays bounded.
+const MAX_CACHED_SEGMENT_LEN: usize = 64;
+
+const DEFAULT_CAPACITY: usize = 1024;
+
+struct CacheEntry {
+    infos: Vec<GlyphInfo>,
+    positions: Vec<GlyphPosition>,
+    last_used: u64,
+}
+
+struct ShapedSegment {
+    range: Range<usize>,
+    cluster_base: u32,
+    infos: Vec<GlyphInfo>,
+    positions: Vec<GlyphPosition>,
+}
+
+/// A cache of shaped words.
+///
+/// [`ShapeCache::shape`] splits the buffer at spaces, looks up each piece
+/// in the cache and only shapes the pieces that are missing. The pieces are
+/// then joined back together, and any two neighbours whose join is marked
+/// with [`GlyphFlags::UNSAFE_TO_CONCAT`] are shaped again as one piece.
+///
+/// Entries are keyed by the text, the font, the variation coordinates and
+/// script shapers of the shaper, the segment properties of the buffer and the
+/// shaping options, so one cache can be shared by several fonts.
+///
+/// When the cache is full, the least recently used quarter of the entries is
+/// dropped.
+pub struct ShapeCache {
+    capacity: usize,
+    entries: BTreeMap<Vec<u32>, CacheEntry>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl Default for ShapeCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl ShapeCache {
+    /// Creates an empty cache holding at most `capacity` shaped words.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: BTreeMap::new(),
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns the maximum number of shaped words the cache holds.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of shaped words in the cache.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the cache holds no shaped words.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the number of words that were found in the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns the number of words that had to be shaped.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Removes all entries and resets the hit and miss counters.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.clock = 0;
+        self.hits = 0;
+        self.misses = 0;
+    }
+
+    /// Shapes the buffer content like [`Shaper::shape`](crate::Shaper::shape),
+    /// reusing the results of earlier calls for words seen before.
+    ///
+    /// The cache is bypassed, and the whole buffer shaped at once, when
+    /// custom font or Unicode functions, a lookup profile, float positions or
+    /// features with a limited range are set, or when the buffer clusters
+    /// are not monotonic.
+    pub fn shape(
+        &mut self,
+        shaper: &crate::Shaper,
+        buffer: UnicodeBuffer,
+        mut options: ShapeOptions<'_>,
+    ) -> GlyphBuffer {
+        let src = &buffer.0;
+        let Some(prefix) = self.key_prefix(shaper, src, &options) else {
+            return shaper.shape(buffer, options);
+        };
+        if src.info[1..src.len]
+            .iter()
+            .zip(&src.info[..src.len])
+            .any(|(info, prev)| info.cluster < prev.cluster)
+        {
+            return shaper.shape(buffer, options);
+        }
+
+        let mut shaped: Vec<ShapedSegment> = Vec::new();
+        for range in segments(src) {
+            let mut segment = self.shape_segment(shaper, src, range, &prefix, &mut options);
+            while let Some(prev) = shaped.last() {
+                if is_safe_to_concat(prev, &segment, src.direction) {
+                    break;
+                }
+                let range = prev.range.start..segment.range.end;
+                shaped.pop();
+                segment = self.shape_segment(shaper, src, range, &prefix, &mut options);
+            }
+            shaped.push(segment);
+        }
+
+        let mut result = UnicodeBuffer::with_properties_of(src).0;
+        let produce_unsafe_to_concat = src.flags.contains(BufferFlags::PRODUCE_UNSAFE_TO_CONCAT);
+        let mut append = |segment: &ShapedSegment| {
+            result.info.extend(segment.infos.iter().map(|info| {
+                let mut info = *info;
+                info.cluster += segment.cluster_base;
+                if !produce_unsafe_to_concat {
+                    info.mask &= !GlyphFlags::UNSAFE_TO_CONCAT.0;
+                }
+                info
+            }));
+            result.pos.extend_from_slice(&segment.positions);
+        };
+        if src.direction.is_backward() {
+            shaped.iter().rev().for_each(&mut append);
+        } else {
+            shaped.iter().for_each(&mut append);
+        }
+        result.len = result.info.len();
+        result.have_positions = true;
+
+        GlyphBuffer(result)
+    }
+
+    // Returns the part of the key shared by all segments of the buffer, or
+    // `None` if the buffer can't be shaped through the cache.
+    fn key_prefix(
+        &self,
+        shaper: &crate::Shaper,
+        buffer: &hb_buffer_t,
+        options: &ShapeOptions<'_>,
+    ) -> Option<Vec<u32>> {
+        #[cfg(feature = "profile")]
+        if options.profile.is_some() {
+            return None;
+        }
+        if self.capacity == 0
+            || buffer.len == 0
+            || buffer.direction == Direction::Invalid
+            || options.font_funcs.is_some()
+            || options.unicode_funcs.is_some()
+            || options.float_positions
+            || !options.features.iter().all(|feature| feature.is_global())
+            || !options
+                .aat_features
+                .iter()
+                .all(|feature| feature.is_global())
+        {
+            return None;
+        }
+
+        fn push_option_f32(key: &mut Vec<u32>, value: Option<f32>) {
+            key.push(u32::from(value.is_some()));
+            key.push(value.map_or(0, f32::to_bits));
+        }
+
+        fn push_layout_preference(key: &mut Vec<u32>, preference: LayoutPreference) {
+            key.push(match preference.aat {
+                AatPreference::Auto => 0,
+                AatPreference::Prefer => 1,
+                AatPreference::Forbid => 2,
+            });
+            key.push(u32::from(preference.disable_kern));
+        }
+
+        fn push_usize(key: &mut Vec<u32>, value: usize) {
+            let value = value as u64;
+            key.extend([(value >> 32) as u32, value as u32]);
+        }
+
+        let mut key = Vec::new();
+
+        // The cache doesn't borrow the font, so another font may be loaded
+        // at the address of a dropped one. The checksum adjustment of `head`
+        // tells most such fonts apart.
+        match &shaper.font {
+            FontKind::FontRef(data) => {
+                let bytes = data.font.data().as_bytes();
+                push_usize(&mut key, bytes.as_ptr() as usize);
+                push_usize(&mut key, bytes.len());
+                key.push(
+                    data.font
+                        .head()
+                        .map_or(0, |head| head.checksum_adjustment()),
+                );
+            }
+            FontKind::FontInstance(instance, _) => {
+                push_usize(&mut key, core::ptr::from_ref(*instance) as usize);
+            }
+        }
+        key.push(
+            buffer
+                .script
+                .and_then(|script| shaper.script_shapers?.get_with_id(script))
+                .map_or(0, |(id, _)| id as u32 + 1),
+        );
+
+        let coords = shaper.coords();
+        key.push(coords.len() as u32);
+        key.extend(coords.iter().map(|coord| coord.to_bits() as u16 as u32));
+
+        key.push(match buffer.direction {
+            Direction::Invalid => 0,
+            Direction::LeftToRight => 1,
+            Direction::RightToLeft => 2,
+            Direction::TopToBottom => 3,
+            Direction::BottomToTop => 4,
+        });
+        key.push(
+            buffer
+                .script
+                .map_or(0, |script| u32::from_be_bytes(script.tag().to_be_bytes())),
+        );
+        let language = buffer.language.as_ref().map_or("", |lang| lang.as_str());
+        key.push(language.len() as u32);
+        key.extend(language.bytes().map(u32::from));
+        key.push(buffer.cluster_level);
+        key.push(buffer.invisible.map_or(0, |glyph| glyph.to_u32() + 1));
+        key.push(u32::from(buffer.not_found_variation_selector.is_some()));
+        key.push(buffer.not_found_variation_selector.unwrap_or(0));
+
+        key.push(match options.engine {
+            ShapeEngine::Auto => 0,
+            ShapeEngine::OpenType => 1,
+            ShapeEngine::Fallback => 2,
+        });
+        push_layout_preference(&mut key, options.layout_preference);
+        key.push(options.normalization_mode.to_hb() as u32);
+        key.push(u32::from(options.scale.is_some()));
+        let (x_scale, y_scale) = options.scale.unwrap_or_default();
+        key.extend([x_scale as u32, y_scale as u32]);
+        push_option_f32(&mut key, options.point_size);
+        push_option_f32(&mut key, options.track);
+        push_option_f32(&mut key, options.letter_spacing);
+        let (x_embolden, y_embolden, in_place) = options.synthetic_bold;
+        key.extend([
+            x_embolden.to_bits(),
+            y_embolden.to_bits(),
+            u32::from(in_place),
+        ]);
+        key.push(options.synthetic_slant.to_bits());
+        key.push(options.features.len() as u32);
+        for feature in options.features {
+            key.extend([u32::from_be_bytes(feature.tag.to_be_bytes()), feature.value]);
+        }
+        key.push(options.aat_features.len() as u32);
+        for feature in options.aat_features {
+            key.extend([u32::from(feature.feature_type), u32::from(feature.selector)]);
+        }
+
+        key.push(u32::from(options.plan.is_some()));
+        if let Some(plan) = options.plan {
+            push_layout_preference(&mut key, plan.layout_preference);
+            key.push(plan.user_features.len() as u32);
+            for feature in &plan.user_features {
+                key.extend([
+                    u32::from_be_bytes(feature.tag.to_be_bytes()),
+                    feature.value,
+                    u32::from(feature.is_global()),
+                ]);
+            }
+        }
+
+        Some(key)
+    }
+
+    fn shape_segment(
+        &mut self,
+        shaper: &crate::Shaper,
+        src: &hb_buffer_t,
+        range: Range<usize>,
+        prefix: &[u32],
+        options: &mut ShapeOptions<'_>,
+    ) -> ShapedSegment {
+        let is_first = range.start == 0;
+        let is_last = range.end == src.len;
+        let cluster_base = src.info[range.start].cluster;
+
+        let mut flags = src.flags | BufferFlags::PRODUCE_UNSAFE_TO_CONCAT;
+        if !is_first {
+            flags.remove(BufferFlags::BEGINNING_OF_TEXT);
+        }
+        if !is_last {
+            flags.remove(BufferFlags::END_OF_TEXT);
+        }
+        let pre_context = if is_first {
+            &src.context[0][..src.context_len[0]]
+        } else {
+            &[]
+        };
+        let post_context = if is_last {
+            &src.context[1][..src.context_len[1]]
+        } else {
+            &[]
+        };
+
+        let mut key = prefix.to_vec();
+        key.push(flags.bits());
+        key.push(pre_context.len() as u32);
+        key.extend_from_slice(pre_context);
+        key.push(post_context.len() as u32);
+        key.extend_from_slice(post_context);
+        for info in &src.info[range.clone()] {
+            key.extend([info.glyph_id, info.cluster - cluster_base]);
+        }
+
+        self.clock += 1;
+        if let Some(entry) = self.entries.get_mut(&key) {
+            self.hits += 1;
+            entry.last_used = self.clock;
+            return ShapedSegment {
+                range,
+                cluster_base,
+                infos: entry.infos.clone(),
+                positions: entry.positions.clone(),
+            };
+        }
+        self.misses += 1;
+
+        let mut buffer = UnicodeBuffer::with_properties_of(src);
+        buffer.0.flags = flags;
+        buffer.0.context_len = [pre_context.len(), post_context.len()];
+        buffer.push_glyph_infos(&src.info[range.clone()]);
+        for info in &mut buffer.0.info[..buffer.0.len] {
+            info.cluster -= cluster_base;
+        }
+        let glyphs = shaper.shape(buffer, options.reborrow());
+        let infos = glyphs.glyph_infos().to_vec();
+        let positions = glyphs.glyph_positions().to_vec();
+
+        if range.len() <= MAX_CACHED_SEGMENT_LEN {
+            if self.entries.len() >= self.capacity {
+                self.evict();
+            }
+            self.entries.insert(
+                key,
+                CacheEntry {
+                    infos: infos.clone(),
+                    positions: positions.clone(),
+                    last_used: self.clock,
+                },
+            );
+        }
+
+        ShapedSegment {
+            range,
+            cluster_base,
+            infos,
+            positions,
+        }
+    }
+
+    // Drops the least recently used quarter of the entries.
+    fn evict(&mut self) {
+        let mut ages: Vec<u64> = self.entries.values().map(|entry| entry.last_used).collect();
+        let count = (ages.len() / 4).max(1);
+        let (_, &mut threshold, _) = ages.select_nth_unstable(count - 1);
+        self.entries.retain(|_, entry| entry.last_used > threshold);
+    }
+}
+
+fn is_space(codepoint: u32) -> bool {
+    matches!(codepoint, 0x0009 | 0x0020 | 0x3000)
+}
+
+// Splits the buffer into runs of spaces and runs of everything else,
+// without splitting clusters.
+fn segments(buffer: &hb_buffer_t) -> impl Iterator<Item = Range<usize>> + '_ {
+    let infos = &buffer.info[..buffer.len];
+    let mut start = 0;
+    (1..=infos.len()).filter_map(move |i| {
+        let is_boundary = i == infos.len()
+            || (is_space(infos[i].glyph_id) != is_space(infos[i - 1].glyph_id)
+                && infos[i].cluster != infos[i - 1].cluster);
+        if !is_boundary {
+            return None;
+        }
+        let range = start..i;
+        start = i;
+        Some(range)
+    })
+}
+
+// Checks the glyphs at the logical end of `prev` and the logical start of
+// `next` for `UNSAFE_TO_CONCAT`.
+fn is_safe_to_concat(prev: &ShapedSegment, next: &ShapedSegment, direction: Direction) -> bool {
+    let (prev_end, next_start) = if direction.is_backward() {
+        (prev.infos.first(), next.infos.last())
+    } else {
+        (prev.infos.last(), next.infos.first())
+    };
+    !prev_end.is_some_and(GlyphInfo::unsafe_to_concat)
+        && !next_start.is_some_and(GlyphInfo::unsafe_to_concat)
+}
@@ -3,6 +3,8 @@ use core::ops::Range;
 use read_fonts::{
     tables::{
         ankr::Ankr,
+        cff::Cff,
+        cff2::Cff2,
         cmap::{Cmap, CmapSubtable, PlatformId},
         feat::Feat,
         gdef::Gdef,
@@ -10,6 +12,7 @@ use read_fonts::{
         gpos::Gpos,
         gsub::Gsub,
         gvar::Gvar,
+        hhea::Hhea,
         hmtx::Hmtx,
         hvar::Hvar,
         kern::Kern,
@@ -17,13 +20,14 @@ use read_fonts::{
         loca::Loca,
         morx::Morx,
         mvar::Mvar,
+        os2::{Os2, SelectionFlags},
         trak::Trak,
         vmtx::Vmtx,
         vorg::Vorg,
         vvar::Vvar,
     },
     types::Tag,
-    FontData, FontRead, FontRef, TableProvider, TopLevelTable,
+    FontData, FontRead, FontRef, ReadError, TableProvider, TopLevelTable,
 };
 
 // https://docs.microsoft.com/en-us/typography/opentype/spec/cmap#windows-platform-platform-id--3
@@ -41,6 +45,28 @@ const UNICODE_2_0_FULL_ENCODING: u16 = 4;
 //const UNICODE_VARIATION_ENCODING: u16 = 5;
 const UNICODE_FULL_ENCODING: u16 = 6;
 
+/// Returns the horizontal ascender and descender, from `OS/2` if
+/// `USE_TYPO_METRICS` is set and from `hhea` otherwise, falling back to
+/// 0.8 and -0.2 em as HarfBuzz does when neither table has them.
+///
+/// hb_font_t::get_h_extents_with_fallback: <https://github.com/harfbuzz/harfbuzz/blob/88adc6437ef561486a5adf1822410297ef4a852b/src/hb-font.hh>
+fn ascent_descent(os2: Option<&Os2>, hhea: Option<&Hhea>, units_per_em: u16) -> (i16, i16) {
+    match (os2, hhea) {
+        (Some(os2), _)
+            if os2
+                .fs_selection()
+                .contains(SelectionFlags::USE_TYPO_METRICS) =>
+        {
+            (os2.s_typo_ascender(), os2.s_typo_descender())
+        }
+        (_, Some(hhea)) => (hhea.ascender().to_i16(), hhea.descender().to_i16()),
+        _ => {
+            let ascent = (f32::from(units_per_em) * 0.8) as i16;
+            (ascent, ascent.saturating_sub_unsigned(units_per_em))
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct TableRanges {
     pub num_glyphs: u32,
@@ -58,6 +84,8 @@ pub struct TableRanges {
     pub vmtx: TableRange,
     pub vvar: TableRange,
     pub vorg: TableRange,
+    pub cff: TableRange,
+    pub cff2: TableRange,
     pub mvar: TableRange,
     pub cmap: TableRange,
     pub cmap_subtable: Option<SelectedCmapSubtable>,
@@ -66,6 +94,8 @@ pub struct TableRanges {
     pub gsub: TableRange,
     pub gpos: TableRange,
     pub morx: TableRange,
+    #[cfg(feature = "mort")]
+    pub mort: TableRange,
     pub kerx: TableRange,
     pub ankr: TableRange,
     pub kern: TableRange,
@@ -91,13 +121,7 @@ impl TableRanges {
         });
         let os2 = font.os2().ok();
         let hhea = font.hhea().ok();
-        let (ascent, descent) = if let Some(os2) = &os2 {
-            (os2.s_typo_ascender(), os2.s_typo_descender())
-        } else if let Some(hhea) = &hhea {
-            (hhea.ascender().to_i16(), hhea.descender().to_i16())
-        } else {
-            (0, 0) // TODO
-        };
+        let (ascent, descent) = ascent_descent(os2.as_ref(), hhea.as_ref(), units_per_em);
         let num_h_metrics = hhea
             .map(|hhea| hhea.number_of_h_metrics())
             .unwrap_or_default();
@@ -114,6 +138,8 @@ impl TableRanges {
         let vmtx = offset(Vmtx::TAG);
         let vvar = offset(Vvar::TAG);
         let vorg = offset(Vorg::TAG);
+        let cff = offset(Cff::TAG);
+        let cff2 = offset(Cff2::TAG);
         let mvar = offset(Mvar::TAG);
         let cmap = offset(Cmap::TAG);
         let cmap_table: Option<Cmap> = cmap.resolve_table(font);
@@ -140,6 +166,8 @@ impl TableRanges {
         let gsub = offset(Gsub::TAG);
         let gpos = offset(Gpos::TAG);
         let morx = offset(Morx::TAG);
+        #[cfg(feature = "mort")]
+        let mort = offset(Tag::new(b"mort"));
         let kerx = offset(Kerx::TAG);
         let ankr = offset(Ankr::TAG);
         let kern = offset(Kern::TAG);
@@ -161,6 +189,8 @@ impl TableRanges {
             vmtx,
             vvar,
             vorg,
+            cff,
+            cff2,
             mvar,
             cmap,
             cmap_subtable,
@@ -169,6 +199,8 @@ impl TableRanges {
             gsub,
             gpos,
             morx,
+            #[cfg(feature = "mort")]
+            mort,
             kerx,
             ankr,
             kern,
@@ -187,13 +219,7 @@ impl TableRanges {
         });
         let os2 = font.os2().ok();
         let hhea = font.hhea().ok();
-        let (ascent, descent) = if let Some(os2) = &os2 {
-            (os2.s_typo_ascender(), os2.s_typo_descender())
-        } else if let Some(hhea) = &hhea {
-            (hhea.ascender().to_i16(), hhea.descender().to_i16())
-        } else {
-            (0, 0) // TODO
-        };
+        let (ascent, descent) = ascent_descent(os2.as_ref(), hhea.as_ref(), units_per_em);
         let num_h_metrics = hhea
             .map(|hhea| hhea.number_of_h_metrics())
             .unwrap_or_default();
@@ -217,6 +243,8 @@ impl TableRanges {
             vmtx: TableRange::default(),
             vvar: TableRange::default(),
             vorg: TableRange::default(),
+            cff: TableRange::default(),
+            cff2: TableRange::default(),
             mvar: TableRange::default(),
             cmap: TableRange::default(),
             cmap_subtable: None,
@@ -225,6 +253,8 @@ impl TableRanges {
             gsub: TableRange::default(),
             gpos: TableRange::default(),
             morx: TableRange::default(),
+            #[cfg(feature = "mort")]
+            mort: TableRange::default(),
             kerx: TableRange::default(),
             ankr: TableRange::default(),
             kern: TableRange::default(),
@@ -234,6 +264,20 @@ impl TableRanges {
     }
 }
 
+/// Returns true if the font has a `cmap`, `GDEF`, `GSUB` or `GPOS` table
+/// that can't be read.
+///
+/// Missing tables are fine; the shaper is expected to work without them.
+pub fn has_broken_layout_tables<'a>(font: &impl TableProvider<'a>) -> bool {
+    fn is_broken<T>(table: Result<T, ReadError>) -> bool {
+        matches!(table, Err(err) if !matches!(err, ReadError::TableIsMissing(_)))
+    }
+    is_broken(font.cmap())
+        || is_broken(font.gdef())
+        || is_broken(font.gsub())
+        || is_broken(font.gpos())
+}
+
 #[derive(Copy, Clone, Default, Debug)]
 pub struct TableRange(u32, u32);
 
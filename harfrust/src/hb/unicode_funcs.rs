@@ -0,0 +1,149 @@
+//! Customizable Unicode character properties.
+
+use crate::unicode::{self, CharExt, Codepoint, GeneralCategory};
+use crate::Script;
+
+/// Customizable Unicode property callback surface.
+///
+/// By default, shaping uses the Unicode Character Database compiled into
+/// HarfRust (or the ICU4X data when the `icu` feature is enabled). Pass an
+/// implementation of this trait to
+/// [`ShapeOptions::unicode_funcs`](crate::ShapeOptions::unicode_funcs) to
+/// shape with properties from a different Unicode version, for example to
+/// stay consistent with an application's own line breaker.
+///
+/// Every method defaults to the built-in data, so implementations only need
+/// to override the properties they provide.
+pub trait UnicodeFuncs {
+    /// Returns the `Script` property of a character.
+    fn script(&self, c: u32) -> Script {
+        unicode::script_for(c)
+    }
+
+    /// Returns the `General_Category` property of a character.
+    fn general_category(&self, c: u32) -> GeneralCategory {
+        unicode::general_category_for(c)
+    }
+
+    /// Returns the `Canonical_Combining_Class` property of a character.
+    fn combining_class(&self, c: u32) -> u8 {
+        unicode::combining_class_for(c)
+    }
+
+    /// Returns the `Bidi_Mirroring_Glyph` property of a character, if any.
+    fn mirroring(&self, c: u32) -> Option<u32> {
+        unicode::mirroring_for(c)
+    }
+
+    /// Returns the canonical composition of a pair of characters, if any.
+    fn compose(&self, a: u32, b: u32) -> Option<u32> {
+        unicode::compose(a, b)
+    }
+
+    /// Returns the canonical decomposition of a character, if any.
+    ///
+    /// Singleton decompositions are returned with `0` as the second
+    /// character.
+    fn decompose(&self, ab: u32) -> Option<(u32, u32)> {
+        unicode::decompose(ab)
+    }
+
+    /// Returns `true` if the character has the `Extended_Pictographic`
+    /// property.
+    fn is_emoji_extended_pictographic(&self, c: u32) -> bool {
+        c.is_emoji_extended_pictographic()
+    }
+
+    /// Returns `true` if the character should be treated as
+    /// `Default_Ignorable_Code_Point`.
+    ///
+    /// The built-in implementation leaves out a few ignorable characters,
+    /// such as the Hangul fillers, that fonts expect to render as regular
+    /// spacing glyphs.
+    fn is_default_ignorable(&self, c: u32) -> bool {
+        c.is_default_ignorable()
+    }
+}
+
+/// The Unicode properties built into HarfRust.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BuiltinUnicodeFuncs;
+
+impl UnicodeFuncs for BuiltinUnicodeFuncs {}
+
+/// Routes Unicode property queries to the user provided functions or,
+/// without them, directly to the built-in data.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct UnicodeFuncsDispatch<'u> {
+    funcs: Option<&'u (dyn UnicodeFuncs + 'u)>,
+}
+
+impl<'u> UnicodeFuncsDispatch<'u> {
+    pub(crate) fn new(funcs: Option<&'u (dyn UnicodeFuncs + 'u)>) -> Self {
+        Self { funcs }
+    }
+
+    #[inline]
+    pub(crate) fn script(self, c: Codepoint) -> Script {
+        match self.funcs {
+            Some(funcs) => funcs.script(c),
+            None => c.script(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn general_category(self, c: Codepoint) -> GeneralCategory {
+        match self.funcs {
+            Some(funcs) => funcs.general_category(c),
+            None => c.general_category(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn modified_combining_class(self, c: Codepoint) -> u8 {
+        match self.funcs {
+            Some(funcs) => unicode::modify_combining_class(c, funcs.combining_class(c)),
+            None => c.modified_combining_class(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn mirroring(self, c: Codepoint) -> Option<Codepoint> {
+        match self.funcs {
+            Some(funcs) => funcs.mirroring(c),
+            None => c.mirroring(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn compose(self, a: Codepoint, b: Codepoint) -> Option<Codepoint> {
+        match self.funcs {
+            Some(funcs) => funcs.compose(a, b),
+            None => unicode::compose(a, b),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn decompose(self, ab: Codepoint) -> Option<(Codepoint, Codepoint)> {
+        match self.funcs {
+            Some(funcs) => funcs.decompose(ab),
+            None => unicode::decompose(ab),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_emoji_extended_pictographic(self, c: Codepoint) -> bool {
+        match self.funcs {
+            Some(funcs) => funcs.is_emoji_extended_pictographic(c),
+            None => c.is_emoji_extended_pictographic(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_default_ignorable(self, c: Codepoint) -> bool {
+        match self.funcs {
+            Some(funcs) => funcs.is_default_ignorable(c),
+            None => c.is_default_ignorable(),
+        }
+    }
+}
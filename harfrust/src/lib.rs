@@ -22,7 +22,7 @@ mod digest_u32_set;
 pub(crate) type U32Set = digest_u32_set::DigestU32Set;
 
 pub use read_fonts::{
-    types::{GlyphId, Tag},
+    types::{GlyphId, NameId, Tag},
     FontRef,
 };
 
@@ -45,13 +45,32 @@ pub mod font {
     pub(crate) use read_fonts::model::*;
 }
 
-pub use hb::buffer::{GlyphBuffer, GlyphFlags, GlyphInfo, GlyphPosition, UnicodeBuffer};
-pub use hb::common::{script, Direction, Feature, Language, Script, Variation};
+pub use hb::aat::layout_feat_table::{AatFeatureSelector, AatFeatureType};
+pub use hb::buffer::{
+    GlyphBuffer, GlyphFlags, GlyphInfo, GlyphPosition, GlyphPositionF32, UnicodeBuffer,
+};
+pub use hb::clusters::GlyphCluster;
+pub use hb::common::{script, AatFeature, Direction, Feature, Language, Script, Variation};
 pub use hb::face::{
-    hb_font_t as Shaper, GlyphExtents, ShapeOptions, ShaperBuilder, ShaperData, ShaperInstance,
+    hb_font_t as Shaper, GlyphExtents, ShapeEngine, ShapeOptions, ShaperBuilder, ShaperData,
+    ShaperInstance,
 };
+pub use hb::justify::Justification;
 
-pub use hb::ot_shape_plan::{hb_ot_shape_plan_t as ShapePlan, ShapePlanKey};
+pub use hb::ot_feature_names::{FeatureNameIds, FeatureNames};
+pub use hb::ot_metrics::MetricsTag;
+pub use hb::ot_shape_plan::{
+    hb_ot_shape_plan_t as ShapePlan, AatPreference, LayoutPreference, NormalizationMode,
+    PlanLookup, ShapePlanKey, ShaperKind,
+};
+#[cfg(feature = "profile")]
+pub use hb::profile::{LookupCounts, LookupProfile, LookupStats};
+pub use hb::script_shaper::{
+    BuiltinShaper, Normalizer, ScriptShaper, ShapePlanner, ShaperBuffer, ShaperRegistry,
+};
+pub use hb::shape_cache::ShapeCache;
+pub use hb::unicode_funcs::{BuiltinUnicodeFuncs, UnicodeFuncs};
+pub use unicode::GeneralCategory;
 
 /// Type alias for a normalized variation coordinate.
 pub type NormalizedCoord = read_fonts::types::F2Dot14;
@@ -40,8 +40,11 @@ pub mod hb_unicode_funcs_t {
 
 /// Data type for the "General_Category" (gc) property from the Unicode
 /// Character Database.
+///
+/// The categories are the associated constants; other values can't be
+/// constructed.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub struct GeneralCategory(pub u8);
+pub struct GeneralCategory(pub(crate) u8);
 
 #[allow(unused)]
 impl GeneralCategory {
@@ -108,6 +111,7 @@ impl GeneralCategory {
 }
 
 impl GeneralCategory {
+    /// Returns `true` for the mark categories (`Mc`, `Me` and `Mn`).
     pub fn is_mark(&self) -> bool {
         matches!(
             *self,
@@ -115,6 +119,7 @@ impl GeneralCategory {
         )
     }
 
+    /// Returns `true` for the letter categories (`Ll`, `Lm`, `Lo`, `Lt` and `Lu`).
     pub fn is_letter(&self) -> bool {
         matches!(
             *self,
@@ -417,6 +422,27 @@ static MODIFIED_COMBINING_CLASS: &[u8; 256] = &[
     combining_class::Invalid,
 ];
 
+/// Maps the canonical combining class `k` of `u` to the class used for
+/// mark reordering.
+pub(crate) fn modify_combining_class(u: Codepoint, k: u8) -> u8 {
+    // Reorder SAKOT to ensure it comes after any tone marks.
+    if u == 0x1A60 {
+        return 254;
+    }
+
+    // Reorder PADMA to ensure it comes after any vowel marks.
+    if u == 0x0FC6 {
+        return 254;
+    }
+
+    // Reorder TSA -PHRU to reorder before U+0F74
+    if u == 0x0F39 {
+        return 127;
+    }
+
+    MODIFIED_COMBINING_CLASS[k as usize]
+}
+
 pub trait CharExt {
     fn script(self) -> Script;
     fn general_category(self) -> GeneralCategory;
@@ -473,26 +499,7 @@ impl CharExt for Codepoint {
     }
 
     fn modified_combining_class(self) -> u8 {
-        let u = self;
-
-        // Reorder SAKOT to ensure it comes after any tone marks.
-        if u == 0x1A60 {
-            return 254;
-        }
-
-        // Reorder PADMA to ensure it comes after any vowel marks.
-        if u == 0x0FC6 {
-            return 254;
-        }
-
-        // Reorder TSA -PHRU to reorder before U+0F74
-        if u == 0x0F39 {
-            return 127;
-        }
-
-        let k = u.combining_class();
-
-        MODIFIED_COMBINING_CLASS[k as usize]
+        modify_combining_class(self, self.combining_class())
     }
 
     fn is_emoji_extended_pictographic(self) -> bool {
@@ -0,0 +1,226 @@
+use std::fs;
+use std::path::PathBuf;
+
+use harfrust::{
+    AatFeature, AatFeatureSelector, AatFeatureType, FontRef, ShapeOptions, ShaperData, Tag,
+    UnicodeBuffer,
+};
+use read_fonts::types::NameId;
+
+/// A raw feature type with no OpenType equivalent.
+const FEATURE_TYPE: u16 = 200;
+/// The selector turning the feature on; the next one turns it off.
+const SELECTOR_ON: u16 = 2;
+/// An exclusive feature type declared in the `feat` table.
+const EXCLUSIVE_TYPE: u16 = 300;
+
+fn morx_font_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join("text-rendering-tests")
+        .join("TestMORXOne.ttf")
+}
+
+fn words(values: &[u16]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect()
+}
+
+/// Returns the font with `table` added, or replacing the table with the
+/// same tag.
+fn with_table(font: &[u8], tag: &[u8; 4], table: Vec<u8>) -> Vec<u8> {
+    let read_u32 =
+        |offset: usize| u32::from_be_bytes(font[offset..offset + 4].try_into().unwrap()) as usize;
+    let num_tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+    let mut tables: Vec<([u8; 4], Vec<u8>)> = (0..num_tables)
+        .map(|i| 12 + 16 * i)
+        .map(|record| {
+            let (offset, len) = (read_u32(record + 8), read_u32(record + 12));
+            (
+                font[record..record + 4].try_into().unwrap(),
+                font[offset..offset + len].to_vec(),
+            )
+        })
+        .filter(|(existing, _)| existing != tag)
+        .collect();
+    tables.push((*tag, table));
+    tables.sort_by_key(|(tag, _)| *tag);
+
+    let mut data = font[..4].to_vec();
+    data.extend(words(&[tables.len() as u16, 0, 0, 0]));
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, table) in &tables {
+        data.extend(tag);
+        data.extend(0u32.to_be_bytes());
+        data.extend((offset as u32).to_be_bytes());
+        data.extend((table.len() as u32).to_be_bytes());
+        offset += table.len().next_multiple_of(4);
+    }
+    for (_, table) in &tables {
+        data.extend(table);
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+    data
+}
+
+/// Returns the data of `TestMORXOne.ttf` with its substitution moved to a
+/// raw feature that is off by default and declared in a new `feat` table,
+/// as a non-exclusive feature named "TestMORXOne" with one setting named
+/// "ABC". The `feat` table also declares an exclusive feature, with no
+/// effect, named "Regular", with selectors 0 and 1 and 1 as the default.
+fn font_with_raw_feature() -> Vec<u8> {
+    let font = fs::read(morx_font_path()).expect("failed to read test font");
+    let font_ref = FontRef::new(&font).expect("failed to parse test font");
+    let mut morx = font_ref
+        .table_data(Tag::new(b"morx"))
+        .expect("no morx table")
+        .as_bytes()
+        .to_vec();
+    // The chain's default flags, then its first feature entry.
+    morx[8..12].copy_from_slice(&0u32.to_be_bytes());
+    morx[24..28].copy_from_slice(&words(&[FEATURE_TYPE, SELECTOR_ON]));
+
+    // Header, two feature names, and their settings.
+    let mut feat = 0x0001_0000u32.to_be_bytes().to_vec();
+    feat.extend(words(&[2, 0, 0, 0]));
+    feat.extend(words(&[FEATURE_TYPE, 1, 0, 36, 0, 1]));
+    feat.extend(words(&[EXCLUSIVE_TYPE, 2, 0, 40, 0xC001, 2]));
+    feat.extend(words(&[SELECTOR_ON, 19]));
+    feat.extend(words(&[0, 1, 1, 2]));
+
+    let font = with_table(&font, b"morx", morx);
+    with_table(&font, b"feat", feat)
+}
+
+fn shape_glyphs(font_data: &[u8], aat_features: &[AatFeature]) -> Vec<u32> {
+    let font = FontRef::new(font_data).expect("failed to parse test font");
+    let data = ShaperData::new(&font);
+    let shaper = data.shaper(&font).build();
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str("ABC");
+    buffer.guess_segment_properties();
+    shaper
+        .shape(buffer, ShapeOptions::new().aat_features(aat_features))
+        .glyph_infos()
+        .iter()
+        .map(|info| info.glyph_id)
+        .collect()
+}
+
+#[test]
+fn raw_aat_feature_enables_substitution() {
+    let original = shape_glyphs(&fs::read(morx_font_path()).unwrap(), &[]);
+    let font = font_with_raw_feature();
+    let plain = shape_glyphs(&font, &[]);
+    assert_ne!(plain, original);
+    assert_eq!(
+        shape_glyphs(&font, &[AatFeature::new(FEATURE_TYPE, SELECTOR_ON, ..)]),
+        original
+    );
+    // The odd selector after a non-exclusive setting turns it off.
+    assert_eq!(
+        shape_glyphs(&font, &[AatFeature::new(FEATURE_TYPE, SELECTOR_ON + 1, ..)]),
+        plain
+    );
+}
+
+#[test]
+fn raw_aat_feature_applies_to_its_range() {
+    let original = shape_glyphs(&fs::read(morx_font_path()).unwrap(), &[]);
+    let font = font_with_raw_feature();
+    let plain = shape_glyphs(&font, &[]);
+    let partial = shape_glyphs(&font, &[AatFeature::new(FEATURE_TYPE, SELECTOR_ON, 2..)]);
+    assert_eq!(partial[..2], plain[..2]);
+    assert_eq!(partial[2], original[2]);
+}
+
+#[test]
+fn undeclared_aat_features_are_rejected() {
+    let font = font_with_raw_feature();
+    let plain = shape_glyphs(&font, &[]);
+    // A selector the feat table doesn't declare.
+    assert_eq!(
+        shape_glyphs(&font, &[AatFeature::new(FEATURE_TYPE, SELECTOR_ON + 2, ..)]),
+        plain
+    );
+    // A feature type it doesn't declare.
+    assert_eq!(
+        shape_glyphs(&font, &[AatFeature::new(FEATURE_TYPE + 1, SELECTOR_ON, ..)]),
+        plain
+    );
+}
+
+fn with_shaper<T>(font_data: &[u8], f: impl FnOnce(&harfrust::Shaper) -> T) -> T {
+    let font = FontRef::new(font_data).expect("failed to parse test font");
+    let data = ShaperData::new(&font);
+    let shaper = data.shaper(&font).build();
+    f(&shaper)
+}
+
+#[test]
+fn aat_feature_types_list_feat_table() {
+    let types = with_shaper(&font_with_raw_feature(), |shaper| {
+        shaper.aat_feature_types()
+    });
+    assert_eq!(
+        types,
+        [
+            AatFeatureType {
+                feature_type: FEATURE_TYPE,
+                name_id: NameId::new(1),
+                name: Some("TestMORXOne".into()),
+                is_exclusive: false,
+                default_selector: None,
+                selectors: vec![AatFeatureSelector {
+                    name_id: NameId::new(19),
+                    name: Some("ABC".into()),
+                    enable: SELECTOR_ON,
+                    disable: SELECTOR_ON + 1,
+                }],
+            },
+            AatFeatureType {
+                feature_type: EXCLUSIVE_TYPE,
+                name_id: NameId::new(2),
+                name: Some("Regular".into()),
+                is_exclusive: true,
+                default_selector: Some(1),
+                selectors: vec![
+                    AatFeatureSelector {
+                        name_id: NameId::new(1),
+                        name: Some("TestMORXOne".into()),
+                        enable: 0,
+                        disable: 1,
+                    },
+                    AatFeatureSelector {
+                        name_id: NameId::new(2),
+                        name: Some("Regular".into()),
+                        enable: 1,
+                        disable: 1,
+                    },
+                ],
+            },
+        ]
+    );
+}
+
+#[test]
+fn aat_feature_types_are_empty_without_feat() {
+    let font = fs::read(morx_font_path()).unwrap();
+    assert!(with_shaper(&font, |shaper| shaper.aat_feature_types()).is_empty());
+}
+
+#[test]
+fn name_resolves_name_table_entries() {
+    let font = fs::read(morx_font_path()).unwrap();
+    with_shaper(&font, |shaper| {
+        assert_eq!(shaper.name(NameId::new(1)).as_deref(), Some("TestMORXOne"));
+        assert_eq!(
+            shaper.name(NameId::new(6)).as_deref(),
+            Some("TestMORXOne-Regular")
+        );
+        assert_eq!(shaper.name(NameId::new(3)), None);
+    });
+}
@@ -0,0 +1,517 @@
+#![cfg(feature = "beyond_64k")]
+
+use harfrust::{
+    Feature, FontRef, GlyphBuffer, GlyphPosition, ShapeOptions, ShaperData, Tag, UnicodeBuffer,
+};
+
+/// The font whose `cmap`, `GDEF`, `GSUB` and `GPOS` tables are replaced.
+const FONT: &[u8] = include_bytes!("fonts/text-rendering-tests/TestGPOSOne.ttf");
+
+// Glyphs mapped from "a", acute, "b", grave, "c" and "d" by the `cmap`.
+const A: u32 = 70000;
+const ACUTE: u32 = 70001;
+const B: u32 = 70002;
+const GRAVE: u32 = 70003;
+const C: u32 = 70004;
+const D: u32 = 70005;
+// Glyphs only reachable through `GSUB`.
+const A_ALT: u32 = 70010;
+const B_ALT: u32 = 70011;
+const D_ALT: u32 = 70013;
+const C_D: u32 = 70020;
+
+const X_ADVANCE: u16 = 0x0004;
+const IGNORE_MARKS: u16 = 0x0008;
+
+fn words(values: &[u16]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect()
+}
+
+fn u24s(values: &[u32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_be_bytes()[1..].to_vec())
+        .collect()
+}
+
+/// Returns the font with the given tables added or replaced.
+fn with_tables(font: &[u8], new_tables: Vec<([u8; 4], Vec<u8>)>) -> Vec<u8> {
+    let read_u32 =
+        |offset: usize| u32::from_be_bytes(font[offset..offset + 4].try_into().unwrap()) as usize;
+    let num_tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+    let mut tables: Vec<([u8; 4], Vec<u8>)> = (0..num_tables)
+        .map(|i| 12 + 16 * i)
+        .map(|record| {
+            let (offset, len) = (read_u32(record + 8), read_u32(record + 12));
+            (
+                font[record..record + 4].try_into().unwrap(),
+                font[offset..offset + len].to_vec(),
+            )
+        })
+        .filter(|(tag, _)| new_tables.iter().all(|(new_tag, _)| new_tag != tag))
+        .collect();
+    tables.extend(new_tables);
+    tables.sort_by_key(|(tag, _)| *tag);
+
+    let mut data = font[..4].to_vec();
+    data.extend(words(&[tables.len() as u16, 0, 0, 0]));
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, table) in &tables {
+        data.extend(tag);
+        data.extend(0u32.to_be_bytes());
+        data.extend((offset as u32).to_be_bytes());
+        data.extend((table.len() as u32).to_be_bytes());
+        offset += table.len().next_multiple_of(4);
+    }
+    for (_, table) in &tables {
+        data.extend(table);
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+    data
+}
+
+/// A `cmap` table with a single format 12 subtable.
+fn cmap() -> Vec<u8> {
+    let mappings = [
+        ('a', A),
+        ('b', B),
+        ('c', C),
+        ('d', D),
+        ('\u{0300}', GRAVE),
+        ('\u{0301}', ACUTE),
+    ];
+    let mut data = words(&[0, 1, 3, 10, 0, 12, 12, 0]);
+    data.extend((16 + 12 * mappings.len() as u32).to_be_bytes());
+    data.extend(0u32.to_be_bytes());
+    data.extend((mappings.len() as u32).to_be_bytes());
+    for (c, gid) in mappings {
+        data.extend((c as u32).to_be_bytes());
+        data.extend((c as u32).to_be_bytes());
+        data.extend(gid.to_be_bytes());
+    }
+    data
+}
+
+/// A `GDEF` table of the given major version whose glyph classes are in a
+/// ClassDef format 4 table.
+fn gdef(version: u16) -> Vec<u8> {
+    let ranges = [
+        (A, A, 1),
+        (ACUTE, ACUTE, 3),
+        (B, B, 1),
+        (GRAVE, GRAVE, 3),
+        (C, A_ALT, 1),
+        (C_D, C_D, 2),
+    ];
+    let mut data = if version == 1 {
+        words(&[1, 0, 12, 0, 0, 0])
+    } else {
+        [words(&[2, 0]), u24s(&[23, 0, 0, 0, 0]), vec![0; 4]].concat()
+    };
+    data.extend(words(&[4]));
+    data.extend(u24s(&[ranges.len() as u32]));
+    for (first, last, class) in ranges {
+        data.extend(u24s(&[first, last]));
+        data.extend(words(&[class]));
+    }
+    data
+}
+
+/// A `GSUB` or `GPOS` table of the given major version with a `DFLT` script
+/// whose default language system has every feature, and lookups with a
+/// single subtable each.
+///
+/// The offsets to the script, feature and lookup lists and to the lookups
+/// are 16-bit in version 1 and 24-bit in version 2.
+fn layout_table(
+    version: u16,
+    features: &[(&[u8; 4], &[u16])],
+    lookups: &[(u16, u16, Vec<u8>)],
+) -> Vec<u8> {
+    let offset_len = if version == 1 { 2 } else { 3 };
+    let offset = |value: usize| (value as u32).to_be_bytes()[4 - offset_len..].to_vec();
+    let mut data = words(&[version, 0]);
+    data.resize(4 + 3 * offset_len, 0);
+    if version == 2 {
+        // No FeatureVariations.
+        data.extend(0u32.to_be_bytes());
+    }
+    let set_list_offset = |data: &mut Vec<u8>, index: usize| {
+        let pos = 4 + index * offset_len;
+        let value = offset(data.len());
+        data[pos..pos + offset_len].copy_from_slice(&value);
+    };
+
+    set_list_offset(&mut data, 0);
+    data.extend(words(&[1]));
+    data.extend(b"DFLT");
+    data.extend(words(&[8, 4, 0, 0, 0xFFFF, features.len() as u16]));
+    data.extend(words(&(0..features.len() as u16).collect::<Vec<_>>()));
+
+    set_list_offset(&mut data, 1);
+    data.extend(words(&[features.len() as u16]));
+    let mut feature_offset = 2 + 6 * features.len() as u16;
+    for (tag, lookup_indices) in features {
+        data.extend(*tag);
+        data.extend(words(&[feature_offset]));
+        feature_offset += 4 + 2 * lookup_indices.len() as u16;
+    }
+    for (_, lookup_indices) in features {
+        data.extend(words(&[0, lookup_indices.len() as u16]));
+        data.extend(words(lookup_indices));
+    }
+
+    set_list_offset(&mut data, 2);
+    data.extend(words(&[lookups.len() as u16]));
+    let mut lookup_offset = 2 + offset_len * lookups.len();
+    for (_, _, subtable) in lookups {
+        data.extend(offset(lookup_offset));
+        lookup_offset += 8 + subtable.len();
+    }
+    for (lookup_type, flags, subtable) in lookups {
+        data.extend(words(&[*lookup_type, *flags, 1, 8]));
+        data.extend(subtable);
+    }
+    data
+}
+
+/// A Coverage format 3 table, 4 + 3 * `glyphs.len()` bytes long.
+fn coverage3(glyphs: &[u32]) -> Vec<u8> {
+    let mut data = words(&[3, glyphs.len() as u16]);
+    data.extend(u24s(glyphs));
+    data
+}
+
+/// Substitutes "a" by delta and ligates "c" and "d".
+fn gsub(version: u16) -> Vec<u8> {
+    let single = [words(&[3]), u24s(&[8, A_ALT - A]), coverage3(&[A])].concat();
+    let ligature = [
+        words(&[2]),
+        u24s(&[10]),
+        words(&[1]),
+        u24s(&[17]),
+        coverage3(&[C]),
+        // Ligature set with a single ligature.
+        words(&[1, 4]),
+        u24s(&[C_D]),
+        words(&[2]),
+        u24s(&[D]),
+    ]
+    .concat();
+    layout_table(
+        version,
+        &[(b"ccmp", &[0]), (b"liga", &[1])],
+        &[(1, 0, single), (4, 0, ligature)],
+    )
+}
+
+/// A mark attachment subtable, for a single mark glyph with its anchor at
+/// (20, 0) and a single glyph to attach it to, with `attach_array` holding
+/// the anchors of the latter.
+fn mark_attachment(mark: u32, attach_to: u32, attach_array: Vec<u8>) -> Vec<u8> {
+    [
+        words(&[2]),
+        u24s(&[16, 23]),
+        words(&[1]),
+        u24s(&[30, 42]),
+        coverage3(&[mark]),
+        coverage3(&[attach_to]),
+        words(&[1, 0, 6, 1, 20, 0]),
+        attach_array,
+    ]
+    .concat()
+}
+
+/// Kerns "a.alt b" by -50 with glyph pairs and "b a.alt" by -30 with glyph
+/// classes, and attaches acute to "b" and to the "c_d" ligature and grave
+/// to acute.
+fn gpos(version: u16) -> Vec<u8> {
+    let pair_glyphs = [
+        words(&[3]),
+        u24s(&[14]),
+        words(&[X_ADVANCE, 0, 1]),
+        u24s(&[21]),
+        coverage3(&[A_ALT]),
+        words(&[1]),
+        u24s(&[B]),
+        words(&[-50i16 as u16]),
+    ]
+    .concat();
+    let pair_classes = [
+        words(&[4]),
+        u24s(&[27]),
+        words(&[X_ADVANCE, 0]),
+        u24s(&[39, 49]),
+        words(&[2, 2, 0, 0, 0, -30i16 as u16]),
+        // Coverage format 4 with a single range.
+        words(&[4, 1]),
+        u24s(&[B, B]),
+        words(&[0]),
+        // ClassDef format 3 for the first glyph.
+        words(&[3]),
+        u24s(&[B, 1]),
+        words(&[1]),
+        // ClassDef format 4 for the second glyph.
+        words(&[4]),
+        u24s(&[1, A_ALT, A_ALT]),
+        words(&[1]),
+    ]
+    .concat();
+    let mark_to_base = mark_attachment(ACUTE, B, words(&[1, 4, 1, 300, 500]));
+    let mark_to_mark = mark_attachment(GRAVE, ACUTE, words(&[1, 4, 1, 40, 200]));
+    // A ligature attachment with two components.
+    let mark_to_ligature = mark_attachment(
+        ACUTE,
+        C_D,
+        words(&[1, 4, 2, 6, 12, 1, 100, 400, 1, 400, 400]),
+    );
+    layout_table(
+        version,
+        &[(b"kern", &[0, 1]), (b"mark", &[2, 4]), (b"mkmk", &[3])],
+        &[
+            (2, 0, pair_glyphs),
+            (2, IGNORE_MARKS, pair_classes),
+            (4, 0, mark_to_base),
+            (6, 0, mark_to_mark),
+            (5, 0, mark_to_ligature),
+        ],
+    )
+}
+
+/// Returns the font with `GDEF`, `GSUB` and `GPOS` tables of the given
+/// major version.
+fn font(version: u16) -> Vec<u8> {
+    with_tables(
+        FONT,
+        vec![
+            (*b"GDEF", gdef(version)),
+            (*b"GPOS", gpos(version)),
+            (*b"GSUB", gsub(version)),
+            (*b"cmap", cmap()),
+        ],
+    )
+}
+
+fn shape(font_data: &[u8], text: &str, features: &[Feature]) -> GlyphBuffer {
+    let font = FontRef::new(font_data).unwrap();
+    let data = ShaperData::new(&font);
+    let shaper = data.shaper(&font).build();
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    shaper.shape(buffer, ShapeOptions::new().features(features))
+}
+
+/// Returns the glyph ids and positions, and the positions without the
+/// `GPOS` features.
+fn shape_with_and_without_gpos(text: &str) -> (Vec<u32>, Vec<GlyphPosition>, Vec<GlyphPosition>) {
+    let font = font(1);
+    let glyphs = shape(&font, text, &[]);
+    let no_gpos = [b"kern", b"mark", b"mkmk"].map(|tag| Feature::new(Tag::new(tag), 0, ..));
+    let unpositioned = shape(&font, text, &no_gpos);
+    (
+        glyphs
+            .glyph_infos()
+            .iter()
+            .map(|info| info.glyph_id)
+            .collect(),
+        glyphs.glyph_positions().to_vec(),
+        unpositioned.glyph_positions().to_vec(),
+    )
+}
+
+#[test]
+fn pairs_and_mark_to_base_and_mark() {
+    let (glyphs, pos, unpositioned) = shape_with_and_without_gpos("ab\u{0301}\u{0300}a");
+    assert_eq!(glyphs, [A_ALT, B, ACUTE, GRAVE, A_ALT]);
+    assert_eq!(pos[0].x_advance, unpositioned[0].x_advance - 50);
+    assert_eq!(pos[1].x_advance, unpositioned[1].x_advance - 30);
+    assert_eq!(pos[4].x_advance, unpositioned[4].x_advance);
+    // Marks are offset back over the advance of their base, and grave
+    // is offset by (20, 200) from acute.
+    let b_advance = pos[1].x_advance;
+    assert_eq!((pos[2].x_offset, pos[2].y_offset), (280 - b_advance, 500));
+    assert_eq!((pos[3].x_offset, pos[3].y_offset), (300 - b_advance, 700));
+}
+
+#[test]
+fn ligature_and_mark_to_ligature() {
+    let (glyphs, pos, unpositioned) = shape_with_and_without_gpos("cd\u{0301}");
+    assert_eq!(glyphs, [C_D, ACUTE]);
+    assert_eq!(pos[0].x_advance, unpositioned[0].x_advance);
+    // The acute follows "d", so it attaches to the second component.
+    assert_eq!(
+        (pos[1].x_offset, pos[1].y_offset),
+        (380 - pos[0].x_advance, 400)
+    );
+}
+
+#[test]
+fn version_2_headers() {
+    let (v1, v2) = (font(1), font(2));
+    for text in ["ab\u{0301}\u{0300}a", "cd\u{0301}"] {
+        let expected = shape(&v1, text, &[]);
+        let glyphs = shape(&v2, text, &[]);
+        let summary = |glyphs: &GlyphBuffer| {
+            glyphs
+                .glyph_infos()
+                .iter()
+                .zip(glyphs.glyph_positions())
+                .map(|(info, pos)| {
+                    (
+                        info.glyph_id,
+                        info.cluster,
+                        [pos.x_advance, pos.y_advance, pos.x_offset, pos.y_offset],
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(summary(&glyphs), summary(&expected), "{text}");
+    }
+}
+
+/// A ClassDef format 3 table giving classes to glyphs from `start`.
+fn class_def3(start: u32, classes: &[u16]) -> Vec<u8> {
+    [
+        words(&[3]),
+        u24s(&[start, classes.len() as u32]),
+        words(classes),
+    ]
+    .concat()
+}
+
+/// A context subtable of the given format with 24-bit offsets to `tables`,
+/// which follow in order, and to `set_count` rule sets, all null except for
+/// the one at `set_index`, which has the single rule `rule`.
+fn context_subtable(
+    format: u16,
+    tables: &[Vec<u8>],
+    set_count: usize,
+    set_index: usize,
+    rule: Vec<u8>,
+) -> Vec<u8> {
+    let mut offset = 4 + 3 * (tables.len() + set_count);
+    let mut data = words(&[format]);
+    for table in tables {
+        data.extend(u24s(&[offset as u32]));
+        offset += table.len();
+    }
+    data.extend(words(&[set_count as u16]));
+    for i in 0..set_count {
+        data.extend(u24s(&[if i == set_index { offset as u32 } else { 0 }]));
+    }
+    data.extend(tables.concat());
+    data.extend(words(&[1, 4]));
+    data.extend(rule);
+    data
+}
+
+/// The index of the lookup substituting "b" and "d" by their alternates.
+const ALT_LOOKUP: u16 = 4;
+
+/// A context rule applying the alternate lookup at `seq_index`.
+fn rule(input: &[u32], seq_index: u16) -> Vec<u8> {
+    [
+        words(&[input.len() as u16 + 1, 1]),
+        u24s(input),
+        words(&[seq_index, ALT_LOOKUP]),
+    ]
+    .concat()
+}
+
+/// A chained context rule applying the alternate lookup to the first input
+/// glyph.
+fn chain_rule(backtrack: &[u32], input: &[u32], lookahead: &[u32]) -> Vec<u8> {
+    [
+        words(&[backtrack.len() as u16]),
+        u24s(backtrack),
+        words(&[input.len() as u16 + 1]),
+        u24s(input),
+        words(&[lookahead.len() as u16]),
+        u24s(lookahead),
+        words(&[1, 0, ALT_LOOKUP]),
+    ]
+    .concat()
+}
+
+/// Substitutes "b" in "ab" and "d" in "cd" with context formats 4 and 5,
+/// and "b" in "dbc" and "d" in "ada" with chained context formats 4 and 5.
+fn context_gsub() -> Vec<u8> {
+    let context4 = context_subtable(4, &[coverage3(&[A])], 1, 0, rule(&[B], 1));
+    // "c" is in class 1 and "d" in class 2.
+    let context5 = context_subtable(
+        5,
+        &[coverage3(&[C]), class_def3(C, &[1, 2])],
+        2,
+        1,
+        rule(&[2], 1),
+    );
+    let chain_context4 = context_subtable(4, &[coverage3(&[B])], 1, 0, chain_rule(&[D], &[], &[C]));
+    // "a" is in class 1 of the backtrack and lookahead classes and "d" in
+    // class 1 of the input classes.
+    let chain_context5 = context_subtable(
+        5,
+        &[
+            coverage3(&[D]),
+            class_def3(A, &[1]),
+            class_def3(D, &[1]),
+            class_def3(A, &[1]),
+        ],
+        2,
+        1,
+        chain_rule(&[1], &[], &[1]),
+    );
+    let alternates = [
+        words(&[4]),
+        u24s(&[13]),
+        words(&[2]),
+        u24s(&[B_ALT, D_ALT]),
+        coverage3(&[B, D]),
+    ]
+    .concat();
+    layout_table(
+        2,
+        &[(b"ccmp", &[0, 1, 2, 3])],
+        &[
+            (5, 0, context4),
+            (5, 0, context5),
+            (6, 0, chain_context4),
+            (6, 0, chain_context5),
+            (1, 0, alternates),
+        ],
+    )
+}
+
+#[test]
+fn context_and_chained_context() {
+    let font = with_tables(
+        FONT,
+        vec![
+            (*b"GDEF", gdef(2)),
+            (*b"GPOS", gpos(2)),
+            (*b"GSUB", context_gsub()),
+            (*b"cmap", cmap()),
+        ],
+    );
+    for (text, expected) in [
+        ("ab", [A, B_ALT].as_slice()),
+        ("cd", &[C, D_ALT]),
+        ("dbc", &[D, B_ALT, C]),
+        ("ada", &[A, D_ALT, A]),
+        ("ba", &[B, A]),
+        ("dc", &[D, C]),
+        ("db", &[D, B]),
+        ("ad", &[A, D]),
+    ] {
+        let glyphs = shape(&font, text, &[]);
+        let glyphs: Vec<_> = glyphs
+            .glyph_infos()
+            .iter()
+            .map(|info| info.glyph_id)
+            .collect();
+        assert_eq!(glyphs, expected, "{text}");
+    }
+}
@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::PathBuf;
+
+use harfrust::{Direction, FontRef, GlyphBuffer, ShapeOptions, ShaperData, UnicodeBuffer};
+use read_fonts::types::GlyphId;
+
+/// A font with a feh-alef ligature, `fehDotless_alef-ar`, that has one
+/// caret at 208 in its GDEF `LigCaretList`.
+fn lig_caret_font_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join("in-house")
+        .join("8339c821814d9bad7c77169332327ad8b0f33c81.ttf")
+}
+
+fn with_lig_caret_shaper<T>(f: impl FnOnce(&harfrust::Shaper) -> T) -> T {
+    let font_data = fs::read(lig_caret_font_path()).expect("failed to read test font");
+    let font = FontRef::new(&font_data).expect("failed to parse test font");
+    let data = ShaperData::new(&font);
+    let shaper = data.shaper(&font).build();
+    f(&shaper)
+}
+
+fn shape(shaper: &harfrust::Shaper, text: &str) -> GlyphBuffer {
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    shaper.shape(buffer, ShapeOptions::new())
+}
+
+// Feh, alef.
+const FEH_ALEF: &str = "\u{0641}\u{0627}";
+// fehDotless_alef-ar.
+const LIGATURE: GlyphId = GlyphId::new(11);
+
+#[test]
+fn ligature_carets_come_from_gdef() {
+    with_lig_caret_shaper(|shaper| {
+        let glyphs = shape(shaper, FEH_ALEF);
+        assert!(glyphs
+            .glyph_infos()
+            .iter()
+            .any(|info| info.glyph_id == LIGATURE.to_u32()));
+        let ligature = LIGATURE;
+        assert_eq!(
+            shaper.ligature_carets(ligature, Direction::RightToLeft, None),
+            [208]
+        );
+        let scale = shaper.units_per_em() * 2;
+        assert_eq!(
+            shaper.ligature_carets(ligature, Direction::RightToLeft, Some((scale, scale))),
+            [416]
+        );
+        // Glyphs outside the LigCaretList have no carets.
+        assert!(shaper
+            .ligature_carets(GlyphId::new(0), Direction::RightToLeft, None)
+            .is_empty());
+    });
+}
+
+#[test]
+fn offset_to_x_uses_ligature_carets() {
+    with_lig_caret_shaper(|shaper| {
+        let glyphs = shape(shaper, FEH_ALEF);
+        let advance: i32 = glyphs
+            .glyph_positions()
+            .iter()
+            .map(|pos| pos.x_advance)
+            .sum();
+        // Right to left, so the start of the text is at the end of the run.
+        let xs: Vec<_> = [0, 2, 4]
+            .into_iter()
+            .map(|offset| glyphs.offset_to_x_with_carets(FEH_ALEF, offset, shaper, None))
+            .collect();
+        assert_eq!(xs, [advance, 208, 0]);
+        assert_eq!(
+            glyphs.x_to_offset_with_carets(FEH_ALEF, 200, shaper, None),
+            2
+        );
+        // Without font carets the ligature is split evenly.
+        assert_eq!(glyphs.offset_to_x(FEH_ALEF, 2), advance / 2);
+    });
+}
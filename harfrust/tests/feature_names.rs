@@ -0,0 +1,265 @@
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use harfrust::{FeatureNameIds, FeatureNames, FontRef, Language, Script, ShaperData, Tag};
+use read_fonts::types::NameId;
+
+const CV01: Tag = Tag::new(b"cv01");
+const SS01: Tag = Tag::new(b"ss01");
+const LATIN: Script = Script::from_iso15924_tag(Tag::new(b"Latn")).unwrap();
+
+fn words(values: &[u16]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect()
+}
+
+/// Returns the font with `table` added, or replacing the table with the
+/// same tag.
+fn with_table(font: &[u8], tag: &[u8; 4], table: Vec<u8>) -> Vec<u8> {
+    let read_u32 =
+        |offset: usize| u32::from_be_bytes(font[offset..offset + 4].try_into().unwrap()) as usize;
+    let num_tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+    let mut tables: Vec<([u8; 4], Vec<u8>)> = (0..num_tables)
+        .map(|i| 12 + 16 * i)
+        .map(|record| {
+            let (offset, len) = (read_u32(record + 8), read_u32(record + 12));
+            (
+                font[record..record + 4].try_into().unwrap(),
+                font[offset..offset + len].to_vec(),
+            )
+        })
+        .filter(|(existing, _)| existing != tag)
+        .collect();
+    tables.push((*tag, table));
+    tables.sort_by_key(|(tag, _)| *tag);
+
+    let mut data = font[..4].to_vec();
+    data.extend(words(&[tables.len() as u16, 0, 0, 0]));
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, table) in &tables {
+        data.extend(tag);
+        data.extend(0u32.to_be_bytes());
+        data.extend((offset as u32).to_be_bytes());
+        data.extend((table.len() as u32).to_be_bytes());
+        offset += table.len().next_multiple_of(4);
+    }
+    for (_, table) in &tables {
+        data.extend(table);
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+    data
+}
+
+/// A `GSUB` table with no lookups whose features are:
+///
+/// 0. `cv01` for `DFLT`, labelled 256, for "a".
+/// 1. `cv01` for `latn`, labelled 257 with tooltip 258, sample text 259
+///    and parameters 260 and 261, for "a" and "g".
+/// 2. `cv01` for `latn`/`TRK `, labelled 262, for "i".
+/// 3. `ss01` for every language system, labelled 263.
+fn gsub() -> Vec<u8> {
+    let lang_sys = |features: &[u16]| {
+        let mut data = words(&[0, 0xFFFF, features.len() as u16]);
+        data.extend(words(features));
+        data
+    };
+    let character_variant = |ids: [u16; 5], characters: &[char]| {
+        let mut data = words(&[4, 0, 0, ids[0], ids[1], ids[2], ids[3], ids[4]]);
+        data.extend(words(&[characters.len() as u16]));
+        for &c in characters {
+            data.extend(&(c as u32).to_be_bytes()[1..]);
+        }
+        data
+    };
+    let features = [
+        (CV01, character_variant([256, 0, 0, 0, 0], &['a'])),
+        (
+            CV01,
+            character_variant([257, 258, 259, 2, 260], &['a', 'g']),
+        ),
+        (CV01, character_variant([262, 0, 0, 0, 0], &['i'])),
+        (SS01, words(&[4, 0, 0, 263])),
+    ];
+
+    // Header, then the script list with DFLT and latn.
+    let mut data = words(&[1, 0, 10, 0, 0]);
+    let dflt = lang_sys(&[0, 3]);
+    let latn_default = lang_sys(&[1, 3]);
+    let latn_trk = lang_sys(&[2, 3]);
+    data.extend(words(&[2]));
+    data.extend(b"DFLT");
+    data.extend(words(&[14]));
+    data.extend(b"latn");
+    data.extend(words(&[14 + 4 + dflt.len() as u16]));
+    data.extend(words(&[4, 0]));
+    data.extend(&dflt);
+    data.extend(words(&[10, 1]));
+    data.extend(b"TRK ");
+    data.extend(words(&[10 + latn_default.len() as u16]));
+    data.extend(&latn_default);
+    data.extend(&latn_trk);
+
+    let feature_list = data.len() as u16;
+    data[6..8].copy_from_slice(&feature_list.to_be_bytes());
+    data.extend(words(&[features.len() as u16]));
+    let mut offset = 2 + 6 * features.len() as u16;
+    for (tag, feature) in &features {
+        data.extend(tag.to_be_bytes());
+        data.extend(words(&[offset]));
+        offset += feature.len() as u16;
+    }
+    for (_, feature) in &features {
+        data.extend(feature);
+    }
+
+    let lookup_list = data.len() as u16;
+    data[8..10].copy_from_slice(&lookup_list.to_be_bytes());
+    data.extend(words(&[0]));
+    data
+}
+
+/// A `name` table with English Windows names for IDs 256 to 263.
+fn name() -> Vec<u8> {
+    let names = [
+        "Default a",
+        "Latin a and g",
+        "Tooltip",
+        "ag",
+        "Open",
+        "Closed",
+        "Dotless i",
+        "Set one",
+    ];
+    let strings: Vec<Vec<u8>> = names
+        .iter()
+        .map(|name| words(&name.encode_utf16().collect::<Vec<_>>()))
+        .collect();
+    let mut data = words(&[0, names.len() as u16, 6 + 12 * names.len() as u16]);
+    let mut offset = 0;
+    for (id, string) in (256..).zip(&strings) {
+        data.extend(words(&[3, 1, 0x0409, id, string.len() as u16, offset]));
+        offset += string.len() as u16;
+    }
+    data.extend(strings.concat());
+    data
+}
+
+fn font_with_feature_params() -> Vec<u8> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join("text-rendering-tests")
+        .join("TestMORXOne.ttf");
+    let font = fs::read(path).expect("failed to read test font");
+    let font = with_table(&font, b"GSUB", gsub());
+    with_table(&font, b"name", name())
+}
+
+fn with_shaper<T>(f: impl FnOnce(&harfrust::Shaper) -> T) -> T {
+    let font_data = font_with_feature_params();
+    let font = FontRef::new(&font_data).expect("failed to parse test font");
+    let data = ShaperData::new(&font);
+    let shaper = data.shaper(&font).build();
+    f(&shaper)
+}
+
+#[test]
+fn character_variant_name_ids() {
+    with_shaper(|shaper| {
+        let ids = shaper
+            .feature_name_ids(CV01, Some(LATIN), None)
+            .expect("no cv01 name ids");
+        assert_eq!(
+            ids,
+            FeatureNameIds {
+                label: Some(NameId::new(257)),
+                tooltip: Some(NameId::new(258)),
+                sample_text: Some(NameId::new(259)),
+                first_param_label: Some(NameId::new(260)),
+                num_named_parameters: 2,
+            }
+        );
+        assert_eq!(
+            ids.param_labels().collect::<Vec<_>>(),
+            [NameId::new(260), NameId::new(261)]
+        );
+        assert_eq!(
+            shaper.feature_characters(CV01, Some(LATIN), None),
+            ['a', 'g']
+        );
+    });
+}
+
+#[test]
+fn character_variant_names() {
+    with_shaper(|shaper| {
+        assert_eq!(
+            shaper.feature_names(CV01, Some(LATIN), None),
+            Some(FeatureNames {
+                label: Some("Latin a and g".into()),
+                tooltip: Some("Tooltip".into()),
+                sample_text: Some("ag".into()),
+                param_labels: vec!["Open".into(), "Closed".into()],
+                characters: vec!['a', 'g'],
+            })
+        );
+    });
+}
+
+#[test]
+fn stylistic_set_name_ids() {
+    with_shaper(|shaper| {
+        assert_eq!(
+            shaper.feature_name_ids(SS01, Some(LATIN), None),
+            Some(FeatureNameIds {
+                label: Some(NameId::new(263)),
+                ..FeatureNameIds::default()
+            })
+        );
+        assert!(shaper
+            .feature_characters(SS01, Some(LATIN), None)
+            .is_empty());
+        assert_eq!(
+            shaper
+                .feature_names(SS01, Some(LATIN), None)
+                .and_then(|names| names.label),
+            Some("Set one".into())
+        );
+    });
+}
+
+#[test]
+fn feature_params_follow_the_selected_language_system() {
+    with_shaper(|shaper| {
+        let label = |script, language: Option<&Language>| {
+            shaper
+                .feature_name_ids(CV01, script, language)
+                .and_then(|ids| ids.label)
+        };
+        let turkish = Language::from_str("tr").unwrap();
+        let greek = Script::from_iso15924_tag(Tag::new(b"Grek")).unwrap();
+        assert_eq!(label(None, None), Some(NameId::new(256)));
+        assert_eq!(label(Some(greek), None), Some(NameId::new(256)));
+        assert_eq!(label(Some(LATIN), None), Some(NameId::new(257)));
+        assert_eq!(label(Some(LATIN), Some(&turkish)), Some(NameId::new(262)));
+        assert_eq!(
+            shaper.feature_characters(CV01, Some(LATIN), Some(&turkish)),
+            ['i']
+        );
+    });
+}
+
+#[test]
+fn missing_features_have_no_names() {
+    with_shaper(|shaper| {
+        let cv02 = Tag::new(b"cv02");
+        assert_eq!(shaper.feature_name_ids(cv02, Some(LATIN), None), None);
+        assert_eq!(shaper.feature_names(cv02, Some(LATIN), None), None);
+        assert!(shaper
+            .feature_characters(cv02, Some(LATIN), None)
+            .is_empty());
+    });
+}
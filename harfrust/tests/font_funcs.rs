@@ -3,7 +3,7 @@ use std::path::PathBuf;
 
 use harfrust::{
     font::{AdvanceWidthBatch, BuiltinFontFuncs, FontFuncs},
-    Direction, FontRef, ShapeOptions, ShaperData, UnicodeBuffer,
+    Direction, FontRef, ShapeOptions, ShaperData, ShaperInstance, Tag, UnicodeBuffer, Variation,
 };
 use read_fonts::types::GlyphId;
 
@@ -458,3 +458,95 @@ fn font_funcs_extents_override_is_used() {
         .iter()
         .any(|pos| pos.x_offset != 0 || pos.y_offset != 0));
 }
+
+/// Glyph extents as `(x_bearing, y_bearing, width, height)`.
+type Extents = (i32, i32, i32, i32);
+
+struct VerticalOriginExtentsFuncs {
+    extents: Vec<Extents>,
+}
+
+impl FontFuncs for VerticalOriginExtentsFuncs {
+    fn vertical_origin(&mut self, builtin: &BuiltinFontFuncs, glyph: GlyphId) -> (i32, i32) {
+        let extents = builtin.extents(glyph).unwrap_or_default();
+        self.extents.push((
+            extents.x_bearing,
+            extents.y_bearing,
+            extents.width,
+            extents.height,
+        ));
+        builtin.vertical_origin(glyph)
+    }
+}
+
+fn vertical_origin_extents(font_name: &str, text: &str) -> (Vec<Extents>, Vec<i32>) {
+    let font_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join("in-house")
+        .join(font_name);
+    let font_data = fs::read(font_path).expect("failed to read test font");
+    let font = FontRef::new(&font_data).expect("failed to parse test font");
+    let data = ShaperData::new(&font);
+    let instance = ShaperInstance::from_variations(
+        &font,
+        [Variation {
+            tag: Tag::new(b"wght"),
+            value: 700.0,
+        }],
+    );
+    let shaper = data.shaper(&font).instance(Some(&instance)).build();
+    let mut funcs = VerticalOriginExtentsFuncs {
+        extents: Vec::new(),
+    };
+    let mut buffer = buffer_with_text(text);
+    buffer.set_direction(Direction::TopToBottom);
+    let glyphs = shaper.shape(buffer, ShapeOptions::new().font_funcs(Some(&mut funcs)));
+    let y_offsets = glyphs
+        .glyph_positions()
+        .iter()
+        .map(|pos| pos.y_offset)
+        .collect();
+    (funcs.extents, y_offsets)
+}
+
+#[test]
+fn builtin_extents_apply_glyf_variations() {
+    // NotoSans-VF.abc.ttf has no vmtx, so glyphs are centered on the
+    // ascender/descender box using their varied extents.
+    let (extents, y_offsets) = vertical_origin_extents("NotoSans-VF.abc.ttf", "ab");
+    assert_eq!(extents, [(42, 557, 487, -567), (78, 760, 510, -770)]);
+    assert_eq!(y_offsets, [-954, -1056]);
+}
+
+#[test]
+fn builtin_extents_apply_cff2_variations() {
+    let (extents, y_offsets) = vertical_origin_extents("NotoSansCJK-VF.abc.otf", "AB");
+    assert_eq!(extents, [(-4, 741, 649, -741), (91, 741, 550, -741)]);
+    // The vertical origin comes from VORG.
+    assert_eq!(y_offsets, [-880, -880]);
+}
+
+#[test]
+fn builtin_vertical_fallback_uses_hhea_without_use_typo_metrics() {
+    // FDArrayTest257.otf is a CFF font without vmtx or VORG whose OS/2 typo
+    // metrics (880, -120) differ from hhea (1160, -320); USE_TYPO_METRICS is
+    // not set, so the advance is the hhea ascender minus descender and
+    // glyphs are centered on it.
+    let font_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join("text-rendering-tests")
+        .join("FDArrayTest257.otf");
+    let mut buffer = buffer_with_text("\u{3001}\u{3002}");
+    buffer.set_direction(Direction::TopToBottom);
+    let glyphs = with_test_shaper_from_path(font_path, |shaper| {
+        shaper.shape(buffer, ShapeOptions::new())
+    });
+    let positions: Vec<_> = glyphs
+        .glyph_positions()
+        .iter()
+        .map(|pos| (pos.y_advance, pos.y_offset))
+        .collect();
+    assert_eq!(positions, [(-1480, -1092), (-1480, -1098)]);
+}
@@ -0,0 +1,307 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use harfrust::{
+    script, BufferFlags, BuiltinShaper, Direction, FontRef, GlyphBuffer, Justification,
+    ScriptShaper, ShapeOptions, ShapePlan, ShaperBuffer, ShaperData, ShaperRegistry, Tag,
+    UnicodeBuffer,
+};
+
+/// A font with only a `wdth` axis, ranging from 60 to 402 with the default at
+/// the maximum. The space advance is 266 at the minimum and 639 at the
+/// default.
+fn wdth_font_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join("in-house")
+        .join("ab40c89624a6104e5d0a2308e448a989302f515b.ttf")
+}
+
+fn justify(
+    text: &str,
+    direction: Direction,
+    min_target: f32,
+    max_target: f32,
+) -> (GlyphBuffer, Justification) {
+    let font_data = fs::read(wdth_font_path()).expect("failed to read test font");
+    let font = FontRef::new(&font_data).expect("failed to parse test font");
+    let data = ShaperData::new(&font);
+    let shaper = data.shaper(&font).build();
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    buffer.set_direction(direction);
+    shaper.shape_justify(buffer, ShapeOptions::new(), min_target, max_target, None)
+}
+
+fn advance(glyphs: &GlyphBuffer) -> i32 {
+    glyphs
+        .glyph_positions()
+        .iter()
+        .map(|pos| pos.x_advance + pos.y_advance)
+        .sum()
+}
+
+#[test]
+fn fitting_text_is_not_justified() {
+    let (glyphs, justification) = justify("   ", Direction::LeftToRight, 1900.0, 2000.0);
+    assert_eq!(advance(&glyphs), 3 * 639);
+    assert_eq!(justification, Justification::default());
+}
+
+#[test]
+fn shrinks_along_the_wdth_axis() {
+    let (glyphs, justification) = justify("   ", Direction::LeftToRight, 1200.0, 1300.0);
+    let advance = advance(&glyphs);
+    assert!((1200..=1300).contains(&advance), "advance {advance}");
+    let variation = justification.variation.expect("no variation");
+    assert_eq!(variation.tag, Tag::new(b"wdth"));
+    assert!(60.0 < variation.value && variation.value < 402.0);
+    assert_eq!(justification.letter_spacing, None);
+}
+
+#[test]
+fn tracks_once_the_axis_cannot_expand() {
+    let (glyphs, justification) = justify("   ", Direction::LeftToRight, 2400.0, 2410.0);
+    let advance = advance(&glyphs);
+    assert!((2400..=2410).contains(&advance), "advance {advance}");
+    assert_eq!(
+        justification.variation.map(|variation| variation.value),
+        Some(402.0)
+    );
+    assert!(justification
+        .letter_spacing
+        .is_some_and(|spacing| spacing > 0.0));
+}
+
+#[test]
+fn tracks_once_the_axis_cannot_shrink() {
+    let (glyphs, justification) = justify("   ", Direction::LeftToRight, 300.0, 320.0);
+    let advance = advance(&glyphs);
+    assert!((300..=320).contains(&advance), "advance {advance}");
+    assert_eq!(
+        justification.variation.map(|variation| variation.value),
+        Some(60.0)
+    );
+    assert!(justification
+        .letter_spacing
+        .is_some_and(|spacing| spacing < 0.0));
+}
+
+#[test]
+#[allow(clippy::cast_precision_loss)]
+fn vertical_targets_are_magnitudes() {
+    let (glyphs, _) = justify("   ", Direction::TopToBottom, 0.0, f32::MAX);
+    let natural = -advance(&glyphs);
+    assert!(natural > 0);
+
+    let (min_target, max_target) = (natural + 300, natural + 310);
+    let (glyphs, justification) = justify(
+        "   ",
+        Direction::TopToBottom,
+        min_target as f32,
+        max_target as f32,
+    );
+    assert!(glyphs.glyph_positions().iter().all(|pos| pos.y_advance < 0));
+    let advance = -advance(&glyphs);
+    assert!(
+        (min_target..=max_target).contains(&advance),
+        "advance {advance}"
+    );
+    assert!(justification
+        .letter_spacing
+        .is_some_and(|spacing| spacing > 0.0));
+}
+
+#[test]
+fn inverted_target_range_is_swapped() {
+    let (glyphs, justification) = justify("   ", Direction::LeftToRight, 1300.0, 1200.0);
+    let advance = advance(&glyphs);
+    assert!((1200..=1300).contains(&advance), "advance {advance}");
+    assert_eq!(
+        justification,
+        justify("   ", Direction::LeftToRight, 1200.0, 1300.0).1
+    );
+}
+
+#[test]
+fn nan_target_is_ignored() {
+    let (glyphs, justification) = justify("   ", Direction::LeftToRight, f32::NAN, 1250.0);
+    let (expected_glyphs, expected) = justify("   ", Direction::LeftToRight, 1250.0, 1250.0);
+    assert_eq!(advance(&glyphs), advance(&expected_glyphs));
+    assert_eq!(justification, expected);
+
+    let (glyphs, justification) = justify("   ", Direction::LeftToRight, f32::NAN, f32::NAN);
+    assert_eq!(advance(&glyphs), 3 * 639);
+    assert_eq!(justification, Justification::default());
+}
+
+#[test]
+fn plan_is_rebuilt_for_each_instance() {
+    let font_data = fs::read(wdth_font_path()).expect("failed to read test font");
+    let font = FontRef::new(&font_data).expect("failed to parse test font");
+    let data = ShaperData::new(&font);
+    let shaper = data.shaper(&font).build();
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str("   ");
+    buffer.guess_segment_properties();
+    let plan = ShapePlan::new(
+        &shaper,
+        buffer.direction(),
+        Some(buffer.script()),
+        buffer.language().as_ref(),
+        &[],
+    );
+    let (glyphs, justification) = shaper.shape_justify(
+        buffer,
+        ShapeOptions::new().plan(Some(&plan)),
+        1200.0,
+        1300.0,
+        None,
+    );
+    let (expected_glyphs, expected) = justify("   ", Direction::LeftToRight, 1200.0, 1300.0);
+    assert_eq!(advance(&glyphs), advance(&expected_glyphs));
+    assert_eq!(justification, expected);
+}
+
+/// A font mapping Arabic letters, U+0640 ARABIC TATWEEL and U+200C ZERO
+/// WIDTH NON-JOINER to glyphs of 1000 units, with no layout tables.
+fn arabic_font_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join("text-rendering-tests")
+        .join("FDArrayTest65535.otf")
+}
+
+/// Shapes `text`, justifies it with tatweels by `extra_width` and returns
+/// the original and justified glyphs, together with the tatweel glyph id.
+fn justify_with_tatweel(text: &str, extra_width: f32) -> (GlyphBuffer, GlyphBuffer, u32) {
+    let font_data = fs::read(arabic_font_path()).expect("failed to read test font");
+    let font = FontRef::new(&font_data).expect("failed to parse test font");
+    let data = ShaperData::new(&font);
+    let shaper = data.shaper(&font).build();
+    let shape = |text: &str| {
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.guess_segment_properties();
+        buffer.set_flags(BufferFlags::PRODUCE_SAFE_TO_INSERT_TATWEEL);
+        shaper.shape(buffer, ShapeOptions::new())
+    };
+    let tatweel = shape("\u{0640}").glyph_infos()[0].glyph_id;
+    let glyphs = shape(text);
+    let justified =
+        shaper.justify_with_tatweel(text, shape(text), ShapeOptions::new(), extra_width);
+    (glyphs, justified, tatweel)
+}
+
+/// Returns the clusters of the glyphs in logical order, with `None` for
+/// tatweels.
+fn logical_clusters(glyphs: &GlyphBuffer, tatweel: u32) -> Vec<Option<u32>> {
+    glyphs
+        .glyph_infos()
+        .iter()
+        .rev()
+        .map(|info| (info.glyph_id != tatweel).then_some(info.cluster))
+        .collect()
+}
+
+#[test]
+fn tatweels_are_inserted_before_the_flagged_cluster() {
+    // Seen, lam, alef, meem: tatweels go after seen, before lam.
+    let text = "\u{0633}\u{0644}\u{0627}\u{0645}";
+    let (glyphs, justified, tatweel) = justify_with_tatweel(text, 2500.0);
+    assert!(glyphs
+        .glyph_infos()
+        .iter()
+        .any(|info| info.cluster == 2 && info.safe_to_insert_tatweel()));
+    assert_eq!(
+        logical_clusters(&justified, tatweel),
+        [Some(0), None, None, Some(2), Some(4), Some(6)]
+    );
+    let tatweel_clusters: Vec<_> = justified
+        .glyph_infos()
+        .iter()
+        .filter(|info| info.glyph_id == tatweel)
+        .map(|info| info.cluster)
+        .collect();
+    assert_eq!(tatweel_clusters, [2, 2]);
+    assert_eq!(advance(&justified), advance(&glyphs) + 2000);
+}
+
+#[test]
+fn tatweels_are_spread_over_words() {
+    // Two words, seen lam and beh seen, split by whitespace.
+    let text = "\u{0633}\u{0644} \u{0628}\u{0633}";
+    let (glyphs, justified, tatweel) = justify_with_tatweel(text, 2000.0);
+    assert_eq!(
+        logical_clusters(&justified, tatweel),
+        [Some(0), None, Some(2), Some(4), Some(5), None, Some(7)]
+    );
+    assert_eq!(advance(&justified), advance(&glyphs) + 2000);
+}
+
+#[test]
+fn zero_width_non_joiner_splits_words() {
+    // Seen lam, a non-joiner, then seen lam again: each part gets a point.
+    let text = "\u{0633}\u{0644}\u{200C}\u{0633}\u{0644}";
+    let (_, justified, tatweel) = justify_with_tatweel(text, 2000.0);
+    let clusters = logical_clusters(&justified, tatweel);
+    assert_eq!(
+        clusters.iter().filter(|cluster| cluster.is_none()).count(),
+        2
+    );
+    assert_eq!(clusters[..3], [Some(0), None, Some(2)]);
+    assert_eq!(clusters[clusters.len() - 3..], [Some(7), None, Some(9)]);
+}
+
+#[test]
+fn narrow_extra_width_inserts_nothing() {
+    let text = "\u{0633}\u{0644}\u{0627}\u{0645}";
+    let (glyphs, justified, tatweel) = justify_with_tatweel(text, 999.0);
+    assert!(justified
+        .glyph_infos()
+        .iter()
+        .all(|info| info.glyph_id != tatweel));
+    assert_eq!(advance(&justified), advance(&glyphs));
+}
+
+#[test]
+fn script_shapers_are_kept_for_each_instance() {
+    static SETUP_MASKS_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    struct CountingShaper;
+
+    impl ScriptShaper for CountingShaper {
+        fn setup_masks(
+            &self,
+            base: BuiltinShaper,
+            plan: &ShapePlan,
+            buffer: &mut ShaperBuffer<'_, '_, '_>,
+        ) {
+            SETUP_MASKS_CALLS.fetch_add(1, Ordering::Relaxed);
+            base.setup_masks(plan, buffer);
+        }
+    }
+
+    static COUNTING_SHAPER: CountingShaper = CountingShaper;
+
+    let mut registry = ShaperRegistry::new();
+    registry.register(script::LATIN, &COUNTING_SHAPER);
+
+    let font_data = fs::read(wdth_font_path()).expect("failed to read test font");
+    let font = FontRef::new(&font_data).expect("failed to parse test font");
+    let data = ShaperData::new(&font);
+    let shaper = data.shaper(&font).script_shapers(Some(&registry)).build();
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str("   ");
+    buffer.guess_segment_properties();
+    buffer.set_script(script::LATIN);
+    let (_, justification) =
+        shaper.shape_justify(buffer, ShapeOptions::new(), 1200.0, 1300.0, None);
+    assert!(justification.variation.is_some());
+    // Once for the default instance and at least once for a varied one.
+    assert!(SETUP_MASKS_CALLS.load(Ordering::Relaxed) > 1);
+}
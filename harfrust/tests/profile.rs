@@ -0,0 +1,156 @@
+#![cfg(feature = "profile")]
+
+use harfrust::{FontRef, LookupCounts, LookupProfile, ShapeOptions, ShaperData, UnicodeBuffer};
+
+/// An Arabic font with `GSUB` and `GPOS` lookups.
+const ARABIC_FONT: &[u8] =
+    include_bytes!("fonts/in-house/8339c821814d9bad7c77169332327ad8b0f33c81.ttf");
+
+/// A font whose single `morx` subtable substitutes "A" and "C".
+const MORX_FONT: &[u8] = include_bytes!("fonts/text-rendering-tests/TestMORXOne.ttf");
+
+const fn counts(attempts: u64, successes: u64, glyphs_visited: u64) -> LookupCounts {
+    LookupCounts {
+        attempts,
+        successes,
+        glyphs_visited,
+    }
+}
+
+fn shape_with_profile(font_data: &[u8], text: &str, profile: &mut LookupProfile) {
+    let font = FontRef::new(font_data).unwrap();
+    let data = ShaperData::new(&font);
+    let shaper = data.shaper(&font).build();
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    shaper.shape(buffer, ShapeOptions::new().profile(Some(profile)));
+}
+
+fn profile(font_data: &[u8], text: &str) -> LookupProfile {
+    let mut profile = LookupProfile::new();
+    shape_with_profile(font_data, text, &mut profile);
+    profile
+}
+
+#[test]
+fn gsub_and_gpos_counts() {
+    // Feh and alef: ccmp decomposes feh, then init, fina and rlig apply.
+    let profile = profile(ARABIC_FONT, "\u{0641}\u{0627}");
+
+    let gsub = profile.gsub_lookups();
+    assert_eq!(gsub.len(), 8);
+    assert_eq!(gsub[0].counts, counts(1, 1, 2));
+    assert_eq!(gsub[0].subtables, [counts(1, 1, 1)]);
+    // Lookups of features that are not applied are never tried.
+    assert_eq!(gsub[2].counts, LookupCounts::default());
+    assert!(gsub[2].subtables.is_empty());
+    // Glyphs that pass the lookup digest but not the subtable's.
+    assert_eq!(gsub[5].counts, counts(0, 0, 3));
+    assert_eq!(gsub[5].subtables, [counts(0, 0, 0)]);
+    assert_eq!(gsub[7].counts, counts(1, 1, 1));
+
+    let gpos = profile.gpos_lookups();
+    assert_eq!(gpos.len(), 3);
+    assert_eq!(gpos[0].counts, counts(1, 0, 2));
+    assert_eq!(gpos[2].counts, counts(1, 1, 2));
+    assert!(profile.morx_chains().is_empty());
+}
+
+#[test]
+fn morx_counts() {
+    let profile = profile(MORX_FONT, "ABC");
+    assert!(profile.gsub_lookups().is_empty());
+    assert_eq!(profile.morx_chains(), [vec![counts(1, 1, 3)]]);
+
+    // Subtables are skipped when none of their glyphs are in the buffer.
+    let profile = self::profile(MORX_FONT, "B");
+    assert!(profile.morx_chains().is_empty());
+}
+
+#[test]
+fn profiles_accumulate_merge_and_clear() {
+    let mut profile = LookupProfile::new();
+    shape_with_profile(MORX_FONT, "ABC", &mut profile);
+    shape_with_profile(MORX_FONT, "A", &mut profile);
+    assert_eq!(profile.morx_chains(), [vec![counts(2, 2, 4)]]);
+
+    let mut merged = self::profile(ARABIC_FONT, "\u{0641}\u{0627}");
+    merged.merge(&profile);
+    merged.merge(&profile);
+    assert_eq!(merged.morx_chains(), [vec![counts(4, 4, 8)]]);
+    assert_eq!(merged.gsub_lookups()[0].counts, counts(1, 1, 2));
+
+    merged.clear();
+    assert_eq!(merged, LookupProfile::new());
+}
+
+#[cfg(feature = "mort")]
+#[test]
+fn mort_counts() {
+    let font = mort_font();
+    let profile = profile(&font, "AB");
+    assert_eq!(profile.morx_chains(), [vec![counts(1, 1, 2)]]);
+
+    // A subtable that runs without changing any glyph doesn't succeed.
+    let profile = self::profile(&font, "C");
+    assert_eq!(profile.morx_chains(), [vec![counts(1, 0, 1)]]);
+}
+
+/// Returns the `morx` font with its `morx` table replaced by a `mort` table
+/// whose single noncontextual subtable substitutes "B" with "A.alt".
+#[cfg(feature = "mort")]
+fn mort_font() -> Vec<u8> {
+    const B: u16 = 3;
+    const A_ALT: u16 = 5;
+
+    let words = |values: &[u16]| -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    };
+    // Version 1 with one chain, whose default flags enable its single
+    // subtable; the subtable is noncontextual for all directions and holds
+    // a trimmed array lookup.
+    let mort = words(&[
+        1, 0, 0, 1, // header
+        0, 1, 0, 28, 0, 1, // chain
+        16, 0x2004, 0, 1, // subtable header
+        8, B, 1, A_ALT, // lookup
+    ]);
+
+    let read_u32 = |offset: usize| {
+        u32::from_be_bytes(MORX_FONT[offset..offset + 4].try_into().unwrap()) as usize
+    };
+    let num_tables = u16::from_be_bytes([MORX_FONT[4], MORX_FONT[5]]) as usize;
+    let mut tables: Vec<([u8; 4], Vec<u8>)> = (0..num_tables)
+        .map(|i| 12 + 16 * i)
+        .map(|record| {
+            let (offset, len) = (read_u32(record + 8), read_u32(record + 12));
+            (
+                MORX_FONT[record..record + 4].try_into().unwrap(),
+                MORX_FONT[offset..offset + len].to_vec(),
+            )
+        })
+        .filter(|(tag, _)| tag != b"morx")
+        .collect();
+    tables.push((*b"mort", mort));
+    tables.sort_by_key(|(tag, _)| *tag);
+
+    let mut data = MORX_FONT[..4].to_vec();
+    data.extend(words(&[tables.len() as u16, 0, 0, 0]));
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, table) in &tables {
+        data.extend(tag);
+        data.extend(0u32.to_be_bytes());
+        data.extend((offset as u32).to_be_bytes());
+        data.extend((table.len() as u32).to_be_bytes());
+        offset += table.len().next_multiple_of(4);
+    }
+    for (_, table) in &tables {
+        data.extend(table);
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+    data
+}
@@ -3,7 +3,8 @@ use std::path::PathBuf;
 
 use harfrust::{
     font::{AdvanceWidthBatch, BuiltinFontFuncs, FontFuncs},
-    FontRef, ShapeOptions, ShaperData, UnicodeBuffer,
+    Direction, FontRef, MetricsTag, ShapeEngine, ShapeOptions, ShaperData, ShaperInstance, Tag,
+    UnicodeBuffer, Variation,
 };
 use read_fonts::types::GlyphId;
 
@@ -219,3 +220,408 @@ fn shape_negative_scale_flips_and_doubles_advances() {
         assert_eq!(scaled.y_advance, -(baseline.y_advance * 2));
     }
 }
+
+#[test]
+fn font_metrics_use_shaping_scale() {
+    with_test_shaper(|shaper| {
+        let upem = shaper.units_per_em();
+        for metric in [
+            MetricsTag::HorizontalAscender,
+            MetricsTag::HorizontalDescender,
+            MetricsTag::HorizontalLineGap,
+            MetricsTag::UnderlineOffset,
+            MetricsTag::StrikeoutSize,
+        ] {
+            let unscaled = shaper.metric(metric, None).unwrap();
+            let scaled = shaper.metric(metric, Some((upem * 2, upem * 2))).unwrap();
+            assert_eq!(scaled, unscaled * 2, "{metric:?}");
+        }
+        assert!(shaper.metric(MetricsTag::HorizontalAscender, None).unwrap() > 0);
+        assert!(
+            shaper
+                .metric(MetricsTag::HorizontalDescender, None)
+                .unwrap()
+                < 0
+        );
+    });
+}
+
+#[test]
+fn font_metrics_follow_the_instance() {
+    // MVAR varies the x-height and strikeout offset of this font, but not
+    // its ascender and underline. Expected values are from
+    // `hb_ot_metrics_get_position`.
+    let font_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join("in-house")
+        .join("NotoSans-VF.abc.ttf");
+    let font_data = fs::read(font_path).expect("failed to read test font");
+    let font = FontRef::new(&font_data).expect("failed to parse test font");
+    let data = ShaperData::new(&font);
+    let metrics = |shaper: &harfrust::Shaper, scale| {
+        [
+            MetricsTag::HorizontalAscender,
+            MetricsTag::XHeight,
+            MetricsTag::UnderlineOffset,
+            MetricsTag::UnderlineSize,
+            MetricsTag::StrikeoutOffset,
+        ]
+        .map(|metric| shaper.metric(metric, scale).unwrap())
+    };
+
+    let shaper = data.shaper(&font).build();
+    assert_eq!(metrics(&shaper, None), [1069, 536, -100, 50, 322]);
+
+    let instance = ShaperInstance::from_variations(
+        &font,
+        [
+            Variation {
+                tag: Tag::new(b"wght"),
+                value: 900.0,
+            },
+            Variation {
+                tag: Tag::new(b"wdth"),
+                value: 62.5,
+            },
+        ],
+    );
+    let shaper = data.shaper(&font).instance(Some(&instance)).build();
+    assert_eq!(metrics(&shaper, None), [1069, 553, -100, 50, 332]);
+    assert_eq!(
+        metrics(&shaper, Some((2000, 2000))),
+        [2138, 1106, -200, 100, 664]
+    );
+}
+
+#[test]
+fn letter_spacing_is_added_per_grapheme_and_scaled() {
+    with_test_shaper(|shaper| {
+        let upem = shaper.units_per_em();
+        let baseline = shaper.shape(buffer_with_text("Text"), ShapeOptions::new());
+        let spaced = shaper.shape(
+            buffer_with_text("Text"),
+            ShapeOptions::new()
+                .scale(Some(upem * 2))
+                .letter_spacing(Some(50.0)),
+        );
+        assert_eq!(baseline.len(), spaced.len());
+        for (baseline, spaced) in baseline
+            .glyph_positions()
+            .iter()
+            .zip(spaced.glyph_positions())
+        {
+            assert_eq!(spaced.x_advance, baseline.x_advance * 2 + 100);
+        }
+    });
+}
+
+#[test]
+fn letter_spacing_keeps_marks_on_their_base_in_rtl() {
+    // A font without GPOS, so marks get fallback positioning. Beh, fatha,
+    // beh.
+    let font_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join("text-rendering-tests")
+        .join("FDArrayTest65535.otf");
+    let (baseline, spaced) = with_test_shaper_from_path(font_path, |shaper| {
+        let text = "\u{0628}\u{064E}\u{0628}";
+        (
+            shaper.shape(buffer_with_text(text), ShapeOptions::new()),
+            shaper.shape(
+                buffer_with_text(text),
+                ShapeOptions::new().letter_spacing(Some(100.0)),
+            ),
+        )
+    });
+    let clusters: Vec<_> = spaced
+        .glyph_infos()
+        .iter()
+        .map(|info| info.cluster)
+        .collect();
+    assert_eq!(clusters, [4, 0, 0]);
+    let advances: Vec<_> = spaced
+        .glyph_positions()
+        .iter()
+        .zip(baseline.glyph_positions())
+        .map(|(spaced, baseline)| spaced.x_advance - baseline.x_advance)
+        .collect();
+    assert_eq!(advances, [100, 0, 100]);
+    // The mark keeps its offset from the base.
+    let (mark, baseline_mark) = (spaced.glyph_positions()[1], baseline.glyph_positions()[1]);
+    assert_eq!(
+        (mark.x_offset, mark.y_offset),
+        (baseline_mark.x_offset, baseline_mark.y_offset)
+    );
+}
+
+/// Returns the data of `font` with its `trak` table replaced by one with a
+/// tight, a normal and a loose track, adding -50, 0 and 80 units at all
+/// sizes.
+fn with_three_tracks(mut font: Vec<u8>) -> Vec<u8> {
+    let words = |values: &[u16]| -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    };
+    let fixed = |value: i16| (i32::from(value) << 16).to_be_bytes();
+    // Header: version, format, horizontal and vertical data offsets.
+    let mut trak = fixed(1).to_vec();
+    trak.extend(words(&[0, 12, 0, 0]));
+    // Horizontal track data: three tracks, one size at offset 44.
+    trak.extend(words(&[3, 1]));
+    trak.extend(44u32.to_be_bytes());
+    for (i, track) in [-1, 0, 1].into_iter().enumerate() {
+        trak.extend(fixed(track));
+        trak.extend(words(&[256, 48 + 2 * i as u16]));
+    }
+    // Size table, then the value of each track.
+    trak.extend(fixed(12));
+    for value in [-50i16, 0, 80] {
+        trak.extend(value.to_be_bytes());
+    }
+
+    let offset = font.len().next_multiple_of(4);
+    let num_tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+    let record = (0..num_tables)
+        .map(|i| 12 + 16 * i)
+        .find(|&record| &font[record..record + 4] == b"trak")
+        .expect("no trak table");
+    font[record + 8..record + 12].copy_from_slice(&(offset as u32).to_be_bytes());
+    font[record + 12..record + 16].copy_from_slice(&(trak.len() as u32).to_be_bytes());
+    font.resize(offset, 0);
+    font.extend(trak);
+    font
+}
+
+#[test]
+fn track_option_selects_tracks() {
+    let font_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join("in-house")
+        .join("TRAK.ttf");
+    let font_data = with_three_tracks(fs::read(font_path).expect("failed to read test font"));
+    let font = FontRef::new(&font_data).expect("failed to parse test font");
+    let data = ShaperData::new(&font);
+    let shaper = data.shaper(&font).build();
+    let advances = |track: Option<f32>| -> Vec<i32> {
+        shaper
+            .shape(buffer_with_text("ABC"), ShapeOptions::new().track(track))
+            .glyph_positions()
+            .iter()
+            .map(|pos| pos.x_advance)
+            .collect()
+    };
+    assert_eq!(advances(None), [1000; 3]);
+    assert_eq!(advances(Some(0.0)), [1000; 3]);
+    assert_eq!(advances(Some(-1.0)), [950; 3]);
+    assert_eq!(advances(Some(1.0)), [1080; 3]);
+    // Values between tracks are interpolated, and values past the last
+    // track use it.
+    assert_eq!(advances(Some(0.5)), [1040; 3]);
+    assert_eq!(advances(Some(-2.0)), [950; 3]);
+    assert_eq!(advances(Some(2.0)), [1080; 3]);
+}
+
+#[test]
+fn fallback_engine_scales_advances_and_keeps_clusters() {
+    with_test_shaper(|shaper| {
+        assert_eq!(shaper.default_engine(), ShapeEngine::OpenType);
+        let upem = shaper.units_per_em();
+        let text = "abc";
+        let baseline = shaper.shape(
+            buffer_with_text(text),
+            ShapeOptions::new().engine(ShapeEngine::Fallback),
+        );
+        let mut buffer = buffer_with_text(text);
+        buffer.set_direction(Direction::RightToLeft);
+        let reversed = shaper.shape(
+            buffer,
+            ShapeOptions::new()
+                .engine(ShapeEngine::Fallback)
+                .scale(Some(upem * 2)),
+        );
+
+        assert_eq!(baseline.len(), 3);
+        assert_eq!(
+            baseline
+                .glyph_infos()
+                .iter()
+                .map(|info| info.cluster)
+                .collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        for (baseline, reversed) in baseline
+            .glyph_infos()
+            .iter()
+            .zip(baseline.glyph_positions())
+            .zip(
+                reversed
+                    .glyph_infos()
+                    .iter()
+                    .zip(reversed.glyph_positions())
+                    .rev(),
+            )
+        {
+            assert_eq!(baseline.0.glyph_id, reversed.0.glyph_id);
+            assert_eq!(baseline.0.cluster, reversed.0.cluster);
+            assert_eq!(reversed.1.x_advance, baseline.1.x_advance * 2);
+        }
+    });
+}
+
+#[test]
+fn synthetic_bold_widens_nonzero_advances() {
+    let font_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join("in-house")
+        .join("8228d035fcd65d62ec9728fb34f42c63be93a5d3.ttf");
+
+    let (baseline, bold, grade, upem) = with_test_shaper_from_path(font_path, |shaper| {
+        let text = "x\u{0301}AVX\u{0301}";
+        let baseline = shaper.shape(buffer_with_text(text), ShapeOptions::new());
+        let bold = shaper.shape(
+            buffer_with_text(text),
+            ShapeOptions::new().synthetic_bold(0.05, 0.05, false),
+        );
+        let grade = shaper.shape(
+            buffer_with_text(text),
+            ShapeOptions::new().synthetic_bold(0.05, 0.05, true),
+        );
+        (baseline, bold, grade, shaper.units_per_em())
+    });
+
+    let strength = (f64::from(upem) * 0.05).round() as i32;
+    for (baseline, bold) in baseline
+        .glyph_positions()
+        .iter()
+        .zip(bold.glyph_positions())
+    {
+        if baseline.x_advance == 0 {
+            assert_eq!(bold.x_advance, 0);
+        } else {
+            assert_eq!(bold.x_advance, baseline.x_advance + strength);
+        }
+    }
+    for (baseline, grade) in baseline
+        .glyph_positions()
+        .iter()
+        .zip(grade.glyph_positions())
+    {
+        assert_eq!(grade.x_advance, baseline.x_advance);
+    }
+}
+
+#[test]
+fn synthetic_slant_shifts_offsets_along_slant() {
+    let font_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join("rb_custom")
+        .join("Linefont.ttf");
+
+    // Linefont positions its joins with GPOS, so offsets don't depend on
+    // the (slanted) glyph extents.
+    let (baseline, slanted) = with_test_shaper_from_path(font_path, |shaper| {
+        let text = "x\u{0301}ax\u{0301}";
+        let baseline = shaper.shape(buffer_with_text(text), ShapeOptions::new());
+        let slanted = shaper.shape(
+            buffer_with_text(text),
+            ShapeOptions::new().synthetic_slant(0.2),
+        );
+        (baseline, slanted)
+    });
+
+    assert!(baseline
+        .glyph_positions()
+        .iter()
+        .any(|pos| pos.y_offset != 0));
+    for (baseline, slanted) in baseline
+        .glyph_positions()
+        .iter()
+        .zip(slanted.glyph_positions())
+    {
+        assert_eq!(slanted.x_advance, baseline.x_advance);
+        assert_eq!(slanted.y_offset, baseline.y_offset);
+        let shift = (0.2 * f64::from(baseline.y_offset)).round() as i32;
+        assert_eq!(slanted.x_offset, baseline.x_offset + shift);
+    }
+}
+
+#[test]
+fn float_positions_scale_font_units_without_rounding() {
+    let (font_units, float, rounded, upem) = with_test_shaper(|shaper| {
+        let text = "abc cba";
+        let font_units = shaper.shape(buffer_with_text(text), ShapeOptions::new());
+        let float = shaper.shape(
+            buffer_with_text(text),
+            ShapeOptions::new().scale(Some(13)).float_positions(true),
+        );
+        let rounded = shaper.shape(buffer_with_text(text), ShapeOptions::new().scale(Some(13)));
+        (font_units, float, rounded, shaper.units_per_em())
+    });
+
+    assert!(font_units.glyph_positions_f32().is_none());
+    assert!(rounded.glyph_positions_f32().is_none());
+    let float_positions = float.glyph_positions_f32().unwrap();
+    assert_eq!(float_positions.len(), font_units.len());
+    let mult = 13.0 / f64::from(upem);
+    for (float, font_units) in float_positions.iter().zip(font_units.glyph_positions()) {
+        let expected = (f64::from(font_units.x_advance) * mult) as f32;
+        assert!((float.x_advance - expected).abs() < 1e-4);
+        assert!(float.x_advance.fract() != 0.0);
+    }
+    for (float, pos) in float_positions.iter().zip(float.glyph_positions()) {
+        assert_eq!(pos.x_advance, float.x_advance.round() as i32);
+        assert_eq!(pos.x_offset, float.x_offset.round() as i32);
+    }
+    assert_eq!(
+        float
+            .glyph_positions()
+            .iter()
+            .map(|pos| pos.x_advance)
+            .collect::<Vec<_>>(),
+        rounded
+            .glyph_positions()
+            .iter()
+            .map(|pos| pos.x_advance)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn float_positions_keep_font_funcs_in_requested_scale() {
+    struct AdvanceFuncs;
+
+    impl FontFuncs for AdvanceFuncs {
+        fn advance_width(&mut self, _: &BuiltinFontFuncs, _: GlyphId) -> i32 {
+            100
+        }
+    }
+
+    let mut funcs = AdvanceFuncs;
+
+    let glyphs = with_test_shaper(|shaper| {
+        shaper.shape(
+            buffer_with_text("abc"),
+            ShapeOptions::new()
+                .scale(Some(shaper.units_per_em() * 2))
+                .float_positions(true)
+                .font_funcs(Some(&mut funcs)),
+        )
+    });
+
+    assert!(glyphs
+        .glyph_positions_f32()
+        .unwrap()
+        .iter()
+        .all(|pos| (pos.x_advance - 100.0).abs() < 1e-4));
+    assert!(glyphs
+        .glyph_positions()
+        .iter()
+        .all(|pos| pos.x_advance == 100));
+}
@@ -0,0 +1,346 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use harfrust::{
+    script, BuiltinShaper, Direction, FontRef, NormalizationMode, Normalizer, ScriptShaper,
+    ShapeOptions, ShapePlan, ShapePlanKey, ShapePlanner, ShaperBuffer, ShaperData, ShaperKind,
+    ShaperRegistry, Tag, UnicodeBuffer,
+};
+
+fn test_font_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join("rb_custom")
+        .join("OpenSans.subset1.ttf")
+}
+
+/// A font with glyphs for Latin letters and combining marks.
+fn latin_font_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join("text-rendering-tests")
+        .join("FDArrayTest257.otf")
+}
+
+fn with_test_font<T>(f: impl FnOnce(&FontRef, &ShaperData) -> T) -> T {
+    with_font(test_font_path(), f)
+}
+
+fn with_font<T>(path: PathBuf, f: impl FnOnce(&FontRef, &ShaperData) -> T) -> T {
+    let font_data = fs::read(path).expect("failed to read test font");
+    let font = FontRef::new(&font_data).expect("failed to parse test font");
+    let data = ShaperData::new(&font);
+    f(&font, &data)
+}
+
+#[test]
+fn script_shaper_hooks_are_called() {
+    static SETUP_MASKS_CALLS: AtomicUsize = AtomicUsize::new(0);
+    static MASKED: AtomicUsize = AtomicUsize::new(0);
+
+    struct LatinShaper;
+
+    impl ScriptShaper for LatinShaper {
+        fn base(&self, default: ShaperKind) -> ShaperKind {
+            assert_eq!(default, ShaperKind::Default);
+            ShaperKind::Hebrew
+        }
+
+        fn collect_features(&self, base: BuiltinShaper, planner: &mut ShapePlanner<'_, '_>) {
+            assert_eq!(base.kind(), ShaperKind::Hebrew);
+            assert_eq!(planner.script(), Some(script::LATIN));
+            planner.add_feature(Tag::new(b"liga"));
+            base.collect_features(planner);
+        }
+
+        fn setup_masks(
+            &self,
+            base: BuiltinShaper,
+            plan: &ShapePlan,
+            buffer: &mut ShaperBuffer<'_, '_, '_>,
+        ) {
+            SETUP_MASKS_CALLS.fetch_add(1, Ordering::Relaxed);
+            let mask = plan.feature_mask(Tag::new(b"liga"));
+            for i in 0..buffer.len() {
+                if buffer.glyph_infos()[i].glyph_id == u32::from('b') {
+                    buffer.add_mask(i, mask);
+                    MASKED.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            base.setup_masks(plan, buffer);
+        }
+    }
+
+    static LATIN_SHAPER: LatinShaper = LatinShaper;
+
+    let mut registry = ShaperRegistry::new();
+    registry.register(script::LATIN, &LATIN_SHAPER);
+    assert!(registry.get(script::LATIN).is_some());
+    assert!(registry.get(script::ARABIC).is_none());
+
+    with_test_font(|font, data| {
+        let builtin = data.shaper(font).build();
+        let custom = data.shaper(font).script_shapers(Some(&registry)).build();
+
+        let plan = ShapePlan::new(
+            &custom,
+            Direction::LeftToRight,
+            Some(script::LATIN),
+            None,
+            &[],
+        );
+        assert!(plan.uses_script_shaper());
+        assert_eq!(plan.shaper(), ShaperKind::Hebrew);
+
+        let plan = ShapePlan::new(
+            &builtin,
+            Direction::LeftToRight,
+            Some(script::LATIN),
+            None,
+            &[],
+        );
+        assert!(!plan.uses_script_shaper());
+        assert_eq!(plan.shaper(), ShaperKind::Default);
+
+        let plan = ShapePlan::new(
+            &custom,
+            Direction::RightToLeft,
+            Some(script::ARABIC),
+            None,
+            &[],
+        );
+        assert!(!plan.uses_script_shaper());
+
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str("abc");
+        buffer.guess_segment_properties();
+        let glyphs = custom.shape(buffer, ShapeOptions::new());
+        assert_eq!(glyphs.len(), 3);
+        assert_eq!(SETUP_MASKS_CALLS.load(Ordering::Relaxed), 1);
+        assert_eq!(MASKED.load(Ordering::Relaxed), 1);
+    });
+}
+
+#[test]
+fn script_shaper_without_overrides_matches_base_shaper() {
+    struct Passthrough;
+
+    impl ScriptShaper for Passthrough {}
+
+    static PASSTHROUGH: Passthrough = Passthrough;
+
+    let mut registry = ShaperRegistry::new();
+    registry.register(script::LATIN, &PASSTHROUGH);
+
+    with_test_font(|font, data| {
+        let builtin = data.shaper(font).build();
+        let custom = data.shaper(font).script_shapers(Some(&registry)).build();
+        let shape = |shaper: &harfrust::Shaper| {
+            let mut buffer = UnicodeBuffer::new();
+            buffer.push_str("abc cba");
+            buffer.guess_segment_properties();
+            let glyphs = shaper.shape(buffer, ShapeOptions::new());
+            glyphs
+                .glyph_infos()
+                .iter()
+                .zip(glyphs.glyph_positions())
+                .map(|(info, pos)| (info.glyph_id, info.cluster, pos.x_advance))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(shape(&custom), shape(&builtin));
+    });
+
+    registry.unregister(script::LATIN);
+    assert!(registry.get(script::LATIN).is_none());
+}
+
+#[test]
+fn shape_plan_key_matches_script_shapers() {
+    struct Passthrough;
+
+    impl ScriptShaper for Passthrough {}
+
+    static LATIN_SHAPER: Passthrough = Passthrough;
+    static OTHER_LATIN_SHAPER: Passthrough = Passthrough;
+
+    let mut registry = ShaperRegistry::new();
+    registry.register(script::LATIN, &LATIN_SHAPER);
+    let mut other_registry = ShaperRegistry::new();
+    other_registry.register(script::LATIN, &OTHER_LATIN_SHAPER);
+
+    with_test_font(|font, data| {
+        let custom = data.shaper(font).script_shapers(Some(&registry)).build();
+        let plan = ShapePlan::new(
+            &custom,
+            Direction::LeftToRight,
+            Some(script::LATIN),
+            None,
+            &[],
+        );
+        let key = |script_shapers| {
+            ShapePlanKey::new(Some(script::LATIN), Direction::LeftToRight)
+                .script_shapers(script_shapers)
+        };
+        assert!(!key(None).matches(&plan));
+        assert!(key(Some(&registry)).matches(&plan));
+        assert!(!key(Some(&other_registry)).matches(&plan));
+        // A copy of the registry has the same shapers.
+        let copy = registry.clone();
+        assert!(key(Some(&copy)).matches(&plan));
+        // Registering the same shaper again invalidates the plan.
+        let mut reregistered = registry.clone();
+        reregistered.register(script::LATIN, &LATIN_SHAPER);
+        assert!(!key(Some(&reregistered)).matches(&plan));
+
+        let builtin = data.shaper(font).build();
+        let plan = ShapePlan::new(
+            &builtin,
+            Direction::LeftToRight,
+            Some(script::LATIN),
+            None,
+            &[],
+        );
+        assert!(key(None).matches(&plan));
+        assert!(!key(Some(&registry)).matches(&plan));
+    });
+}
+
+fn shape_latin(registry: Option<&ShaperRegistry>, text: &str) -> Vec<(u32, u32)> {
+    with_font(latin_font_path(), |font, data| {
+        let shaper = data.shaper(font).script_shapers(registry).build();
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.guess_segment_properties();
+        let glyphs = shaper.shape(buffer, ShapeOptions::new());
+        glyphs
+            .glyph_infos()
+            .iter()
+            .map(|info| (info.glyph_id, info.cluster))
+            .collect()
+    })
+}
+
+#[test]
+fn script_shaper_normalization_hooks_are_called() {
+    static REORDER_MARKS_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    struct DecomposingShaper;
+
+    impl ScriptShaper for DecomposingShaper {
+        fn normalization_preference(&self, _: BuiltinShaper) -> NormalizationMode {
+            NormalizationMode::Decomposed
+        }
+
+        fn decompose(
+            &self,
+            base: BuiltinShaper,
+            normalizer: &Normalizer<'_, '_, '_, '_>,
+            ab: u32,
+        ) -> Option<(u32, u32)> {
+            assert_eq!(normalizer.plan().script(), Some(script::LATIN));
+            if ab == u32::from('c') {
+                return Some((u32::from('e'), 0));
+            }
+            base.decompose(normalizer, ab)
+        }
+
+        fn reorder_marks(
+            &self,
+            base: BuiltinShaper,
+            plan: &ShapePlan,
+            buffer: &mut ShaperBuffer<'_, '_, '_>,
+            start: usize,
+            end: usize,
+        ) {
+            REORDER_MARKS_CALLS.fetch_add(1, Ordering::Relaxed);
+            let marks: Vec<_> = buffer.glyph_infos()[start..end]
+                .iter()
+                .map(|info| info.glyph_id)
+                .collect();
+            assert_eq!(marks, [0x316, 0x301]);
+            base.reorder_marks(plan, buffer, start, end);
+        }
+    }
+
+    struct NonComposingShaper;
+
+    impl ScriptShaper for NonComposingShaper {
+        fn compose(
+            &self,
+            _: BuiltinShaper,
+            _: &Normalizer<'_, '_, '_, '_>,
+            _: u32,
+            _: u32,
+        ) -> Option<u32> {
+            None
+        }
+    }
+
+    static DECOMPOSING_SHAPER: DecomposingShaper = DecomposingShaper;
+    static NON_COMPOSING_SHAPER: NonComposingShaper = NonComposingShaper;
+
+    // 'e' is glyph 102, U+0301 glyph 2, U+0316 glyph 23 and 'é' glyph 234.
+    assert_eq!(
+        shape_latin(None, "\u{e9}\u{316}c"),
+        [(234, 0), (23, 0), (100, 4)]
+    );
+
+    let mut registry = ShaperRegistry::new();
+    registry.register(script::LATIN, &DECOMPOSING_SHAPER);
+    with_font(latin_font_path(), |font, data| {
+        let shaper = data.shaper(font).script_shapers(Some(&registry)).build();
+        let plan = ShapePlan::new(
+            &shaper,
+            Direction::LeftToRight,
+            Some(script::LATIN),
+            None,
+            &[],
+        );
+        assert_eq!(
+            plan.normalization_mode(NormalizationMode::Auto),
+            NormalizationMode::Decomposed
+        );
+    });
+    assert_eq!(
+        shape_latin(Some(&registry), "\u{e9}\u{316}c"),
+        [(102, 0), (23, 0), (2, 0), (102, 4)]
+    );
+    assert_eq!(REORDER_MARKS_CALLS.load(Ordering::Relaxed), 1);
+
+    assert_eq!(shape_latin(None, "e\u{301}"), [(234, 0)]);
+    registry.register(script::LATIN, &NON_COMPOSING_SHAPER);
+    assert_eq!(shape_latin(Some(&registry), "e\u{301}"), [(102, 0), (2, 0)]);
+}
+
+#[test]
+fn script_shaper_can_edit_the_buffer() {
+    struct EditingShaper;
+
+    impl ScriptShaper for EditingShaper {
+        fn preprocess_text(
+            &self,
+            base: BuiltinShaper,
+            plan: &ShapePlan,
+            buffer: &mut ShaperBuffer<'_, '_, '_>,
+        ) {
+            base.preprocess_text(plan, buffer);
+            // "ae" becomes "be", then "bec" and then "cbe".
+            buffer.replace(0, u32::from('b'));
+            buffer.insert(buffer.len(), u32::from('c'));
+            buffer.move_entry(2, 0);
+        }
+    }
+
+    static EDITING_SHAPER: EditingShaper = EditingShaper;
+
+    let mut registry = ShaperRegistry::new();
+    registry.register(script::LATIN, &EDITING_SHAPER);
+    // 'b' is glyph 99, 'c' glyph 100 and 'e' glyph 102.
+    assert_eq!(
+        shape_latin(Some(&registry), "ae"),
+        [(100, 0), (99, 0), (102, 0)]
+    );
+}
@@ -0,0 +1,112 @@
+use std::fs;
+use std::path::PathBuf;
+
+use harfrust::{FontRef, GlyphBuffer, ShapeCache, ShapeOptions, ShaperData, UnicodeBuffer};
+
+fn font_path(dir: &str, name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join(dir)
+        .join(name)
+}
+
+fn with_shaper<T>(font_path: PathBuf, f: impl FnOnce(&harfrust::Shaper) -> T) -> T {
+    let font_data = fs::read(font_path).expect("failed to read test font");
+    let font = FontRef::new(&font_data).expect("failed to parse test font");
+    let data = ShaperData::new(&font);
+    let shaper = data.shaper(&font).build();
+    f(&shaper)
+}
+
+fn buffer_with_text(text: &str) -> UnicodeBuffer {
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    buffer
+}
+
+fn assert_same_output(cached: &GlyphBuffer, direct: &GlyphBuffer) {
+    assert_eq!(cached.len(), direct.len());
+    for (cached, direct) in cached.glyph_infos().iter().zip(direct.glyph_infos()) {
+        assert_eq!(cached.glyph_id, direct.glyph_id);
+        assert_eq!(cached.cluster, direct.cluster);
+    }
+    for (cached, direct) in cached
+        .glyph_positions()
+        .iter()
+        .zip(direct.glyph_positions())
+    {
+        assert_eq!(cached.x_advance, direct.x_advance);
+        assert_eq!(cached.y_advance, direct.y_advance);
+        assert_eq!(cached.x_offset, direct.x_offset);
+        assert_eq!(cached.y_offset, direct.y_offset);
+    }
+}
+
+#[test]
+fn cached_shaping_matches_direct_shaping() {
+    let fonts = [
+        (
+            font_path("rb_custom", "OpenSans.subset1.ttf"),
+            "abc cba  abc",
+        ),
+        (
+            font_path("in-house", "1af868501dfcfd16184116b966f7fb2bd310623c.ttf"),
+            "\u{628}\u{633}\u{645} \u{627}\u{644}\u{644}\u{647} \u{628}\u{633}\u{645}",
+        ),
+    ];
+    for (font_path, text) in fonts {
+        with_shaper(font_path, |shaper| {
+            let mut cache = ShapeCache::new(16);
+            let direct = shaper.shape(buffer_with_text(text), ShapeOptions::new());
+            let first = cache.shape(shaper, buffer_with_text(text), ShapeOptions::new());
+            assert_same_output(&first, &direct);
+            assert!(cache.hits() > 0);
+
+            let misses = cache.misses();
+            let second = cache.shape(shaper, buffer_with_text(text), ShapeOptions::new());
+            assert_same_output(&second, &direct);
+            assert_eq!(cache.misses(), misses);
+        });
+    }
+}
+
+#[test]
+fn cache_is_keyed_by_font() {
+    let mut cache = ShapeCache::default();
+    let fonts = [
+        font_path("text-rendering-tests", "FDArrayTest257.otf"),
+        font_path("in-house", "49c9f7485c1392fa09a1b801bc2ffea79275f22e.ttf"),
+    ];
+    let mut glyph_ids = Vec::new();
+    for font_path in fonts {
+        with_shaper(font_path, |shaper| {
+            let cached = cache.shape(shaper, buffer_with_text("abc"), ShapeOptions::new());
+            let direct = shaper.shape(buffer_with_text("abc"), ShapeOptions::new());
+            assert_same_output(&cached, &direct);
+            glyph_ids.push(
+                cached
+                    .glyph_infos()
+                    .iter()
+                    .map(|info| info.glyph_id)
+                    .collect::<Vec<_>>(),
+            );
+        });
+    }
+    assert_ne!(glyph_ids[0], glyph_ids[1]);
+    assert_eq!(cache.hits(), 0);
+    assert_eq!(cache.misses(), 2);
+}
+
+#[test]
+fn cache_size_is_bounded() {
+    with_shaper(font_path("rb_custom", "OpenSans.subset1.ttf"), |shaper| {
+        let mut cache = ShapeCache::new(4);
+        for text in ["a", "b", "c", "ab", "ba", "abc", "cba", "bca"] {
+            cache.shape(shaper, buffer_with_text(text), ShapeOptions::new());
+            assert!(cache.len() <= cache.capacity());
+        }
+        assert_eq!(cache.misses(), 8);
+    });
+}
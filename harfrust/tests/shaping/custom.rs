@@ -446,3 +446,87 @@ fn glyph_flags_003() {
         "[X|A@586,0#1|B@1225,0#1|C@1851,0#1|E@2447,0#1]"
     );
 }
+
+#[test]
+fn vertical_001() {
+    assert_eq!(
+        shape(
+            "tests/fonts/rb_custom/NotoSansCJK.subset1.otf",
+            "\u{4F60}\u{597D}\u{FF0C}",
+            "--direction=ttb",
+        ),
+        "[gid1=0@-500,-880+0,-1000|gid3=1@-500,-880+0,-1000|gid5=2@-500,-880+0,-1000]"
+    );
+}
+
+#[test]
+fn vertical_002() {
+    assert_eq!(
+        shape(
+            "tests/fonts/rb_custom/NotoSansCJK.subset1.otf",
+            "\u{4F60}\u{597D}\u{FF0C}",
+            "--direction=ttb --features=-vert",
+        ),
+        "[gid1=0@-500,-880+0,-1000|gid3=1@-500,-880+0,-1000|gid6=2@-500,-880+0,-1000]"
+    );
+}
+
+#[test]
+fn vertical_003() {
+    assert_eq!(
+        shape(
+            "tests/fonts/text-rendering-tests/FDArrayTest257.otf",
+            "\u{3001}\u{3002}\u{FF08}\u{2026}\u{300C}",
+            "--direction=ttb",
+        ),
+        "[gid18=0@-500,-1092+0,-1480|gid19=1@-500,-1098+0,-1480|gid54=2@-500,-1092+0,-1480|gid26=3@-500,-1092+0,-1480|gid66=4@-500,-1092+0,-1480]"
+    );
+}
+
+#[test]
+fn vertical_004() {
+    assert_eq!(
+        shape(
+            "tests/fonts/text-rendering-tests/FDArrayTest257.otf",
+            "\u{3001}\u{3002}\u{FF08}\u{2026}\u{300C}",
+            "--direction=ltr",
+        ),
+        "[gid2=0+1000|gid3=1+1000|gid9=2+1000|gid39=3+1000|gid13=4+1000]"
+    );
+}
+
+#[test]
+fn vertical_005() {
+    assert_eq!(
+        shape(
+            "tests/fonts/in-house/1c04a16f32a39c26c851b7fc014d2e8d298ba2b8.ttf",
+            "\u{2010}",
+            "--direction=ttb",
+        ),
+        "[gid1=0@-369,-1753+0,-2384]"
+    );
+}
+
+#[test]
+fn vertical_006() {
+    assert_eq!(
+        shape(
+            "tests/fonts/in-house/1af868501dfcfd16184116b966f7fb2bd310623c.ttf",
+            "\u{0628}\u{0644}\u{0647}",
+            "--direction=ttb",
+        ),
+        "[beh-ar=0@-100,-550+0,-1000|lam-ar=1@-200,-550+0,-1000|heh-ar=2@-150,-550+0,-1000]"
+    );
+}
+
+#[test]
+fn vertical_007() {
+    assert_eq!(
+        shape(
+            "tests/fonts/in-house/f443753e8ffe8e8aae606cfba158e00334b6efb1.ttf",
+            "\u{1784}\u{179A}\u{179F}",
+            "--direction=ttb",
+        ),
+        "[uni1784=0@-775,-2850+0,-4100|uni179a=1@-387,-2750+0,-4100|uni179f=2@-1162,-2750+0,-4100]"
+    );
+}
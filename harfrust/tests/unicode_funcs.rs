@@ -0,0 +1,85 @@
+use std::fs;
+use std::path::PathBuf;
+
+use harfrust::{
+    script, BuiltinUnicodeFuncs, Direction, FontRef, Script, ShapeOptions, ShaperData,
+    UnicodeBuffer, UnicodeFuncs,
+};
+
+fn test_font_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fonts")
+        .join("rb_custom")
+        .join("OpenSans.subset1.ttf")
+}
+
+fn with_test_shaper<T>(f: impl FnOnce(&harfrust::Shaper) -> T) -> T {
+    let font_data = fs::read(test_font_path()).expect("failed to read test font");
+    let font = FontRef::new(&font_data).expect("failed to parse test font");
+    let data = ShaperData::new(&font);
+    let shaper = data.shaper(&font).build();
+    f(&shaper)
+}
+
+#[test]
+fn unicode_funcs_script_is_used_for_guessing() {
+    struct AllArabic;
+
+    impl UnicodeFuncs for AllArabic {
+        fn script(&self, _: u32) -> Script {
+            script::ARABIC
+        }
+    }
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str("abc");
+    buffer.guess_segment_properties_with(&AllArabic);
+    assert_eq!(buffer.script(), script::ARABIC);
+    assert_eq!(buffer.direction(), Direction::RightToLeft);
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str("abc");
+    buffer.guess_segment_properties_with(&BuiltinUnicodeFuncs);
+    assert_eq!(buffer.script(), script::LATIN);
+    assert_eq!(buffer.direction(), Direction::LeftToRight);
+}
+
+#[test]
+fn unicode_funcs_default_ignorables_are_used_for_shaping() {
+    // Treats U+2010 HYPHEN as a default ignorable, as a newer Unicode
+    // version might do for some character.
+    struct IgnorableHyphen;
+
+    impl UnicodeFuncs for IgnorableHyphen {
+        fn is_default_ignorable(&self, c: u32) -> bool {
+            c == 0x2010 || BuiltinUnicodeFuncs.is_default_ignorable(c)
+        }
+    }
+
+    let shape = |funcs: Option<&dyn UnicodeFuncs>| {
+        with_test_shaper(|shaper| {
+            let mut buffer = UnicodeBuffer::new();
+            buffer.push_str("a\u{2010}b");
+            buffer.guess_segment_properties();
+            let glyphs = shaper.shape(buffer, ShapeOptions::new().unicode_funcs(funcs));
+            glyphs
+                .glyph_infos()
+                .iter()
+                .zip(glyphs.glyph_positions())
+                .map(|(info, pos)| (info.cluster, pos.x_advance))
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let builtin = shape(None);
+    assert_eq!(builtin.len(), 3);
+    assert_ne!(builtin[1].1, 0);
+    assert_eq!(shape(Some(&BuiltinUnicodeFuncs)), builtin);
+
+    let custom = shape(Some(&IgnorableHyphen));
+    let hyphen = custom.iter().find(|(cluster, _)| *cluster == 1);
+    assert!(hyphen.is_none_or(|&(_, advance)| advance == 0));
+    assert_eq!(custom.first(), builtin.first());
+    assert_eq!(custom.last(), builtin.last());
+}
@@ -0,0 +1,103 @@
+use std::str::FromStr;
+
+use harfrust::{script, Direction, FontRef, GlyphId, Language, Script, ShapePlan, ShaperData, Tag};
+
+/// An Arabic font whose `ccmp` decomposes feh into dotless feh and a dot,
+/// `init` and `fina` select joining forms and `rlig` ligates dotless feh and
+/// alef.
+const ARABIC_FONT: &[u8] =
+    include_bytes!("fonts/in-house/8339c821814d9bad7c77169332327ad8b0f33c81.ttf");
+
+const ALEF: u32 = 2;
+const ALEF_FINA: u32 = 5;
+const FEH: u32 = 6;
+const FEH_DOTLESS: u32 = 7;
+const FEH_DOTLESS_INIT: u32 = 10;
+
+/// A font whose `locl` feature only substitutes the Arabic decimal
+/// separator for the Persian language system.
+const PERSIAN_FONT: &[u8] =
+    include_bytes!("fonts/in-house/507637795ce4f2975593da54d12b46f76c7cc4cc.ttf");
+
+const DECIMAL_SEPARATOR: u32 = 1;
+
+fn would_substitute(
+    font_data: &[u8],
+    script: Script,
+    language: Option<&str>,
+    feature_tag: &[u8; 4],
+    glyphs: &[u32],
+    zero_context: bool,
+) -> bool {
+    let font = FontRef::new(font_data).unwrap();
+    let data = ShaperData::new(&font);
+    let shaper = data.shaper(&font).build();
+    let language = language.map(|language| Language::from_str(language).unwrap());
+    let plan = ShapePlan::new(
+        &shaper,
+        Direction::RightToLeft,
+        Some(script),
+        language.as_ref(),
+        &[],
+    );
+    let glyphs: Vec<_> = glyphs.iter().copied().map(GlyphId::new).collect();
+    shaper.would_substitute(&plan, Tag::new(feature_tag), &glyphs, zero_context)
+}
+
+fn arabic(feature_tag: &[u8; 4], glyphs: &[u32]) -> bool {
+    would_substitute(
+        ARABIC_FONT,
+        script::ARABIC,
+        None,
+        feature_tag,
+        glyphs,
+        false,
+    )
+}
+
+#[test]
+fn single_glyphs() {
+    assert!(arabic(b"ccmp", &[FEH]));
+    assert!(arabic(b"init", &[FEH_DOTLESS]));
+    assert!(!arabic(b"init", &[ALEF]));
+    assert!(arabic(b"fina", &[ALEF]));
+}
+
+#[test]
+fn ligatures_match_the_whole_sequence() {
+    let ligature = [FEH_DOTLESS_INIT, ALEF_FINA];
+    assert!(arabic(b"rlig", &ligature));
+    assert!(would_substitute(
+        ARABIC_FONT,
+        script::ARABIC,
+        None,
+        b"rlig",
+        &ligature,
+        true
+    ));
+    assert!(!arabic(b"rlig", &[ALEF_FINA, FEH_DOTLESS_INIT]));
+    assert!(!arabic(b"rlig", &[FEH_DOTLESS_INIT]));
+}
+
+#[test]
+fn missing_features_and_empty_input_never_substitute() {
+    assert!(!arabic(b"liga", &[FEH]));
+    assert!(!arabic(b"ccmp", &[]));
+}
+
+#[test]
+fn features_are_looked_up_for_the_plan_language() {
+    let locl = |language| {
+        would_substitute(
+            PERSIAN_FONT,
+            script::ARABIC,
+            language,
+            b"locl",
+            &[DECIMAL_SEPARATOR],
+            false,
+        )
+    };
+    assert!(locl(Some("fa")));
+    assert!(!locl(None));
+    assert!(!locl(Some("ar")));
+}
@@ -8,9 +8,12 @@ use std::path::PathBuf;
 use clap::Parser;
 use harfrust::{
     font::{Font, FontInstance},
-    shape as shape_impl, BufferClusterLevel, BufferFlags, Direction, Feature, Language,
-    SerializeFlags, ShapeOptions, UnicodeBuffer, Variation,
+    shape as shape_impl, AatPreference, BufferClusterLevel, BufferFlags, Direction, Feature,
+    Language, LayoutPreference, SerializeFlags, ShapeEngine, ShapeOptions, UnicodeBuffer,
+    Variation,
 };
+#[cfg(feature = "profile")]
+use harfrust::{LookupCounts, LookupProfile};
 
 #[derive(Clone, Parser)]
 #[command(name = "hr-shape", version, about = "Shape text using HarfRust")]
@@ -35,6 +38,18 @@ pub struct Args {
     #[arg(long)]
     font_ptem: Option<f32>,
 
+    /// Set synthetic bold strength as one or two fractions of the scale
+    #[arg(long, value_parser = parse_embolden)]
+    font_bold: Option<(f32, f32)>,
+
+    /// Set synthetic in-place bold (grade) strength as one or two fractions of the scale
+    #[arg(long, value_parser = parse_embolden)]
+    font_grade: Option<(f32, f32)>,
+
+    /// Set synthetic slant as a horizontal-to-vertical ratio
+    #[arg(long, allow_negative_numbers = true)]
+    font_slant: Option<f32>,
+
     /// Comma-separated list of font variations
     #[arg(long, value_delimiter = ',')]
     variations: Vec<Variation>,
@@ -87,6 +102,22 @@ pub struct Args {
     #[arg(long)]
     script: Option<harfrust::Script>,
 
+    /// Set comma-separated list of shapers to try (ot, fallback)
+    #[arg(long, value_delimiter = ',')]
+    shapers: Vec<String>,
+
+    /// Use AAT morx/kerx tables whenever the font has them
+    #[arg(long, conflicts_with = "forbid_aat")]
+    prefer_aat: bool,
+
+    /// Never use AAT morx/kerx/trak tables
+    #[arg(long)]
+    forbid_aat: bool,
+
+    /// Ignore the legacy kern table
+    #[arg(long)]
+    disable_kern_table: bool,
+
     /// Comma-separated list of font features
     #[arg(long, value_delimiter = ',')]
     features: Vec<Feature>,
@@ -187,6 +218,11 @@ pub struct Args {
     #[arg(short = 'n', long, default_value_t = 1)]
     num_iterations: u32,
 
+    /// Write GSUB/GPOS/morx/mort lookup coverage counts as JSON to file-name
+    #[cfg(feature = "profile")]
+    #[arg(long, value_name = "FILE")]
+    lookup_profile: Option<PathBuf>,
+
     /// Ignored; accepted for hb-shape compatibility
     #[arg(long, hide = true)]
     font_funcs: Option<String>,
@@ -356,6 +392,35 @@ pub fn render(mut args: Args) -> Result<String, String> {
         f.bits()
     };
 
+    let engine = if args.shapers.is_empty() {
+        ShapeEngine::Auto
+    } else {
+        args.shapers
+            .iter()
+            .find_map(|shaper| match shaper.as_str() {
+                "ot" => Some(ShapeEngine::OpenType),
+                "fallback" => Some(ShapeEngine::Fallback),
+                _ => None,
+            })
+            .ok_or_else(|| "Error: all shapers failed.".to_string())?
+    };
+
+    let layout_preference = LayoutPreference::new()
+        .aat(if args.prefer_aat {
+            AatPreference::Prefer
+        } else if args.forbid_aat {
+            AatPreference::Forbid
+        } else {
+            AatPreference::Auto
+        })
+        .disable_kern(args.disable_kern_table);
+
+    let embolden = match (args.font_bold, args.font_grade) {
+        (_, Some((x, y))) => (x, y, true),
+        (Some((x, y)), None) => (x, y, false),
+        (None, None) => (0.0, 0.0, false),
+    };
+
     let language = args.language;
     let features = &args.features;
 
@@ -392,6 +457,9 @@ pub fn render(mut args: Args) -> Result<String, String> {
         text.split('\n').filter(|s| !s.is_empty()).collect()
     };
 
+    #[cfg(feature = "profile")]
+    let mut profile = args.lookup_profile.as_ref().map(|_| LookupProfile::new());
+
     let mut output = Vec::new();
     for (line_idx, text) in lines.iter().enumerate() {
         let line_no = line_idx + 1;
@@ -412,6 +480,9 @@ pub fn render(mut args: Args) -> Result<String, String> {
 
         let glyph_buffer = {
             let mut result = None;
+            // Only the first iteration is profiled.
+            #[cfg(feature = "profile")]
+            let mut line_profile = profile.as_mut();
             for _ in 0..args.num_iterations {
                 let mut buffer = UnicodeBuffer::new();
                 buffer.push_str(text);
@@ -445,13 +516,16 @@ pub fn render(mut args: Args) -> Result<String, String> {
 
                 buffer.guess_segment_properties();
 
-                result = Some(shape_impl(
-                    &instance,
-                    buffer,
-                    ShapeOptions::new()
-                        .point_size(args.font_ptem)
-                        .features(features),
-                ));
+                let options = ShapeOptions::new()
+                    .point_size(args.font_ptem)
+                    .synthetic_bold(embolden.0, embolden.1, embolden.2)
+                    .synthetic_slant(args.font_slant.unwrap_or(0.0))
+                    .features(features)
+                    .engine(engine)
+                    .layout_preference(layout_preference);
+                #[cfg(feature = "profile")]
+                let options = options.profile(line_profile.take());
+                result = Some(shape_impl(&instance, buffer, options));
             }
             result.unwrap()
         };
@@ -467,9 +541,76 @@ pub fn render(mut args: Args) -> Result<String, String> {
         .unwrap();
     }
 
+    #[cfg(feature = "profile")]
+    if let (Some(path), Some(profile)) = (args.lookup_profile.as_ref(), profile.as_ref()) {
+        std::fs::write(path, serialize_lookup_profile(profile))
+            .map_err(|e| format!("Error: cannot write '{}': {e}", path.display()))?;
+    }
+
     String::from_utf8(output).map_err(|e| format!("Error: invalid UTF-8 output: {e}"))
 }
 
+/// Serializes a lookup profile as JSON.
+///
+/// Lookups are written in lookup-list order, `morx` subtables in chain order.
+/// Only the first iteration of each line is profiled.
+#[cfg(feature = "profile")]
+fn serialize_lookup_profile(profile: &LookupProfile) -> String {
+    use std::fmt::Write;
+
+    fn write_counts(s: &mut String, counts: &LookupCounts) {
+        write!(
+            s,
+            "\"attempts\":{},\"successes\":{},\"glyphs_visited\":{}",
+            counts.attempts, counts.successes, counts.glyphs_visited
+        )
+        .unwrap();
+    }
+
+    fn write_subtables(s: &mut String, subtables: &[LookupCounts]) {
+        s.push_str("\"subtables\":[");
+        for (i, counts) in subtables.iter().enumerate() {
+            if i > 0 {
+                s.push(',');
+            }
+            s.push('{');
+            write_counts(s, counts);
+            s.push('}');
+        }
+        s.push(']');
+    }
+
+    let mut s = String::from("{");
+    for (name, lookups) in [
+        ("gsub", profile.gsub_lookups()),
+        ("gpos", profile.gpos_lookups()),
+    ] {
+        write!(s, "\"{name}\":[").unwrap();
+        for (i, stats) in lookups.iter().enumerate() {
+            if i > 0 {
+                s.push(',');
+            }
+            write!(s, "{{\"lookup\":{i},").unwrap();
+            write_counts(&mut s, &stats.counts);
+            s.push(',');
+            write_subtables(&mut s, &stats.subtables);
+            s.push('}');
+        }
+        s.push_str("],");
+    }
+    s.push_str("\"morx\":[");
+    for (i, subtables) in profile.morx_chains().iter().enumerate() {
+        if i > 0 {
+            s.push(',');
+        }
+        write!(s, "{{\"chain\":{i},").unwrap();
+        write_subtables(&mut s, subtables);
+        s.push('}');
+    }
+    s.push_str("]}\n");
+    s
+}
+
 fn normalize_args(args: &mut Args) {
     if args.short_v {
         args.verbose = true;
@@ -534,6 +675,21 @@ fn parse_cluster(s: &str) -> Result<BufferClusterLevel, String> {
     }
 }
 
+fn parse_embolden(s: &str) -> Result<(f32, f32), String> {
+    let parse = |s: &str| {
+        s.trim()
+            .parse::<f32>()
+            .map_err(|_| "invalid embolden strength".to_string())
+    };
+    match s.split_once([',', ' ']) {
+        Some((x, y)) => Ok((parse(x)?, parse(y)?)),
+        None => {
+            let x = parse(s)?;
+            Ok((x, x))
+        }
+    }
+}
+
 fn serialize_unicode(text: &str, utf8_clusters: bool) -> String {
     use std::fmt::Write;
 
@@ -550,3 +706,70 @@ fn serialize_unicode(text: &str, utf8_clusters: bool) -> String {
     }
     s
 }
+
+#[cfg(all(test, feature = "profile"))]
+mod tests {
+    use super::shape;
+
+    /// Shapes `text` twice with `--lookup-profile` and returns the output
+    /// and the written profile.
+    fn shape_with_profile(font: &str, text: &str, name: &str) -> (String, String) {
+        let font = format!(
+            "{}/../harfrust/tests/fonts/{font}",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let path =
+            std::env::temp_dir().join(format!("hr-shape-{name}-{}.json", std::process::id()));
+        let options = format!("-n 2 --lookup-profile={}", path.display());
+        let output = shape(&font, text, &options).unwrap();
+        let json = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        (output, json)
+    }
+
+    #[test]
+    fn lookup_profile_json() {
+        // Feh and alef; only the first of the two runs is profiled.
+        let (output, json) = shape_with_profile(
+            "in-house/8339c821814d9bad7c77169332327ad8b0f33c81.ttf",
+            "\u{0641}\u{0627}",
+            "gsub-gpos",
+        );
+        assert_eq!(
+            output,
+            "[dotabove-ar=0@188,546+0|fehDotless_alef-ar=0+752]\n"
+        );
+        let expected = concat!(
+            r#"{"gsub":["#,
+            r#"{"lookup":0,"attempts":1,"successes":1,"glyphs_visited":2,"subtables":[{"attempts":1,"successes":1,"glyphs_visited":1}]},"#,
+            r#"{"lookup":1,"attempts":1,"successes":1,"glyphs_visited":2,"subtables":[{"attempts":1,"successes":1,"glyphs_visited":1}]},"#,
+            r#"{"lookup":2,"attempts":0,"successes":0,"glyphs_visited":0,"subtables":[]},"#,
+            r#"{"lookup":3,"attempts":0,"successes":0,"glyphs_visited":0,"subtables":[]},"#,
+            r#"{"lookup":4,"attempts":1,"successes":1,"glyphs_visited":3,"subtables":[{"attempts":1,"successes":1,"glyphs_visited":1}]},"#,
+            r#"{"lookup":5,"attempts":0,"successes":0,"glyphs_visited":3,"subtables":[{"attempts":0,"successes":0,"glyphs_visited":0}]},"#,
+            r#"{"lookup":6,"attempts":1,"successes":1,"glyphs_visited":3,"subtables":[{"attempts":1,"successes":1,"glyphs_visited":1}]},"#,
+            r#"{"lookup":7,"attempts":1,"successes":1,"glyphs_visited":1,"subtables":[{"attempts":1,"successes":1,"glyphs_visited":1}]}],"#,
+            r#""gpos":["#,
+            r#"{"lookup":0,"attempts":1,"successes":0,"glyphs_visited":2,"subtables":[{"attempts":1,"successes":0,"glyphs_visited":1}]},"#,
+            r#"{"lookup":1,"attempts":1,"successes":0,"glyphs_visited":2,"subtables":[{"attempts":1,"successes":0,"glyphs_visited":1}]},"#,
+            r#"{"lookup":2,"attempts":1,"successes":1,"glyphs_visited":2,"subtables":[{"attempts":1,"successes":1,"glyphs_visited":1}]}],"#,
+            "\"morx\":[]}\n",
+        );
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn morx_profile_json() {
+        let (output, json) =
+            shape_with_profile("text-rendering-tests/TestMORXOne.ttf", "ABC", "morx");
+        assert_eq!(output, "[A.alt=0+1000|B=1+1000|C.alt=2+1000]\n");
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"gsub":[],"gpos":[],"morx":["#,
+                r#"{"chain":0,"subtables":[{"attempts":1,"successes":1,"glyphs_visited":3}]}"#,
+                "]}\n",
+            )
+        );
+    }
+}
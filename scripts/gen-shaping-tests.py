@@ -29,10 +29,6 @@ IGNORE_TEST_CASES = [
     "collections_001",
     # Requires support for the ltag table.
     "macos_002",
-
-    # https://github.com/harfbuzz/harfrust/pull/52
-    "vertical_015",
-    "vertical_017",
 ]
 
 